@@ -0,0 +1,78 @@
+//! An append-only log of every expression evaluated in the REPL, which serves
+//! as a record of how results were arrived at.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A log file which each evaluation is appended to as a line holding the time
+/// it happened, the expression and its result or error, separated by tabs.
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    /// Open the log at `path` for appending, creating it if it does not exist.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(AuditLog { file })
+    }
+
+    /// Append the evaluation of `input` to the log, where `result` is either
+    /// the formatted result or the error it failed with.
+    pub fn record(
+        &mut self,
+        input: &str,
+        result: Result<&str, &str>,
+    ) -> io::Result<()> {
+        let result = match result {
+            Ok(value) => value.to_string(),
+            Err(e) => format!("error: {}", e),
+        };
+
+        // each record is written at once so that it is never split up
+        let line = format!(
+            "{}\t{}\t{}\n",
+            timestamp(SystemTime::now()),
+            input,
+            result
+        );
+        self.file.write_all(line.as_bytes())
+    }
+}
+
+/// Format `time` in UTC as an RFC 3339 timestamp, e.g.
+/// `2022-11-05T14:03:09Z`.
+fn timestamp(time: SystemTime) -> String {
+    // a clock set before 1970 is logged as 1970
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // convert days since 1970-01-01 to a date in the proleptic gregorian
+    // calendar, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,141 @@
+//! Checking the syntax of script files without evaluating them.
+
+use std::{fs, io};
+
+use cocoa::{
+    ast::Expr, error::Diagnostic, script::import_path, template::Template,
+};
+use colored::*;
+
+/// Parse every expression or template in the file at `path`, one per line,
+/// reporting each syntax error along with where it is and its code, and
+/// returning how many there were.
+///
+/// With `json`, each error is printed as a JSON object on a line of its own
+/// instead, holding the `line` it is on, counted from 1, and the
+/// `diagnostic` as serialized by `Diagnostic::to_json`, for editors to read.
+///
+/// Blank lines and REPL commands, which start with `:`, are skipped.
+pub fn check(path: &str, json: bool) -> io::Result<usize> {
+    let source = fs::read_to_string(path)?;
+    let mut errors = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with(':') {
+            continue;
+        }
+
+        // templates and imports are definitions rather than expressions,
+        // and imports are not followed since only this file is checked
+        let parsed = if import_path(line).is_some() {
+            Ok(())
+        } else if line.trim_start().starts_with("template ") {
+            line.parse::<Template>().map(|_| ())
+        } else {
+            line.parse::<Expr>().map(|_| ())
+        };
+        let diagnostic = match parsed {
+            Ok(()) => continue,
+            Err(e) => Diagnostic::error(&e),
+        };
+        errors += 1;
+
+        if json {
+            println!(
+                r#"{{"line":{},"diagnostic":{}}}"#,
+                i + 1,
+                diagnostic.to_json()
+            );
+            continue;
+        }
+
+        // lines and columns are counted from 1, like other tools
+        let error = format!("error[{}]:", diagnostic.code.id()).red();
+        match diagnostic.span {
+            Some(span) => {
+                println!(
+                    "{}:{}:{}: {} {}",
+                    path,
+                    i + 1,
+                    span.start + 1,
+                    error,
+                    diagnostic.message
+                );
+                println!("    {}", line);
+                println!(
+                    "    {}{}",
+                    " ".repeat(span.start),
+                    "^".repeat(span.end - span.start).red()
+                );
+            }
+            None => {
+                println!(
+                    "{}:{}: {} {}",
+                    path,
+                    i + 1,
+                    error,
+                    diagnostic.message
+                );
+                println!("    {}", line);
+            }
+        }
+    }
+
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::check;
+
+    /// Write `source` to a script named `name` in the temporary directory,
+    /// returning its path.
+    fn script(name: &str, source: &str) -> String {
+        let path = env::temp_dir().join(format!(
+            "expresso-check-{}-{}",
+            process::id(),
+            name
+        ));
+        fs::write(&path, source).unwrap();
+
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn valid_script() {
+        let path = script(
+            "valid.txt",
+            "template area(r) := pi * r^2\n\
+             \n\
+             :precision 3\n\
+             import \"other.txt\"\n\
+             area(2) + sqrt(16)\n",
+        );
+
+        assert_eq!(0, check(&path, false).unwrap());
+        assert_eq!(0, check(&path, true).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_script() {
+        let path = script(
+            "invalid.txt",
+            "1 + 2\n\
+             (1 + 2\n\
+             template f(x) :=\n\
+             3 * * 4\n",
+        );
+
+        assert_eq!(3, check(&path, false).unwrap());
+        assert_eq!(3, check(&path, true).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_script() {
+        assert!(check("no/such/script.txt", false).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! The documentation of functions, operators and constants shown by `:doc`,
+//! rendered from the metadata cocoa keeps about each of them.
+
+use cocoa::{
+    evaluator::Evaluator,
+    function::{Category, Domain},
+    lexer::lex,
+    token::{Constant, FuncKind, OpKind, Token},
+};
+use colored::*;
+
+/// The number of columns descriptions are wrapped to.
+const WIDTH: usize = 80;
+
+/// Render the documentation of the function, operator or constant `name`:
+/// how it is called, what it evaluates to, the values its arguments may take
+/// and examples along with their answers.
+pub fn describe(name: &str) -> Result<String, String> {
+    // constants are lexed as the numbers they stand for
+    if let Some(c) = Constant::all().find(|c| c.name() == name) {
+        let mut lines = vec![c.name().bold().to_string()];
+        lines.extend(wrap(c.description(), ""));
+        lines.push(format!("{} {}", "category:".dimmed(), c.category()));
+        lines.push(format!("  {} = {}", c.name(), c.value()));
+
+        return Ok(lines.join("\n"));
+    }
+
+    let tokens = lex(&mut name.chars().peekable()).ok();
+    let (signature, description, category, domains, examples) =
+        match tokens.as_deref() {
+            Some(&[Token::Func(f)]) => (
+                f.signature(),
+                f.description(),
+                f.category().to_string(),
+                domains(f.params(), |i| f.domain(i)),
+                f.examples(),
+            ),
+            Some(&[Token::Op(op)]) => (
+                op.signature(),
+                op.description(),
+                op.category().to_string(),
+                domains(op.params(), |i| op.domain(i)),
+                op.examples(),
+            ),
+            _ => {
+                return Err(format!(
+                    "no function, operator or constant named '{}', use :doc \
+                     to list them",
+                    name
+                ))
+            }
+        };
+
+    let mut lines = vec![signature.bold().to_string()];
+    lines.extend(wrap(description, ""));
+    lines.push(format!("{} {}", "category:".dimmed(), category));
+    if !domains.is_empty() {
+        lines.push(format!("{} {}", "domain:".dimmed(), domains.join(", ")));
+    }
+
+    // examples are evaluated afresh, so that they are not affected by the
+    // settings or variables of the session
+    let mut evaluator = Evaluator::new();
+    evaluator.set_deterministic(true);
+    for example in examples {
+        let answer = match evaluator.eval_value(example) {
+            Ok(value) => evaluator.format_value(&value),
+            Err(e) => e.to_string(),
+        };
+        lines.push(format!("  {} = {}", example, answer));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Render the list of every function, operator and constant by category.
+pub fn categories() -> String {
+    let mut lines = Vec::new();
+
+    for category in Category::all() {
+        let functions = FuncKind::all()
+            .filter(|f| f.category() == category)
+            .map(|f| f.name().to_string());
+        let operators = OpKind::all()
+            .filter(|op| op.category() == category)
+            .map(|op| op.to_string());
+        let constants = Constant::all()
+            .filter(|c| c.category() == category)
+            .map(|c| c.name().to_string());
+        let names: Vec<String> =
+            functions.chain(operators).chain(constants).collect();
+
+        lines.extend(wrap(&names.join(", "), &format!("{}: ", category)));
+    }
+    lines.push(
+        "Use :doc <name> to see what one does, e.g. :doc round or :doc %."
+            .dimmed()
+            .to_string(),
+    );
+
+    lines.join("\n")
+}
+
+/// Describe the values the arguments `params` may take, where `domain` gives
+/// the domain of the argument at an index, leaving out those which may be any
+/// real number.
+fn domains(params: &[&str], domain: impl Fn(usize) -> Domain) -> Vec<String> {
+    params
+        .iter()
+        .enumerate()
+        .filter(|&(i, &param)| param != "..." && domain(i) != Domain::Real)
+        .map(|(i, param)| format!("{} in {}", param, domain(i)))
+        .collect()
+}
+
+/// Wrap `text` into lines of at most `WIDTH` columns, where the first line
+/// starts with `label` and the others are indented to line up with it.
+fn wrap(text: &str, label: &str) -> Vec<String> {
+    let indent = " ".repeat(label.len());
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && indent.len() + line.len() + word.len() >= WIDTH {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => format!("{}{}", label.bold(), line),
+            _ => format!("{}{}", indent, line),
+        })
+        .collect()
+}
@@ -0,0 +1,159 @@
+//! The list of REPL commands shown by `:help`, one per line, along with the
+//! other things that can be entered besides expressions.
+
+use colored::*;
+
+/// The width usages are padded to, so that the descriptions of all but the
+/// longest line up.
+const USAGE_WIDTH: usize = 28;
+
+/// Every command, how it is entered and what it does, in the order they are
+/// listed.
+const COMMANDS: &[(&str, &str)] = &[
+    (":help", "list the commands"),
+    (":doc [name]", "list the functions, or show what one does"),
+    (":m+", "add the last answer to memory, used as `mem`"),
+    (":m-", "subtract the last answer from memory"),
+    (":mr", "show the memory"),
+    (":mc", "clear the memory"),
+    (
+        ":precision <digits|off>",
+        "round answers to a number of digits",
+    ),
+    (
+        ":rounding <mode>",
+        "round half-up, half-even or toward-zero",
+    ),
+    (
+        ":snap <on|off|tolerance>",
+        "show answers near a short number as it",
+    ),
+    (
+        ":nan <permissive|strict>",
+        "treat NaN or infinite answers as errors",
+    ),
+    (
+        ":strict-division <on|off>",
+        "treat dividing by zero as an error",
+    ),
+    (
+        ":overflow <on|off>",
+        "treat answers too large for a float as errors",
+    ),
+    (":compensated <on|off>", "sum with Kahan summation"),
+    (
+        ":cfrac [terms]",
+        "show the continued fraction of the last answer",
+    ),
+    (":bits", "show the IEEE-754 bit pattern of the last answer"),
+    (":frombits <hex>", "turn a bit pattern back into a number"),
+    (
+        ":table <expr>, <var>, <start>, <stop>, <step>",
+        "tabulate an expression",
+    ),
+    (":plot <expr>, <var>, <start>, <stop>", "plot an expression"),
+    (
+        ":export <file> <expr>, <var>, <start>, <stop>, <points>",
+        "write values to a CSV",
+    ),
+    (
+        ":fit <file> [degree]",
+        "fit a polynomial to the points of a CSV file",
+    ),
+    (
+        ":base <dec|hex|oct|bin>",
+        "show integer answers in another base",
+    ),
+    (
+        ":width <8|16|32|64|off>",
+        "make integer arithmetic wrap around",
+    ),
+    (
+        ":mode <float|int|rational>",
+        "calculate with floats, integers or exact",
+    ),
+    (":rpn", "enter lines in reverse Polish notation, or go back"),
+    (
+        ":cache [size|off|clear]",
+        "limit, disable or empty the result cache",
+    ),
+    (":seed <n|off>", "make rand() repeat the same numbers"),
+    (":deterministic <on|off>", "make every result reproducible"),
+    (
+        ":autocorrect <on|off>",
+        "treat obvious typos as the name they are of",
+    ),
+    (
+        ":angle-hints <on|off>",
+        "also show answers in degrees where it helps",
+    ),
+    (
+        ":exact-trig <on|off>",
+        "give sin, cos and tan of pi/6 and pi/4 exactly",
+    ),
+    (
+        ":symbolic <on|off>",
+        "keep pi, exp(1), roots and unknowns exact",
+    ),
+    (":approx", "show the last answer as a number"),
+    (":expand <expr>", "expand an expression symbolically"),
+    (
+        ":alias [name = target]",
+        "list aliases, or give something a name",
+    ),
+    (":unalias <name>", "remove an alias"),
+    (
+        ":templates",
+        "list the templates, defined with f(x) := expr",
+    ),
+    (":untemplate <name>", "remove a template"),
+    (
+        ":latex <name|expr>",
+        "typeset a template or expression as LaTeX",
+    ),
+    (
+        ":import <file>",
+        "run a script of templates and expressions",
+    ),
+    (
+        ":prelude <on|off>",
+        "add or remove the templates of the prelude",
+    ),
+    (":undo", "undo the last definition, alias or memory change"),
+    (":redo", "redo what was undone"),
+    (":prompt <template|default>", "change the prompt"),
+    (":history", "list the lines entered so far"),
+    (
+        ":save-output <file>",
+        "write everything shown so far to a file",
+    ),
+    (
+        ":transcript <on file|off>",
+        "keep writing what is shown to a file",
+    ),
+];
+
+/// The other things that can be entered, and what they do.
+const OTHER: &[(&str, &str)] = &[
+    ("!!, !N", "repeat the last line, or line N of :history"),
+    ("ans, _N", "the last answer, or the Nth"),
+    ("Ctrl-d", "quit"),
+];
+
+/// Render the list of every command, one per line.
+pub fn commands() -> String {
+    let mut lines: Vec<String> = COMMANDS
+        .iter()
+        .chain(OTHER)
+        .map(|(usage, description)| {
+            format!("{:USAGE_WIDTH$}  {}", usage, description.dimmed())
+        })
+        .collect();
+    lines.push(
+        "Use :doc <name> to see what a function, operator or constant does."
+            .dimmed()
+            .to_string(),
+    );
+
+    lines.join("\n")
+}
@@ -0,0 +1,67 @@
+//! Colouring of the line being typed, using the classes cocoa gives its
+//! tokens.
+
+use std::borrow::Cow;
+
+use cocoa::highlight::{classify, TokenClass};
+use colored::*;
+use rustyline::{
+    completion::Completer, highlight::Highlighter, hint::Hinter,
+    validate::Validator, Helper,
+};
+
+/// Highlights expressions as they are typed into the REPL.
+pub struct LineHighlighter;
+
+impl Highlighter for LineHighlighter {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        // commands are not expressions
+        if line.trim_start().starts_with(':') {
+            return Cow::Borrowed(line);
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut highlighted = String::with_capacity(line.len());
+        // the index of the first character not yet added
+        let mut pos = 0;
+
+        for (span, class) in classify(line) {
+            highlighted.extend(&chars[pos..span.start]);
+
+            let text: String = chars[span.start..span.end].iter().collect();
+            let text = match class {
+                TokenClass::Number => text.yellow(),
+                TokenClass::Function => text.blue(),
+                TokenClass::Constant => text.cyan(),
+                TokenClass::String => text.green(),
+                TokenClass::Error => text.red(),
+                TokenClass::Operator
+                | TokenClass::Paren
+                | TokenClass::Comma => text.normal(),
+            };
+            highlighted += &text.to_string();
+
+            pos = span.end;
+        }
+        highlighted.extend(&chars[pos..]);
+
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // any character typed may change the class of the tokens around it
+        true
+    }
+}
+
+impl Completer for LineHighlighter {
+    type Candidate = String;
+}
+
+impl Hinter for LineHighlighter {
+    type Hint = String;
+}
+
+impl Validator for LineHighlighter {}
+
+impl Helper for LineHighlighter {}
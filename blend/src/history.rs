@@ -0,0 +1,146 @@
+//! Shell style references to earlier lines of input, where `!!` is the last
+//! line and `!N` is the Nth.
+
+/// Check whether `s` ends with something a `!` after it would be the
+/// factorial of, e.g. a number or a closing bracket.
+fn ends_with_operand(s: &str) -> bool {
+    match s.trim_end().chars().last() {
+        Some(c) => c.is_ascii_alphanumeric() || "_.)]!".contains(c),
+        None => false,
+    }
+}
+
+/// Parse the reference at the start of `s`, which starts just after a `!`,
+/// returning the entry of `history` it refers to and the rest of `s`.
+fn reference<'a, 'h>(
+    s: &'a str,
+    history: &'h [String],
+) -> Option<Result<(&'h str, &'a str), String>> {
+    if let Some(rest) = s.strip_prefix('!') {
+        return Some(match history.last() {
+            Some(entry) => Ok((entry.as_str(), rest)),
+            None => Err("no history to refer to with !!".to_string()),
+        });
+    }
+
+    let digits =
+        s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+
+    let (n, rest) = s.split_at(digits);
+    let entry = n
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| history.get(n.checked_sub(1)?))
+        .ok_or_else(|| format!("no history entry {}", n));
+
+    Some(entry.map(|entry| (entry.as_str(), rest)))
+}
+
+/// Replace the references in `line` to earlier entries of `history`, which
+/// is numbered from 1, returning `None` if there are none.
+///
+/// A `!` directly after an operand is a factorial rather than a reference.
+/// A reference that makes up the whole line is replaced by the entry as is,
+/// and one within an expression by the entry in brackets, so that e.g.
+/// `2 * !!` doubles the whole of the last expression.
+pub fn expand(
+    line: &str,
+    history: &[String],
+) -> Result<Option<String>, String> {
+    if let Some(Ok((entry, ""))) = line
+        .trim()
+        .strip_prefix('!')
+        .and_then(|s| reference(s, history))
+    {
+        return Ok(Some(entry.to_string()));
+    }
+
+    let mut expanded = String::new();
+    let mut rest = line;
+    let mut found = false;
+
+    while let Some(i) = rest.find('!') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if ends_with_operand(&expanded) {
+            expanded.push('!');
+            continue;
+        }
+
+        match reference(rest, history) {
+            Some(entry) => {
+                let (entry, after) = entry?;
+                expanded.push_str(&format!("({})", entry));
+                rest = after;
+                found = true;
+            }
+            None => expanded.push('!'),
+        }
+    }
+    expanded.push_str(rest);
+
+    Ok(found.then_some(expanded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+
+    fn history() -> Vec<String> {
+        vec!["1 + 2".to_string(), "sqrt(16)".to_string()]
+    }
+
+    #[test]
+    fn last_line() {
+        assert_eq!(Ok(Some("sqrt(16)".to_string())), expand("!!", &history()));
+        assert_eq!(
+            Ok(Some("2 * (sqrt(16))".to_string())),
+            expand("2 * !!", &history())
+        );
+    }
+
+    #[test]
+    fn numbered_line() {
+        assert_eq!(Ok(Some("1 + 2".to_string())), expand("!1", &history()));
+        assert_eq!(
+            Ok(Some("(1 + 2) * (sqrt(16))".to_string())),
+            expand("!1 * !2", &history())
+        );
+    }
+
+    #[test]
+    fn factorials_are_kept() {
+        assert_eq!(Ok(None), expand("3!", &history()));
+        assert_eq!(Ok(None), expand("(1 + 2)! + x!", &history()));
+        assert_eq!(
+            Ok(Some("3! + (1 + 2)".to_string())),
+            expand("3! + !1", &history())
+        );
+    }
+
+    #[test]
+    fn no_references() {
+        assert_eq!(Ok(None), expand("1 + 2", &history()));
+        assert_eq!(Ok(None), expand("!x", &history()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Err("no history to refer to with !!".to_string()),
+            expand("!!", &[])
+        );
+        assert_eq!(
+            Err("no history entry 3".to_string()),
+            expand("!3", &history())
+        );
+        assert_eq!(
+            Err("no history entry 0".to_string()),
+            expand("2 * !0", &history())
+        );
+    }
+}
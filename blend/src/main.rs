@@ -1,47 +1,1422 @@
+mod audit;
+mod check;
+mod doc;
+mod help;
+mod highlight;
+mod history;
+mod plot;
+mod transcript;
+
+use std::io::{self, IsTerminal};
+
 use colored::*;
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
+use rustyline::{Config, Editor};
+
+use audit::AuditLog;
+use cocoa::{
+    analysis::{angle_hint, AngleHint},
+    ast::Expr,
+    error::{Diagnostic, SpanError},
+    evaluator::{Domain, Evaluator, NanPolicy, Progress, Radix},
+    math::{
+        cfrac, convergent,
+        stats::{fit, fitpoly},
+        Rounding,
+    },
+    partial::parse_partial,
+    render::to_latex,
+    rpn::Stack,
+    session::{Outcome, Session},
+    value::Value,
+};
+use highlight::LineHighlighter;
+use transcript::Transcript;
+
+/// The prompt shown before each line of input by default.
+const PROMPT: &str = "> ";
+
+/// The prompt shown before the lines continuing an unfinished expression.
+const CONTINUATION: &str = "... ";
+
+/// The placeholders a prompt template may have, see `State::prompt`.
+const PLACEHOLDERS: [&str; 4] = ["{n}", "{base}", "{mode}", "{angle}"];
+
+/// The unit `sin`, `cos` and `tan` take angles in, which is shown by the
+/// `{angle}` placeholder of the prompt.
+const ANGLE_UNIT: &str = "rad";
+
+/// How close answers must be to a number with fewer digits to be displayed as
+/// it, once snapping is turned on with `:snap on`.
+const DEFAULT_SNAP: f64 = 1e-9;
+
+/// The variable the memory register can be recalled through in expressions.
+const MEMORY: &str = "mem";
+
+/// The number of continued fraction terms `:cfrac` shows by default.
+const CFRAC_TERMS: usize = 10;
+
+/// The most results of expensive functions that are cached by default.
+const CACHE_SIZE: usize = 1024;
+
+/// State that is kept between the lines entered into the REPL.
+struct State {
+    /// The evaluator lines are run in, along with the lines run so far, which
+    /// `!!` and `!N` refer to, and their results.
+    ///
+    /// The memory register, similar to the M+/M-/MR/MC keys of a physical
+    /// calculator, is held in a variable of the evaluator so that changes to
+    /// it can be undone.
+    session: Session,
+    /// Where every evaluation is recorded, if anywhere.
+    log: Option<AuditLog>,
+    /// The template the prompt is made from, see `State::prompt`.
+    prompt: String,
+    /// The width of the prompt the current line was shown after, so that the
+    /// markers under errors line up with the line.
+    prompt_width: usize,
+    /// Everything shown so far, which can be saved or written out as it goes.
+    transcript: Transcript,
+    /// Whether to show what a result would be if the arguments of `sin`,
+    /// `cos` and `tan` which look like degrees were taken as degrees.
+    angle_hints: bool,
+    /// The stack lines are entered onto in reverse Polish notation, if they
+    /// are.
+    rpn: Option<Stack>,
+}
+
+impl State {
+    fn new() -> Self {
+        let mut state = State {
+            session: Session::new(),
+            log: None,
+            prompt: PROMPT.to_string(),
+            prompt_width: PROMPT.len(),
+            transcript: Transcript::default(),
+            angle_hints: false,
+            rpn: None,
+        };
+        let evaluator = state.session.evaluator_mut();
+        // the memory starts out clear, which is not a change to undo
+        evaluator
+            .set_variable(MEMORY, 0.0)
+            .expect("the memory variable is a valid identifier");
+        evaluator.set_cache_size(CACHE_SIZE);
+        evaluator.set_progress(Some(Box::new(show_progress)));
+
+        state
+    }
+
+    /// Get the value held in the memory register.
+    fn memory(&self) -> f64 {
+        self.session.evaluator().variable(MEMORY).unwrap_or(0.0)
+    }
+
+    fn set_memory(&mut self, n: f64) {
+        self.session
+            .set_variable(MEMORY, n)
+            .expect("the memory variable is a valid identifier");
+    }
+
+    /// Run each line of `input`, which holds several lines when a block of
+    /// text is pasted, in order.
+    fn run_batch(&mut self, input: &str) {
+        if !input.contains('\n') {
+            self.run_expanded(input);
+            return;
+        }
+
+        // blank lines separating the pasted expressions are skipped
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            // show which line each result belongs to, which also lines up
+            // the markers under errors with the line they point at
+            self.echo(line);
+            self.run_expanded(line);
+        }
+    }
+
+    /// Run `line` after replacing its references to earlier lines, showing
+    /// what it expanded to if there were any.
+    fn run_expanded(&mut self, line: &str) {
+        let line = match history::expand(line, self.session.history()) {
+            Ok(Some(expanded)) => {
+                self.echo(&expanded);
+                expanded
+            }
+            Ok(None) => line.to_string(),
+            Err(e) => {
+                self.say(e.red());
+                return;
+            }
+        };
+
+        self.run(&line);
+    }
+
+    /// Make the prompt from its template, where `{n}` is the number the next
+    /// line will have in the history, `{base}` is the base answers are
+    /// displayed in, `{mode}` is the domain they are calculated in and
+    /// `{angle}` is the unit angles are taken in.
+    fn prompt(&self) -> String {
+        let evaluator = self.session.evaluator();
+
+        self.prompt
+            .replace("{n}", &(self.session.history().len() + 1).to_string())
+            .replace("{base}", &evaluator.radix().to_string())
+            .replace("{mode}", &evaluator.domain().to_string())
+            .replace("{angle}", ANGLE_UNIT)
+    }
+
+    /// Show `line` after the prompt as if it had been entered, so that its
+    /// result or error can be told apart from those of other lines.
+    fn echo(&mut self, line: &str) {
+        let prompt = self.prompt();
+        self.prompt_width = prompt.chars().count();
+
+        self.say(format!("{}{}", prompt, line));
+    }
+
+    /// Print `text` and add it to the transcript.
+    fn say(&mut self, text: impl std::fmt::Display) {
+        let text = text.to_string();
+        println!("{}", text);
+        self.record(&text);
+    }
+
+    /// Add `text` to the transcript without printing it, which is how lines
+    /// already shown by the editor are kept.
+    fn record(&mut self, text: &str) {
+        if let Err(e) = self.transcript.record(text) {
+            // stop so that the error is not repeated for every line after it
+            let path = self.transcript.stop().unwrap_or_default();
+            println!(
+                "{}",
+                format!(
+                    "could not write to '{}', stopped the transcript: {}",
+                    path, e
+                )
+                .red()
+            );
+        }
+    }
+
+    /// Run the REPL command, expression, definition or import `line`.
+    fn run(&mut self, line: &str) {
+        if let Some(cmd) = line.trim_start().strip_prefix(':') {
+            if let Err(e) = self.command(cmd) {
+                self.say(e.red());
+            }
+            self.session.remember(line);
+            return;
+        }
+
+        if self.rpn.is_some() {
+            self.enter(line);
+            if !line.trim().is_empty() {
+                self.session.remember(line);
+            }
+            return;
+        }
+
+        self.evaluate(line);
+    }
+
+    /// Run the script at `path` in the evaluator, so that its definitions
+    /// can be used, saying how many there were.
+    fn import(&mut self, path: &str) -> Result<(), String> {
+        let defined = self.session.import(path).map_err(|e| e.to_string())?;
+        self.say_imported(path, defined);
+
+        Ok(())
+    }
+
+    /// Say that a script at `path` which defined `defined` templates was
+    /// imported.
+    fn say_imported(&mut self, path: &str, defined: usize) {
+        let s = if defined == 1 { "" } else { "s" };
+        self.say(format!(
+            "imported {} definition{} from {}",
+            defined, s, path
+        ));
+    }
+
+    /// Enter `line` onto the stack in reverse Polish notation, showing the
+    /// stack afterwards, or the error it failed with.
+    fn enter(&mut self, line: &str) {
+        let Some(stack) = &mut self.rpn else { return };
+
+        if let Err(e) = stack.eval(self.session.evaluator(), line) {
+            if let Some(e) = e.downcast_ref::<SpanError>() {
+                self.say(format!(
+                    "{}{}",
+                    " ".repeat(self.prompt_width + e.span.start),
+                    "^".repeat(e.span.end - e.span.start).red()
+                ));
+            }
+            self.say(e.to_string().red());
+            return;
+        }
+
+        let values: Vec<String> = stack
+            .values()
+            .iter()
+            .map(|&n| self.session.evaluator().format(n))
+            .collect();
+        // the top of the stack is the answer to a line, like the value of
+        // an expression
+        if let Some(top) = stack.top() {
+            self.session.push_result(Value::Number(top));
+        }
+
+        if values.is_empty() {
+            self.say("(empty)".dimmed());
+        } else {
+            self.say(values.join(" "));
+        }
+    }
+
+    /// Run the expression, definition or import `line` in the session,
+    /// printing the result of an expression or the error it failed with, and
+    /// recording it in the log if there is one.
+    fn evaluate(&mut self, line: &str) {
+        let result = self.session.eval_line(line);
+        let warnings = self.session.evaluator().take_warnings();
+
+        for note in self.session.evaluator().take_notes() {
+            self.say(note.dimmed());
+        }
+        for warning in warnings {
+            if let Some(span) = warning.span {
+                self.say(format!(
+                    "{}{}",
+                    " ".repeat(self.prompt_width + span.start),
+                    "^".repeat(span.end - span.start).yellow()
+                ));
+            }
+            self.say(warning.to_string().yellow());
+        }
+
+        let result = match result {
+            Ok(Outcome::Value(value)) => {
+                let formatted = self.session.evaluator().format_value(&value);
+                self.say(&formatted);
+                self.note_snapped(&value);
+                self.hint_degrees(line);
+
+                Ok(formatted)
+            }
+            Ok(Outcome::Imported { path, defined }) => {
+                self.say_imported(&path, defined);
+                return;
+            }
+            Ok(Outcome::Defined(_) | Outcome::Nothing) => return,
+            Err(e) => {
+                // point at the part of the line that caused the error
+                if let Some(e) = e.downcast_ref::<SpanError>() {
+                    self.say(format!(
+                        "{}{}",
+                        " ".repeat(self.prompt_width + e.span.start),
+                        "^".repeat(e.span.end - e.span.start).red()
+                    ));
+                }
+                self.say(e.to_string().red());
+
+                Err(e.to_string())
+            }
+        };
+
+        if let Some(log) = &mut self.log {
+            if let Err(e) =
+                log.record(line, result.as_deref().map_err(String::as_str))
+            {
+                self.say(format!("could not write to the log: {}", e).red());
+            }
+        }
+    }
+
+    /// Point out that `value` is displayed as a number it was snapped to,
+    /// rather than as it is, if it was.
+    fn note_snapped(&mut self, value: &Value) {
+        let note = match value {
+            Value::Number(n)
+                if self.session.evaluator().snapped(*n).is_some() =>
+            {
+                format!("(display only, the answer is {})", n)
+            }
+            Value::List(list)
+                if list.iter().any(|&n| {
+                    self.session.evaluator().snapped(n).is_some()
+                }) =>
+            {
+                "(display only, the answers are not exactly these)".to_string()
+            }
+            _ => return,
+        };
+
+        self.say(note.dimmed());
+    }
+
+    /// Show what `line` would evaluate to if the arguments of its calls to
+    /// `sin`, `cos` and `tan` which look like degrees were taken as degrees,
+    /// if angle hints are on.
+    fn hint_degrees(&mut self, line: &str) {
+        if !self.angle_hints {
+            return;
+        }
+
+        let hint = match line.parse() {
+            Ok(expr) => angle_hint(&expr, self.session.evaluator()),
+            Err(_) => None,
+        };
+        if let Some(AngleHint { calls, in_degrees }) = hint {
+            let calls: Vec<String> =
+                calls.iter().map(|call| call.to_string()).collect();
+            self.say(
+                format!(
+                    "with {} in degrees: {}",
+                    calls.join(", "),
+                    self.session.evaluator().format_value(&in_degrees)
+                )
+                .dimmed(),
+            );
+        }
+    }
+
+    /// Show whether a setting is on, where `on` is whether it is, if `arg` is
+    /// empty, or turn it on or off with `set` if `arg` is "on" or "off".
+    fn toggle(
+        &mut self,
+        arg: &str,
+        on: bool,
+        set: impl FnOnce(&mut Self, bool),
+    ) -> Result<(), String> {
+        match arg {
+            "" if on => self.say("on"),
+            "" => self.say("off"),
+            "on" => set(self, true),
+            "off" => set(self, false),
+            _ => return Err(format!("expected 'on' or 'off', got '{}'", arg)),
+        }
+
+        Ok(())
+    }
+
+    /// Run the REPL command `cmd`, which is a line without its leading `:`.
+    fn command(&mut self, cmd: &str) -> Result<(), String> {
+        let (name, arg) = match cmd.trim().split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (cmd.trim(), ""),
+        };
+
+        match name {
+            op @ ("m+" | "m-") => {
+                let last =
+                    self.session.ans().ok_or("no result to store in memory")?;
+
+                if op == "m+" {
+                    self.set_memory(self.memory() + last);
+                } else {
+                    self.set_memory(self.memory() - last);
+                }
+            }
+            "mr" => self.say(self.session.evaluator().format(self.memory())),
+            "mc" => self.set_memory(0.0),
+            "rounding" if arg.is_empty() => {
+                self.say(self.session.evaluator().rounding())
+            }
+            "rounding" => {
+                let rounding =
+                    arg.parse::<Rounding>().map_err(|e| e.to_string())?;
+                self.session.evaluator_mut().set_rounding(rounding);
+            }
+            "precision" => match arg {
+                "" => match self.session.evaluator().precision() {
+                    Some(digits) => self.say(digits),
+                    None => self.say("off"),
+                },
+                "off" => self.session.evaluator_mut().set_precision(None),
+                _ => {
+                    let digits = arg.parse().map_err(|_| {
+                        format!(
+                            "expected a number of digits or 'off', got '{}'",
+                            arg
+                        )
+                    })?;
+                    self.session.evaluator_mut().set_precision(Some(digits));
+                }
+            },
+            "snap" => match arg {
+                "" => match self.session.evaluator().snap() {
+                    Some(epsilon) => self.say(epsilon),
+                    None => self.say("off"),
+                },
+                "on" => {
+                    self.session.evaluator_mut().set_snap(Some(DEFAULT_SNAP))
+                }
+                "off" => self.session.evaluator_mut().set_snap(None),
+                _ => {
+                    let epsilon = arg
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|epsilon| *epsilon >= 0.0)
+                        .ok_or_else(|| {
+                            format!(
+                                "expected a tolerance, 'on' or 'off', got '{}'",
+                                arg
+                            )
+                        })?;
+                    self.session.evaluator_mut().set_snap(Some(epsilon));
+                }
+            },
+            "nan" => match arg {
+                "" => match self.session.evaluator().nan_policy() {
+                    NanPolicy::Permissive => self.say("permissive"),
+                    NanPolicy::Strict => self.say("strict"),
+                },
+                "permissive" => self
+                    .session
+                    .evaluator_mut()
+                    .set_nan_policy(NanPolicy::Permissive),
+                "strict" => self
+                    .session
+                    .evaluator_mut()
+                    .set_nan_policy(NanPolicy::Strict),
+                _ => {
+                    return Err(format!(
+                        "expected 'permissive' or 'strict', got '{}'",
+                        arg
+                    ))
+                }
+            },
+            "strict-division" => {
+                let on = self.session.evaluator().strict_division();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_strict_division(on)
+                })?
+            }
+            "overflow" => {
+                let on = self.session.evaluator().detect_overflow();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_detect_overflow(on)
+                })?
+            }
+            "compensated" => {
+                let on = self.session.evaluator().compensated();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_compensated(on)
+                })?
+            }
+            "base" if arg.is_empty() => {
+                self.say(self.session.evaluator().radix())
+            }
+            "base" => {
+                let radix = arg.parse::<Radix>().map_err(|e| e.to_string())?;
+                self.session.evaluator_mut().set_radix(radix);
+            }
+            "rpn" => {
+                self.rpn = match self.rpn {
+                    Some(_) => None,
+                    None => Some(Stack::new()),
+                };
+
+                match self.rpn {
+                    Some(_) => self.say(
+                        "reverse Polish notation on, e.g. 2 3 + 4 *, use :rpn \
+                         again to turn it off",
+                    ),
+                    None => self.say("reverse Polish notation off"),
+                }
+            }
+            "mode" if arg.is_empty() => {
+                self.say(self.session.evaluator().domain())
+            }
+            "mode" => {
+                let domain =
+                    arg.parse::<Domain>().map_err(|e| e.to_string())?;
+                self.session
+                    .evaluator_mut()
+                    .set_domain(domain)
+                    .map_err(|e| e.to_string())?;
+
+                // show the last answer again as a number of the new domain
+                if let Some(last) = self.session.ans() {
+                    match self.session.evaluator().in_domain(last) {
+                        Some(value) => self
+                            .say(self.session.evaluator().format_value(&value)),
+                        None => self.say(
+                            format!(
+                                "(the last answer is not a number of the {} \
+                                 domain)",
+                                domain
+                            )
+                            .dimmed(),
+                        ),
+                    }
+                }
+            }
+            "width" => match arg {
+                "" => match self.session.evaluator().width() {
+                    Some(width) => self.say(width),
+                    None => self.say("off"),
+                },
+                "off" => self
+                    .session
+                    .evaluator_mut()
+                    .set_width(None)
+                    .expect("no width is always valid"),
+                _ => {
+                    let width = arg.parse().map_err(|_| {
+                        format!(
+                            "expected a number of bits or 'off', got '{}'",
+                            arg
+                        )
+                    })?;
+                    self.session
+                        .evaluator_mut()
+                        .set_width(Some(width))
+                        .map_err(|e| e.to_string())?;
+                }
+            },
+            "seed" => match arg {
+                "" => match self.session.evaluator().seed() {
+                    Some(seed) => self.say(seed),
+                    None => self.say("off"),
+                },
+                "off" => self.session.evaluator_mut().set_seed(None),
+                _ => {
+                    let seed = arg.parse().map_err(|_| {
+                        format!("expected a seed or 'off', got '{}'", arg)
+                    })?;
+                    self.session.evaluator_mut().set_seed(Some(seed));
+                }
+            },
+            "deterministic" => {
+                let on = self.session.evaluator().deterministic();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_deterministic(on)
+                })?
+            }
+            "prelude" => {
+                let on = self.session.evaluator().prelude();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_prelude(on)
+                })?
+            }
+            "autocorrect" => {
+                let on = self.session.evaluator().autocorrect();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_autocorrect(on)
+                })?
+            }
+            "exact-trig" => {
+                let on = self.session.evaluator().exact_trig();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_exact_trig(on)
+                })?
+            }
+            "symbolic" => {
+                let on = self.session.evaluator().symbolic();
+                self.toggle(arg, on, |state, on| {
+                    state.session.evaluator_mut().set_symbolic(on)
+                })?
+            }
+            "approx" => {
+                let last =
+                    self.session.ans().ok_or("no result to approximate")?;
+                self.say(self.session.evaluator().format(last));
+            }
+            "expand" => {
+                // expanding is symbolic whether or not answers are
+                let symbolic = self.session.evaluator().symbolic();
+                self.session.evaluator_mut().set_symbolic(true);
+                let value = self.session.evaluator().eval_value(arg);
+                self.session.evaluator_mut().set_symbolic(symbolic);
+
+                let value = value.map_err(|e| e.to_string())?;
+                self.say(self.session.evaluator().format_value(&value));
+            }
+            "angle-hints" => {
+                let on = self.angle_hints;
+                self.toggle(arg, on, |state, on| state.angle_hints = on)?
+            }
+            "cache" => match arg {
+                "" if self.session.evaluator().cache_size() == 0 => {
+                    self.say("off")
+                }
+                "" => self.say(format!(
+                    "{} of {} results cached",
+                    self.session.evaluator().cache_len(),
+                    self.session.evaluator().cache_size()
+                )),
+                "clear" => self.session.evaluator_mut().clear_cache(),
+                "off" => self.session.evaluator_mut().set_cache_size(0),
+                _ => {
+                    let size = arg.parse().map_err(|_| {
+                        format!(
+                            "expected a number of results, 'clear' or 'off', got '{}'",
+                            arg
+                        )
+                    })?;
+                    self.session.evaluator_mut().set_cache_size(size);
+                }
+            },
+            // aliases are listed as the commands that define them, so that
+            // they can be saved and pasted back in later
+            "alias" if arg.is_empty() => {
+                let aliases: Vec<String> = self
+                    .session
+                    .evaluator()
+                    .aliases()
+                    .into_iter()
+                    .map(|(name, target)| {
+                        format!(":alias {} = {}", name, target)
+                    })
+                    .collect();
+
+                for alias in aliases {
+                    self.say(alias);
+                }
+            }
+            "alias" => {
+                let (name, target) = arg.split_once('=').ok_or_else(|| {
+                    format!("expected <name> = <target>, got '{}'", arg)
+                })?;
+
+                self.session
+                    .set_alias(name.trim(), target.trim())
+                    .map_err(|e| e.to_string())?;
+            }
+            "unalias" => {
+                if self.session.remove_alias(arg).is_none() {
+                    return Err(format!("no alias named '{}'", arg));
+                }
+            }
+            "undo" => match self.session.undo().map_err(|e| e.to_string())? {
+                Some(undone) => self.say(format!("undid {}", undone)),
+                None => return Err("nothing to undo".to_string()),
+            },
+            "redo" => match self.session.redo().map_err(|e| e.to_string())? {
+                Some(redone) => self.say(format!("redid {}", redone)),
+                None => return Err("nothing to redo".to_string()),
+            },
+            "import" if arg.is_empty() => {
+                return Err("expected :import <file>".to_string())
+            }
+            "help" => self.say(help::commands()),
+            "doc" => match arg {
+                "" => self.say(doc::categories()),
+                _ => self.say(doc::describe(arg)?),
+            },
+            "import" => self.import(arg)?,
+            "templates" => {
+                let templates: Vec<String> = self
+                    .session
+                    .evaluator()
+                    .templates()
+                    .iter()
+                    .map(|template| template.to_string())
+                    .collect();
+
+                for template in templates {
+                    self.say(template);
+                }
+            }
+            "untemplate" => {
+                if self.session.remove_template(arg).is_none() {
+                    return Err(format!("no template named '{}'", arg));
+                }
+            }
+            "latex" => {
+                let latex = match self.session.evaluator().template(arg) {
+                    Some(template) => template.to_latex(),
+                    None => {
+                        let expr =
+                            arg.parse::<Expr>().map_err(|e| e.to_string())?;
+                        to_latex(&expr)
+                    }
+                };
+
+                self.say(latex);
+            }
+            "prompt" if arg.is_empty() => {
+                self.say(format!("{:?}", self.prompt))
+            }
+            "prompt" => {
+                // the template is quoted to keep any spaces at its end,
+                // otherwise a space is added to separate it from the input
+                let template = match arg.strip_prefix('"') {
+                    Some(quoted) => quoted
+                        .strip_suffix('"')
+                        .ok_or("unmatched '\"' in the prompt")?
+                        .to_string(),
+                    None if arg == "default" => PROMPT.to_string(),
+                    None => format!("{} ", arg),
+                };
+
+                let rest = PLACEHOLDERS
+                    .iter()
+                    .fold(template.clone(), |rest, p| rest.replace(p, ""));
+                if rest.contains(['{', '}']) {
+                    return Err(format!(
+                        "unknown placeholder in '{}', expected {}",
+                        template,
+                        PLACEHOLDERS.join(", ")
+                    ));
+                }
+
+                self.prompt = template;
+            }
+            "history" => {
+                let lines: Vec<String> = self
+                    .session
+                    .history()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| format!("{:>4}  {}", i + 1, line))
+                    .collect();
+
+                for line in lines {
+                    self.say(line);
+                }
+            }
+            "save-output" => {
+                if arg.is_empty() {
+                    return Err("expected :save-output <file>".to_string());
+                }
+
+                let lines = self
+                    .transcript
+                    .save(arg)
+                    .map_err(|e| format!("could not write '{}': {}", arg, e))?;
+                self.say(format!("wrote {} lines to {}", lines, arg));
+            }
+            "transcript" => match arg.split_once(char::is_whitespace) {
+                Some(("on", path)) => {
+                    let path = path.trim();
+                    self.transcript.start(path).map_err(|e| {
+                        format!("could not create '{}': {}", path, e)
+                    })?;
+                }
+                _ if arg == "off" => {
+                    self.transcript
+                        .stop()
+                        .ok_or("no transcript is being recorded")?;
+                }
+                _ if arg.is_empty() => match self.transcript.path() {
+                    Some(path) => self.say(format!("on, writing to {}", path)),
+                    None => self.say("off"),
+                },
+                _ => {
+                    return Err(format!(
+                        "expected 'on <file>' or 'off', got '{}'",
+                        arg
+                    ))
+                }
+            },
+            "bits" => {
+                let last = self.session.ans().ok_or("no result to inspect")?;
+                self.say(format_bits(last));
+            }
+            "frombits" => {
+                let digits = arg.strip_prefix("0x").unwrap_or(arg);
+                let bits = u64::from_str_radix(digits, 16).map_err(|_| {
+                    format!("expected a 64-bit hex pattern, got '{}'", arg)
+                })?;
+                let n = f64::from_bits(bits);
+
+                self.say(self.session.evaluator().format(n));
+                self.session.push_result(Value::Number(n));
+            }
+            "cfrac" => {
+                let last = self.session.ans().ok_or("no result to expand")?;
+                let n = match arg {
+                    "" => CFRAC_TERMS,
+                    _ => arg.parse().map_err(|_| {
+                        format!("expected a number of terms, got '{}'", arg)
+                    })?,
+                };
+
+                self.say(format_cfrac(last, n));
+            }
+            "table" => {
+                // split from the right, since the expression may itself
+                // contain commas
+                let mut parts: Vec<&str> = arg.rsplitn(5, ',').collect();
+                parts.reverse();
+
+                let [input, var, start, stop, step] = parts[..] else {
+                    return Err(
+                        "expected :table <expr>, <var>, <start>, <stop>, <step>"
+                            .to_string(),
+                    );
+                };
+
+                let [start, stop, step] = [start, stop, step].map(|s| {
+                    self.session.evaluator().eval(s).map_err(|e| e.to_string())
+                });
+                let (start, stop, step) = (start?, stop?, step?);
+
+                let (input, var) = (input.trim(), var.trim());
+                let rows = self
+                    .session
+                    .evaluator_mut()
+                    .table(input, var, start, stop, step)
+                    .map_err(|e| e.to_string())?;
+
+                let cells: Vec<(String, String)> = rows
+                    .into_iter()
+                    .map(|(x, y)| {
+                        let y = match y {
+                            Ok(y) => self.session.evaluator().format(y),
+                            Err(e) => e.to_string(),
+                        };
+                        (self.session.evaluator().format(x), y)
+                    })
+                    .collect();
+
+                self.say(format_table(var, input, &cells));
+            }
+            "plot" => {
+                let mut parts: Vec<&str> = arg.rsplitn(4, ',').collect();
+                parts.reverse();
+
+                let [input, var, start, stop] = parts[..] else {
+                    return Err(
+                        "expected :plot <expr>, <var>, <start>, <stop>"
+                            .to_string(),
+                    );
+                };
+
+                let start = self
+                    .session
+                    .evaluator()
+                    .eval(start)
+                    .map_err(|e| e.to_string())?;
+                let stop = self
+                    .session
+                    .evaluator()
+                    .eval(stop)
+                    .map_err(|e| e.to_string())?;
+
+                let plot = plot::plot(
+                    self.session.evaluator_mut(),
+                    input.trim(),
+                    var.trim(),
+                    start,
+                    stop,
+                )?;
+                self.say(plot);
+            }
+            "export" => {
+                const USAGE: &str =
+                    "expected :export <file> <expr>, <var>, <start>, <stop>, <points>";
+
+                let (path, rest) =
+                    arg.split_once(char::is_whitespace).ok_or(USAGE)?;
+                let mut parts: Vec<&str> = rest.rsplitn(5, ',').collect();
+                parts.reverse();
+
+                let [input, var, start, stop, points] = parts[..] else {
+                    return Err(USAGE.to_string());
+                };
+
+                let start = self
+                    .session
+                    .evaluator()
+                    .eval(start)
+                    .map_err(|e| e.to_string())?;
+                let stop = self
+                    .session
+                    .evaluator()
+                    .eval(stop)
+                    .map_err(|e| e.to_string())?;
+                let points: usize = points.trim().parse().map_err(|_| {
+                    format!(
+                        "expected a number of points, got '{}'",
+                        points.trim()
+                    )
+                })?;
+                if points < 2 {
+                    return Err("expected at least 2 points".to_string());
+                }
+
+                let (input, var) = (input.trim(), var.trim());
+                let step = (stop - start) / (points - 1) as f64;
+                let rows = self
+                    .session
+                    .evaluator_mut()
+                    .table(input, var, start, stop, step)
+                    .map_err(|e| e.to_string())?;
+
+                std::fs::write(path, format_csv(var, input, &rows)).map_err(
+                    |e| format!("could not write '{}': {}", path, e),
+                )?;
+                self.say(format!("wrote {} rows to {}", rows.len(), path));
+            }
+            "fit" => {
+                let (path, degree) = match arg.split_once(char::is_whitespace) {
+                    Some((path, degree)) => {
+                        let degree = degree.trim().parse().map_err(|_| {
+                            format!(
+                                "expected a degree, got '{}'",
+                                degree.trim()
+                            )
+                        })?;
+                        (path, degree)
+                    }
+                    None if arg.is_empty() => {
+                        return Err("expected :fit <file> [degree]".to_string())
+                    }
+                    None => (arg, 1),
+                };
+
+                let csv = std::fs::read_to_string(path)
+                    .map_err(|e| format!("could not read '{}': {}", path, e))?;
+                let (xs, ys) = read_points(&csv)?;
+
+                // lines have a closed form fit, which needs no decomposition
+                let (coefficients, r_squared) = match degree {
+                    1 => {
+                        let line = fit(&xs, &ys).map_err(|e| e.to_string())?;
+                        (vec![line.intercept, line.slope], line.r_squared)
+                    }
+                    _ => {
+                        let poly = fitpoly(&xs, &ys, degree)
+                            .map_err(|e| e.to_string())?;
+                        (poly.coefficients, poly.r_squared)
+                    }
+                };
+
+                self.say(format!(
+                    "y = {}, r^2 = {}",
+                    format_poly(self.session.evaluator(), &coefficients),
+                    self.session.evaluator().format(r_squared)
+                ));
+            }
+            _ => return Err(format!("unknown command ':{}'", name)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Show how far through a long evaluation the evaluator is, clearing the line
+/// it is shown on once it is done.
+fn show_progress(progress: Progress) {
+    if progress.done == progress.total {
+        eprint!("\r\x1b[K");
+    } else {
+        eprint!("\r{} {}/{}", progress.task, progress.done, progress.total);
+    }
+}
+
+/// Format the first n terms of the continued fraction expansion of x, along
+/// with the fraction they are equal to.
+fn format_cfrac(x: f64, n: usize) -> String {
+    let terms = cfrac(x, n);
+    let rest: Vec<String> =
+        terms.iter().skip(1).map(|a| a.to_string()).collect();
+
+    let mut s = match terms.first() {
+        Some(a0) if rest.is_empty() => format!("[{}]", a0),
+        Some(a0) => format!("[{}; {}]", a0, rest.join(", ")),
+        None => "[]".to_string(),
+    };
+
+    if let Some((p, q)) = convergent(&terms) {
+        s += &format!(" = {}/{}", p, q);
+    }
+
+    s
+}
+
+/// Format a table of values as two right aligned columns, headed by the
+/// variable and the expression it was tabulated with.
+fn format_table(var: &str, input: &str, cells: &[(String, String)]) -> String {
+    let left = cells
+        .iter()
+        .map(|(x, _)| x.len())
+        .chain([var.len()])
+        .max()
+        .unwrap_or(0);
+    let right = cells
+        .iter()
+        .map(|(_, y)| y.len())
+        .chain([input.len()])
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![
+        format!("{:>left$} | {:>right$}", var, input),
+        format!("{}-+-{}", "-".repeat(left), "-".repeat(right)),
+    ];
+    lines.extend(
+        cells
+            .iter()
+            .map(|(x, y)| format!("{:>left$} | {:>right$}", x, y)),
+    );
+
+    lines.join("\n")
+}
+
+/// Format the rows produced by `Evaluator::table` as CSV, headed by the
+/// variable and the expression, leaving the value empty where the expression
+/// could not be evaluated.
+///
+/// Values are written in full rather than with the evaluator's display
+/// settings, so that no precision is lost.
+fn format_csv<E>(
+    var: &str,
+    input: &str,
+    rows: &[(f64, Result<f64, E>)],
+) -> String {
+    // quote fields which contain a comma or a quote, doubling any quotes
+    let field = |s: &str| match s.contains([',', '"']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    };
+
+    let mut csv = format!("{},{}\n", field(var), field(input));
+    for (x, y) in rows {
+        match y {
+            Ok(y) => csv += &format!("{},{}\n", x, y),
+            Err(_) => csv += &format!("{},\n", x),
+        }
+    }
+
+    csv
+}
+
+/// Format the polynomial with `coefficients`, starting with the constant
+/// term, with its highest power first.
+fn format_poly(evaluator: &Evaluator, coefficients: &[f64]) -> String {
+    let mut s = String::new();
+
+    for (i, &c) in coefficients.iter().enumerate().rev() {
+        let power = match i {
+            0 => String::new(),
+            1 => " x".to_string(),
+            _ => format!(" x^{}", i),
+        };
+
+        if s.is_empty() {
+            s += &format!("{}{}", evaluator.format(c), power);
+        } else if c.is_sign_negative() {
+            s += &format!(" - {}{}", evaluator.format(-c), power);
+        } else {
+            s += &format!(" + {}{}", evaluator.format(c), power);
+        }
+    }
+
+    s
+}
+
+/// Read points from the first two columns of `csv`, skipping a header line
+/// and any rows with an empty value, such as those written by `:export` where
+/// the expression is undefined.
+fn read_points(csv: &str) -> Result<(Vec<f64>, Vec<f64>), String> {
+    let mut points = (vec![], vec![]);
+
+    for (i, line) in csv.lines().enumerate() {
+        let mut fields = line.split(',').map(str::trim);
+        let (x, y) = match (fields.next(), fields.next()) {
+            (Some(x), Some(y)) if !x.is_empty() && !y.is_empty() => (x, y),
+            _ => continue,
+        };
+
+        match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => {
+                points.0.push(x);
+                points.1.push(y);
+            }
+            _ if i == 0 => continue,
+            _ => {
+                return Err(format!(
+                    "line {}: expected two numbers, got '{}'",
+                    i + 1,
+                    line
+                ))
+            }
+        }
+    }
+
+    Ok(points)
+}
 
-use cocoa::{lexer::lex, parser::parse};
+/// Format the raw IEEE-754 bit pattern of x in hex, along with its sign,
+/// exponent and mantissa fields.
+fn format_bits(x: f64) -> String {
+    let bits = x.to_bits();
+
+    format!(
+        "0x{:016x} (sign {}, exponent 0x{:03x}, mantissa 0x{:013x})",
+        bits,
+        bits >> 63,
+        (bits >> 52) & 0x7ff,
+        bits & ((1 << 52) - 1)
+    )
+}
+
+/// What expresso was started to do.
+#[derive(Default)]
+enum Mode {
+    /// Run the REPL.
+    #[default]
+    Repl,
+    /// Check the syntax of a script file, `expresso check <file>`.
+    Check(String),
+    /// Evaluate a single expression and print its result,
+    /// `expresso -e <expr>`, which can read numbers piped into expresso.
+    Eval(String),
+    /// Evaluate each line piped into expresso and print its result, which is
+    /// what expresso does when its input is not a terminal.
+    Pipe,
+}
+
+/// The options expresso is started with.
+#[derive(Default)]
+struct Options {
+    mode: Mode,
+    /// The file to append a record of every evaluation to, if any.
+    log: Option<String>,
+    /// Whether to start without the templates of the prelude.
+    no_prelude: bool,
+    /// Whether to print errors as JSON, for other programs to read, with
+    /// `check` and `-e`.
+    json: bool,
+}
+
+impl Options {
+    /// Parse the command line arguments `args`, excluding the name of the
+    /// program.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--log" => {
+                    let path =
+                        args.next().ok_or("expected a file after --log")?;
+                    options.log = Some(path);
+                }
+                "--no-prelude" => options.no_prelude = true,
+                "--json" => options.json = true,
+                "-e" | "--eval" if matches!(options.mode, Mode::Repl) => {
+                    let input = args.next().ok_or_else(|| {
+                        format!("expected an expression after {}", arg)
+                    })?;
+                    options.mode = Mode::Eval(input);
+                }
+                "check" if matches!(options.mode, Mode::Repl) => {
+                    let path = args.next().ok_or("expected a file to check")?;
+                    options.mode = Mode::Check(path);
+                }
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+
+        if options.log.is_some() && matches!(options.mode, Mode::Check(_)) {
+            return Err(
+                "--log cannot be used with check, which evaluates nothing"
+                    .to_string(),
+            );
+        }
+
+        Ok(options)
+    }
+}
 
 fn main() {
-    repl();
+    let mut options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e.red());
+            std::process::exit(2);
+        }
+    };
+    // there is no one to interact with when input is piped in
+    if matches!(options.mode, Mode::Repl) && !io::stdin().is_terminal() {
+        options.mode = Mode::Pipe;
+    }
+
+    match &options.mode {
+        Mode::Repl => repl(&options),
+        Mode::Check(path) => match check::check(path, options.json) {
+            Ok(0) => (),
+            Ok(errors) => {
+                let s = if errors == 1 { "" } else { "s" };
+                eprintln!("{}", format!("found {} error{}", errors, s).red());
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!("could not read '{}': {}", path, e).red()
+                );
+                std::process::exit(2);
+            }
+        },
+        Mode::Eval(input) => {
+            if !eval(input, &options) {
+                std::process::exit(1);
+            }
+        }
+        Mode::Pipe => {
+            if !pipe(&options) {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Evaluate `input` and print its result, returning whether it succeeded.
+fn eval(input: &str, options: &Options) -> bool {
+    let mut log = match open_log(options) {
+        Ok(log) => log,
+        Err(e) => {
+            eprintln!("{}", format!("could not open the log: {}", e).red());
+            return false;
+        }
+    };
+    let mut evaluator = Evaluator::new();
+    evaluator.set_prelude(!options.no_prelude);
+    // standard input is free to hold data, since no lines are read from it
+    evaluator.set_allow_stdin(true);
+
+    let (result, warnings) = evaluator.eval_with_warnings(input);
+    for warning in warnings {
+        if options.json {
+            eprintln!("{}", warning.to_json());
+        } else {
+            eprintln!("{}", warning.to_string().yellow());
+        }
+    }
+
+    let ok = match &result {
+        Ok(value) => {
+            println!("{}", evaluator.format_value(value));
+            true
+        }
+        Err(e) if options.json => {
+            eprintln!("{}", Diagnostic::error(e).to_json());
+            false
+        }
+        Err(e) => {
+            eprintln!("{}", e.to_string().red());
+            false
+        }
+    };
+
+    if let Some(log) = &mut log {
+        let result = result
+            .map(|value| evaluator.format_value(&value))
+            .map_err(|e| e.to_string());
+        record(log, input, result.as_deref().map_err(String::as_str));
+    }
+
+    ok
+}
+
+/// Evaluate each line of standard input, printing each result or error as it
+/// goes, and returning whether every line succeeded.
+fn pipe(options: &Options) -> bool {
+    let mut log = match open_log(options) {
+        Ok(log) => log,
+        Err(e) => {
+            eprintln!("{}", format!("could not open the log: {}", e).red());
+            return false;
+        }
+    };
+    // only used to format results the way the REPL does
+    let evaluator = Evaluator::new();
+    let mut ok = true;
+
+    let mut lines_evaluator = Evaluator::new();
+    lines_evaluator.set_prelude(!options.no_prelude);
+
+    for (line, result) in
+        cocoa::eval_lines_with(io::stdin().lock(), lines_evaluator)
+    {
+        let result = match result {
+            Ok(n) => {
+                let formatted = evaluator.format(n);
+                println!("{}", formatted);
+                Ok(formatted)
+            }
+            Err(e) => {
+                eprintln!("{}", format!("{}: {}", line, e).red());
+                ok = false;
+                Err(e.to_string())
+            }
+        };
+
+        if let Some(log) = &mut log {
+            record(log, &line, result.as_deref().map_err(String::as_str));
+        }
+    }
+
+    ok
+}
+
+/// Open the file given with `--log`, if there is one.
+fn open_log(options: &Options) -> io::Result<Option<AuditLog>> {
+    options.log.as_deref().map(AuditLog::open).transpose()
 }
 
-fn repl() {
+/// Append the evaluation of `input` to `log`, printing why if it could not
+/// be written.
+fn record(log: &mut AuditLog, input: &str, result: Result<&str, &str>) {
+    if let Err(e) = log.record(input, result) {
+        eprintln!("{}", format!("could not write to the log: {}", e).red());
+    }
+}
+
+fn repl(options: &Options) {
     println!(
         r#"expresso REPL v0.1.0
 
 Enter expressions to see their answer or press Ctrl-d to quit.
+Use :help to list the commands.
 "#
     );
 
-    let mut rl = match Editor::<()>::new() {
+    // pasted text is inserted as a whole rather than each newline in it
+    // accepting the line, so that it can be run as a batch
+    let config = Config::builder().bracketed_paste(true).build();
+    let mut rl = match Editor::with_config(config) {
         Ok(editor) => editor,
         Err(e) => {
             println!("{}", e.to_string().red());
             return;
         }
     };
+    rl.set_helper(Some(LineHighlighter));
 
-    loop {
-        let line = rl.readline("> ");
-
-        match line {
-            Ok(line) => {
-                let tokens = match lex(&mut line.chars().peekable()) {
-                    Ok(ts) => ts,
-                    Err(e) => {
-                        println!("{}", e.to_string().red());
-                        continue;
-                    }
-                };
+    let mut state = State::new();
+    state
+        .session
+        .evaluator_mut()
+        .set_prelude(!options.no_prelude);
 
-                match parse(&mut tokens.into_iter().peekable(), 0) {
-                    Ok(n) => println!("{}", n),
-                    Err(e) => println!("{}", e.to_string().red()),
-                }
+    if let Some(path) = &options.log {
+        match AuditLog::open(path) {
+            Ok(log) => state.log = Some(log),
+            Err(e) => {
+                println!(
+                    "{}",
+                    format!("could not open '{}': {}", path, e).red()
+                );
+                return;
             }
+        }
+    }
+
+    'repl: loop {
+        let prompt = state.prompt();
+        state.prompt_width = prompt.chars().count();
+
+        let typed = match rl.readline(&prompt) {
+            Ok(input) => input,
             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
                 break;
             }
@@ -49,6 +1424,49 @@ Enter expressions to see their answer or press Ctrl-d to quit.
                 println!("{}", err.to_string().red());
                 break;
             }
+        };
+
+        // keep reading the lines after an unfinished expression, such as
+        // `sin(2 +`, until it is finished, which lines of reverse Polish
+        // notation never are
+        let mut input = typed.clone();
+        while state.rpn.is_none() && unfinished(&input) {
+            match rl.readline(CONTINUATION) {
+                Ok(line) => {
+                    input.push(' ');
+                    input += &line;
+                }
+                // the expression can be abandoned without quitting
+                Err(ReadlineError::Interrupted) => continue 'repl,
+                Err(ReadlineError::Eof) => break 'repl,
+                Err(err) => {
+                    println!("{}", err.to_string().red());
+                    break 'repl;
+                }
+            }
         }
+
+        if input != typed {
+            // show the whole expression so that any error can be pointed at
+            // within it
+            state.echo(&input);
+        } else if !input.contains('\n') {
+            // pasted lines are shown again as they are run, so only a single
+            // line needs keeping as it was entered
+            state.record(&format!("{}{}", prompt, input));
+        }
+        state.run_batch(&input);
     }
 }
+
+/// Check whether `input` is an expression which has been started but not
+/// finished, such as `sin(2 +`, so that the rest of it can be entered on the
+/// lines after it.
+fn unfinished(input: &str) -> bool {
+    let input = input.trim();
+
+    !input.is_empty()
+        && !input.starts_with(':')
+        && !input.contains('\n')
+        && matches!(parse_partial(input), Ok(partial) if !partial.complete())
+}
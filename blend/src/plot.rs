@@ -0,0 +1,257 @@
+//! Plotting expressions in the terminal, as text or, with the `graphics`
+//! feature, as an image in terminals which can display one.
+
+#[cfg(feature = "graphics")]
+mod graphics;
+
+use cocoa::evaluator::Evaluator;
+
+/// The number of columns a plot drawn with text is wide.
+const TEXT_WIDTH: usize = 64;
+
+/// The number of lines a plot drawn with text is high.
+const TEXT_HEIGHT: usize = 20;
+
+/// What is drawn at a point of a plot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pixel {
+    Empty,
+    /// Part of an axis, along with the character it is drawn with as text.
+    Axis(char),
+    Curve,
+}
+
+/// A grid of pixels which an expression has been plotted onto, with each
+/// column holding a single sample of the expression.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Pixel>,
+    /// The values of the expression at the bottom and top of the canvas.
+    y_range: (f64, f64),
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, y_range: (f64, f64)) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![Pixel::Empty; width * height],
+            y_range,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> Pixel {
+        self.pixels[y * self.width + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.pixels[y * self.width + x] = pixel;
+    }
+
+    /// Get the row `y` is drawn in, counting down from the top.
+    fn row(&self, y: f64) -> usize {
+        let (min, max) = self.y_range;
+
+        // casting is safe since the value is rounded and within the canvas
+        ((max - y) / (max - min) * (self.height - 1) as f64).round() as usize
+    }
+}
+
+/// Plot `input` as the variable `var` goes from `start` to `stop`, returning
+/// what to print to show the plot.
+///
+/// With the `graphics` feature, the plot is an image if the terminal supports
+/// the kitty graphics protocol or sixels, and is drawn with text otherwise.
+pub fn plot(
+    evaluator: &mut Evaluator,
+    input: &str,
+    var: &str,
+    start: f64,
+    stop: f64,
+) -> Result<String, String> {
+    if start >= stop {
+        return Err(format!("cannot plot from {} to {}", start, stop));
+    }
+
+    #[cfg(feature = "graphics")]
+    if let Some(protocol) = graphics::Protocol::detect() {
+        let (width, height) = graphics::SIZE;
+        let canvas =
+            sample(evaluator, input, var, (start, stop), width, height)?;
+
+        return Ok(format!(
+            "{}\n{}",
+            protocol.encode(&canvas),
+            describe_ranges(evaluator, var, (start, stop), canvas.y_range)
+        ));
+    }
+
+    let canvas = sample(
+        evaluator,
+        input,
+        var,
+        (start, stop),
+        TEXT_WIDTH,
+        TEXT_HEIGHT,
+    )?;
+
+    Ok(format_text(evaluator, &canvas, (start, stop)))
+}
+
+/// Evaluate `input` at one value of `var` for each column of a canvas, then
+/// draw the axes and a curve through the values onto it.
+fn sample(
+    evaluator: &mut Evaluator,
+    input: &str,
+    var: &str,
+    (start, stop): (f64, f64),
+    width: usize,
+    height: usize,
+) -> Result<Canvas, String> {
+    let step = (stop - start) / (width - 1) as f64;
+    let rows = evaluator
+        .table(input, var, start, stop, step)
+        .map_err(|e| e.to_string())?;
+
+    let mut first_error = None;
+    let ys: Vec<Option<f64>> = rows
+        .into_iter()
+        .take(width)
+        .map(|(_, y)| match y {
+            Ok(y) if y.is_finite() => Some(y),
+            Ok(_) => None,
+            Err(e) => {
+                first_error.get_or_insert(e.to_string());
+                None
+            }
+        })
+        .collect();
+
+    let defined = ys.iter().flatten();
+    let (min, max) = defined
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |r, &y| {
+            (r.0.min(y), r.1.max(y))
+        });
+
+    let y_range = match (min, max) {
+        _ if min > max => {
+            return Err(first_error.unwrap_or_else(|| {
+                format!("{} is not finite anywhere to plot", input)
+            }))
+        }
+        // give a constant a range so it is drawn in the middle of the plot
+        _ if min == max => (min - 1.0, max + 1.0),
+        _ => (min, max),
+    };
+
+    let mut canvas = Canvas::new(width, height, y_range);
+
+    if y_range.0 <= 0.0 && 0.0 <= y_range.1 {
+        let row = canvas.row(0.0);
+        (0..width).for_each(|x| canvas.set(x, row, Pixel::Axis('-')));
+    }
+    // casting is safe since the value is rounded and within the canvas
+    let zero = (-start / step).round() as usize;
+    // an axis at the left edge would only be drawn over the border
+    if start <= 0.0 && 0.0 <= stop && zero > 0 {
+        let col = zero;
+        for y in 0..height {
+            let c = match canvas.get(col, y) {
+                Pixel::Axis(_) => '+',
+                _ => '|',
+            };
+            canvas.set(col, y, Pixel::Axis(c));
+        }
+    }
+
+    let mut previous = None;
+    for (x, y) in ys.into_iter().enumerate() {
+        let Some(y) = y else {
+            previous = None;
+            continue;
+        };
+        let row = canvas.row(y);
+
+        // join the value to the previous one with a vertical line, so that
+        // steep parts of the curve are not drawn as scattered points, unless
+        // it jumps so far that it is most likely a discontinuity as in 1/x
+        let joined =
+            previous.filter(|&p: &usize| p.abs_diff(row) <= height / 2);
+        let (top, bottom) = match joined {
+            Some(p) if p < row => (p + 1, row),
+            Some(p) if p > row => (row, p - 1),
+            _ => (row, row),
+        };
+        (top..=bottom).for_each(|r| canvas.set(x, r, Pixel::Curve));
+
+        previous = Some(row);
+    }
+
+    Ok(canvas)
+}
+
+/// Draw `canvas` with text, labelling the vertical axis with the range of
+/// the expression and the horizontal axis with the range of the variable.
+fn format_text(
+    evaluator: &Evaluator,
+    canvas: &Canvas,
+    (start, stop): (f64, f64),
+) -> String {
+    let (min, max) = (
+        evaluator.format(canvas.y_range.0),
+        evaluator.format(canvas.y_range.1),
+    );
+    let margin = min.len().max(max.len());
+
+    let mut lines: Vec<String> = (0..canvas.height)
+        .map(|y| {
+            let label = match y {
+                0 => &max,
+                _ if y == canvas.height - 1 => &min,
+                _ => "",
+            };
+            let row: String = (0..canvas.width)
+                .map(|x| match canvas.get(x, y) {
+                    Pixel::Empty => ' ',
+                    Pixel::Axis(c) => c,
+                    Pixel::Curve => '*',
+                })
+                .collect();
+
+            format!("{:>margin$} |{}", label, row)
+        })
+        .collect();
+
+    lines.push(format!("{:margin$} +{}", "", "-".repeat(canvas.width)));
+
+    let start = evaluator.format(start);
+    let gap = (canvas.width + 1).saturating_sub(start.len());
+    lines.push(format!(
+        "{:margin$}  {}{:>gap$}",
+        "",
+        start,
+        evaluator.format(stop)
+    ));
+
+    lines.join("\n")
+}
+
+/// Describe the ranges a plot covers, for plots which are drawn without
+/// labels.
+#[cfg(feature = "graphics")]
+fn describe_ranges(
+    evaluator: &Evaluator,
+    var: &str,
+    (start, stop): (f64, f64),
+    (min, max): (f64, f64),
+) -> String {
+    format!(
+        "{} from {} to {}, values from {} to {}",
+        var,
+        evaluator.format(start),
+        evaluator.format(stop),
+        evaluator.format(min),
+        evaluator.format(max)
+    )
+}
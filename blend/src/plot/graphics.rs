@@ -0,0 +1,197 @@
+//! Encoding plots as images using the graphics protocols supported by some
+//! terminals.
+
+use std::env;
+
+use super::{Canvas, Pixel};
+
+/// The width and height in pixels of a plot drawn as an image.
+pub const SIZE: (usize, usize) = (480, 240);
+
+/// The colour of the curve of a plot, as red, green and blue.
+const CURVE: [u8; 3] = [31, 119, 180];
+
+/// The colour of the axes of a plot, as red, green and blue.
+const AXIS: [u8; 3] = [128, 128, 128];
+
+/// The most base64 encoded bytes the kitty graphics protocol allows in a
+/// single escape sequence.
+const KITTY_CHUNK: usize = 4096;
+
+/// A protocol for displaying images in a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The graphics protocol of the kitty terminal, which is also supported by
+    /// e.g. WezTerm and Konsole.
+    Kitty,
+    /// The sixel format of DEC terminals, which is supported by e.g. foot,
+    /// mlterm and xterm when it is started with `-ti vt340`.
+    Sixel,
+}
+
+impl Protocol {
+    /// Find which protocol the terminal supports from the environment
+    /// variables it sets, if any.
+    ///
+    /// Terminals are not queried directly since waiting for a reply would
+    /// hang on those which do not understand the query.
+    pub fn detect() -> Option<Protocol> {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || matches!(var("TERM_PROGRAM").as_str(), "WezTerm" | "ghostty")
+        {
+            Some(Protocol::Kitty)
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+        {
+            Some(Protocol::Sixel)
+        } else {
+            None
+        }
+    }
+
+    /// Encode `canvas` as an escape sequence that displays it as an image.
+    pub(super) fn encode(self, canvas: &Canvas) -> String {
+        match self {
+            Protocol::Kitty => kitty(canvas),
+            Protocol::Sixel => sixel(canvas),
+        }
+    }
+}
+
+/// Encode `canvas` as transparent RGBA pixels sent with the kitty graphics
+/// protocol, split into as many escape sequences as its size needs.
+fn kitty(canvas: &Canvas) -> String {
+    let rgba: Vec<u8> = canvas
+        .pixels
+        .iter()
+        .flat_map(|pixel| match pixel {
+            Pixel::Empty => [0, 0, 0, 0],
+            Pixel::Axis(_) => [AXIS[0], AXIS[1], AXIS[2], 255],
+            Pixel::Curve => [CURVE[0], CURVE[1], CURVE[2], 255],
+        })
+        .collect();
+
+    let data = base64(&rgba);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut s = String::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        // only the first sequence describes the image, and every sequence
+        // but the last says that more are to follow
+        let keys = match i {
+            0 => format!("a=T,f=32,s={},v={},", canvas.width, canvas.height),
+            _ => String::new(),
+        };
+        let more = (i + 1 < chunks.len()) as u8;
+        let chunk = std::str::from_utf8(chunk).expect("base64 is always ascii");
+
+        s += &format!("\x1b_G{}m={};{}\x1b\\", keys, more, chunk);
+    }
+
+    s
+}
+
+/// Encode `canvas` as a sixel image with a transparent background.
+///
+/// Sixel images are drawn in bands six pixels high, with each band drawn in
+/// one pass per colour and each column of a pass encoded as a character whose
+/// bits say which of its six pixels are that colour.
+fn sixel(canvas: &Canvas) -> String {
+    // the sixel palette is in percentages rather than bytes
+    let percent = |c: u8| u32::from(c) * 100 / 255;
+    let colours = [(1, Pixel::Axis(' '), AXIS), (2, Pixel::Curve, CURVE)];
+
+    let mut s = format!("\x1bP0;1q\"1;1;{};{}", canvas.width, canvas.height);
+    for (register, _, [r, g, b]) in colours {
+        s += &format!(
+            "#{};2;{};{};{}",
+            register,
+            percent(r),
+            percent(g),
+            percent(b)
+        );
+    }
+
+    for band in (0..canvas.height).step_by(6) {
+        let mut passes = Vec::new();
+
+        for (register, colour, _) in colours {
+            let columns: Vec<u8> = (0..canvas.width)
+                .map(|x| {
+                    (0..6)
+                        .filter(|dy| band + dy < canvas.height)
+                        .filter(|dy| {
+                            let pixel = canvas.get(x, band + dy);
+                            std::mem::discriminant(&pixel)
+                                == std::mem::discriminant(&colour)
+                        })
+                        .fold(0, |bits, dy| bits | 1 << dy)
+                })
+                .collect();
+
+            if columns.iter().any(|&bits| bits != 0) {
+                passes.push(format!("#{}{}", register, run_length(&columns)));
+            }
+        }
+
+        // `$` returns to the start of the band for the next colour, and `-`
+        // moves down to the next band
+        s += &passes.join("$");
+        s += "-";
+    }
+
+    s + "\x1b\\"
+}
+
+/// Encode the columns of a sixel pass, writing runs of the same column as a
+/// count rather than repeating them.
+fn run_length(columns: &[u8]) -> String {
+    let mut s = String::new();
+    let mut i = 0;
+
+    while i < columns.len() {
+        let bits = columns[i];
+        let run = columns[i..].iter().take_while(|&&b| b == bits).count();
+        let c = char::from(b'?' + bits);
+
+        if run > 3 {
+            s += &format!("!{}{}", run, c);
+        } else {
+            (0..run).for_each(|_| s.push(c));
+        }
+
+        i += run;
+    }
+
+    s
+}
+
+/// Encode `bytes` in standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 63) as usize]));
+            } else {
+                s.push('=');
+            }
+        }
+    }
+
+    s
+}
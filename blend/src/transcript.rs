@@ -0,0 +1,141 @@
+//! A plain text copy of everything shown in the REPL, which can be saved to a
+//! file at any point or written to one as the session goes on.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+/// The inputs and outputs of a session, as they were shown but without the
+/// escape codes that colour them.
+#[derive(Default)]
+pub struct Transcript {
+    lines: Vec<String>,
+    /// The file lines are written to as they are recorded, and its path.
+    file: Option<(String, File)>,
+}
+
+impl Transcript {
+    /// Add `text`, which may span several lines, to the transcript and write
+    /// it to the file being recorded to, if there is one.
+    pub fn record(&mut self, text: &str) -> io::Result<()> {
+        let text = strip_ansi(text);
+
+        if let Some((_, file)) = &mut self.file {
+            writeln!(file, "{}", text)?;
+        }
+        self.lines.extend(text.lines().map(str::to_string));
+
+        Ok(())
+    }
+
+    /// Write the whole session so far to `path`, replacing anything in it.
+    pub fn save(&self, path: &str) -> io::Result<usize> {
+        let mut contents = self.lines.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        std::fs::write(path, contents)?;
+
+        Ok(self.lines.len())
+    }
+
+    /// Start writing every line recorded from now on to `path`, replacing
+    /// anything in it.
+    pub fn start(&mut self, path: &str) -> io::Result<()> {
+        self.file = Some((path.to_string(), File::create(path)?));
+
+        Ok(())
+    }
+
+    /// Stop writing lines to the file being recorded to, returning its path.
+    pub fn stop(&mut self) -> Option<String> {
+        self.file.take().map(|(path, _)| path)
+    }
+
+    /// The path of the file being recorded to, if there is one.
+    pub fn path(&self) -> Option<&str> {
+        self.file.as_ref().map(|(path, _)| path.as_str())
+    }
+}
+
+/// Remove the escape sequences in `text`, such as `\x1b[31m` which sets its
+/// colour, or `\x1b]8;;url\x1b\\` which starts a hyperlink.
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // a control sequence, such as a colour, ends with the first
+            // character from @ to ~ after its parameters
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // operating system commands, device control strings and the
+            // other strings run until the string terminator ESC \, or a bell
+            Some(']' | 'P' | '_' | '^' | 'X') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.next_if_eq(&'\\').is_some() {
+                        break;
+                    }
+                }
+            }
+            // the other escape sequences are some intermediate characters
+            // followed by the character which ends them, such as ESC ( B
+            Some(' '..='/') => {
+                while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+                chars.next();
+            }
+            _ => (),
+        }
+    }
+
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_ansi;
+
+    #[test]
+    fn colours() {
+        assert_eq!(
+            "red bold",
+            strip_ansi("\x1b[31mred\x1b[0m \x1b[1mbold\x1b[0m")
+        );
+        assert_eq!("1 + 2", strip_ansi("\x1b[38;5;208m1\x1b[m + 2"));
+    }
+
+    #[test]
+    fn strings() {
+        // a hyperlink, ended by a string terminator and by a bell
+        assert_eq!(
+            "docs",
+            strip_ansi("\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x07")
+        );
+        // a device control string and an application program command
+        assert_eq!(
+            "a b",
+            strip_ansi("a\x1bP1$r0m\x1b\\ \x1b_Gf=100;AAAA\x1b\\b")
+        );
+    }
+
+    #[test]
+    fn other_sequences() {
+        assert_eq!("ab", strip_ansi("a\x1b(Bb"));
+        assert_eq!("ab", strip_ansi("a\x1b7b"));
+    }
+}
@@ -0,0 +1,65 @@
+//! Benchmarks of lexing numeric-heavy batch input, with a batch for each kind
+//! of number, from those parsed directly as they are lexed to those with too
+//! many digits to be.
+
+use cocoa::lexer::lex;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// The number of expressions in a batch.
+const LINES: usize = 1000;
+
+/// The number of numbers in each expression of a batch.
+const NUMBERS: usize = 8;
+
+/// Generate a batch of sums of numbers made by `number` from random integers,
+/// with a fixed seed so that every run lexes the same input.
+fn batch(number: impl Fn(u64) -> String) -> Vec<String> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        // xorshift, which is plenty random for varying the digits
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    (0..LINES)
+        .map(|_| {
+            let numbers: Vec<String> =
+                (0..NUMBERS).map(|_| number(next())).collect();
+
+            numbers.join(" + ")
+        })
+        .collect()
+}
+
+fn numbers(c: &mut Criterion) {
+    let batches = [
+        ("integers", batch(|n| (n % 1_000_000).to_string())),
+        (
+            "decimals",
+            batch(|n| format!("{}.{}", n % 1000, n % 1_000_000)),
+        ),
+        (
+            "fractions",
+            batch(|n| format!("0.{:09}", n % 1_000_000_000)),
+        ),
+        ("long", batch(|n| format!("{}.{}", n, n.reverse_bits()))),
+    ];
+    let mut group = c.benchmark_group("lex");
+
+    for (name, batch) in &batches {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                for line in batch {
+                    black_box(lex(&mut line.chars().peekable()).unwrap());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, numbers);
+criterion_main!(benches);
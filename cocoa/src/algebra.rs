@@ -0,0 +1,405 @@
+//! Evaluation of expressions over values other than floats, such as those of
+//! a tropical semiring or symbolic values, by folding their trees with the
+//! operations of an `Algebra`.
+
+use anyhow::{bail, Result};
+
+use crate::{
+    ast::{Expr, ExprKind},
+    error::spanned,
+    evaluator::Evaluator,
+    interpreter::Interpreter,
+    message::{Code, Message},
+    token::{FuncKind, OpKind},
+};
+
+/// The values an expression can be evaluated to and the operations on them
+/// that its operators and functions stand for.
+///
+/// Only addition, multiplication, exponentiation and calling functions are
+/// required. Subtraction is adding the negation, and every other operator,
+/// such as negation, division, `%` or `!`, is not supported unless it is
+/// provided.
+///
+/// # Examples
+/// ```
+/// use anyhow::{bail, Result};
+/// use cocoa::algebra::{eval, Algebra};
+///
+/// /// The min-plus semiring, in which shortest paths can be found.
+/// struct Tropical;
+///
+/// impl Algebra for Tropical {
+///     type Value = f64;
+///
+///     fn number(&self, n: f64) -> Result<f64> {
+///         Ok(n)
+///     }
+///
+///     fn add(&self, a: f64, b: f64) -> Result<f64> {
+///         Ok(a.min(b))
+///     }
+///
+///     fn mul(&self, a: f64, b: f64) -> Result<f64> {
+///         Ok(a + b)
+///     }
+///
+///     fn pow(&self, a: f64, b: f64) -> Result<f64> {
+///         Ok(a * b)
+///     }
+///
+///     fn apply_fn(&self, name: &str, _args: Vec<f64>) -> Result<f64> {
+///         bail!("unknown function '{}'", name)
+///     }
+/// }
+///
+/// // the shorter of going 3 then 4, or 5 then 1
+/// assert_eq!(6.0, eval(&Tropical, "3 * 4 + 5 * 1").unwrap());
+/// assert_eq!(6.0, eval(&Tropical, "2^3").unwrap());
+/// assert_eq!(
+///     "the algebra does not support '-'",
+///     eval(&Tropical, "3 - 1").unwrap_err().to_string()
+/// );
+/// ```
+pub trait Algebra {
+    /// The values expressions are evaluated to.
+    type Value;
+
+    /// Get the value of the number `n`, including named constants such as
+    /// `pi`.
+    fn number(&self, n: f64) -> Result<Self::Value>;
+
+    /// Get the value of the variable `name`.
+    ///
+    /// By default there are no variables.
+    fn variable(&self, name: &str) -> Result<Self::Value> {
+        bail!(Code::UnknownIdentifier.with(&[&name]))
+    }
+
+    /// Add `a` and `b`.
+    fn add(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value>;
+
+    /// Subtract `b` from `a`.
+    ///
+    /// By default this is adding the negation of `b`.
+    fn sub(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value> {
+        let b = self.neg(b)?;
+        self.add(a, b)
+    }
+
+    /// Multiply `a` by `b`.
+    fn mul(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value>;
+
+    /// Divide `a` by `b`.
+    ///
+    /// By default division is not supported.
+    fn div(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value> {
+        let _ = (a, b);
+        bail!(unsupported(OpKind::Slash))
+    }
+
+    /// Take the remainder of dividing `a` by `b`.
+    ///
+    /// By default remainders are not supported.
+    fn rem(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value> {
+        let _ = (a, b);
+        bail!(unsupported(OpKind::Modulo))
+    }
+
+    /// Raise `a` to the power of `b`.
+    fn pow(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value>;
+
+    /// Shift `a` left by `b` bits.
+    ///
+    /// By default shifts are not supported.
+    fn shl(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value> {
+        let _ = (a, b);
+        bail!(unsupported(OpKind::Shl))
+    }
+
+    /// Shift `a` right by `b` bits.
+    ///
+    /// By default shifts are not supported.
+    fn shr(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value> {
+        let _ = (a, b);
+        bail!(unsupported(OpKind::Shr))
+    }
+
+    /// Negate `a`.
+    ///
+    /// By default negation is not supported.
+    fn neg(&self, a: Self::Value) -> Result<Self::Value> {
+        let _ = a;
+        bail!(unsupported(OpKind::Minus))
+    }
+
+    /// Take the factorial of `a`.
+    ///
+    /// By default factorials are not supported.
+    fn factorial(&self, a: Self::Value) -> Result<Self::Value> {
+        let _ = a;
+        bail!(unsupported(OpKind::Factorial))
+    }
+
+    /// Call the function `name`, which may be builtin such as `sin` or not,
+    /// with `args`.
+    fn apply_fn(
+        &self,
+        name: &str,
+        args: Vec<Self::Value>,
+    ) -> Result<Self::Value>;
+}
+
+/// Numbers, with the builtin functions and the variables, constants and
+/// functions an `Evaluator` knows, e.g. to wrap in an algebra which logs
+/// each operation.
+///
+/// Operators are applied as they are in the expressions the evaluator
+/// evaluates, so its settings such as `set_nan_policy` apply to them as well
+/// as to the functions it calls.
+///
+/// # Examples
+/// ```
+/// use std::cell::RefCell;
+///
+/// use anyhow::Result;
+/// use cocoa::{algebra::{eval, Algebra}, evaluator::Evaluator};
+///
+/// /// Evaluates like an evaluator, recording every function called.
+/// struct Logged {
+///     evaluator: Evaluator,
+///     calls: RefCell<Vec<String>>,
+/// }
+///
+/// impl Algebra for Logged {
+///     type Value = f64;
+///
+///     fn number(&self, n: f64) -> Result<f64> {
+///         self.evaluator.number(n)
+///     }
+///
+///     fn variable(&self, name: &str) -> Result<f64> {
+///         // `Evaluator::variable` looks a variable up without failing
+///         Algebra::variable(&self.evaluator, name)
+///     }
+///
+///     fn add(&self, a: f64, b: f64) -> Result<f64> {
+///         self.evaluator.add(a, b)
+///     }
+///
+///     fn mul(&self, a: f64, b: f64) -> Result<f64> {
+///         self.evaluator.mul(a, b)
+///     }
+///
+///     fn pow(&self, a: f64, b: f64) -> Result<f64> {
+///         self.evaluator.pow(a, b)
+///     }
+///
+///     fn apply_fn(&self, name: &str, args: Vec<f64>) -> Result<f64> {
+///         self.calls.borrow_mut().push(format!("{}{:?}", name, args));
+///         self.evaluator.apply_fn(name, args)
+///     }
+/// }
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.set_variable("x", 16.0).unwrap();
+/// let logged = Logged { evaluator, calls: RefCell::new(vec![]) };
+///
+/// assert_eq!(6.0, eval(&logged, "sqrt(x) + max(1, 2)").unwrap());
+/// assert_eq!(vec!["sqrt[16.0]", "max[1.0, 2.0]"], *logged.calls.borrow());
+///
+/// let mut evaluator = Evaluator::new();
+/// assert_eq!(2.0, eval(&evaluator, "(8 - 2) / 3").unwrap());
+/// assert_eq!(9.0, eval(&evaluator, "7 % 4 + 3!").unwrap());
+/// assert_eq!(8.0, eval(&evaluator, "1 << 3").unwrap());
+/// assert!(eval(&evaluator, "1.5!").is_err());
+///
+/// evaluator.set_width(Some(8)).unwrap();
+/// assert_eq!(-128.0, eval(&evaluator, "127 + 1").unwrap());
+/// evaluator.set_strict_division(true);
+/// assert!(eval(&evaluator, "1 / 0").is_err());
+/// ```
+impl Algebra for Evaluator {
+    type Value = f64;
+
+    fn number(&self, n: f64) -> Result<f64> {
+        Ok(n)
+    }
+
+    fn variable(&self, name: &str) -> Result<f64> {
+        let name = self.alias(name).unwrap_or(name);
+
+        match self.constant(name).or_else(|| self.variable(name)) {
+            Some(n) => Ok(n),
+            None => bail!(Code::UnknownIdentifier.with(&[&name])),
+        }
+    }
+
+    fn add(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Plus, a, b)
+    }
+
+    fn sub(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Minus, a, b)
+    }
+
+    fn mul(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Star, a, b)
+    }
+
+    fn div(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Slash, a, b)
+    }
+
+    fn rem(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Modulo, a, b)
+    }
+
+    fn pow(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Caret, a, b)
+    }
+
+    fn shl(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Shl, a, b)
+    }
+
+    fn shr(&self, a: f64, b: f64) -> Result<f64> {
+        Interpreter::without_spans(self).infix(OpKind::Shr, a, b)
+    }
+
+    fn neg(&self, a: f64) -> Result<f64> {
+        Ok(-a)
+    }
+
+    fn factorial(&self, a: f64) -> Result<f64> {
+        Interpreter::without_spans(self).postfix(OpKind::Factorial, a)
+    }
+
+    fn apply_fn(&self, name: &str, args: Vec<f64>) -> Result<f64> {
+        let name = self.alias(name).unwrap_or(name);
+
+        if let Some(&f) = FuncKind::ALL.iter().find(|f| f.name() == name) {
+            return self.call(f, &args);
+        }
+
+        if let Some(template) = self.template(name) {
+            let args: Vec<Expr> = args
+                .into_iter()
+                .map(|n| Expr::new(ExprKind::Number(n)))
+                .collect();
+
+            return eval_expr(self, &template.expand(&args)?);
+        }
+
+        match self.function(name) {
+            Some(f) => f.call(name, &args),
+            None => bail!(Code::UnknownFunction.with(&[&name])),
+        }
+    }
+}
+
+/// Parse `input` and evaluate it over `algebra`.
+///
+/// # Arguments
+///
+/// * `algebra` - The values to evaluate to and the operations on them.
+/// * `input` - The expression to evaluate.
+///
+/// # Examples
+/// ```
+/// use anyhow::Result;
+/// use cocoa::algebra::{eval, Algebra};
+///
+/// /// Expressions written out in full, with every operation bracketed.
+/// struct Symbolic;
+///
+/// impl Algebra for Symbolic {
+///     type Value = String;
+///
+///     fn number(&self, n: f64) -> Result<String> {
+///         Ok(n.to_string())
+///     }
+///
+///     fn variable(&self, name: &str) -> Result<String> {
+///         Ok(name.to_string())
+///     }
+///
+///     fn add(&self, a: String, b: String) -> Result<String> {
+///         Ok(format!("({} + {})", a, b))
+///     }
+///
+///     fn mul(&self, a: String, b: String) -> Result<String> {
+///         Ok(format!("({} * {})", a, b))
+///     }
+///
+///     fn pow(&self, a: String, b: String) -> Result<String> {
+///         Ok(format!("({} ^ {})", a, b))
+///     }
+///
+///     fn neg(&self, a: String) -> Result<String> {
+///         Ok(format!("-{}", a))
+///     }
+///
+///     fn apply_fn(&self, name: &str, args: Vec<String>) -> Result<String> {
+///         Ok(format!("{}({})", name, args.join(", ")))
+///     }
+/// }
+///
+/// assert_eq!("((2 * x) + -sin(y))", eval(&Symbolic, "2 * x - sin(y)").unwrap());
+/// assert!(eval(&Symbolic, "x / 2").is_err());
+/// assert!(eval(&Symbolic, "[1, 2]").is_err());
+/// ```
+pub fn eval<A: Algebra + ?Sized>(algebra: &A, input: &str) -> Result<A::Value> {
+    eval_expr(algebra, &input.parse()?)
+}
+
+/// Evaluate the expression tree `expr` over `algebra`.
+///
+/// # Arguments
+///
+/// * `algebra` - The values to evaluate to and the operations on them.
+/// * `expr` - The expression to evaluate.
+pub fn eval_expr<A: Algebra + ?Sized>(
+    algebra: &A,
+    expr: &Expr,
+) -> Result<A::Value> {
+    let eval = |e: &Expr| eval_expr(algebra, e);
+
+    match &expr.kind {
+        ExprKind::Number(n) => algebra.number(*n),
+        ExprKind::Ident(name) => algebra.variable(name),
+        ExprKind::Unary(OpKind::Plus, e) | ExprKind::Group(e) => eval(e),
+        ExprKind::Unary(OpKind::Minus, e) => algebra.neg(eval(e)?),
+        ExprKind::Binary(op, lhs, rhs) => {
+            let (a, b) = (eval(lhs)?, eval(rhs)?);
+
+            match op {
+                OpKind::Plus => algebra.add(a, b),
+                OpKind::Minus => algebra.sub(a, b),
+                OpKind::Star => algebra.mul(a, b),
+                OpKind::Slash => algebra.div(a, b),
+                OpKind::Modulo => algebra.rem(a, b),
+                OpKind::Caret => algebra.pow(a, b),
+                OpKind::Shl => algebra.shl(a, b),
+                OpKind::Shr => algebra.shr(a, b),
+                &op => Err(spanned(expr.span, unsupported(op))),
+            }
+        }
+        ExprKind::Postfix(OpKind::Factorial, e) => algebra.factorial(eval(e)?),
+        ExprKind::Unary(op, _) | ExprKind::Postfix(op, _) => {
+            Err(spanned(expr.span, unsupported(*op)))
+        }
+        ExprKind::Call(callee, args) => {
+            let args = args.iter().map(eval).collect::<Result<_>>()?;
+
+            algebra.apply_fn(callee.name(), args)
+        }
+        ExprKind::Str(_) => Err(spanned(expr.span, Code::AlgebraString)),
+        ExprKind::List(_) => Err(spanned(expr.span, Code::AlgebraList)),
+    }
+}
+
+/// Describe the error for an operator that an algebra does not support.
+fn unsupported(op: OpKind) -> Message {
+    Code::AlgebraUnsupported.with(&[&op])
+}
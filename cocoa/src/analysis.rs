@@ -0,0 +1,459 @@
+//! Tools which answer questions about expressions rather than evaluating
+//! them, such as whether two expressions are the same, or where they differ.
+
+use anyhow::Result;
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    evaluator::Evaluator,
+    math::random::Rng,
+    token::FuncKind,
+    value::Value,
+};
+
+/// The number of points two expressions with variables are compared at.
+const SAMPLES: usize = 64;
+
+/// The most points drawn while looking for `SAMPLES` points at which two
+/// expressions are both defined.
+const MAX_DRAWS: usize = 4 * SAMPLES;
+
+/// The seed of the points expressions are compared at, which is fixed so that
+/// sampling is reproducible.
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// How close two values have to be to be considered the same, relative to the
+/// larger of their magnitudes, or absolutely for values smaller than 1.
+const TOLERANCE: f64 = 1e-9;
+
+/// The largest argument of a trigonometric function which `angle_hint`
+/// suspects of being in degrees.
+const MAX_DEGREES: f64 = 360.0;
+
+/// How confident `equivalent` is that two expressions are equivalent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// The expressions simplify to the same expression, or have no variables
+    /// and evaluate to the same value, so are certainly equivalent.
+    Equivalent,
+    /// The expressions agreed at every point they were compared at, though
+    /// they may differ elsewhere.
+    ProbablyEquivalent {
+        /// The number of points at which both expressions were defined, and
+        /// so actually compared, as a fraction of the number sought, from 0
+        /// to 1.
+        confidence: f64,
+    },
+    /// The expressions differ.
+    NotEquivalent {
+        /// The values of the variables at which the expressions differ.
+        point: Vec<(String, f64)>,
+    },
+}
+
+/// Check whether the expressions `a` and `b` are numerically equivalent.
+///
+/// Both expressions are first simplified, and are certainly equivalent if they
+/// simplify to the same expression. Otherwise they are evaluated at the same
+/// pseudo-random values of the variables they use, which are any names that
+/// are not constants, and are probably equivalent if their values agree
+/// everywhere. Points at which neither expression is defined, e.g. where both
+/// take the square root of a negative number, are skipped and another point is
+/// drawn in their place, while a point at which only one of them is defined is
+/// a difference.
+///
+/// The points are the same on every call, so the verdict for a given pair of
+/// expressions never changes.
+///
+/// # Arguments
+///
+/// * `a` - An expression.
+/// * `b` - The expression to compare `a` against.
+///
+/// # Examples
+/// ```
+/// use cocoa::analysis::{equivalent, Verdict};
+///
+/// assert_eq!(Verdict::Equivalent, equivalent("x + 0", "1 * x").unwrap());
+/// assert!(matches!(
+///     equivalent("(x + 1)^2", "x^2 + 2*x + 1").unwrap(),
+///     Verdict::ProbablyEquivalent { .. }
+/// ));
+/// assert!(matches!(
+///     equivalent("sqrt(x^2)", "x").unwrap(),
+///     Verdict::NotEquivalent { .. }
+/// ));
+///
+/// // neither is defined for negative x, which does not count against them
+/// assert_eq!(
+///     Verdict::ProbablyEquivalent { confidence: 1.0 },
+///     equivalent("sqrt(x)", "x^0.5").unwrap()
+/// );
+/// // only one is defined for negative x
+/// assert!(matches!(
+///     equivalent("sqrt(x)^2", "x").unwrap(),
+///     Verdict::NotEquivalent { .. }
+/// ));
+/// ```
+pub fn equivalent(a: &str, b: &str) -> Result<Verdict> {
+    let (expr_a, expr_b): (Expr, Expr) = (a.parse()?, b.parse()?);
+
+    if expr_a.simplify() == expr_b.simplify() {
+        return Ok(Verdict::Equivalent);
+    }
+
+    let mut evaluator = Evaluator::new();
+    let variables: Vec<String> = expr_a
+        .idents()
+        .union(&expr_b.idents())
+        .filter(|name| evaluator.constant(name).is_none())
+        .map(|name| name.to_string())
+        .collect();
+
+    let samples = if variables.is_empty() { 1 } else { SAMPLES };
+    let mut rng = Rng::new(SEED);
+    let mut compared = 0;
+    let mut first_error = None;
+
+    for i in 0..MAX_DRAWS {
+        if compared == samples {
+            break;
+        }
+
+        let point: Vec<(String, f64)> = variables
+            .iter()
+            .map(|name| (name.clone(), sample(&mut rng, i)))
+            .collect();
+
+        for (name, value) in &point {
+            evaluator.set_variable(name, *value)?;
+        }
+
+        let value_a = evaluator.eval(a);
+        let value_b = evaluator.eval(b);
+
+        match (defined(&value_a), defined(&value_b)) {
+            (Some(x), Some(y)) if close(x, y) => compared += 1,
+            // there is nothing to compare where neither is defined
+            (None, None) => {
+                first_error = first_error.or(value_a.err());
+            }
+            // either the values differ or only one of them is defined
+            _ => return Ok(Verdict::NotEquivalent { point }),
+        }
+    }
+
+    match (compared, first_error) {
+        // neither expression could be evaluated anywhere, which is most
+        // likely a mistake such as calling a function that does not exist
+        (0, Some(e)) => Err(e),
+        _ if variables.is_empty() => Ok(Verdict::Equivalent),
+        _ => Ok(Verdict::ProbablyEquivalent {
+            confidence: compared as f64 / samples as f64,
+        }),
+    }
+}
+
+/// A part of one expression which differs from another, found by
+/// `diff_exprs`.
+///
+/// Each subtree has the span of the input it was parsed from, if it was
+/// parsed, so that the change can be pointed at in either expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A subtree of the first expression which was replaced by a different
+    /// subtree in the second.
+    Replaced {
+        /// The subtree in the first expression.
+        before: Expr,
+        /// The subtree in the second expression.
+        after: Expr,
+    },
+    /// An element of a list or an argument in the first expression which is
+    /// not in the second.
+    Removed(Expr),
+    /// An element of a list or an argument in the second expression which is
+    /// not in the first.
+    Inserted(Expr),
+}
+
+/// Find the smallest subtrees which differ between the expressions `a` and
+/// `b`, in the order they appear in.
+///
+/// Both trees are walked together while their nodes have the same operator,
+/// or call the same function, and a node which differs is replaced as a
+/// whole. The elements of lists and the arguments of calls are lined up
+/// first, so that an element inserted into or removed from the middle of a
+/// list is a single change. Brackets are ignored, so the expressions are the
+/// same exactly when there are no changes.
+///
+/// # Arguments
+///
+/// * `a` - The first version of an expression.
+/// * `b` - The second version of the expression.
+///
+/// # Examples
+/// ```
+/// use cocoa::{
+///     analysis::{diff_exprs, Change},
+///     ast::Expr,
+///     token::Span,
+/// };
+///
+/// let diff = |a: &str, b: &str| {
+///     diff_exprs(&a.parse().unwrap(), &b.parse().unwrap())
+/// };
+///
+/// let changes = diff("2 * x + sin(y)", "2 * x + cos(y ^ 2)");
+/// match &changes[..] {
+///     [Change::Replaced { before, after }] => {
+///         assert_eq!("sin(y)", before.to_string());
+///         assert_eq!(Some(Span::new(8, 14)), before.span);
+///         assert_eq!("cos(y ^ 2)", after.to_string());
+///     }
+///     _ => panic!("expected one replacement, got {:?}", changes),
+/// }
+///
+/// let changes = diff("max(a, b, c)", "max(a, c, (d))");
+/// assert_eq!(2, changes.len());
+/// assert!(matches!(&changes[0], Change::Removed(e) if e.to_string() == "b"));
+/// assert!(matches!(&changes[1], Change::Inserted(e) if e.to_string() == "(d)"));
+///
+/// assert!(diff("(a + b) * c", "((a + b)) * c").is_empty());
+/// ```
+pub fn diff_exprs(a: &Expr, b: &Expr) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_into(a, b, &mut changes);
+
+    changes
+}
+
+/// Push the changes between `a` and `b` onto `changes`, like `diff_exprs`.
+fn diff_into(a: &Expr, b: &Expr, changes: &mut Vec<Change>) {
+    match (&a.kind, &b.kind) {
+        (ExprKind::Group(a), _) => diff_into(a, b, changes),
+        (_, ExprKind::Group(b)) => diff_into(a, b, changes),
+        (ExprKind::Unary(o1, e1), ExprKind::Unary(o2, e2))
+        | (ExprKind::Postfix(o1, e1), ExprKind::Postfix(o2, e2))
+            if o1 == o2 =>
+        {
+            diff_into(e1, e2, changes)
+        }
+        (ExprKind::Binary(o1, l1, r1), ExprKind::Binary(o2, l2, r2))
+            if o1 == o2 =>
+        {
+            diff_into(l1, l2, changes);
+            diff_into(r1, r2, changes);
+        }
+        (ExprKind::Call(c1, args1), ExprKind::Call(c2, args2)) if c1 == c2 => {
+            diff_all(args1, args2, changes)
+        }
+        (ExprKind::List(e1), ExprKind::List(e2)) => diff_all(e1, e2, changes),
+        _ if a == b => (),
+        _ => changes.push(Change::Replaced {
+            before: a.clone(),
+            after: b.clone(),
+        }),
+    }
+}
+
+/// Push the changes between the sequences of expressions `a` and `b` onto
+/// `changes`, lining up the expressions which are in both first.
+///
+/// Between those, the expressions which are left are paired up and compared
+/// in order, and any left over in either sequence were removed or inserted.
+fn diff_all(a: &[Expr], b: &[Expr], changes: &mut Vec<Change>) {
+    // the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(&a[i], &b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let (mut removed, mut inserted) = (vec![], vec![]);
+
+    // diff the expressions which are not in both, up to the next which is
+    let flush = |removed: &mut Vec<&Expr>,
+                 inserted: &mut Vec<&Expr>,
+                 changes: &mut Vec<Change>| {
+        let paired = removed.len().min(inserted.len());
+        for (a, b) in removed.iter().zip(inserted.iter()) {
+            diff_into(a, b, changes);
+        }
+
+        changes.extend(
+            removed.drain(..).skip(paired).cloned().map(Change::Removed),
+        );
+        changes.extend(
+            inserted
+                .drain(..)
+                .skip(paired)
+                .cloned()
+                .map(Change::Inserted),
+        );
+    };
+
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && same(&a[i], &b[j]) {
+            flush(&mut removed, &mut inserted, changes);
+            i += 1;
+            j += 1;
+        } else if j == b.len()
+            || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            removed.push(&a[i]);
+            i += 1;
+        } else {
+            inserted.push(&b[j]);
+            j += 1;
+        }
+    }
+
+    flush(&mut removed, &mut inserted, changes);
+}
+
+/// Check whether `a` and `b` are the same, ignoring brackets.
+fn same(a: &Expr, b: &Expr) -> bool {
+    let mut changes = vec![];
+    diff_into(a, b, &mut changes);
+
+    changes.is_empty()
+}
+
+/// Calls of trigonometric functions in an expression whose arguments look
+/// like they were meant in degrees, found by `angle_hint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AngleHint {
+    /// The calls whose arguments look like degrees, such as `sin(90)`, in
+    /// the order they appear in.
+    pub calls: Vec<Expr>,
+    /// The value of the whole expression with the arguments of those calls
+    /// taken as degrees.
+    pub in_degrees: Value,
+}
+
+/// Check whether `expr` calls `sin`, `cos` or `tan` with an argument which
+/// looks like it was meant in degrees, giving the value `expr` would have if
+/// it were.
+///
+/// Trigonometric functions take radians, so `sin(90)` is almost certainly
+/// meant to be `sin(rad(90))`. An argument is suspected of being in degrees
+/// if it evaluates to a whole multiple of 15 degrees of at most a full turn,
+/// such as 30, 45 or 180, which are rarely meant as radians.
+///
+/// # Arguments
+///
+/// * `expr` - The expression to check.
+/// * `evaluator` - The evaluator to evaluate the arguments and the
+///   expression with.
+///
+/// # Examples
+/// ```
+/// use cocoa::{analysis::angle_hint, evaluator::Evaluator, value::Value};
+///
+/// let evaluator = Evaluator::new();
+/// let hint = |input: &str| angle_hint(&input.parse().unwrap(), &evaluator);
+///
+/// let found = hint("2 * sin(90)").unwrap();
+/// assert_eq!("sin(90)", found.calls[0].to_string());
+/// assert_eq!(Value::Number(2.0), found.in_degrees);
+///
+/// let found = hint("cos(180) + tan(1)").unwrap();
+/// assert_eq!(1, found.calls.len());
+///
+/// assert_eq!(None, hint("sin(pi / 2)"));
+/// assert_eq!(None, hint("sin(rad(90))"));
+/// ```
+pub fn angle_hint(expr: &Expr, evaluator: &Evaluator) -> Option<AngleHint> {
+    let mut calls = vec![];
+    let in_degrees = to_degrees(expr, evaluator, &mut calls);
+
+    if calls.is_empty() {
+        return None;
+    }
+
+    let in_degrees = evaluator.eval_value(&in_degrees.to_string()).ok()?;
+    Some(AngleHint { calls, in_degrees })
+}
+
+/// Get `expr` with the arguments of its trigonometric calls which look like
+/// degrees converted to radians, pushing those calls onto `calls`.
+fn to_degrees(
+    expr: &Expr,
+    evaluator: &Evaluator,
+    calls: &mut Vec<Expr>,
+) -> Expr {
+    let mut convert = |e: &Expr| Box::new(to_degrees(e, evaluator, calls));
+
+    let kind = match &expr.kind {
+        ExprKind::Call(Callee::Builtin(f), args)
+            if matches!(f, FuncKind::Sin | FuncKind::Cos | FuncKind::Tan)
+                && args.len() == 1
+                && looks_like_degrees(&args[0], evaluator) =>
+        {
+            calls.push(expr.clone());
+            let arg = Expr::new(ExprKind::Call(
+                Callee::Builtin(FuncKind::Rad),
+                args.clone(),
+            ));
+
+            ExprKind::Call(Callee::Builtin(*f), vec![arg])
+        }
+        ExprKind::Unary(op, e) => ExprKind::Unary(*op, convert(e)),
+        ExprKind::Postfix(op, e) => ExprKind::Postfix(*op, convert(e)),
+        ExprKind::Group(e) => ExprKind::Group(convert(e)),
+        ExprKind::Binary(op, lhs, rhs) => {
+            ExprKind::Binary(*op, convert(lhs), convert(rhs))
+        }
+        ExprKind::Call(callee, args) => ExprKind::Call(
+            callee.clone(),
+            args.iter().map(|arg| *convert(arg)).collect(),
+        ),
+        ExprKind::List(elements) => {
+            ExprKind::List(elements.iter().map(|e| *convert(e)).collect())
+        }
+        kind => kind.clone(),
+    };
+
+    Expr::spanned(kind, expr.span)
+}
+
+/// Check whether the argument `arg` of a trigonometric function looks like
+/// it was meant in degrees.
+fn looks_like_degrees(arg: &Expr, evaluator: &Evaluator) -> bool {
+    match evaluator.eval(&arg.to_string()) {
+        Ok(n) => n != 0.0 && n.abs() <= MAX_DEGREES && n % 15.0 == 0.0,
+        Err(_) => false,
+    }
+}
+
+/// Get the result of an evaluation if it is defined, i.e. it succeeded and is
+/// not NaN.
+fn defined(result: &Result<f64>) -> Option<f64> {
+    result.as_ref().ok().copied().filter(|n| !n.is_nan())
+}
+
+/// Check whether `x` and `y` are the same up to floating point error.
+fn close(x: f64, y: f64) -> bool {
+    x == y || (x - y).abs() <= TOLERANCE * x.abs().max(y.abs()).max(1.0)
+}
+
+/// Get a value to evaluate expressions at for the `i`th sample, cycling
+/// through different magnitudes and integers so that expressions which only
+/// differ in some of these are told apart.
+fn sample(rng: &mut Rng, i: usize) -> f64 {
+    let mut uniform = |max: f64| max * (2.0 * rng.next_f64() - 1.0);
+
+    match i % 4 {
+        0 => uniform(10.0),
+        1 => uniform(1.0),
+        2 => uniform(20.0).round(),
+        _ => uniform(1000.0),
+    }
+}
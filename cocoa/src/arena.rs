@@ -0,0 +1,376 @@
+//! An arena of expression nodes, which holds a whole tree in a few flat
+//! vectors rather than allocating each node separately like `ast::Expr`.
+
+use std::ops::Index;
+
+use anyhow::Result;
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    lexer::lex_spanned,
+    parser::{expect_end, parse_into},
+    token::{FuncKind, OpKind, Span},
+};
+
+/// The index of a node in an `ExprArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+/// A run of nodes in an `ExprArena`, such as the arguments of a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Children {
+    start: u32,
+    len: u32,
+}
+
+/// A piece of text held by an `ExprArena`, such as the name of a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Text {
+    start: u32,
+    len: u32,
+}
+
+/// A node of an expression tree held by an `ExprArena`, along with the span
+/// of the input it was parsed from, if known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Node {
+    /// What the expression is.
+    pub kind: NodeKind,
+    /// The part of the input the expression was parsed from.
+    pub span: Option<Span>,
+}
+
+/// The different kinds of nodes, which mirror `ast::ExprKind` but refer to
+/// other nodes and text by their place in the arena.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    /// A number.
+    Number(f64),
+    /// A name that is not called, such as a constant or a variable.
+    Ident(Text),
+    /// A string, such as the name passed to `env`.
+    Str(Text),
+    /// A prefix operator applied to an expression, i.e. unary plus or minus.
+    Unary(OpKind, NodeId),
+    /// A postfix operator applied to an expression, i.e. factorial.
+    Postfix(OpKind, NodeId),
+    /// An infix operator applied to two expressions.
+    Binary(OpKind, NodeId, NodeId),
+    /// A function called with some arguments.
+    Call(NodeCallee, Children),
+    /// An expression in brackets.
+    Group(NodeId),
+    /// A list of expressions in square brackets.
+    List(Children),
+}
+
+/// A function that is called in a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeCallee {
+    /// A builtin function.
+    Builtin(FuncKind),
+    /// A function which is not builtin, such as one provided by a plugin.
+    Named(Text),
+}
+
+/// Expression trees stored as nodes in a flat vector, which refer to each
+/// other by index.
+///
+/// Parsing into an arena does not allocate for each node, and since nodes
+/// own nothing, dropping or clearing an arena takes the same time however
+/// large the trees in it are. An arena can be cleared and reused to parse
+/// many expressions without allocating for each of them.
+///
+/// # Examples
+/// ```
+/// use cocoa::{arena::{ExprArena, NodeKind}, token::OpKind};
+///
+/// let mut arena = ExprArena::new();
+/// let root = arena.parse("x + f(1, 2)").unwrap();
+///
+/// let NodeKind::Binary(OpKind::Plus, lhs, rhs) = arena[root].kind else {
+///     panic!("expected a sum");
+/// };
+/// assert!(matches!(arena[lhs].kind, NodeKind::Ident(name) if arena.text(name) == "x"));
+/// assert!(matches!(arena[rhs].kind, NodeKind::Call(_, args) if arena.children(args).len() == 2));
+///
+/// assert_eq!("x + f(1, 2)", arena.to_expr(root).to_string());
+/// assert_eq!(5, arena.len());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ExprArena {
+    nodes: Vec<Node>,
+    /// The children of every call and list, each run stored contiguously.
+    children: Vec<NodeId>,
+    /// The names and strings of every node, one after another.
+    text: String,
+    /// Children which have been parsed but not yet stored as a run, since
+    /// the children of an outer call are interleaved with those of calls in
+    /// its arguments until each call is finished.
+    pending: Vec<NodeId>,
+}
+
+impl ExprArena {
+    /// Create an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the whole expression `input` into the arena, returning the
+    /// root of its tree.
+    ///
+    /// Nodes of trees parsed earlier are kept, so several expressions can
+    /// share an arena. If parsing fails, the arena may hold some nodes of
+    /// the expression which are not part of any tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The expression to parse.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::arena::ExprArena;
+    ///
+    /// let mut arena = ExprArena::new();
+    /// let a = arena.parse("1 + 2").unwrap();
+    /// let b = arena.parse("sqrt(x)").unwrap();
+    ///
+    /// assert_eq!("1 + 2", arena.to_expr(a).to_string());
+    /// assert_eq!("sqrt(x)", arena.to_expr(b).to_string());
+    /// assert!(arena.parse("1 + 2)").is_err());
+    /// ```
+    pub fn parse(&mut self, input: &str) -> Result<NodeId> {
+        let mut tokens = lex_spanned(&mut input.chars().peekable())?
+            .into_iter()
+            .peekable();
+
+        let root = parse_into(self, &mut tokens, 0)?;
+        expect_end(&mut tokens)?;
+
+        Ok(root)
+    }
+
+    /// Remove every node from the arena, keeping the memory it has allocated
+    /// so that it can be reused.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::arena::ExprArena;
+    ///
+    /// let mut arena = ExprArena::new();
+    /// arena.parse("a * b").unwrap();
+    /// arena.clear();
+    ///
+    /// assert!(arena.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.children.clear();
+        self.text.clear();
+        self.pending.clear();
+    }
+
+    /// Get the number of nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check whether the arena has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get the nodes in a run of children, in order.
+    pub fn children(&self, children: Children) -> &[NodeId] {
+        let start = children.start as usize;
+
+        &self.children[start..start + children.len as usize]
+    }
+
+    /// Get the name or string a piece of text refers to.
+    pub fn text(&self, text: Text) -> &str {
+        let start = text.start as usize;
+
+        &self.text[start..start + text.len as usize]
+    }
+
+    /// Get the number of nodes on the longest path from `id` to a leaf of
+    /// its tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The root of the tree to measure.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::arena::ExprArena;
+    ///
+    /// let mut arena = ExprArena::new();
+    /// let root = arena.parse("1 + sqrt(2 * x)").unwrap();
+    ///
+    /// assert_eq!(4, arena.depth(root));
+    /// ```
+    pub fn depth(&self, id: NodeId) -> usize {
+        // children are always added before their parents, so the depth of
+        // each node can be found from those before it without recursing
+        let mut depths: Vec<usize> = Vec::with_capacity(id.0 as usize + 1);
+
+        for node in &self.nodes[..=id.0 as usize] {
+            let depth = |id: NodeId| depths[id.0 as usize];
+            let deepest = |children| {
+                self.children(children)
+                    .iter()
+                    .map(|&id| depth(id))
+                    .max()
+                    .unwrap_or(0)
+            };
+
+            let below = match node.kind {
+                NodeKind::Number(_) | NodeKind::Ident(_) | NodeKind::Str(_) => {
+                    0
+                }
+                NodeKind::Unary(_, e)
+                | NodeKind::Postfix(_, e)
+                | NodeKind::Group(e) => depth(e),
+                NodeKind::Binary(_, lhs, rhs) => depth(lhs).max(depth(rhs)),
+                NodeKind::Call(_, children) | NodeKind::List(children) => {
+                    deepest(children)
+                }
+            };
+            depths.push(below + 1);
+        }
+
+        depths[id.0 as usize]
+    }
+
+    /// Convert the tree rooted at `id` into an `Expr`, which can then be
+    /// simplified or formatted.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The root of the tree to convert.
+    pub fn to_expr(&self, id: NodeId) -> Expr {
+        // long chains of operators such as `1 + 1 + ... + 1` are built from
+        // the start of the chain rather than by descending into them, so
+        // that they cannot overflow the stack
+        let mut chain = vec![];
+        let mut first = id;
+        while let NodeKind::Binary(_, lhs, _) = self[first].kind {
+            chain.push(first);
+            first = lhs;
+        }
+        if first != id {
+            let start = self.to_expr(first);
+
+            return chain.into_iter().rev().fold(start, |lhs, id| {
+                let node = self[id];
+                let NodeKind::Binary(op, _, rhs) = node.kind else {
+                    unreachable!("the chain only has binary operators")
+                };
+                let rhs = self.to_expr(rhs);
+
+                Expr::spanned(
+                    ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+                    node.span,
+                )
+            });
+        }
+
+        let node = self[id];
+        let exprs = |children| {
+            self.children(children).iter().map(|&id| self.to_expr(id))
+        };
+
+        let kind = match node.kind {
+            NodeKind::Number(n) => ExprKind::Number(n),
+            NodeKind::Ident(name) => ExprKind::Ident(self.text(name).into()),
+            NodeKind::Str(s) => ExprKind::Str(self.text(s).into()),
+            NodeKind::Unary(op, e) => {
+                ExprKind::Unary(op, Box::new(self.to_expr(e)))
+            }
+            NodeKind::Postfix(op, e) => {
+                ExprKind::Postfix(op, Box::new(self.to_expr(e)))
+            }
+            NodeKind::Binary(op, lhs, rhs) => ExprKind::Binary(
+                op,
+                Box::new(self.to_expr(lhs)),
+                Box::new(self.to_expr(rhs)),
+            ),
+            NodeKind::Call(callee, args) => {
+                let callee = match callee {
+                    NodeCallee::Builtin(f) => Callee::Builtin(f),
+                    NodeCallee::Named(name) => {
+                        Callee::Named(self.text(name).into())
+                    }
+                };
+
+                ExprKind::Call(callee, exprs(args).collect())
+            }
+            NodeKind::Group(e) => ExprKind::Group(Box::new(self.to_expr(e))),
+            NodeKind::List(elements) => {
+                ExprKind::List(exprs(elements).collect())
+            }
+        };
+
+        Expr::spanned(kind, node.span)
+    }
+
+    /// Add a node to the arena, returning its index.
+    pub(crate) fn push(
+        &mut self,
+        kind: NodeKind,
+        span: Option<Span>,
+    ) -> NodeId {
+        let id = NodeId(index(self.nodes.len()));
+        self.nodes.push(Node { kind, span });
+
+        id
+    }
+
+    /// Add `s` to the text of the arena.
+    pub(crate) fn push_text(&mut self, s: &str) -> Text {
+        let start = index(self.text.len());
+        self.text.push_str(s);
+
+        Text {
+            start,
+            len: index(s.len()),
+        }
+    }
+
+    /// Hold on to `id` until it is stored in a run of children by
+    /// `finish_children`.
+    pub(crate) fn push_pending(&mut self, id: NodeId) {
+        self.pending.push(id);
+    }
+
+    /// Get the number of children that are pending, which marks where the
+    /// children of a call or list that is about to be parsed start.
+    pub(crate) fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Store the children that have been pending since `mark` as a run.
+    pub(crate) fn finish_children(&mut self, mark: usize) -> Children {
+        let start = index(self.children.len());
+        self.children.extend(self.pending.drain(mark..));
+
+        Children {
+            start,
+            len: index(self.children.len()) - start,
+        }
+    }
+}
+
+impl Index<NodeId> for ExprArena {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+}
+
+/// Convert an index into the arena to the width it is stored with.
+fn index(i: usize) -> u32 {
+    u32::try_from(i).expect("arenas are indexed with 32 bits")
+}
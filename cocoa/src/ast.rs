@@ -0,0 +1,900 @@
+//! A tree representation of expressions, which every expression is parsed into
+//! before it is evaluated, and which can be inspected and transformed.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f64::consts::PI,
+    fmt,
+    str::FromStr,
+};
+
+use anyhow::{Error, Result};
+
+use crate::{
+    evaluator::Evaluator,
+    interpreter::apply_op,
+    lexer::lex_spanned,
+    math::factorial,
+    parser::{expect_end, parse_expr},
+    token::{Bindable, FuncKind, OpKind, Span, Token},
+};
+
+/// The binding power of unary plus and minus, which is 15 more than their
+/// infix binding power.
+pub(crate) const UNARY_BP: u8 = 20;
+
+/// A node of an expression tree, along with the span of the input it was
+/// parsed from, if known.
+///
+/// Two expressions are equal if they have the same structure, regardless of
+/// their spans.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    /// What the expression is.
+    pub kind: ExprKind,
+    /// The part of the input the expression was parsed from.
+    pub span: Option<Span>,
+}
+
+/// The different kinds of expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// A number.
+    Number(f64),
+    /// A name that is not called, such as a constant or a variable.
+    Ident(String),
+    /// A string, such as the name passed to `env`.
+    Str(String),
+    /// A prefix operator applied to an expression, i.e. unary plus or minus.
+    Unary(OpKind, Box<Expr>),
+    /// A postfix operator applied to an expression, i.e. factorial.
+    Postfix(OpKind, Box<Expr>),
+    /// An infix operator applied to two expressions.
+    Binary(OpKind, Box<Expr>, Box<Expr>),
+    /// A function called with some arguments.
+    Call(Callee, Vec<Expr>),
+    /// An expression in brackets.
+    Group(Box<Expr>),
+    /// A list of expressions in square brackets.
+    List(Vec<Expr>),
+}
+
+/// A function that is called in an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callee {
+    /// A builtin function.
+    Builtin(FuncKind),
+    /// A function which is not builtin, such as one provided by a plugin.
+    Named(String),
+}
+
+impl Callee {
+    /// Get the name the function is referred to by in expressions.
+    pub fn name(&self) -> &str {
+        match self {
+            Callee::Builtin(f) => f.name(),
+            Callee::Named(name) => name,
+        }
+    }
+}
+
+/// Statistics about the size and makeup of an expression, see
+/// `Expr::metrics`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of nodes in the tree, counting brackets as nodes.
+    pub nodes: usize,
+    /// The number of nodes on the longest path from the root to a leaf.
+    pub depth: usize,
+    /// How many times each operator is used, where unary and binary minus
+    /// are both counted as `OpKind::Minus`.
+    pub operators: BTreeMap<OpKind, usize>,
+    /// How many times each function is called, by name.
+    pub functions: BTreeMap<String, usize>,
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Expr {
+    /// Create an expression without a span.
+    pub fn new(kind: ExprKind) -> Self {
+        Self { kind, span: None }
+    }
+
+    /// Create an expression parsed from `span`.
+    pub fn spanned(kind: ExprKind, span: Option<Span>) -> Self {
+        Self { kind, span }
+    }
+
+    /// Drop `self` without descending into long chains of operators such as
+    /// `1 + 1 + ... + 1`, which dropping it as usual does once for each
+    /// operator, so that they cannot overflow the stack.
+    pub(crate) fn dismantle(mut self) {
+        while let ExprKind::Binary(_, lhs, _) = self.kind {
+            self = *lhs;
+        }
+    }
+
+    /// Get the names in `self` which are not called, such as constants and
+    /// variables, in alphabetical order.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let expr: Expr = "x^2 + sin(y) * x".parse().unwrap();
+    ///
+    /// assert_eq!(vec!["x", "y"], expr.idents().into_iter().collect::<Vec<_>>());
+    /// ```
+    pub fn idents(&self) -> BTreeSet<&str> {
+        let mut idents = BTreeSet::new();
+        self.collect_idents(&mut idents);
+
+        idents
+    }
+
+    fn collect_idents<'a>(&'a self, idents: &mut BTreeSet<&'a str>) {
+        match &self.kind {
+            ExprKind::Number(_) | ExprKind::Str(_) => (),
+            ExprKind::Ident(name) => {
+                idents.insert(name);
+            }
+            ExprKind::Unary(_, e) | ExprKind::Postfix(_, e) => {
+                e.collect_idents(idents)
+            }
+            ExprKind::Group(e) => e.collect_idents(idents),
+            ExprKind::Binary(_, lhs, rhs) => {
+                lhs.collect_idents(idents);
+                rhs.collect_idents(idents);
+            }
+            ExprKind::Call(_, args) | ExprKind::List(args) => {
+                args.iter().for_each(|arg| arg.collect_idents(idents))
+            }
+        }
+    }
+
+    /// Measure the size and makeup of `self`, e.g. to limit how complex
+    /// expressions entered by users may be.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::Expr, token::OpKind};
+    ///
+    /// let expr: Expr = "-x^2 + sin(x) * sin(2)".parse().unwrap();
+    /// let metrics = expr.metrics();
+    ///
+    /// assert_eq!(10, metrics.nodes);
+    /// assert_eq!(4, metrics.depth);
+    /// assert_eq!(Some(&1), metrics.operators.get(&OpKind::Minus));
+    /// assert_eq!(Some(&2), metrics.functions.get("sin"));
+    /// assert_eq!(4, metrics.operators.values().sum::<usize>());
+    /// ```
+    pub fn metrics(&self) -> Metrics {
+        let mut metrics = Metrics::default();
+        metrics.depth = self.collect_metrics(&mut metrics);
+
+        metrics
+    }
+
+    /// Add the nodes of `self` to `metrics`, returning the depth of `self`.
+    fn collect_metrics(&self, metrics: &mut Metrics) -> usize {
+        metrics.nodes += 1;
+
+        let depth = match &self.kind {
+            ExprKind::Number(_) | ExprKind::Ident(_) | ExprKind::Str(_) => 0,
+            ExprKind::Unary(op, e) | ExprKind::Postfix(op, e) => {
+                *metrics.operators.entry(*op).or_default() += 1;
+                e.collect_metrics(metrics)
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                *metrics.operators.entry(*op).or_default() += 1;
+                lhs.collect_metrics(metrics)
+                    .max(rhs.collect_metrics(metrics))
+            }
+            ExprKind::Call(callee, args) => {
+                *metrics
+                    .functions
+                    .entry(callee.name().to_string())
+                    .or_default() += 1;
+                args.iter()
+                    .map(|arg| arg.collect_metrics(metrics))
+                    .max()
+                    .unwrap_or(0)
+            }
+            ExprKind::Group(e) => e.collect_metrics(metrics),
+            ExprKind::List(elements) => elements
+                .iter()
+                .map(|e| e.collect_metrics(metrics))
+                .max()
+                .unwrap_or(0),
+        };
+
+        depth + 1
+    }
+
+    /// Evaluate `self` at each of `xs` as the value of the variable `var`,
+    /// returning the results in the same order, with an evaluator made by
+    /// `Evaluator::new`, see `Evaluator::eval_many` for evaluating it with
+    /// other settings, variables or functions.
+    ///
+    /// The expression is compiled once into a program for a stack machine,
+    /// which is then run for each value into a preallocated vector, making
+    /// this much quicker than evaluating it value by value, e.g. to sample a
+    /// curve for a plot.
+    ///
+    /// Results which cannot be evaluated, such as `sqrt(x)` for negative x,
+    /// or `x + y` since `y` is not defined, are NaN.
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - The name of the variable.
+    /// * `xs` - The values of the variable to evaluate the expression at.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let expr: Expr = "x^2 - 2*x + max(x, 1)".parse().unwrap();
+    /// assert_eq!(vec![1.0, 0.0, 2.0, 6.0], expr.eval_many("x", &[0.0, 1.0, 2.0, 3.0]));
+    ///
+    /// let ys = "sqrt(x)".parse::<Expr>().unwrap().eval_many("x", &[-1.0, 4.0]);
+    /// assert!(ys[0].is_nan());
+    /// assert_eq!(2.0, ys[1]);
+    ///
+    /// let ys = "x + y".parse::<Expr>().unwrap().eval_many("x", &[1.0, 2.0]);
+    /// assert!(ys.iter().all(|y| y.is_nan()));
+    /// ```
+    pub fn eval_many(&self, var: &str, xs: &[f64]) -> Vec<f64> {
+        Evaluator::new()
+            .eval_many(self, var, xs)
+            .expect("evaluators without limits never exceed them")
+    }
+
+    /// Evaluate `self` to a number with an evaluator made by
+    /// `Evaluator::new`, which has the default settings and the templates of
+    /// the prelude, see `Evaluator::eval_expr` for evaluating it with other
+    /// settings, variables or functions.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let expr: Expr = "2 + 3 * 4".parse().unwrap();
+    /// assert_eq!(14.0, expr.eval().unwrap());
+    /// assert_eq!(14.0, expr.simplify().eval().unwrap());
+    /// assert_eq!(1.0, "sec(0)".parse::<Expr>().unwrap().eval().unwrap());
+    ///
+    /// assert!("sqrt(-1) + x".parse::<Expr>().unwrap().eval().is_err());
+    /// assert!("[1, 2]".parse::<Expr>().unwrap().eval().is_err());
+    /// ```
+    pub fn eval(&self) -> Result<f64> {
+        Evaluator::new().eval_expr(self)?.into_number()
+    }
+
+    /// Simplify `self` by folding constant subexpressions and applying
+    /// algebraic identities such as `x + 0 = x` and `x * 1 = x`.
+    ///
+    /// Constants are folded using the default settings of an `Evaluator`,
+    /// and only when the result is a finite number, so that errors are left
+    /// to be reported when the expression is evaluated. Identities such as
+    /// `x * 0 = 0` hold for every finite x, but not for infinity or NaN.
+    ///
+    /// Brackets are dropped, since formatting an expression adds back any
+    /// that are needed.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let simplify = |s: &str| s.parse::<Expr>().unwrap().simplify().to_string();
+    ///
+    /// assert_eq!("x + 6", simplify("x + 2 * 3"));
+    /// assert_eq!("x", simplify("(x * 1) + 0"));
+    /// assert_eq!("0", simplify("y - y"));
+    /// assert_eq!("-x ^ 2", simplify("-(--x^2)"));
+    /// assert_eq!("(a + b) * c", simplify("(a + b) * c"));
+    /// assert_eq!("pi * r", simplify("pi * r * 1"));
+    /// ```
+    pub fn simplify(&self) -> Expr {
+        let kind = match &self.kind {
+            ExprKind::Number(_) | ExprKind::Ident(_) | ExprKind::Str(_) => {
+                self.kind.clone()
+            }
+            ExprKind::Group(e) => return e.simplify(),
+            ExprKind::Unary(op, e) => {
+                let e = e.simplify();
+
+                match (op, e.kind) {
+                    (OpKind::Plus, kind) => kind,
+                    (_, ExprKind::Number(n)) => ExprKind::Number(-n),
+                    // double negation
+                    (_, ExprKind::Unary(OpKind::Minus, inner)) => inner.kind,
+                    (_, kind) => ExprKind::Unary(
+                        *op,
+                        Box::new(Expr::spanned(kind, e.span)),
+                    ),
+                }
+            }
+            ExprKind::Postfix(op, e) => {
+                let e = e.simplify();
+
+                match e.kind {
+                    ExprKind::Number(n)
+                        if n >= 0.0 && n.fract() == 0.0 && n <= 170.0 =>
+                    {
+                        // casting is safe since n is an integer in [0, 170]
+                        ExprKind::Number(factorial(n as u64))
+                    }
+                    _ => ExprKind::Postfix(*op, Box::new(e)),
+                }
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                simplify_binary(*op, lhs.simplify(), rhs.simplify())
+            }
+            ExprKind::Call(callee, args) => {
+                let args: Vec<Expr> = args.iter().map(Expr::simplify).collect();
+                let numbers: Option<Vec<f64>> =
+                    args.iter().map(Expr::number).collect();
+
+                let folded = match (callee, numbers) {
+                    // functions of lists may return lists, which cannot be
+                    // folded into a number
+                    (Callee::Builtin(f), Some(_)) if f.is_list_function() => {
+                        None
+                    }
+                    (Callee::Builtin(f), Some(numbers)) => {
+                        f.call(&numbers).ok().filter(|n| n.is_finite())
+                    }
+                    _ => None,
+                };
+
+                match folded {
+                    Some(n) => ExprKind::Number(n),
+                    None => ExprKind::Call(callee.clone(), args),
+                }
+            }
+            ExprKind::List(elements) => {
+                ExprKind::List(elements.iter().map(Expr::simplify).collect())
+            }
+        };
+
+        Expr::spanned(kind, self.span)
+    }
+
+    /// Normalize `self` into a canonical form, so that formulas which differ
+    /// only in the order of the operands of `+` and `*`, in how they are
+    /// bracketed, or in constants which can be folded normalize to the same
+    /// tree.
+    ///
+    /// This simplifies `self` like `simplify`, then puts the operands of
+    /// each chain of additions or multiplications in order, with their
+    /// constants folded into a single number at the end of a sum or the start
+    /// of a product. The normalized expression has the same value as `self`,
+    /// except that reordering the operands of floats may round differently.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let normalize = |s: &str| s.parse::<Expr>().unwrap().normalize();
+    ///
+    /// assert_eq!("a + b + c", normalize("c + (b + a)").to_string());
+    /// assert_eq!("x + 5", normalize("2 + x + 3").to_string());
+    /// assert_eq!("6 * x * y", normalize("y * 2 * x * 3").to_string());
+    /// assert_eq!(normalize("(a + b) * c"), normalize("c * (b + a)"));
+    /// assert_eq!(normalize("0"), normalize("(a + b) - (b + a)"));
+    /// assert_ne!(normalize("a - b"), normalize("b - a"));
+    /// ```
+    pub fn normalize(&self) -> Expr {
+        let mut expr = self.simplify().sorted();
+
+        // putting operands in order may let more be simplified, such as
+        // `(a + b) - (b + a)`, which may in turn let more be put in order
+        loop {
+            let next = expr.simplify().sorted();
+            if next == expr {
+                return next;
+            }
+
+            expr = next;
+        }
+    }
+
+    /// Check whether `self` and `other` have the same structure, where the
+    /// order of the operands of `+` and `*` does not matter, e.g. for
+    /// checking an answer against a reference expression.
+    ///
+    /// Chains of additions or multiplications are compared as a whole, so
+    /// `a + b + c` has the same structure as `c + (b + a)`, and brackets are
+    /// ignored. Nothing is simplified, so unlike their normalized forms,
+    /// `x + 2 * 3` and `x + 6` have different structures.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The expression to compare `self` with.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let same = |a: &str, b: &str| {
+    ///     a.parse::<Expr>()
+    ///         .unwrap()
+    ///         .equivalent_structure(&b.parse().unwrap())
+    /// };
+    ///
+    /// assert!(same("a + b", "b + a"));
+    /// assert!(same("sin(x) * (y + 1)", "(1 + y) * sin(x)"));
+    /// assert!(same("a + b + c", "c + (b + a)"));
+    /// assert!(!same("a - b", "b - a"));
+    /// assert!(!same("a + b * c", "(a + b) * c"));
+    /// assert!(!same("x + 2 * 3", "x + 6"));
+    /// ```
+    pub fn equivalent_structure(&self, other: &Expr) -> bool {
+        match (&self.kind, &other.kind) {
+            (ExprKind::Group(e), _) => e.equivalent_structure(other),
+            (_, ExprKind::Group(e)) => self.equivalent_structure(e),
+            (
+                ExprKind::Binary(op @ (OpKind::Plus | OpKind::Star), _, _),
+                ExprKind::Binary(o, _, _),
+            ) if op == o => {
+                let (mut lhs, mut rhs) = (vec![], vec![]);
+                self.operands(*op, &mut lhs);
+                other.operands(*op, &mut rhs);
+
+                // pair off each operand with one of the same structure
+                lhs.len() == rhs.len()
+                    && lhs.iter().all(|l| {
+                        match rhs.iter().position(|r| l.equivalent_structure(r))
+                        {
+                            Some(i) => {
+                                rhs.swap_remove(i);
+                                true
+                            }
+                            None => false,
+                        }
+                    })
+            }
+            (ExprKind::Unary(o1, e1), ExprKind::Unary(o2, e2))
+            | (ExprKind::Postfix(o1, e1), ExprKind::Postfix(o2, e2)) => {
+                o1 == o2 && e1.equivalent_structure(e2)
+            }
+            (
+                ExprKind::Binary(o1, lhs1, rhs1),
+                ExprKind::Binary(o2, lhs2, rhs2),
+            ) => {
+                o1 == o2
+                    && lhs1.equivalent_structure(lhs2)
+                    && rhs1.equivalent_structure(rhs2)
+            }
+            (ExprKind::Call(c1, args1), ExprKind::Call(c2, args2)) => {
+                c1 == c2 && all_equivalent(args1, args2)
+            }
+            (ExprKind::List(e1), ExprKind::List(e2)) => all_equivalent(e1, e2),
+            _ => self == other,
+        }
+    }
+
+    /// Hash `self` once normalized, so that expressions which normalize to
+    /// the same tree have the same hash.
+    ///
+    /// Unlike `std::hash::Hash`, the hash is the same on every platform and
+    /// in every run of every program, so it can be stored in caches and
+    /// databases to look up formulas by. It is the 64 bit FNV-1a hash of the
+    /// normalized expression's formatted text.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::ast::Expr;
+    ///
+    /// let hash = |s: &str| s.parse::<Expr>().unwrap().stable_hash();
+    ///
+    /// assert_eq!(hash("x * (y + 1)"), hash("(1 + y) * x"));
+    /// assert_ne!(hash("x * (y + 1)"), hash("x * (y + 2)"));
+    /// assert_eq!(0xccce_50f2_5189_8ad8, hash("2 * pi * r"));
+    /// ```
+    pub fn stable_hash(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        self.normalize()
+            .to_string()
+            .bytes()
+            .fold(OFFSET, |hash, b| (hash ^ u64::from(b)).wrapping_mul(PRIME))
+    }
+
+    /// Put the operands of each chain of additions or multiplications in
+    /// `self` in order, folding their constants, where `self` is simplified.
+    fn sorted(&self) -> Expr {
+        let kind = match &self.kind {
+            ExprKind::Number(_) | ExprKind::Ident(_) | ExprKind::Str(_) => {
+                self.kind.clone()
+            }
+            ExprKind::Binary(op @ (OpKind::Plus | OpKind::Star), _, _) => {
+                let mut operands = vec![];
+                self.operands(*op, &mut operands);
+
+                let operands = operands.into_iter().map(Expr::sorted).collect();
+                return sort_operands(*op, operands);
+            }
+            ExprKind::Unary(op, e) => {
+                ExprKind::Unary(*op, Box::new(e.sorted()))
+            }
+            ExprKind::Postfix(op, e) => {
+                ExprKind::Postfix(*op, Box::new(e.sorted()))
+            }
+            ExprKind::Binary(op, lhs, rhs) => ExprKind::Binary(
+                *op,
+                Box::new(lhs.sorted()),
+                Box::new(rhs.sorted()),
+            ),
+            ExprKind::Call(callee, args) => ExprKind::Call(
+                callee.clone(),
+                args.iter().map(Expr::sorted).collect(),
+            ),
+            ExprKind::Group(e) => return e.sorted(),
+            ExprKind::List(elements) => {
+                ExprKind::List(elements.iter().map(Expr::sorted).collect())
+            }
+        };
+
+        Expr::spanned(kind, self.span)
+    }
+
+    /// Push the operands of the chain of `op` at the root of `self` onto
+    /// `operands`, looking through brackets, e.g. `a`, `b` and `c` for
+    /// `a + (b + c)`.
+    fn operands<'a>(&'a self, op: OpKind, operands: &mut Vec<&'a Expr>) {
+        match &self.kind {
+            ExprKind::Binary(o, lhs, rhs) if *o == op => {
+                lhs.operands(op, operands);
+                rhs.operands(op, operands);
+            }
+            ExprKind::Group(e) => e.operands(op, operands),
+            _ => operands.push(self),
+        }
+    }
+
+    /// Turn `self` back into a sequence of tokens, which parses back to the
+    /// same tree, e.g. for tools which rewrite or reformat expressions token
+    /// by token.
+    ///
+    /// Brackets are added wherever the precedence of operators requires
+    /// them, as when formatting the expression, and parse as groups. So a
+    /// tree parses back to itself as long as it has a group wherever
+    /// brackets are needed, which every parsed tree does. Numbers are kept
+    /// as single tokens even when they are negative, so `simplify` giving
+    /// -3 for `-(3)` parses back to -3 rather than to the negation of 3.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::Expr, parser::parse_expr, token::{OpKind, Token}};
+    ///
+    /// let parse = |tokens: Vec<Token>| parse_expr(&mut tokens.into_iter().peekable(), 0).unwrap();
+    ///
+    /// let expr: Expr = "-(2 + x)^2 * max(y, 3)!".parse().unwrap();
+    /// assert_eq!(expr, parse(expr.to_tokens()));
+    ///
+    /// let tokens = "2 * -x".parse::<Expr>().unwrap().to_tokens();
+    /// assert_eq!(
+    ///     vec![
+    ///         Token::Number(2.0),
+    ///         Token::Op(OpKind::Star),
+    ///         Token::Op(OpKind::Minus),
+    ///         Token::Ident("x".to_string()),
+    ///     ],
+    ///     tokens
+    /// );
+    ///
+    /// let negative = "-(3)".parse::<Expr>().unwrap().simplify();
+    /// assert_eq!(vec![Token::Number(-3.0)], negative.to_tokens());
+    /// assert_eq!(negative, parse(negative.to_tokens()));
+    /// ```
+    pub fn to_tokens(&self) -> Vec<Token> {
+        let mut tokens = vec![];
+        self.push_tokens(&mut tokens);
+
+        tokens
+    }
+
+    /// Add the tokens of `self` to `tokens`.
+    fn push_tokens(&self, tokens: &mut Vec<Token>) {
+        // add the tokens of `e` in brackets if its root operator binds looser
+        // than `needed`, or as loose when `strict` is set
+        let operand =
+            |tokens: &mut Vec<Token>, e: &Expr, needed: u8, strict: bool| {
+                match e.token_bp() {
+                    Some(bp) if bp < needed || (strict && bp == needed) => {
+                        tokens.push(Token::LParen);
+                        e.push_tokens(tokens);
+                        tokens.push(Token::RParen);
+                    }
+                    _ => e.push_tokens(tokens),
+                }
+            };
+        let separated = |tokens: &mut Vec<Token>, elements: &[Expr]| {
+            for (i, e) in elements.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(Token::Comma);
+                }
+                e.push_tokens(tokens);
+            }
+        };
+
+        match &self.kind {
+            ExprKind::Number(n) => tokens.push(Token::Number(*n)),
+            ExprKind::Ident(name) => tokens.push(Token::Ident(name.clone())),
+            ExprKind::Str(s) => tokens.push(Token::Str(s.clone())),
+            ExprKind::Unary(op, e) => {
+                tokens.push(Token::Op(*op));
+                operand(tokens, e, UNARY_BP, false);
+            }
+            ExprKind::Postfix(op, e) => {
+                operand(tokens, e, u8::MAX, false);
+                tokens.push(Token::Op(*op));
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                // the same rules as for formatting, see `Display`
+                let right = *op == OpKind::Caret;
+
+                operand(tokens, lhs, op.bp(), right);
+                tokens.push(Token::Op(*op));
+                match rhs.token_bp() {
+                    Some(UNARY_BP) => rhs.push_tokens(tokens),
+                    _ => operand(tokens, rhs, op.bp(), !right),
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                tokens.push(match callee {
+                    Callee::Builtin(f) => Token::Func(*f),
+                    Callee::Named(name) => Token::Ident(name.clone()),
+                });
+                tokens.push(Token::LParen);
+                separated(tokens, args);
+                tokens.push(Token::RParen);
+            }
+            ExprKind::Group(e) => {
+                tokens.push(Token::LParen);
+                e.push_tokens(tokens);
+                tokens.push(Token::RParen);
+            }
+            ExprKind::List(elements) => {
+                tokens.push(Token::LBracket);
+                separated(tokens, elements);
+                tokens.push(Token::RBracket);
+            }
+        }
+    }
+
+    /// Check whether `self` calls `rand`, so two copies of it may have
+    /// different values.
+    fn is_random(&self) -> bool {
+        match &self.kind {
+            ExprKind::Number(_) | ExprKind::Ident(_) | ExprKind::Str(_) => {
+                false
+            }
+            ExprKind::Unary(_, e)
+            | ExprKind::Postfix(_, e)
+            | ExprKind::Group(e) => e.is_random(),
+            ExprKind::Binary(_, lhs, rhs) => lhs.is_random() || rhs.is_random(),
+            ExprKind::Call(callee, args) => {
+                matches!(
+                    callee,
+                    Callee::Builtin(FuncKind::Rand | FuncKind::MonteCarlo)
+                ) || args.iter().any(Expr::is_random)
+            }
+            ExprKind::List(elements) => elements.iter().any(Expr::is_random),
+        }
+    }
+
+    /// Get the value of `self` if it is a number.
+    fn number(&self) -> Option<f64> {
+        match self.kind {
+            ExprKind::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Get the binding power of the operator at the root of `self` like
+    /// `bp`, for tokens rather than text, in which a negative number is a
+    /// single token rather than a unary minus.
+    fn token_bp(&self) -> Option<u8> {
+        match &self.kind {
+            ExprKind::Number(_) => None,
+            _ => self.bp(),
+        }
+    }
+
+    /// Get the binding power of the operator at the root of `self`, which
+    /// decides whether it needs brackets when it is an operand.
+    pub(crate) fn bp(&self) -> Option<u8> {
+        match &self.kind {
+            ExprKind::Binary(op, _, _) => Some(op.bp()),
+            // negative numbers are displayed with a unary minus
+            ExprKind::Unary(_, _) => Some(UNARY_BP),
+            ExprKind::Number(n) if n.is_sign_negative() => Some(UNARY_BP),
+            _ => None,
+        }
+    }
+}
+
+/// Simplify the application of the infix operator `op` to the simplified
+/// operands `lhs` and `rhs`.
+fn simplify_binary(op: OpKind, lhs: Expr, rhs: Expr) -> ExprKind {
+    if let (Some(l), Some(r)) = (lhs.number(), rhs.number()) {
+        let n = apply_op(op, l, r);
+
+        if n.is_finite() {
+            return ExprKind::Number(n);
+        }
+    }
+
+    let is = |e: &Expr, value: f64| e.number() == Some(value);
+
+    match op {
+        OpKind::Plus if is(&lhs, 0.0) => rhs.kind,
+        OpKind::Plus | OpKind::Minus if is(&rhs, 0.0) => lhs.kind,
+        OpKind::Minus if is(&lhs, 0.0) => ExprKind::Unary(op, Box::new(rhs)),
+        OpKind::Minus if lhs == rhs && !lhs.is_random() => {
+            ExprKind::Number(0.0)
+        }
+        OpKind::Star if is(&lhs, 0.0) || is(&rhs, 0.0) => ExprKind::Number(0.0),
+        OpKind::Star if is(&lhs, 1.0) => rhs.kind,
+        OpKind::Star | OpKind::Slash if is(&rhs, 1.0) => lhs.kind,
+        OpKind::Caret if is(&rhs, 1.0) => lhs.kind,
+        OpKind::Caret if is(&rhs, 0.0) || is(&lhs, 1.0) => {
+            ExprKind::Number(1.0)
+        }
+        _ => ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+/// Check whether each expression in `lhs` has the same structure as the one
+/// in the same place in `rhs`, like `Expr::equivalent_structure`.
+fn all_equivalent(lhs: &[Expr], rhs: &[Expr]) -> bool {
+    lhs.len() == rhs.len()
+        && lhs.iter().zip(rhs).all(|(l, r)| l.equivalent_structure(r))
+}
+
+/// Join `operands` with the commutative operator `op` in order, folding the
+/// numbers among them into one.
+fn sort_operands(op: OpKind, operands: Vec<Expr>) -> Expr {
+    let (numbers, mut others): (Vec<Expr>, Vec<Expr>) =
+        operands.into_iter().partition(|e| e.number().is_some());
+
+    let identity = if op == OpKind::Plus { 0.0 } else { 1.0 };
+    let folded = numbers
+        .iter()
+        .filter_map(Expr::number)
+        .fold(identity, |acc, n| apply_op(op, acc, n));
+
+    let mut constants = if folded.is_finite() {
+        vec![Expr::new(ExprKind::Number(folded))]
+    } else {
+        // leave constants which overflow to be reported when evaluated
+        numbers
+    };
+    // nothing is added by adding 0 or multiplying by 1
+    constants.retain(|e| e.number() != Some(identity));
+
+    let key = |e: &Expr| e.to_string();
+    others.sort_by_cached_key(key);
+    constants.sort_by_cached_key(key);
+
+    // constants are written last in a sum, and first in a product
+    let operands = match op {
+        OpKind::Plus => others.into_iter().chain(constants).collect::<Vec<_>>(),
+        _ => constants.into_iter().chain(others).collect(),
+    };
+
+    operands
+        .into_iter()
+        .reduce(|lhs, rhs| {
+            Expr::new(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)))
+        })
+        .unwrap_or_else(|| Expr::new(ExprKind::Number(identity)))
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    /// Parse a whole expression into a tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::{Expr, ExprKind}, token::OpKind};
+    ///
+    /// let expr: Expr = "1 + 2".parse().unwrap();
+    ///
+    /// assert!(matches!(expr.kind, ExprKind::Binary(OpKind::Plus, _, _)));
+    /// assert!("1 + 2)".parse::<Expr>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tokens = lex_spanned(&mut s.chars().peekable())?
+            .into_iter()
+            .peekable();
+
+        let expr = parse_expr(&mut tokens, 0)?;
+        expect_end(&mut tokens)?;
+
+        Ok(expr)
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Format the expression so that it parses back to the same tree, adding
+    /// brackets wherever the precedence of operators requires them.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // wrap `e` in brackets if its root operator binds looser than
+        // `needed`, or as loose when `strict` is set
+        let operand =
+            |f: &mut fmt::Formatter<'_>, e: &Expr, needed: u8, strict: bool| {
+                match e.bp() {
+                    Some(bp) if bp < needed || (strict && bp == needed) => {
+                        write!(f, "({})", e)
+                    }
+                    _ => write!(f, "{}", e),
+                }
+            };
+
+        match &self.kind {
+            // pi is written by name, which lexes back to the same number
+            ExprKind::Number(n) if n.abs() == PI => {
+                write!(f, "{}pi", if n.is_sign_negative() { "-" } else { "" })
+            }
+            ExprKind::Number(n) => write!(f, "{}", n),
+            ExprKind::Ident(name) => write!(f, "{}", name),
+            ExprKind::Str(s) => {
+                write!(
+                    f,
+                    "\"{}\"",
+                    s.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            }
+            ExprKind::Unary(op, e) => {
+                write!(f, "{}", op)?;
+                // `- -x` rather than `--x` keeps the operators readable
+                if e.bp() == Some(UNARY_BP) {
+                    write!(f, " ")?;
+                }
+                operand(f, e, UNARY_BP, false)
+            }
+            ExprKind::Postfix(op, e) => {
+                // any operator binds looser than a postfix operator
+                operand(f, e, u8::MAX, false)?;
+                write!(f, "{}", op)
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                // caret is right associative, so a caret on its left needs
+                // brackets, while every other operator is left associative
+                let right = *op == OpKind::Caret;
+
+                operand(f, lhs, op.bp(), right)?;
+                write!(f, " {} ", op)?;
+                // an operand with a unary minus on the right never needs
+                // brackets, since the minus can only be parsed as a prefix
+                match rhs.bp() {
+                    Some(UNARY_BP) => write!(f, "{}", rhs),
+                    _ => operand(f, rhs, op.bp(), !right),
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                let args: Vec<String> =
+                    args.iter().map(|arg| arg.to_string()).collect();
+
+                write!(f, "{}({})", callee.name(), args.join(", "))
+            }
+            ExprKind::Group(e) => write!(f, "({})", e),
+            ExprKind::List(elements) => {
+                let elements: Vec<String> =
+                    elements.iter().map(|e| e.to_string()).collect();
+
+                write!(f, "[{}]", elements.join(", "))
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Forward mode automatic differentiation, which finds derivatives exactly up
+//! to rounding by evaluating expressions over dual numbers rather than
+//! approximating them with finite differences.
+
+use std::f64::consts::{LN_10, PI};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    algebra::{eval_expr, Algebra},
+    evaluator::Evaluator,
+    message::Code,
+    token::FuncKind,
+};
+
+/// A value along with its derivative with respect to the variable being
+/// differentiated by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Dual {
+    value: f64,
+    derivative: f64,
+}
+
+impl Dual {
+    fn constant(value: f64) -> Self {
+        Self {
+            value,
+            derivative: 0.0,
+        }
+    }
+}
+
+/// Dual numbers, where the variable `var` is `at` and everything else is
+/// known to `evaluator`.
+struct Forward<'a> {
+    evaluator: &'a Evaluator,
+    var: &'a str,
+    at: f64,
+}
+
+impl Algebra for Forward<'_> {
+    type Value = Dual;
+
+    fn number(&self, n: f64) -> Result<Dual> {
+        Ok(Dual::constant(n))
+    }
+
+    fn variable(&self, name: &str) -> Result<Dual> {
+        if name == self.var {
+            return Ok(Dual {
+                value: self.at,
+                derivative: 1.0,
+            });
+        }
+
+        Algebra::variable(self.evaluator, name).map(Dual::constant)
+    }
+
+    fn add(&self, a: Dual, b: Dual) -> Result<Dual> {
+        Ok(Dual {
+            value: a.value + b.value,
+            derivative: a.derivative + b.derivative,
+        })
+    }
+
+    fn sub(&self, a: Dual, b: Dual) -> Result<Dual> {
+        Ok(Dual {
+            value: a.value - b.value,
+            derivative: a.derivative - b.derivative,
+        })
+    }
+
+    fn mul(&self, a: Dual, b: Dual) -> Result<Dual> {
+        Ok(Dual {
+            value: a.value * b.value,
+            derivative: a.derivative * b.value + a.value * b.derivative,
+        })
+    }
+
+    fn div(&self, a: Dual, b: Dual) -> Result<Dual> {
+        Ok(Dual {
+            value: a.value / b.value,
+            derivative: (a.derivative * b.value - a.value * b.derivative)
+                / (b.value * b.value),
+        })
+    }
+
+    fn pow(&self, a: Dual, b: Dual) -> Result<Dual> {
+        let value = a.value.powf(b.value);
+
+        // a constant power is differentiated without taking the log of the
+        // base, which may be negative or zero
+        let derivative = if b.derivative == 0.0 {
+            if b.value == 0.0 {
+                0.0
+            } else {
+                b.value * a.value.powf(b.value - 1.0) * a.derivative
+            }
+        } else {
+            let base = if a.derivative == 0.0 {
+                0.0
+            } else {
+                b.value * a.derivative / a.value
+            };
+
+            value * (b.derivative * a.value.ln() + base)
+        };
+
+        Ok(Dual { value, derivative })
+    }
+
+    fn neg(&self, a: Dual) -> Result<Dual> {
+        Ok(Dual {
+            value: -a.value,
+            derivative: -a.derivative,
+        })
+    }
+
+    fn apply_fn(&self, name: &str, args: Vec<Dual>) -> Result<Dual> {
+        let values: Vec<f64> = args.iter().map(|arg| arg.value).collect();
+        let value = self.evaluator.apply_fn(name, values)?;
+
+        if args.iter().all(|arg| arg.derivative == 0.0) {
+            return Ok(Dual::constant(value));
+        }
+
+        let name = self.evaluator.alias(name).unwrap_or(name);
+        let f = FuncKind::ALL.iter().find(|f| f.name() == name);
+
+        let derivative = match (f, args.as_slice()) {
+            (Some(&f), &[u]) => match slope(f, u.value, value) {
+                Some(slope) => slope * u.derivative,
+                None => bail!(Code::CannotDifferentiate.with(&[&name])),
+            },
+            (Some(FuncKind::Sum), args) => {
+                args.iter().map(|arg| arg.derivative).sum()
+            }
+            (Some(FuncKind::Avg), args) => {
+                args.iter().map(|arg| arg.derivative).sum::<f64>()
+                    / args.len() as f64
+            }
+            // the derivative of whichever argument is chosen
+            (Some(FuncKind::Max | FuncKind::Min), args) => args
+                .iter()
+                .find(|arg| arg.value == value)
+                .map_or(f64::NAN, |arg| arg.derivative),
+            (Some(FuncKind::Fma), &[a, b, c]) => {
+                a.derivative * b.value + a.value * b.derivative + c.derivative
+            }
+            _ => bail!(Code::CannotDifferentiate.with(&[&name])),
+        };
+
+        Ok(Dual { value, derivative })
+    }
+}
+
+/// Get the derivative of the function `f` of a single argument at `x`, where
+/// `y` is its value there, or `None` if it is not known.
+fn slope(f: FuncKind, x: f64, y: f64) -> Option<f64> {
+    let slope = match f {
+        FuncKind::Sin => x.cos(),
+        FuncKind::Cos => -x.sin(),
+        FuncKind::Tan => 1.0 + y * y,
+        FuncKind::Asin => 1.0 / (1.0 - x * x).sqrt(),
+        FuncKind::Acos => -1.0 / (1.0 - x * x).sqrt(),
+        FuncKind::Atan => 1.0 / (1.0 + x * x),
+        FuncKind::Deg => 180.0 / PI,
+        FuncKind::Rad => PI / 180.0,
+        FuncKind::Exp => y,
+        FuncKind::Ln => 1.0 / x,
+        FuncKind::Log => 1.0 / (x * LN_10),
+        FuncKind::Sqrt => 0.5 / y,
+        // functions of many arguments called with one are the identity
+        FuncKind::Max | FuncKind::Min | FuncKind::Sum | FuncKind::Avg => 1.0,
+        FuncKind::Erf => 2.0 / PI.sqrt() * (-x * x).exp(),
+        FuncKind::Erfc => -2.0 / PI.sqrt() * (-x * x).exp(),
+        FuncKind::LambertW if x == 0.0 => 1.0,
+        FuncKind::LambertW => y / (x * (1.0 + y)),
+        _ => return None,
+    };
+
+    Some(slope)
+}
+
+/// Find the derivative of `input` with respect to the variable `var` at
+/// `x0`, exactly up to rounding.
+///
+/// Unlike finite differences, which lose about half of the digits of the
+/// result to cancellation, this evaluates the expression over dual numbers,
+/// carrying the derivative of every subexpression along with its value.
+/// Expressions are evaluated as by `algebra::eval` with an `Evaluator`, and
+/// only builtin functions with known derivatives can be applied to anything
+/// that depends on `var`.
+///
+/// # Arguments
+///
+/// * `input` - The expression to differentiate.
+/// * `var` - The name of the variable to differentiate with respect to.
+/// * `x0` - The value of the variable to find the derivative at.
+///
+/// # Examples
+/// ```
+/// use cocoa::autodiff::diff_exact;
+///
+/// assert_eq!(6.0, diff_exact("x^2", "x", 3.0).unwrap());
+/// assert_eq!(1.0, diff_exact("sin(x)", "x", 0.0).unwrap());
+/// // the chain rule and the quotient rule
+/// assert_eq!(0.5f64.cos(), diff_exact("sin(sqrt(x))", "x", 0.25).unwrap());
+/// assert_eq!(-0.25, diff_exact("1 / x", "x", 2.0).unwrap());
+/// assert_eq!(8.0 * 2f64.ln(), diff_exact("2^(x+1)", "x", 2.0).unwrap());
+///
+/// // expressions which do not depend on the variable are flat
+/// assert_eq!(0.0, diff_exact("pi * 2", "x", 1.0).unwrap());
+/// assert!(diff_exact("gcd(x, 4)", "x", 2.0).is_err());
+/// ```
+pub fn diff_exact(input: &str, var: &str, x0: f64) -> Result<f64> {
+    diff_exact_with(&Evaluator::new(), input, var, x0)
+}
+
+/// Find the derivative of `input` with respect to the variable `var` at `x0`
+/// like `diff_exact`, where other names are the variables, constants,
+/// functions and templates known to `evaluator`.
+///
+/// Calls to templates are expanded before differentiating, so a template
+/// can be differentiated whenever its body can.
+///
+/// # Arguments
+///
+/// * `evaluator` - The evaluator which knows the other names in `input`.
+/// * `input` - The expression to differentiate.
+/// * `var` - The name of the variable to differentiate with respect to.
+/// * `x0` - The value of the variable to find the derivative at.
+///
+/// # Examples
+/// ```
+/// use cocoa::{autodiff::diff_exact_with, evaluator::Evaluator};
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.set_variable("a", 3.0).unwrap();
+///
+/// assert_eq!(12.0, diff_exact_with(&evaluator, "a * x^2", "x", 2.0).unwrap());
+///
+/// evaluator.define_template("template area(r) := pi * r^2".parse().unwrap()).unwrap();
+/// assert_eq!(
+///     4.0 * std::f64::consts::PI,
+///     diff_exact_with(&evaluator, "area(x)", "x", 2.0).unwrap()
+/// );
+/// ```
+pub fn diff_exact_with(
+    evaluator: &Evaluator,
+    input: &str,
+    var: &str,
+    x0: f64,
+) -> Result<f64> {
+    let forward = Forward {
+        evaluator,
+        var,
+        at: x0,
+    };
+
+    let expr = evaluator.expand(&input.parse()?)?;
+
+    Ok(eval_expr(&forward, &expr)?.derivative)
+}
@@ -0,0 +1,176 @@
+//! Compilation of expression trees into programs for a stack machine, which
+//! evaluate an expression at many values of its variables much quicker than
+//! walking its tree each time.
+
+use anyhow::Result;
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    evaluator::Evaluator,
+    interpreter::apply_op,
+    math::factorial,
+    token::{FuncKind, OpKind},
+};
+
+/// An instruction of a `Program`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Instr {
+    /// Push a number.
+    Number(f64),
+    /// Push the value of the variable at the given index.
+    Var(usize),
+    /// Negate the top of the stack.
+    Neg,
+    /// Take the factorial of the top of the stack.
+    Factorial,
+    /// Pop two operands, pushing the operator applied to them.
+    Op(OpKind),
+    /// Pop the given number of arguments, pushing the function called with
+    /// them.
+    Call(FuncKind, usize),
+}
+
+/// An expression of a few variables compiled into instructions for a stack
+/// machine, in postfix order.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Program {
+    code: Vec<Instr>,
+    /// The most values that are ever on the stack at once.
+    depth: usize,
+}
+
+impl Program {
+    /// Compile `expr` as a function of the variables `vars`, returning `None`
+    /// if it cannot be evaluated with only their values known, e.g. since it
+    /// refers to another variable or has a list.
+    pub(crate) fn compile(expr: &Expr, vars: &[&str]) -> Option<Self> {
+        let mut program = Self {
+            code: vec![],
+            depth: 0,
+        };
+        program.emit(expr, vars, 0)?;
+
+        Some(program)
+    }
+
+    /// Add the instructions for `expr` to the program, where `height` values
+    /// are already on the stack below it.
+    fn emit(
+        &mut self,
+        expr: &Expr,
+        vars: &[&str],
+        height: usize,
+    ) -> Option<()> {
+        self.depth = self.depth.max(height + 1);
+
+        let instr = match &expr.kind {
+            ExprKind::Number(n) => Instr::Number(*n),
+            ExprKind::Ident(name) => {
+                Instr::Var(vars.iter().position(|var| var == name)?)
+            }
+            ExprKind::Group(e) | ExprKind::Unary(OpKind::Plus, e) => {
+                return self.emit(e, vars, height)
+            }
+            ExprKind::Unary(OpKind::Minus, e) => {
+                self.emit(e, vars, height)?;
+                Instr::Neg
+            }
+            ExprKind::Postfix(OpKind::Factorial, e) => {
+                self.emit(e, vars, height)?;
+                Instr::Factorial
+            }
+            ExprKind::Binary(op, lhs, rhs) if *op != OpKind::Equals => {
+                self.emit(lhs, vars, height)?;
+                self.emit(rhs, vars, height + 1)?;
+                Instr::Op(*op)
+            }
+            // functions of lists and expressions, and random numbers, need an
+            // evaluator
+            ExprKind::Call(Callee::Builtin(f), args)
+                if !f.is_list_function()
+                    && !f.is_higher_order()
+                    && *f != FuncKind::Rand =>
+            {
+                for (i, arg) in args.iter().enumerate() {
+                    self.emit(arg, vars, height + i)?;
+                }
+                Instr::Call(*f, args.len())
+            }
+            _ => return None,
+        };
+        self.code.push(instr);
+
+        Some(())
+    }
+
+    /// Run the program with the variables bound to `values` in the order they
+    /// were compiled with, using `stack` for the values being worked on,
+    /// which is NaN if it fails at them.
+    ///
+    /// Functions are called with the settings of `evaluator`, which is also
+    /// charged a step for each instruction, failing if that exceeds its
+    /// limits.
+    pub(crate) fn run(
+        &self,
+        evaluator: &Evaluator,
+        values: &[f64],
+        stack: &mut Vec<f64>,
+    ) -> Result<f64> {
+        evaluator.step_by(self.code.len())?;
+
+        stack.clear();
+        stack.reserve(self.depth);
+
+        for &instr in &self.code {
+            let n = match instr {
+                Instr::Number(n) => n,
+                Instr::Var(i) => values[i],
+                Instr::Neg => -pop(stack),
+                Instr::Factorial => {
+                    let n = pop(stack);
+
+                    // casting is safe since n is a positive integer, and
+                    // huge values saturate to an infinite factorial anyway
+                    if n.is_sign_negative() || n.fract() != 0.0 {
+                        f64::NAN
+                    } else {
+                        factorial(n as u64)
+                    }
+                }
+                Instr::Op(op @ (OpKind::Shl | OpKind::Shr)) => {
+                    let rhs = pop(stack);
+                    let lhs = pop(stack);
+
+                    if lhs.fract() != 0.0 || rhs.fract() != 0.0 || rhs < 0.0 {
+                        f64::NAN
+                    } else {
+                        apply_op(op, lhs, rhs)
+                    }
+                }
+                Instr::Op(op) => {
+                    let rhs = pop(stack);
+                    let lhs = pop(stack);
+
+                    apply_op(op, lhs, rhs)
+                }
+                Instr::Call(f, argc) => {
+                    let args = stack.len() - argc;
+                    let n =
+                        evaluator.call(f, &stack[args..]).unwrap_or(f64::NAN);
+                    stack.truncate(args);
+
+                    n
+                }
+            };
+
+            stack.push(n);
+        }
+
+        Ok(pop(stack))
+    }
+}
+
+/// Pop the top of `stack`, which a compiled program always has a value on.
+fn pop(stack: &mut Vec<f64>) -> f64 {
+    stack.pop().expect("compiled programs never underflow")
+}
@@ -0,0 +1,229 @@
+//! Errors which point at the part of the input that caused them.
+
+use std::{error, fmt};
+
+use anyhow::Error;
+
+use crate::{
+    message::{message, Code, Message},
+    token::Span,
+};
+
+/// An error caused by a specific span of the input.
+///
+/// Errors returned by cocoa are `anyhow::Error`s, which can be downcast to a
+/// `SpanError` to find out where in the input the error occurred, if known.
+///
+/// # Examples
+/// ```
+/// use cocoa::{error::SpanError, evaluator::Evaluator, token::Span};
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.set_strict_division(true);
+///
+/// let e = evaluator.eval("1 + 2 / (3 - 3)").unwrap_err();
+///
+/// assert_eq!(
+///     Some(Span::new(4, 15)),
+///     e.downcast_ref::<SpanError>().map(|e| e.span)
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanError {
+    /// The part of the input that caused the error.
+    pub span: Span,
+    /// A description of the error.
+    pub message: Message,
+}
+
+impl SpanError {
+    /// Create an error caused by `span` with the description `message`.
+    pub fn new(span: Span, message: impl Into<Message>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SpanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for SpanError {}
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something was wrong, but the expression could still be evaluated.
+    Warning,
+    /// The expression could not be evaluated.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found with an expression, and the part of the input it was
+/// found in, if known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Whether the problem stopped the expression from being evaluated.
+    pub severity: Severity,
+    /// The part of the input the problem was found in.
+    pub span: Option<Span>,
+    /// What the problem was, whose `id` is stable across versions.
+    pub code: Code,
+    /// A description of the problem, ending with its hint if it has one.
+    pub message: String,
+    /// A suggestion of how to fix the problem, if there is one.
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic about `span` with the description `message`.
+    ///
+    /// # Arguments
+    ///
+    /// * `severity` - Whether the problem stopped the expression from being
+    ///   evaluated.
+    /// * `span` - The part of the input the problem was found in.
+    /// * `message` - The description of the problem.
+    pub fn new(
+        severity: Severity,
+        span: Option<Span>,
+        message: impl Into<Message>,
+    ) -> Self {
+        let message = message.into();
+
+        Self {
+            severity,
+            span,
+            code: message.code,
+            message: message.to_string(),
+            hint: message.hint.map(|hint| hint.to_string()),
+        }
+    }
+
+    /// Create a warning about `span` with the description `message`.
+    pub fn warning(span: Option<Span>, message: impl Into<Message>) -> Self {
+        Self::new(Severity::Warning, span, message)
+    }
+
+    /// Create a diagnostic for the error `e`, pointing at the span it was
+    /// caused by if it is a `SpanError`.
+    pub fn error(e: &Error) -> Self {
+        let span = e.downcast_ref::<SpanError>().map(|e| e.span);
+        match message(e) {
+            Some(message) => Self::new(Severity::Error, span, message.clone()),
+            None => Self::new(Severity::Error, span, e.to_string()),
+        }
+    }
+
+    /// Serialize `self` as a JSON object, for frontends such as editors
+    /// which read diagnostics from another process.
+    ///
+    /// The object has the `severity`, the stable `code`, the `span` as an
+    /// object of its `start` and `end`, the `message` and the `hint`, with
+    /// `null` for a span or hint which is not known.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{error::Diagnostic, evaluator::Evaluator};
+    ///
+    /// let diagnostics =
+    ///     cocoa::validate_with("2 * pii", &Evaluator::new()).unwrap_err();
+    /// assert_eq!(
+    ///     concat!(
+    ///         r#"{"severity":"error","code":"E014","#,
+    ///         r#""span":{"start":4,"end":7},"#,
+    ///         r#""message":"unknown identifier 'pii', did you mean 'pi'?","#,
+    ///         r#""hint":"did you mean 'pi'?"}"#,
+    ///     ),
+    ///     diagnostics[0].to_json()
+    /// );
+    ///
+    /// let warning = Diagnostic::warning(None, "a \"quoted\"\nline");
+    /// assert_eq!(
+    ///     concat!(
+    ///         r#"{"severity":"warning","code":"E000","span":null,"#,
+    ///         r#""message":"a \"quoted\"\nline","hint":null}"#,
+    ///     ),
+    ///     warning.to_json()
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let span = match self.span {
+            Some(span) => {
+                format!(r#"{{"start":{},"end":{}}}"#, span.start, span.end)
+            }
+            None => "null".to_string(),
+        };
+        let hint = match &self.hint {
+            Some(hint) => json_string(hint),
+            None => "null".to_string(),
+        };
+
+        format!(
+            concat!(
+                r#"{{"severity":"{}","code":"{}","span":{},"#,
+                r#""message":{},"hint":{}}}"#,
+            ),
+            self.severity,
+            self.code.id(),
+            span,
+            json_string(&self.message),
+            hint
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Create an error with the description `message`, which is a `SpanError` if
+/// `span` is known.
+pub(crate) fn spanned(
+    span: Option<Span>,
+    message: impl Into<Message>,
+) -> Error {
+    match span {
+        Some(span) => SpanError::new(span, message).into(),
+        None => Error::new(message.into()),
+    }
+}
+
+/// Quote `s` as a JSON string, escaping the characters which JSON does not
+/// allow in strings as they are.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                quoted.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,2843 @@
+//! An environment which expressions are evaluated in, holding everything that
+//! is not builtin to expresso.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, fs,
+    io::{self, Read},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Error, Result};
+
+#[cfg(feature = "bigint")]
+use crate::value::to_f64;
+use crate::{
+    arena::ExprArena,
+    ast::{Callee, Expr, ExprKind},
+    compile::Program,
+    error::{spanned, Diagnostic},
+    function::check_args,
+    interpreter::{elements, Interpreter},
+    lexer::{lex, lex_spanned},
+    math::{
+        cfrac, compensated_sum, convergent,
+        exact::{self, Rational, Surd},
+        poly::poly_roots_with,
+        random::Rng,
+        round, snap, Rounding,
+    },
+    message::{self, Code, Message},
+    packed::PackedTokens,
+    parser::{expect_end, parse_limited, parse_value_with, too_deep},
+    partial::{drop_unmatched, finish},
+    plugin::{Function, Plugin},
+    prelude,
+    symbolic::Symbolic,
+    template::Template,
+    token::{round_args, Constant, FuncKind, Span, Spanned, Token},
+    value::{count_steps, Value, MAX_LEN},
+};
+
+/// The most rows `Evaluator::table` will produce, so that a mistyped step
+/// cannot evaluate an expression billions of times.
+pub const TABLE_ROWS: usize = 10_000;
+
+/// The seed `rand` starts from in deterministic mode, unless another is set.
+pub const DETERMINISTIC_SEED: u64 = 0;
+
+/// How many iterations of a long evaluation are done between calls to the
+/// progress callback of an evaluator.
+pub const PROGRESS_INTERVAL: usize = 1024;
+
+/// The integer up to which every integer can be held exactly by a float,
+/// 2^53.
+const MAX_EXACT: f64 = 9_007_199_254_740_992.0;
+
+/// The most terms of a continued fraction whose convergent fits in an `i64`,
+/// since the denominators grow at least as fast as the fibonacci numbers.
+const FRACTION_TERMS: usize = 93;
+
+/// The limits on the size of expressions evaluated by `Evaluator::sandboxed`.
+pub const SANDBOX_LIMITS: Limits = Limits {
+    tokens: Some(1_000),
+    depth: Some(64),
+    nesting: Some(16),
+};
+
+/// The most steps an expression evaluated by `Evaluator::sandboxed` may take.
+pub const SANDBOX_STEPS: u64 = 100_000;
+
+/// The longest an expression evaluated by `Evaluator::sandboxed` may take.
+pub const SANDBOX_TIME: Duration = Duration::from_secs(1);
+
+/// How large a result has to be to be warned about as close to overflowing,
+/// which leaves a few orders of magnitude below the largest float.
+const NEAR_OVERFLOW: f64 = 1e300;
+
+/// Limits on the size of the expressions an evaluator will evaluate, which
+/// are checked before any of an expression is evaluated, see
+/// `Evaluator::set_limits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// The most tokens an expression may have, e.g. `sqrt(x)` has 4.
+    pub tokens: Option<usize>,
+    /// The most levels deep the tree of an expression may be, e.g. `1 + x`
+    /// is 2 deep and `1 + sqrt(x)` is 3. Brackets and the like are never
+    /// nested more than `parser::MAX_DEPTH` levels, whatever this is.
+    pub depth: Option<usize>,
+    /// The most function calls that may be nested in each other, e.g.
+    /// `sin(cos(x)) + sin(x)` nests 2.
+    pub nesting: Option<usize>,
+}
+
+/// How results that are not finite numbers, i.e. NaN or infinity, are
+/// treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NanPolicy {
+    /// Non finite results are allowed to propagate through the rest of the
+    /// expression, e.g. `1/0` evaluates to infinity.
+    #[default]
+    Permissive,
+    /// Any operation with a non finite result is an error describing the
+    /// operation, e.g. `1/0` is a division by zero error.
+    Strict,
+}
+
+/// The base integer results are displayed in by `Evaluator::format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Radix {
+    /// Base 10, the way all results are displayed by default.
+    #[default]
+    Decimal,
+    /// Base 16, prefixed with `0x`.
+    Hexadecimal,
+    /// Base 8, prefixed with `0o`.
+    Octal,
+    /// Base 2, prefixed with `0b`.
+    Binary,
+}
+
+impl FromStr for Radix {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dec" => Ok(Radix::Decimal),
+            "hex" => Ok(Radix::Hexadecimal),
+            "oct" => Ok(Radix::Octal),
+            "bin" => Ok(Radix::Binary),
+            _ => bail!(Code::UnknownBase.with(&[&s])),
+        }
+    }
+}
+
+impl fmt::Display for Radix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Radix::Decimal => write!(f, "dec"),
+            Radix::Hexadecimal => write!(f, "hex"),
+            Radix::Octal => write!(f, "oct"),
+            Radix::Binary => write!(f, "bin"),
+        }
+    }
+}
+
+/// The numbers expressions are calculated with, see `Evaluator::set_domain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Domain {
+    /// Floats, the way all expressions are calculated by default.
+    #[default]
+    Float,
+    /// Integers, with the result of every operator and builtin truncated
+    /// towards zero.
+    Int,
+    /// Fractions, kept exact through the operators which keep them exact.
+    Rational,
+    /// Complex numbers, which cannot be calculated with yet.
+    Complex,
+    /// Floats with arbitrary precision, which cannot be calculated with yet.
+    BigFloat,
+}
+
+impl FromStr for Domain {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "float" => Ok(Domain::Float),
+            "int" => Ok(Domain::Int),
+            "rational" => Ok(Domain::Rational),
+            "complex" => Ok(Domain::Complex),
+            "bigfloat" => Ok(Domain::BigFloat),
+            _ => bail!(Code::UnknownDomain.with(&[&s])),
+        }
+    }
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Domain::Float => write!(f, "float"),
+            Domain::Int => write!(f, "int"),
+            Domain::Rational => write!(f, "rational"),
+            Domain::Complex => write!(f, "complex"),
+            Domain::BigFloat => write!(f, "bigfloat"),
+        }
+    }
+}
+
+/// What the result of an expensive calculation is cached by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum CacheKey {
+    /// A call to a builtin, with the bits of its arguments since floats
+    /// cannot be hashed.
+    Call(FuncKind, Vec<u64>),
+    /// The factorial of a number.
+    Factorial(u64),
+}
+
+impl CacheKey {
+    pub(crate) fn call(f: FuncKind, args: &[f64]) -> Self {
+        CacheKey::Call(f, args.iter().map(|x| x.to_bits()).collect())
+    }
+}
+
+/// The results of expensive calculations, which are remembered so that
+/// repeating them is instant.
+#[derive(Debug, Default)]
+struct Cache {
+    /// The most results to remember, where 0 disables the cache.
+    size: usize,
+    results: HashMap<CacheKey, Value>,
+    /// The keys of the results, oldest first, so that the oldest result can
+    /// be forgotten when the cache is full.
+    order: VecDeque<CacheKey>,
+}
+
+impl Cache {
+    /// Forget the oldest results until at most `size` are left.
+    fn shrink_to(&mut self, size: usize) {
+        while self.order.len() > size {
+            if let Some(key) = self.order.pop_front() {
+                self.results.remove(&key);
+            }
+        }
+    }
+}
+
+/// How far through a long evaluation an evaluator is, which is passed to the
+/// callback set with `Evaluator::set_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The name of what is being done, such as `map` or `table`.
+    pub task: &'static str,
+    /// The number of iterations done so far.
+    pub done: usize,
+    /// The number of iterations there are in total.
+    pub total: usize,
+}
+
+/// A callback which is told the progress of long evaluations.
+struct ProgressHook(Box<dyn Fn(Progress) + Send>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ProgressHook")
+    }
+}
+
+/// How much work the expression being evaluated has done so far, which is
+/// checked against the limits of the evaluator.
+#[derive(Debug, Default, Clone, Copy)]
+struct Meter {
+    steps: u64,
+    /// When evaluation started, which is only known if there is a time limit
+    /// since not every platform has a clock.
+    start: Option<Instant>,
+}
+
+/// An environment that resolves the identifiers in an expression which are not
+/// builtins, such as the functions and constants provided by plugins.
+#[derive(Debug, Default)]
+pub struct Evaluator {
+    functions: HashMap<String, Function>,
+    constants: HashMap<String, f64>,
+    variables: HashMap<String, f64>,
+    /// Alternative names for functions and constants, mapped to the names
+    /// they stand for.
+    aliases: HashMap<String, String>,
+    /// Named expressions which calls to them are replaced with.
+    templates: HashMap<String, Template>,
+    /// Names which are going away, mapped to what to use instead.
+    deprecated: HashMap<String, String>,
+    /// The names of the templates defined by the prelude which have not been
+    /// replaced, or `None` if the prelude is not used.
+    prelude: Option<HashSet<String>>,
+    rounding: Rounding,
+    precision: Option<u32>,
+    nan_policy: NanPolicy,
+    strict_division: bool,
+    detect_overflow: bool,
+    compensated: bool,
+    exact_trig: bool,
+    symbolic: bool,
+    domain: Domain,
+    snap: Option<f64>,
+    radix: Radix,
+    width: Option<u32>,
+    // results are cached while evaluating, which only borrows the evaluator
+    cache: RefCell<Cache>,
+    step_limit: Option<u64>,
+    time_limit: Option<Duration>,
+    limits: Limits,
+    meter: Cell<Meter>,
+    progress: Option<ProgressHook>,
+    seed: Option<u64>,
+    deterministic: bool,
+    /// The generator used by `rand`, which is seeded when it is first used.
+    rng: Cell<Option<Rng>>,
+    autocorrect: bool,
+    deny_env: bool,
+    deny_files: bool,
+    allow_stdin: bool,
+    /// The numbers read from standard input, which can only be read once.
+    stdin: RefCell<Option<Vec<f64>>>,
+    // notes are made while evaluating, which only borrows the evaluator
+    notes: RefCell<Vec<String>>,
+    /// The warnings about the expression evaluated last.
+    warnings: RefCell<Vec<Diagnostic>>,
+}
+
+impl Evaluator {
+    /// Create an evaluator that knows about expresso's builtins and the
+    /// templates of the prelude.
+    ///
+    /// Unlike `new`, `Evaluator::default` does not use the prelude.
+    pub fn new() -> Self {
+        let mut evaluator = Self::default();
+        evaluator.set_prelude(true);
+
+        evaluator
+    }
+
+    /// Create an evaluator like `new` which is hardened for evaluating
+    /// expressions from untrusted users, such as those sent to a server.
+    ///
+    /// It cannot read environment variables, files or standard input, and
+    /// expressions are limited to `SANDBOX_LIMITS` in size, `SANDBOX_STEPS`
+    /// steps and `SANDBOX_TIME` in time. Any of these can be changed
+    /// afterwards, e.g. the time limit should be removed with
+    /// `set_time_limit(None)` on platforms without a clock, such as WASM.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let evaluator = Evaluator::sandboxed();
+    /// assert_eq!(3.0, evaluator.eval("1 + 2").unwrap());
+    ///
+    /// assert!(evaluator.eval(r#"env("HOME")"#).is_err());
+    /// assert!(evaluator.eval_value(r#"load("/etc/passwd")"#).is_err());
+    /// assert!(evaluator.eval("sum(map(x^2, x, range(1, 100000)))").is_err());
+    /// assert!(evaluator.eval(&"-".repeat(100)).is_err());
+    ///
+    /// // builtins which loop are cut off part of the way through too
+    /// let error = evaluator.eval_value("montecarlo(x, x, 0, 1, 10^8)");
+    /// assert_eq!(
+    ///     "budget exceeded: took more than 100000 steps",
+    ///     error.unwrap_err().to_string()
+    /// );
+    /// assert!(evaluator.eval("odesolve(y, 0, 1, 1, 10^8)").is_err());
+    /// assert!(evaluator.eval_value("polyroots(range(1, 3000))").is_err());
+    /// ```
+    pub fn sandboxed() -> Self {
+        let mut evaluator = Self::new();
+        evaluator.set_allow_env(false);
+        evaluator.set_allow_files(false);
+        evaluator.set_allow_stdin(false);
+        evaluator.set_limits(SANDBOX_LIMITS);
+        evaluator.set_step_limit(Some(SANDBOX_STEPS));
+        evaluator.set_time_limit(Some(SANDBOX_TIME));
+
+        evaluator
+    }
+
+    /// Set whether the templates of the prelude, such as `sec` and `logb`,
+    /// are defined, which they are for evaluators made with `new`.
+    ///
+    /// The prelude gives way to anything else of the same name, so these
+    /// templates can be replaced by variables, aliases, plugins and other
+    /// templates, and turning the prelude back on does not undo that.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether the prelude is used.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(3.0, evaluator.eval("logb(8, 2)").unwrap());
+    ///
+    /// // the prelude gives way to anything defined by the user
+    /// evaluator.set_variable("sec", 60.0).unwrap();
+    /// assert_eq!(120.0, evaluator.eval("2 * sec").unwrap());
+    ///
+    /// evaluator.set_prelude(false);
+    /// assert!(evaluator.eval("logb(8, 2)").is_err());
+    /// ```
+    pub fn set_prelude(&mut self, enabled: bool) {
+        match (enabled, self.prelude.take()) {
+            (true, Some(names)) => self.prelude = Some(names),
+            (true, None) => {
+                let mut names = HashSet::new();
+                for template in prelude::templates() {
+                    if !self.is_defined(template.name()) {
+                        names.insert(template.name().to_string());
+                        self.templates.insert(
+                            template.name().to_string(),
+                            template.clone(),
+                        );
+                    }
+                }
+
+                self.prelude = Some(names);
+            }
+            (false, names) => {
+                for name in names.unwrap_or_default() {
+                    self.templates.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Get whether the templates of the prelude are defined.
+    pub fn prelude(&self) -> bool {
+        self.prelude.is_some()
+    }
+
+    /// Check whether `name` is a template defined by the prelude, which
+    /// anything else of the same name may replace.
+    fn in_prelude(&self, name: &str) -> bool {
+        self.prelude
+            .as_ref()
+            .is_some_and(|names| names.contains(name))
+    }
+
+    /// Forget the template `name` if it was defined by the prelude, since
+    /// something else of the same name is replacing it.
+    fn replace_prelude(&mut self, name: &str) {
+        if let Some(names) = &mut self.prelude {
+            if names.remove(name) {
+                self.templates.remove(name);
+            }
+        }
+    }
+
+    /// Make the functions and constants of `plugin` available to expressions
+    /// evaluated by `self`.
+    ///
+    /// Loading fails without changing the evaluator if any of the names the
+    /// plugin provides is not a valid identifier or is already defined.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin` - The plugin to load.
+    pub fn load_plugin<P: Plugin + ?Sized>(
+        &mut self,
+        plugin: &P,
+    ) -> Result<()> {
+        let functions = plugin.functions();
+        let constants = plugin.constants();
+
+        let names: Vec<&str> = functions
+            .iter()
+            .map(|&(name, _)| name)
+            .chain(constants.iter().map(|&(name, _)| name))
+            .collect();
+
+        for (i, name) in names.iter().enumerate() {
+            // anything that does not lex to a lone identifier would be
+            // shadowed by a builtin or could never be referred to
+            match lex(&mut name.chars().peekable()).as_deref() {
+                Ok([Token::Ident(_)]) => (),
+                _ => {
+                    bail!(Code::PluginInvalidName.with(&[&plugin.name(), name]))
+                }
+            }
+
+            if (self.is_defined(name) && !self.in_prelude(name))
+                || names[..i].contains(name)
+            {
+                bail!(Code::PluginRedefines.with(&[&plugin.name(), name]))
+            }
+        }
+
+        for name in names {
+            self.replace_prelude(name);
+        }
+
+        for (name, f) in functions {
+            self.functions.insert(name.to_string(), f);
+        }
+        for (name, c) in constants {
+            self.constants.insert(name.to_string(), c);
+        }
+
+        Ok(())
+    }
+
+    /// Bind the variable `name` to `value`, replacing any value it was
+    /// previously bound to.
+    ///
+    /// Unlike constants, variables can be rebound at any time, e.g. to hold
+    /// the results of earlier calculations.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable.
+    /// * `value` - The value to bind the variable to.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_variable("mem", 2.0).unwrap();
+    /// evaluator.set_variable("mem", 3.0).unwrap();
+    ///
+    /// assert_eq!(6.0, evaluator.eval("mem * 2").unwrap());
+    /// assert!(evaluator.set_variable("sin", 1.0).is_err());
+    /// ```
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<()> {
+        match lex(&mut name.chars().peekable()).as_deref() {
+            Ok([Token::Ident(_)]) => (),
+            _ => bail!(Code::InvalidVariableName.with(&[&name])),
+        }
+
+        if self.functions.contains_key(name)
+            || self.constants.contains_key(name)
+            || self.aliases.contains_key(name)
+            || (self.templates.contains_key(name) && !self.in_prelude(name))
+        {
+            bail!(Code::CannotAssign.with(&[&name]))
+        }
+
+        self.replace_prelude(name);
+        self.variables.insert(name.to_string(), value);
+
+        Ok(())
+    }
+
+    /// Unbind the variable `name`, returning the value it was bound to.
+    pub fn remove_variable(&mut self, name: &str) -> Option<f64> {
+        self.variables.remove(name)
+    }
+
+    /// Check whether `name` refers to a function, constant, variable, alias
+    /// or template known to `self`.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+            || self.constants.contains_key(name)
+            || self.variables.contains_key(name)
+            || self.aliases.contains_key(name)
+            || self.templates.contains_key(name)
+    }
+
+    /// Define `template`, replacing any template of the same name, so that
+    /// calls to it are replaced with its body.
+    ///
+    /// A template may call templates which are not defined yet, but not
+    /// itself, whether directly or through other templates, since expanding
+    /// it would never end.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to define, whose name must not already
+    ///   be defined other than as a template.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.define_template("template area(r) := pi * r^2".parse().unwrap()).unwrap();
+    /// evaluator.define_template("template ring(a, b) := area(b) - area(a)".parse().unwrap()).unwrap();
+    ///
+    /// assert_eq!(std::f64::consts::PI * 3.0, evaluator.eval("ring(1, 2)").unwrap());
+    /// assert!(evaluator.eval("area(1, 2)").is_err());
+    ///
+    /// let e = evaluator.define_template("template area(r) := ring(0, r)".parse().unwrap());
+    /// assert_eq!(
+    ///     "template 'area' calls itself through 'ring'",
+    ///     e.unwrap_err().to_string()
+    /// );
+    /// ```
+    pub fn define_template(&mut self, template: Template) -> Result<()> {
+        let name = template.name();
+        if self.is_defined(name) && !self.templates.contains_key(name) {
+            bail!(Code::TemplateAlreadyDefined.with(&[&name]))
+        }
+
+        // follow the calls of the body through the templates it calls, which
+        // cannot call themselves, looking for a call back to `name`
+        let mut stack: Vec<(&str, &str)> = template
+            .calls()
+            .into_iter()
+            .map(|call| (call, call))
+            .collect();
+        let mut seen = vec![];
+        while let Some((call, via)) = stack.pop() {
+            if call == name && via == name {
+                bail!(Code::TemplateCallsItself.with(&[&name]))
+            }
+            if call == name {
+                bail!(Code::TemplateCallsItselfThrough.with(&[&name, &via]))
+            }
+            if seen.contains(&call) {
+                continue;
+            }
+            seen.push(call);
+
+            if let Some(t) = self.templates.get(call) {
+                stack.extend(t.calls().into_iter().map(|call| (call, via)));
+            }
+        }
+
+        if let Some(names) = &mut self.prelude {
+            names.remove(name);
+        }
+        self.templates.insert(name.to_string(), template);
+
+        Ok(())
+    }
+
+    /// Remove the template `name`, returning it.
+    pub fn remove_template(&mut self, name: &str) -> Option<Template> {
+        if let Some(names) = &mut self.prelude {
+            names.remove(name);
+        }
+
+        self.templates.remove(name)
+    }
+
+    /// Look up a template by its name.
+    pub fn template(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    /// Get every template, sorted by name.
+    pub fn templates(&self) -> Vec<&Template> {
+        let mut templates: Vec<&Template> = self.templates.values().collect();
+        templates.sort_unstable_by_key(|t| t.name());
+
+        templates
+    }
+
+    /// Replace every call to a template in `expr` with the body of the
+    /// template, until there are none left, e.g. to print or differentiate
+    /// what an expression using templates stands for.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The expression to expand.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::Expr, evaluator::Evaluator};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.define_template("template sq(x) := x * x".parse().unwrap()).unwrap();
+    /// evaluator.define_template("template norm(a, b) := sqrt(sq(a) + sq(b))".parse().unwrap()).unwrap();
+    ///
+    /// let expr: Expr = "norm(3, y) / 2".parse().unwrap();
+    /// assert_eq!("sqrt(3 * 3 + y * y) / 2", evaluator.expand(&expr).unwrap().to_string());
+    /// ```
+    pub fn expand(&self, expr: &Expr) -> Result<Expr> {
+        let expand = |e: &Expr| self.expand(e).map(Box::new);
+
+        let kind = match &expr.kind {
+            ExprKind::Number(_) | ExprKind::Ident(_) | ExprKind::Str(_) => {
+                expr.kind.clone()
+            }
+            ExprKind::Unary(op, e) => ExprKind::Unary(*op, expand(e)?),
+            ExprKind::Postfix(op, e) => ExprKind::Postfix(*op, expand(e)?),
+            ExprKind::Binary(op, lhs, rhs) => {
+                ExprKind::Binary(*op, expand(lhs)?, expand(rhs)?)
+            }
+            ExprKind::Group(e) => ExprKind::Group(expand(e)?),
+            ExprKind::List(elements) => ExprKind::List(
+                elements
+                    .iter()
+                    .map(|e| self.expand(e))
+                    .collect::<Result<_>>()?,
+            ),
+            ExprKind::Call(callee, args) => {
+                let args: Vec<Expr> = args
+                    .iter()
+                    .map(|arg| self.expand(arg))
+                    .collect::<Result<_>>()?;
+
+                match callee {
+                    Callee::Named(name) => match self.template(name) {
+                        Some(template) => {
+                            let body = template.expand(&args).map_err(|e| {
+                                spanned(expr.span, e.to_string())
+                            })?;
+
+                            return self.expand(&body);
+                        }
+                        None => ExprKind::Call(callee.clone(), args),
+                    },
+                    Callee::Builtin(_) => ExprKind::Call(callee.clone(), args),
+                }
+            }
+        };
+
+        Ok(Expr::spanned(kind, expr.span))
+    }
+
+    /// Make `name` an alternative name for the function or constant `target`,
+    /// replacing any alias of the same name.
+    ///
+    /// An alias of an alias stands for what that alias stands for, so later
+    /// changes to the alias do not affect it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The new name, which must not already be defined other than
+    ///   as an alias.
+    /// * `target` - The name of a builtin or of a function or constant known
+    ///   to `self`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_alias("r", "sqrt").unwrap();
+    /// evaluator.set_alias("half", "pi").unwrap();
+    ///
+    /// assert_eq!(3.0, evaluator.eval("r(9)").unwrap());
+    /// assert_eq!(std::f64::consts::PI, evaluator.eval("half").unwrap());
+    /// assert!(evaluator.set_alias("sin", "cos").is_err());
+    /// assert!(evaluator.set_alias("r", "nothing").is_err());
+    /// ```
+    pub fn set_alias(&mut self, name: &str, target: &str) -> Result<()> {
+        match lex(&mut name.chars().peekable()).as_deref() {
+            Ok([Token::Ident(_)]) => (),
+            _ => bail!(Code::InvalidAliasName.with(&[&name])),
+        }
+
+        if self.functions.contains_key(name)
+            || self.constants.contains_key(name)
+            || self.variables.contains_key(name)
+            || (self.templates.contains_key(name) && !self.in_prelude(name))
+        {
+            bail!(Code::AliasAlreadyDefined.with(&[&name]))
+        }
+
+        let target = self.alias(target).unwrap_or(target).to_string();
+        let known = match lex(&mut target.chars().peekable()).as_deref() {
+            Ok([Token::Func(_) | Token::Number(_)]) => true,
+            Ok([Token::Ident(target)]) => {
+                self.functions.contains_key(target)
+                    || self.constants.contains_key(target)
+                    || self.templates.contains_key(target)
+            }
+            _ => false,
+        };
+        if !known {
+            bail!(Code::AliasUnknown.with(&[&target]))
+        }
+
+        self.replace_prelude(name);
+        self.aliases.insert(name.to_string(), target);
+
+        Ok(())
+    }
+
+    /// Remove the alias `name`, returning the name it stood for.
+    pub fn remove_alias(&mut self, name: &str) -> Option<String> {
+        self.aliases.remove(name)
+    }
+
+    /// Look up the name the alias `name` stands for.
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    /// Mark `name` as deprecated in favour of `replacement`, so that using
+    /// it warns, though it still works as before.
+    ///
+    /// This lets programs embedding cocoa rename the variables, functions
+    /// and templates they provide without breaking expressions which use the
+    /// old names straight away.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name which is going away.
+    /// * `replacement` - The name to use instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, message::Code};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_variable("vat", 0.2).unwrap();
+    /// evaluator.set_variable("tax", 0.2).unwrap();
+    /// evaluator.deprecate("vat", "tax");
+    ///
+    /// let (result, warnings) = evaluator.eval_with_warnings("100 * vat");
+    /// assert_eq!(20.0, result.unwrap().into_number().unwrap());
+    /// assert_eq!(Code::Deprecated, warnings[0].code);
+    /// assert_eq!("'vat' is deprecated, use 'tax' instead", warnings[0].message);
+    /// ```
+    pub fn deprecate(&mut self, name: &str, replacement: &str) {
+        self.deprecated
+            .insert(name.to_string(), replacement.to_string());
+    }
+
+    /// Get what to use instead of `name` if it is deprecated.
+    pub fn deprecation(&self, name: &str) -> Option<&str> {
+        self.deprecated.get(name).map(String::as_str)
+    }
+
+    /// Get every alias as pairs of its name and the name it stands for,
+    /// sorted by name.
+    pub fn aliases(&self) -> Vec<(&str, &str)> {
+        let mut aliases: Vec<(&str, &str)> = self
+            .aliases
+            .iter()
+            .map(|(name, target)| (name.as_str(), target.as_str()))
+            .collect();
+        aliases.sort_unstable();
+
+        aliases
+    }
+
+    /// Look up a function that is not builtin by its name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    /// Look up a constant that is not builtin by its name.
+    pub fn constant(&self, name: &str) -> Option<f64> {
+        self.constants.get(name).copied()
+    }
+
+    /// Look up a variable by its name.
+    pub fn variable(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    /// Set the rounding mode used by `round` and by `format`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rounding` - The rounding mode to use.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, math::Rounding};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(3.0, evaluator.eval("round(2.5)").unwrap());
+    ///
+    /// evaluator.set_rounding(Rounding::HalfEven);
+    /// assert_eq!(2.0, evaluator.eval("round(2.5)").unwrap());
+    /// ```
+    pub fn set_rounding(&mut self, rounding: Rounding) {
+        self.rounding = rounding;
+    }
+
+    /// Get the rounding mode used by `round` and by `format`.
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// Set the number of digits after the decimal point `format` rounds
+    /// results to, or `None` to display results in full.
+    ///
+    /// # Arguments
+    ///
+    /// * `precision` - The number of digits after the decimal point to keep.
+    pub fn set_precision(&mut self, precision: Option<u32>) {
+        self.precision = precision;
+    }
+
+    /// Get the number of digits after the decimal point `format` rounds
+    /// results to.
+    pub fn precision(&self) -> Option<u32> {
+        self.precision
+    }
+
+    /// Set how operations with results that are not finite numbers are
+    /// treated.
+    ///
+    /// # Arguments
+    ///
+    /// * `nan_policy` - The policy to use.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::{Evaluator, NanPolicy};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(f64::INFINITY, evaluator.eval("1/0").unwrap());
+    ///
+    /// evaluator.set_nan_policy(NanPolicy::Strict);
+    /// assert_eq!(
+    ///     "division by zero",
+    ///     evaluator.eval("1/0").unwrap_err().to_string()
+    /// );
+    /// ```
+    pub fn set_nan_policy(&mut self, nan_policy: NanPolicy) {
+        self.nan_policy = nan_policy;
+    }
+
+    /// Get how operations with results that are not finite numbers are
+    /// treated.
+    pub fn nan_policy(&self) -> NanPolicy {
+        self.nan_policy
+    }
+
+    /// Set whether dividing by zero, using either `/` or `%`, is an error.
+    ///
+    /// Unlike the strict NaN policy, this only rejects divisions by zero, and
+    /// the errors are `SpanError`s pointing at the offending division when
+    /// evaluating with `eval`.
+    ///
+    /// # Arguments
+    ///
+    /// * `strict_division` - Whether dividing by zero is an error.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_strict_division(true);
+    ///
+    /// assert!(evaluator.eval("1 / 0").is_err());
+    /// assert!(evaluator.eval("1 % 0").is_err());
+    /// assert!(evaluator.eval("ln(0)").is_err());
+    /// assert_eq!(f64::INFINITY, evaluator.eval("exp(1000)").unwrap());
+    /// ```
+    pub fn set_strict_division(&mut self, strict_division: bool) {
+        self.strict_division = strict_division;
+    }
+
+    /// Get whether dividing by zero is an error.
+    pub fn strict_division(&self) -> bool {
+        self.strict_division
+    }
+
+    /// Set whether sums are compensated for the rounding error of each
+    /// addition, using Neumaier's variant of Kahan summation.
+    ///
+    /// This applies to `sum` and `avg`, including of lists, and to chains of
+    /// `+` and `-`, so that the results of large reductions do not drift from
+    /// the true result as rounding errors build up.
+    ///
+    /// # Arguments
+    ///
+    /// * `compensated` - Whether sums are compensated.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(0.6000000000000001, evaluator.eval("0.1 + 0.2 + 0.3").unwrap());
+    ///
+    /// evaluator.set_compensated(true);
+    /// assert_eq!(0.6, evaluator.eval("0.1 + 0.2 + 0.3").unwrap());
+    /// assert_eq!(2.0, evaluator.eval("sum([1, 10^100, 1, -10^100])").unwrap());
+    /// assert_eq!(0.1, evaluator.eval("avg(map(0.1, x, range(1, 10)))").unwrap());
+    /// assert_eq!(7.0, evaluator.eval("1 + 2 * 3").unwrap());
+    /// ```
+    pub fn set_compensated(&mut self, compensated: bool) {
+        self.compensated = compensated;
+    }
+
+    /// Get whether sums are compensated for their rounding errors.
+    pub fn compensated(&self) -> bool {
+        self.compensated
+    }
+
+    /// Set whether `sin`, `cos` and `tan` of special angles, the multiples of
+    /// `pi / 6` and `pi / 4`, give their exact values.
+    ///
+    /// Without it, `sin(pi / 6)` is `0.49999999999999994` since `pi` is
+    /// rounded. With it, the angle is looked up instead, and results which
+    /// are the irrational values the functions take at special angles, such
+    /// as `sqrt(2) / 2`, are displayed as such by `format`.
+    ///
+    /// # Arguments
+    ///
+    /// * `exact_trig` - Whether special angles give exact values.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(0.49999999999999994, evaluator.eval("sin(pi / 6)").unwrap());
+    ///
+    /// evaluator.set_exact_trig(true);
+    /// assert_eq!(0.5, evaluator.eval("sin(pi / 6)").unwrap());
+    /// assert_eq!(0.0, evaluator.eval("sin(pi)").unwrap());
+    /// assert_eq!(-1.0, evaluator.eval("tan(3 * pi / 4)").unwrap());
+    ///
+    /// let n = evaluator.eval("cos(pi / 4)").unwrap();
+    /// assert_eq!("sqrt(2) / 2", evaluator.format(n));
+    ///
+    /// let n = evaluator.eval("tan(2 * pi / 3)").unwrap();
+    /// assert_eq!("-sqrt(3)", evaluator.format(n));
+    ///
+    /// // tan has no value at odd multiples of pi / 2
+    /// assert_eq!(
+    ///     "tan is undefined at pi / 2",
+    ///     evaluator.eval("tan(pi / 2)").unwrap_err().to_string()
+    /// );
+    /// assert!(evaluator.eval("tan(-3 * pi / 2)").is_err());
+    /// ```
+    pub fn set_exact_trig(&mut self, exact_trig: bool) {
+        self.exact_trig = exact_trig;
+    }
+
+    /// Get whether `sin`, `cos` and `tan` of special angles give their exact
+    /// values.
+    pub fn exact_trig(&self) -> bool {
+        self.exact_trig
+    }
+
+    /// Set whether `pi`, `e` and square roots of non-squares are kept
+    /// symbolic through the operations which keep them exact.
+    ///
+    /// With it, `eval_value` gives a `Value::Symbolic` for results such as
+    /// `2 * pi + pi`, which `format_value` displays as `3 * pi`. Fractions
+    /// are kept exact too, e.g. `1 / 3`, and `e` is written as `exp(1)`.
+    /// Anything which cannot be kept exact, such as `ln(2)`, is approximated,
+    /// as is the result of `eval`.
+    ///
+    /// Names without a value are kept as variables, so that sums and products
+    /// of them are expanded with like terms collected. They can only be used
+    /// in sums, products, exact quotients and whole powers up to 64. `e` is
+    /// not kept as a variable, since it is how Euler's number is shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbolic` - Whether constants are kept symbolic.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_symbolic(true);
+    ///
+    /// let format = |input| {
+    ///     let value = evaluator.eval_value(input).unwrap();
+    ///     evaluator.format_value(&value)
+    /// };
+    /// assert_eq!("3 * pi", format("2 * pi + pi"));
+    /// assert_eq!("1 / 2", format("1 / 3 + 1 / 6"));
+    /// assert_eq!("2 * sqrt(2) + 1", format("sqrt(8) + 1"));
+    /// assert_eq!("sqrt(2) / 2", format("cos(pi / 4)"));
+    /// assert_eq!("e ^ 2", format("exp(1) ^ 2"));
+    /// assert_eq!("6", format("sqrt(3) * sqrt(12)"));
+    /// assert_eq!("2.1972245773362196", format("ln(9)"));
+    /// assert_eq!("x ^ 2 + 2 * x + 1", format("(x + 1)^2"));
+    /// assert_eq!("2 * pi * r", format("pi * r + r * pi"));
+    /// assert!(evaluator.eval_value("sin(x)").is_err());
+    ///
+    /// let error = |input| {
+    ///     evaluator.eval_value(input).unwrap_err().to_string()
+    /// };
+    /// assert_eq!(
+    ///     "'x' has no value, so it can only be raised to powers from -64 to \
+    ///      64, got 100",
+    ///     error("x^100")
+    /// );
+    /// assert_eq!(
+    ///     "'x' has no value, so it can only be used in sums, products, exact \
+    ///      quotients and whole powers",
+    ///     error("exp(1)^x")
+    /// );
+    /// // Euler's number is shown as e, so e cannot also be a variable
+    /// assert_eq!("e - 1", format("exp(1) - 1"));
+    /// assert!(error("e - exp(1)").contains("exp(1)"));
+    ///
+    /// let n = evaluator.eval("3 * pi").unwrap();
+    /// assert_eq!(3.0 * std::f64::consts::PI, n);
+    /// ```
+    pub fn set_symbolic(&mut self, symbolic: bool) {
+        self.symbolic = symbolic;
+    }
+
+    /// Get whether constants are kept symbolic.
+    pub fn symbolic(&self) -> bool {
+        self.symbolic
+    }
+
+    /// Set the numbers expressions are calculated with.
+    ///
+    /// In the `Int` domain, the result of every operator and builtin is
+    /// truncated towards zero, so `7 / 2` is `3`. In the `Rational` domain,
+    /// numbers are written as fractions and kept exact through `+`, `-`, `*`,
+    /// `/` and whole powers, so `eval_value` gives a `Value::Symbolic` for
+    /// `1 / 3`, while anything irrational, such as `pi` and `sqrt(2)`, is
+    /// approximated as usual. The `Complex` and `BigFloat` domains are not
+    /// supported yet, so setting them is an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The numbers to calculate with.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::{Domain, Evaluator};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    ///
+    /// evaluator.set_domain(Domain::Int).unwrap();
+    /// assert_eq!(3.0, evaluator.eval("7 / 2").unwrap());
+    /// assert_eq!(5.0, evaluator.eval("sqrt(2) + sqrt(17)").unwrap());
+    ///
+    /// evaluator.set_domain(Domain::Rational).unwrap();
+    /// let format = |input| {
+    ///     let value = evaluator.eval_value(input).unwrap();
+    ///     evaluator.format_value(&value)
+    /// };
+    /// assert_eq!("7 / 2", format("7 / 2"));
+    /// assert_eq!("3 / 10", format("0.1 + 0.2"));
+    /// assert_eq!("1", format("(2 / 3) ^ 2 * 9 / 4"));
+    /// assert_eq!("3.414213562373095", format("sqrt(2) + 2"));
+    ///
+    /// assert!(evaluator.set_domain(Domain::Complex).is_err());
+    /// assert_eq!(Domain::Rational, evaluator.domain());
+    /// ```
+    pub fn set_domain(&mut self, domain: Domain) -> Result<()> {
+        match domain {
+            Domain::Complex | Domain::BigFloat => {
+                bail!(Code::UnsupportedDomain.with(&[&domain]))
+            }
+            _ => self.domain = domain,
+        }
+
+        Ok(())
+    }
+
+    /// Get the numbers expressions are calculated with.
+    pub fn domain(&self) -> Domain {
+        self.domain
+    }
+
+    /// Get `n`, a result calculated in another domain, as a number of the
+    /// domain of `self`, e.g. to display a previous result after changing
+    /// domains, returning `None` if it is not one.
+    ///
+    /// Results are only converted when doing so keeps their value, so a
+    /// float is a fraction exactly when it is the closest float to one whose
+    /// numerator and denominator fit in an `i64`.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The result to convert.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{
+    ///     evaluator::{Domain, Evaluator},
+    ///     value::Value,
+    /// };
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_domain(Domain::Rational).unwrap();
+    ///
+    /// let value = evaluator.in_domain(1.0 / 3.0).unwrap();
+    /// assert_eq!("1 / 3", evaluator.format_value(&value));
+    /// assert_eq!(Some(Value::Number(2.0)), evaluator.in_domain(2.0));
+    ///
+    /// evaluator.set_domain(Domain::Int).unwrap();
+    /// assert_eq!(None, evaluator.in_domain(2.5));
+    /// ```
+    pub fn in_domain(&self, n: f64) -> Option<Value> {
+        match self.domain {
+            Domain::Int if n.fract() != 0.0 => None,
+            Domain::Rational if n.is_finite() => {
+                let (num, den) = convergent(&cfrac(n, FRACTION_TERMS))?;
+                let fraction = Rational::new(num, den)?;
+
+                (fraction.value() == n).then(|| Symbolic::from(fraction).into())
+            }
+            _ => Some(n.into()),
+        }
+    }
+
+    /// Set how close a result must be to a number with fewer digits, such
+    /// as `0.3` for `0.30000000000000004`, to be displayed as it by `format`,
+    /// or `None` to display results as they are.
+    ///
+    /// This only changes how results are displayed, not the results
+    /// themselves, so `snapped` tells whether a result is displayed as
+    /// something else, e.g. to mark it as such. See `math::snap` for how the
+    /// number displayed is chosen.
+    ///
+    /// # Arguments
+    ///
+    /// * `epsilon` - How far a result may be from the number it is displayed
+    ///   as.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_snap(Some(1e-9));
+    ///
+    /// let n = evaluator.eval("0.1 + 0.2").unwrap();
+    /// assert_eq!(0.30000000000000004, n);
+    /// assert_eq!("0.3", evaluator.format(n));
+    /// assert_eq!(Some(0.3), evaluator.snapped(n));
+    ///
+    /// let n = evaluator.eval("(sqrt(3))^2").unwrap();
+    /// assert_eq!("3", evaluator.format(n));
+    /// assert_eq!(None, evaluator.snapped(0.25));
+    /// ```
+    pub fn set_snap(&mut self, epsilon: Option<f64>) {
+        self.snap = epsilon;
+    }
+
+    /// Get how close a result must be to a number with fewer digits to be
+    /// displayed as it, or `None` if results are displayed as they are.
+    pub fn snap(&self) -> Option<f64> {
+        self.snap
+    }
+
+    /// Get the number which `n` is displayed as by `format` instead of
+    /// itself, or `None` if it is displayed as it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The result to check.
+    pub fn snapped(&self, n: f64) -> Option<f64> {
+        let snapped = snap(n, self.snap?);
+        (snapped != n).then_some(snapped)
+    }
+
+    /// Set whether operations whose result overflowed to infinity or
+    /// underflowed to zero, because it is out of the range of an `f64`, are
+    /// errors.
+    ///
+    /// The errors are `SpanError`s pointing at the operation that lost the
+    /// value when evaluating with `eval`.
+    ///
+    /// # Arguments
+    ///
+    /// * `detect_overflow` - Whether overflow and underflow are errors.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_detect_overflow(true);
+    ///
+    /// assert_eq!(
+    ///     "exp(1000) overflowed to infinity",
+    ///     evaluator.eval("exp(1000)").unwrap_err().to_string()
+    /// );
+    /// assert_eq!(
+    ///     "0.5 ^ 2000 underflowed to zero",
+    ///     evaluator.eval("2 * 0.5^2000").unwrap_err().to_string()
+    /// );
+    /// // huge operands are written in scientific notation
+    /// assert_eq!(
+    ///     concat!(
+    ///         "1.0142320547350045e304 * 22026.465794806718 ",
+    ///         "overflowed to infinity"
+    ///     ),
+    ///     evaluator.eval("exp(700) * exp(10)").unwrap_err().to_string()
+    /// );
+    /// // dividing by zero does not overflow
+    /// assert_eq!(f64::INFINITY, evaluator.eval("1/0").unwrap());
+    ///
+    /// // exact integers overflow when they are used as numbers
+    /// #[cfg(feature = "bigint")]
+    /// {
+    ///     assert!(evaluator.eval_value("10^400").is_ok());
+    ///     assert_eq!(
+    ///         "an integer of 401 digits overflowed to infinity",
+    ///         evaluator.eval("10^400").unwrap_err().to_string()
+    ///     );
+    ///     assert!(evaluator.eval("10^400 + 0.5").is_err());
+    ///     assert!(evaluator.eval("sqrt(10^400)").is_err());
+    /// }
+    /// ```
+    pub fn set_detect_overflow(&mut self, detect_overflow: bool) {
+        self.detect_overflow = detect_overflow;
+    }
+
+    /// Get whether operations that overflow or underflow are errors.
+    pub fn detect_overflow(&self) -> bool {
+        self.detect_overflow
+    }
+
+    /// Fail if overflow is detected and `value` is an exact integer which is
+    /// too large to be held by a number, since using it as one overflows.
+    #[cfg_attr(not(feature = "bigint"), allow(unused_variables))]
+    pub(crate) fn check_range(&self, value: &Value) -> Result<()> {
+        #[cfg(feature = "bigint")]
+        if let Value::Integer(n) = value {
+            if self.detect_overflow && to_f64(n).is_infinite() {
+                let digits = n.magnitude().to_string().len();
+                let integer = format!("an integer of {} digits", digits);
+
+                bail!(Code::Overflowed.with(&[&integer]))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the base `format` displays integer results in.
+    ///
+    /// Non integer results are always displayed in decimal.
+    ///
+    /// # Arguments
+    ///
+    /// * `radix` - The base to display integers in.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::{Evaluator, Radix};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_radix(Radix::Hexadecimal);
+    ///
+    /// assert_eq!("0xff", evaluator.format(255.0));
+    /// assert_eq!("-0x10", evaluator.format(-16.0));
+    /// assert_eq!("0.5", evaluator.format(0.5));
+    /// ```
+    pub fn set_radix(&mut self, radix: Radix) {
+        self.radix = radix;
+    }
+
+    /// Get the base `format` displays integer results in.
+    pub fn radix(&self) -> Radix {
+        self.radix
+    }
+
+    /// Set the number of bits integers are assumed to have, or `None` for no
+    /// particular width.
+    ///
+    /// When displaying integers in a base other than decimal, negative ones
+    /// that fit in the width are displayed as their two's complement
+    /// representation instead of with a minus sign.
+    ///
+    /// Adding, subtracting, multiplying and shifting integers also wraps
+    /// around to the range of a signed integer with the width, emulating
+    /// machine arithmetic.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The number of bits, one of 8, 16, 32 or 64.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::{Evaluator, Radix};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_radix(Radix::Hexadecimal);
+    /// evaluator.set_width(Some(8)).unwrap();
+    ///
+    /// assert_eq!("0xff", evaluator.format(-1.0));
+    /// assert_eq!("0x80", evaluator.format(-128.0));
+    /// // too small for 8 bits
+    /// assert_eq!("-0x81", evaluator.format(-129.0));
+    ///
+    /// evaluator.set_radix(Radix::Binary);
+    /// assert_eq!("0b11111110", evaluator.format(-2.0));
+    ///
+    /// assert!(evaluator.set_width(Some(12)).is_err());
+    ///
+    /// assert_eq!(-128.0, evaluator.eval("127 + 1").unwrap());
+    /// assert_eq!(0.0, evaluator.eval("1 << 8").unwrap());
+    /// // division does not wrap
+    /// assert_eq!(0.5, evaluator.eval("1 / 2").unwrap());
+    /// ```
+    pub fn set_width(&mut self, width: Option<u32>) -> Result<()> {
+        match width {
+            None | Some(8 | 16 | 32 | 64) => self.width = width,
+            Some(width) => bail!(Code::UnsupportedWidth.with(&[&width])),
+        }
+
+        Ok(())
+    }
+
+    /// Get the number of bits integers are assumed to have.
+    pub fn width(&self) -> Option<u32> {
+        self.width
+    }
+
+    /// Set the most results of expensive builtins, such as `prime` and large
+    /// factorials, that `self` remembers so that calling them again with the
+    /// same arguments is instant.
+    ///
+    /// The cache is disabled by default, and setting its size to 0 disables
+    /// it again. Once it is full, the oldest result is forgotten to make room
+    /// for each new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The most results to remember.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_cache_size(1);
+    ///
+    /// evaluator.eval("prime(10000)").unwrap();
+    /// evaluator.eval("prime(10000) + 1").unwrap();
+    /// assert_eq!(1, evaluator.cache_len());
+    ///
+    /// // only the most recent result fits
+    /// evaluator.eval("primepi(10000)").unwrap();
+    /// assert_eq!(1, evaluator.cache_len());
+    ///
+    /// evaluator.clear_cache();
+    /// assert_eq!(0, evaluator.cache_len());
+    /// ```
+    pub fn set_cache_size(&mut self, size: usize) {
+        let cache = self.cache.get_mut();
+
+        cache.size = size;
+        cache.shrink_to(size);
+    }
+
+    /// Get the most results of expensive builtins that `self` remembers.
+    pub fn cache_size(&self) -> usize {
+        self.cache.borrow().size
+    }
+
+    /// Get the number of results of expensive builtins that `self` currently
+    /// remembers.
+    pub fn cache_len(&self) -> usize {
+        self.cache.borrow().results.len()
+    }
+
+    /// Forget every result of an expensive builtin that `self` remembers.
+    pub fn clear_cache(&mut self) {
+        self.cache.get_mut().shrink_to(0);
+    }
+
+    /// Set the most steps evaluating a single expression may take, where a
+    /// step is evaluating a part of the expression such as a number, an
+    /// operator or a function call, after which evaluation fails with a
+    /// budget exceeded error.
+    ///
+    /// Builtins which do more than a fixed amount of work take a step for
+    /// each of their iterations and each element of the lists they are given
+    /// or give, so that e.g. `montecarlo` with a huge number of samples or
+    /// `sum` of a huge range is cut off too. This bounds how long untrusted
+    /// expressions can take regardless of how fast the machine is.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - The most steps to take, or `None` for no limit.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_step_limit(Some(100));
+    ///
+    /// assert_eq!(6.0, evaluator.eval("1 + 2 + 3").unwrap());
+    /// assert_eq!(
+    ///     "budget exceeded: took more than 100 steps",
+    ///     evaluator.eval("sum(map(x^2, x, range(1, 100)))").unwrap_err().to_string()
+    /// );
+    /// assert!(evaluator.eval("sum(range(1, 1000))").is_err());
+    /// assert!(evaluator.eval_value("polyroots(range(1, 100))").is_err());
+    /// ```
+    pub fn set_step_limit(&mut self, steps: Option<u64>) {
+        self.step_limit = steps;
+    }
+
+    /// Get the most steps evaluating a single expression may take.
+    pub fn step_limit(&self) -> Option<u64> {
+        self.step_limit
+    }
+
+    /// Set the longest evaluating a single expression may take, after which
+    /// evaluation fails with a budget exceeded error.
+    ///
+    /// The time is checked at every step, see `set_step_limit`, including
+    /// those within the iterations of builtins. Time limits are ignored in
+    /// deterministic mode, see `set_deterministic`.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The longest evaluation may take, or `None` for no limit.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_time_limit(Some(Duration::from_nanos(1)));
+    ///
+    /// assert!(evaluator.eval("sum(map(x^2, x, range(1, 10000)))").is_err());
+    /// ```
+    pub fn set_time_limit(&mut self, time: Option<Duration>) {
+        self.time_limit = time;
+    }
+
+    /// Get the longest evaluating a single expression may take.
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    /// Set limits on the size of the expressions `self` evaluates, so that
+    /// overly large or deeply nested expressions are rejected with an error
+    /// before any of them is evaluated.
+    ///
+    /// Unlike `set_step_limit`, these limit the expression as it is written
+    /// rather than the work evaluating it takes, and they also stop deeply
+    /// nested expressions from overflowing the stack as they are parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The limits to check expressions against.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::{Evaluator, Limits};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_limits(Limits {
+    ///     tokens: Some(20),
+    ///     depth: Some(5),
+    ///     nesting: Some(2),
+    /// });
+    ///
+    /// assert_eq!(1.0, evaluator.eval("sin(asin(1))").unwrap());
+    /// assert_eq!(
+    ///     "expression has more than 20 tokens",
+    ///     evaluator.eval(&format!("{}1", "1 + ".repeat(10))).unwrap_err().to_string()
+    /// );
+    /// assert_eq!(
+    ///     "expression is nested more than 5 levels deep",
+    ///     evaluator.eval("1 + -(-(-(-2)))").unwrap_err().to_string()
+    /// );
+    /// assert_eq!(
+    ///     "functions are nested more than 2 deep",
+    ///     evaluator.eval("sin(cos(tan(1)))").unwrap_err().to_string()
+    /// );
+    ///
+    /// // nothing is evaluated once a limit is exceeded
+    /// evaluator.set_variable("x", 1.0).unwrap();
+    /// assert!(evaluator.eval("1 / 0 + ((((((x))))))").unwrap_err().to_string().contains("nested"));
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Get the limits on the size of the expressions `self` evaluates.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Set the seed `rand` starts from, so that the same expressions give the
+    /// same random numbers every time, restarting the sequence it generates.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed, or `None` to use a different seed every time
+    ///   unless `self` is deterministic.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_seed(Some(42));
+    /// let first = evaluator.eval("rand()").unwrap();
+    ///
+    /// assert_ne!(first, evaluator.eval("rand()").unwrap());
+    ///
+    /// evaluator.set_seed(Some(42));
+    /// assert_eq!(first, evaluator.eval("rand()").unwrap());
+    /// ```
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+        self.rng.set(None);
+    }
+
+    /// Get the seed `rand` starts from, if it has been set.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Set whether `self` guarantees that evaluating the same expressions
+    /// gives bit-identical results on every run, e.g. for use in tests.
+    ///
+    /// In deterministic mode, `rand` starts from `DETERMINISTIC_SEED` unless
+    /// another seed is set, and time limits are ignored, since whether they
+    /// are exceeded depends on the speed of the machine. Every other builtin
+    /// is already deterministic, as numeric algorithms only depend on their
+    /// arguments and results are formatted exactly.
+    ///
+    /// # Arguments
+    ///
+    /// * `deterministic` - Whether results must be reproducible.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let (mut a, mut b) = (Evaluator::new(), Evaluator::new());
+    /// a.set_deterministic(true);
+    /// b.set_deterministic(true);
+    ///
+    /// assert_eq!(a.eval("rand() + rand()").unwrap(), b.eval("rand() + rand()").unwrap());
+    /// ```
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+        self.rng.set(None);
+    }
+
+    /// Get whether `self` guarantees reproducible results.
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Set whether `env` may read environment variables, which it may by
+    /// default. Frontends evaluating expressions from other users, such as a
+    /// server, should not allow it.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - Whether environment variables may be read.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// std::env::set_var("EXPRESSO_WIDTH", "3");
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(6.0, evaluator.eval(r#"2 * env("EXPRESSO_WIDTH")"#).unwrap());
+    ///
+    /// evaluator.set_allow_env(false);
+    /// assert!(evaluator.eval(r#"env("EXPRESSO_WIDTH")"#).is_err());
+    /// ```
+    pub fn set_allow_env(&mut self, allow: bool) {
+        self.deny_env = !allow;
+    }
+
+    /// Get whether `env` may read environment variables.
+    pub fn allow_env(&self) -> bool {
+        !self.deny_env
+    }
+
+    /// Set whether `load` may read files, which it may by default.
+    /// Frontends evaluating expressions from other users, such as a server,
+    /// should not allow it.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - Whether files may be read.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let path = std::env::temp_dir().join("expresso-load-example.txt");
+    /// std::fs::write(&path, "1 2\n3.5\n").unwrap();
+    /// let input = format!("load({:?})", path.to_str().unwrap());
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!(Value::List(vec![1.0, 2.0, 3.5]), evaluator.eval_value(&input).unwrap());
+    ///
+    /// evaluator.set_allow_files(false);
+    /// assert!(evaluator.eval_value(&input).is_err());
+    /// ```
+    pub fn set_allow_files(&mut self, allow: bool) {
+        self.deny_files = !allow;
+    }
+
+    /// Get whether `load` may read files.
+    pub fn allow_files(&self) -> bool {
+        !self.deny_files
+    }
+
+    /// Set whether `stdin` may read standard input, which it may not by
+    /// default, since only frontends that do not read their own input from
+    /// it, such as one evaluating a single expression, can share it.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - Whether standard input may be read.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// assert!(Evaluator::new().eval("sum(stdin())").is_err());
+    /// ```
+    pub fn set_allow_stdin(&mut self, allow: bool) {
+        self.allow_stdin = allow;
+    }
+
+    /// Get whether `stdin` may read standard input.
+    pub fn allow_stdin(&self) -> bool {
+        self.allow_stdin
+    }
+
+    /// Read the numbers from standard input the first time they are needed,
+    /// and get the same numbers every time after.
+    fn read_stdin(&self) -> Result<Vec<f64>> {
+        if !self.allow_stdin {
+            bail!(Message::new(Code::StdinNotAllowed))
+        }
+
+        if let Some(list) = &*self.stdin.borrow() {
+            return Ok(list.clone());
+        }
+
+        let mut data = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut data) {
+            bail!(Code::CouldNotReadStdin.with(&[&e]))
+        }
+
+        let list = parse_numbers(&data, "standard input")?;
+        *self.stdin.borrow_mut() = Some(list.clone());
+
+        Ok(list)
+    }
+
+    /// Set whether an unknown name that is an obvious typo of a known one,
+    /// i.e. the only known name a single edit away from it, is treated as the
+    /// known name, noting the correction.
+    ///
+    /// # Arguments
+    ///
+    /// * `autocorrect` - Whether to correct typos.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert!(evaluator.eval("sqr(4)").is_err());
+    ///
+    /// evaluator.set_autocorrect(true);
+    /// assert_eq!(2.0, evaluator.eval("sqr(4)").unwrap());
+    /// assert_eq!(vec!["assuming 'sqrt' for 'sqr'"], evaluator.take_notes());
+    /// ```
+    pub fn set_autocorrect(&mut self, autocorrect: bool) {
+        self.autocorrect = autocorrect;
+    }
+
+    /// Get whether `self` corrects obvious typos of known names.
+    pub fn autocorrect(&self) -> bool {
+        self.autocorrect
+    }
+
+    /// Take the notes made about the expressions evaluated since the notes
+    /// were last taken, such as typos that were corrected.
+    pub fn take_notes(&self) -> Vec<String> {
+        self.notes.take()
+    }
+
+    /// Take the warnings about the expression evaluated last, such as a
+    /// number which could not be held exactly or a result so large that
+    /// calculations with it may overflow.
+    ///
+    /// Unlike errors, warnings do not stop the expression from being
+    /// evaluated. They are cleared whenever another expression is evaluated.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, message::Code, token::Span};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// evaluator.eval("18446744073709551615 + 1").unwrap();
+    /// let warnings = evaluator.take_warnings();
+    /// assert_eq!(Code::InexactInteger, warnings[0].code);
+    /// assert_eq!(Some(Span::new(0, 20)), warnings[0].span);
+    ///
+    /// evaluator.eval("10^305 * 10").unwrap();
+    /// assert_eq!(Code::NearOverflow, evaluator.take_warnings()[0].code);
+    ///
+    /// // the number is written in scientific notation, not in full
+    /// evaluator.eval("exp(709)").unwrap();
+    /// let warning = &evaluator.take_warnings()[0];
+    /// assert!(warning.message.starts_with("8.218407461554972e307 is close"));
+    ///
+    /// evaluator.eval("2^53 + 9007199254740992").unwrap();
+    /// assert!(evaluator.take_warnings().is_empty());
+    /// ```
+    pub fn take_warnings(&self) -> Vec<Diagnostic> {
+        self.warnings.take()
+    }
+
+    /// Warn about the expression being evaluated, unless the same warning
+    /// has already been given.
+    pub(crate) fn warn(&self, span: Option<Span>, message: Message) {
+        let warning = Diagnostic::warning(span, message);
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    /// Warn about the integers in `tokens`, which were lexed from `input`,
+    /// which are too large to be held exactly by a float.
+    fn warn_inexact(&self, input: &str, tokens: &[(Token, Span)]) {
+        for (t, span) in tokens {
+            let n = match t {
+                Token::Number(n) if n.abs() >= MAX_EXACT => *n,
+                _ => continue,
+            };
+
+            let text: String = input
+                .chars()
+                .skip(span.start)
+                .take(span.end - span.start)
+                .collect();
+            let digits = text.trim_start_matches('0');
+            if digits.chars().all(|c| c.is_ascii_digit())
+                && digits != format!("{:.0}", n)
+            {
+                self.warn(
+                    Some(*span),
+                    Code::InexactInteger.with(&[&text, &format!("{:.0}", n)]),
+                );
+            }
+        }
+    }
+
+    /// Warn about the result `value` if it is close to overflowing.
+    ///
+    /// Exact integers are warned about too, since they overflow as soon as
+    /// they are used as numbers.
+    fn warn_result(&self, value: &Value) {
+        let n = match value {
+            Value::Number(n) => *n,
+            #[cfg(feature = "bigint")]
+            Value::Integer(n) => to_f64(n),
+            _ => return,
+        };
+
+        if n.is_finite() && n.abs() >= NEAR_OVERFLOW {
+            self.warn(None, Code::NearOverflow.with(&[&message::number(n)]));
+        }
+    }
+
+    /// Make a note about the expression being evaluated, unless the same note
+    /// has already been made.
+    pub(crate) fn note(&self, note: String) {
+        let mut notes = self.notes.borrow_mut();
+        if !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+
+    /// Get every name known to `self` which may be called if `call` is true,
+    /// or which holds a value otherwise, including builtins.
+    pub(crate) fn names(&self, call: bool) -> Vec<&str> {
+        if call {
+            FuncKind::all()
+                .map(|f| f.name())
+                .chain(self.functions.keys().map(String::as_str))
+                .chain(self.templates.keys().map(String::as_str))
+                .chain(self.aliases.keys().map(String::as_str))
+                .collect()
+        } else {
+            Constant::all()
+                .map(|c| c.name())
+                .chain(self.constants.keys().map(String::as_str))
+                .chain(self.variables.keys().map(String::as_str))
+                .chain(self.aliases.keys().map(String::as_str))
+                .collect()
+        }
+    }
+
+    /// Whether operators and builtin functions apply to numbers as plain
+    /// floats, as they do with the default settings, and no builtin is
+    /// deprecated, so that an expression compiled into a program evaluates
+    /// the same as walking its tree.
+    pub(crate) fn plain(&self) -> bool {
+        self.rounding == Rounding::default()
+            && self.nan_policy == NanPolicy::default()
+            && !self.strict_division
+            && !self.detect_overflow
+            && !self.compensated
+            && !self.exact_trig
+            && !self.symbolic
+            && self.domain == Domain::Float
+            && self.width.is_none()
+            && self.deprecated.is_empty()
+    }
+
+    /// Generate the next number for `rand`, seeding the generator if this is
+    /// the first.
+    pub(crate) fn random(&self) -> f64 {
+        let mut rng = self.rng.get().unwrap_or_else(|| {
+            match (self.seed, self.deterministic) {
+                (Some(seed), _) => Rng::new(seed),
+                (None, true) => Rng::new(DETERMINISTIC_SEED),
+                (None, false) => Rng::from_entropy(),
+            }
+        });
+
+        let x = rng.next_f64();
+        self.rng.set(Some(rng));
+
+        x
+    }
+
+    /// Check that the expression made up of `tokens` is within the limits
+    /// of `self`, failing with an error pointing at where it exceeds one if
+    /// it is not.
+    ///
+    /// Tokens which do not make up an expression are not reported here, but
+    /// left to be reported when they are evaluated.
+    pub(crate) fn check_limits<T: Spanned>(
+        &self,
+        tokens: impl Iterator<Item = T> + Clone,
+    ) -> Result<()> {
+        let Limits {
+            tokens: max_tokens,
+            depth,
+            nesting,
+        } = self.limits;
+
+        if let Some(limit) = max_tokens {
+            if let Some(t) = tokens.clone().nth(limit) {
+                return Err(spanned(
+                    t.span(),
+                    Code::TooManyTokens.with(&[&limit]),
+                ));
+            }
+        }
+
+        if let Some(limit) = nesting {
+            // whether each bracket that is open is the start of a call
+            let mut open = vec![];
+            let mut calls = 0;
+            let mut callee = false;
+
+            for t in tokens.clone() {
+                match t.token() {
+                    Token::LParen | Token::LBracket => {
+                        let call = callee && t.token() == &Token::LParen;
+                        open.push(call);
+
+                        if call {
+                            calls += 1;
+                            if calls > limit {
+                                return Err(spanned(
+                                    t.span(),
+                                    Code::NestedCalls.with(&[&limit]),
+                                ));
+                            }
+                        }
+                    }
+                    Token::RParen | Token::RBracket => {
+                        let call = open.pop().unwrap_or(false);
+                        calls -= usize::from(call);
+                    }
+                    _ => (),
+                }
+
+                callee = matches!(t.token(), Token::Func(_) | Token::Ident(_));
+            }
+        }
+
+        if let Some(limit) = depth {
+            let mut arena = ExprArena::new();
+
+            if let Some(root) =
+                parse_limited(&mut arena, &mut tokens.peekable(), limit)?
+            {
+                if arena.depth(root) > limit {
+                    return Err(too_deep(arena[root].span, limit));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start measuring the work done evaluating an expression from scratch.
+    pub(crate) fn start_metering(&self) {
+        let time_limit = self.time_limit.filter(|_| !self.deterministic);
+
+        self.meter.set(Meter {
+            steps: 0,
+            start: time_limit.map(|_| Instant::now()),
+        });
+    }
+
+    /// Count a step of evaluating an expression, failing if it exceeds the
+    /// limits of `self`.
+    pub(crate) fn step(&self) -> Result<()> {
+        self.step_by(1)
+    }
+
+    /// Count `n` steps of evaluating an expression at once, such as one for
+    /// each element of a list a builtin works through, failing if they exceed
+    /// the limits of `self`.
+    pub(crate) fn step_by(&self, n: usize) -> Result<()> {
+        let mut meter = self.meter.get();
+        // casting is safe since a usize is at most 64 bits
+        meter.steps = meter.steps.saturating_add(n as u64);
+        self.meter.set(meter);
+
+        if let Some(limit) = self.step_limit {
+            if meter.steps > limit {
+                bail!(Code::StepBudgetExceeded.with(&[&limit]))
+            }
+        }
+
+        if let (Some(limit), Some(start)) = (self.time_limit, meter.start) {
+            if start.elapsed() > limit {
+                bail!(Code::TimeBudgetExceeded.with(&[&limit.as_millis()]))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set a callback which is called periodically with how far through a
+    /// long evaluation `self` is, such as mapping over a large list or
+    /// tabulating an expression, e.g. so that a frontend can show a progress
+    /// bar.
+    ///
+    /// The callback is called every `PROGRESS_INTERVAL` iterations and once
+    /// the last is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - The callback, or `None` to stop reporting progress.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use cocoa::evaluator::{Evaluator, Progress};
+    ///
+    /// let reports = Arc::new(Mutex::new(vec![]));
+    /// let mut evaluator = Evaluator::new();
+    ///
+    /// let sink = Arc::clone(&reports);
+    /// evaluator.set_progress(Some(Box::new(move |progress| {
+    ///     sink.lock().unwrap().push(progress);
+    /// })));
+    /// evaluator.eval("sum(map(x^2, x, range(1, 2000)))").unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         Progress { task: "map", done: 1024, total: 2000 },
+    ///         Progress { task: "map", done: 2000, total: 2000 },
+    ///     ],
+    ///     *reports.lock().unwrap()
+    /// );
+    /// ```
+    pub fn set_progress(
+        &mut self,
+        progress: Option<Box<dyn Fn(Progress) + Send>>,
+    ) {
+        self.progress = progress.map(ProgressHook);
+    }
+
+    /// Report that `done` of the `total` iterations of `task` are done to the
+    /// progress callback of `self`, if it is due to be told.
+    pub(crate) fn report(&self, task: &'static str, done: usize, total: usize) {
+        if let Some(ProgressHook(progress)) = &self.progress {
+            if done.is_multiple_of(PROGRESS_INTERVAL) || done == total {
+                progress(Progress { task, done, total });
+            }
+        }
+    }
+
+    /// Get the result cached for `key`, or calculate it with `compute` and
+    /// cache it if it succeeds.
+    pub(crate) fn memoize(
+        &self,
+        key: CacheKey,
+        compute: impl FnOnce() -> Result<Value>,
+    ) -> Result<Value> {
+        if let Some(value) = self.cache.borrow().results.get(&key) {
+            return Ok(value.clone());
+        }
+
+        let value = compute()?;
+
+        let mut cache = self.cache.borrow_mut();
+        if cache.size > 0 {
+            let size = cache.size;
+            cache.shrink_to(size - 1);
+            cache.results.insert(key.clone(), value.clone());
+            cache.order.push_back(key);
+        }
+
+        Ok(value)
+    }
+
+    /// Check the result `n` of an operation against the NaN policy of `self`,
+    /// using `describe` to explain the operation if it is rejected.
+    pub(crate) fn check_finite(
+        &self,
+        n: f64,
+        describe: impl FnOnce() -> Message,
+    ) -> Result<f64> {
+        if self.nan_policy == NanPolicy::Strict && !n.is_finite() {
+            bail!(describe())
+        }
+
+        Ok(n)
+    }
+
+    /// Format a result which may be a list for display, formatting each
+    /// number as `format` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The result to format.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_precision(Some(2));
+    ///
+    /// assert_eq!("[0.33, 2]", evaluator.format_value(&Value::List(vec![1.0 / 3.0, 2.0])));
+    /// ```
+    pub fn format_value(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => self.format(*n),
+            Value::List(list) => {
+                let elements: Vec<String> =
+                    list.iter().map(|&n| self.format(n)).collect();
+
+                format!("[{}]", elements.join(", "))
+            }
+            #[cfg(feature = "bigint")]
+            Value::Integer(n) => self.format_big(n),
+            Value::Symbolic(s) => s.to_string(),
+        }
+    }
+
+    /// Format the exact integer `n` in the radix of `self`, without rounding
+    /// it as a number would be.
+    #[cfg(feature = "bigint")]
+    fn format_big(&self, n: &num_bigint::BigInt) -> String {
+        let (radix, prefix) = match self.radix {
+            Radix::Decimal => return n.to_string(),
+            Radix::Hexadecimal => (16, "0x"),
+            Radix::Octal => (8, "0o"),
+            Radix::Binary => (2, "0b"),
+        };
+        let sign = if n.sign() == num_bigint::Sign::Minus {
+            "-"
+        } else {
+            ""
+        };
+
+        format!("{}{}{}", sign, prefix, n.magnitude().to_str_radix(radix))
+    }
+
+    /// Format a result for display, rounding it to the precision of `self`
+    /// using the rounding mode of `self`, and displaying integers in the
+    /// radix of `self`.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The result to format.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, math::Rounding};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// assert_eq!("0.125", evaluator.format(0.125));
+    ///
+    /// evaluator.set_precision(Some(2));
+    /// assert_eq!("0.13", evaluator.format(0.125));
+    ///
+    /// evaluator.set_rounding(Rounding::HalfEven);
+    /// assert_eq!("0.12", evaluator.format(0.125));
+    /// ```
+    pub fn format(&self, n: f64) -> String {
+        let n = self.snapped(n).unwrap_or(n);
+        let n = match self.precision {
+            Some(digits) => {
+                let digits = i32::try_from(digits).unwrap_or(i32::MAX);
+                round(n, digits, self.rounding)
+            }
+            None => n,
+        };
+
+        // avoid displaying results such as -0.001 rounded to 0 as "-0"
+        let n = if n == 0.0 { 0.0 } else { n };
+
+        if self.exact_trig && self.precision.is_none() {
+            if let Some(exact) = Surd::recognize(n) {
+                return exact.to_string();
+            }
+        }
+
+        if self.radix != Radix::Decimal && n.fract() == 0.0 {
+            if let Some(s) = self.format_integer(n) {
+                return s;
+            }
+        }
+
+        n.to_string()
+    }
+
+    /// Format the integer `n` in the radix of `self`, returning `None` if it
+    /// is too large to be converted to a `u64`.
+    fn format_integer(&self, n: f64) -> Option<String> {
+        // 2^64, the smallest magnitude too large for a u64
+        const LIMIT: f64 = 18446744073709551616.0;
+
+        let (sign, bits) = match self.width {
+            // negative numbers which fit in the width are displayed as their
+            // two's complement representation
+            Some(width) if n < 0.0 && n >= -(2f64.powi(width as i32 - 1)) => {
+                // casting is safe since n is an integer that fits in an i64
+                ("", n as i64 as u64 & (u64::MAX >> (64 - width)))
+            }
+            _ if n.abs() < LIMIT => {
+                // casting is safe since the magnitude of n fits in a u64
+                (if n < 0.0 { "-" } else { "" }, n.abs() as u64)
+            }
+            _ => return None,
+        };
+
+        let digits = match self.radix {
+            Radix::Decimal => bits.to_string(),
+            Radix::Hexadecimal => format!("0x{:x}", bits),
+            Radix::Octal => format!("0o{:o}", bits),
+            Radix::Binary => format!("0b{:b}", bits),
+        };
+
+        Some(format!("{}{}", sign, digits))
+    }
+
+    /// Check `args` against the arity and domain of the builtin `f`, then
+    /// evaluate `f` with them using the settings of `self`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The builtin function to evaluate.
+    /// * `args` - The arguments to evaluate the function with.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(f = f.name())))]
+    pub fn call(&self, f: FuncKind, args: &[f64]) -> Result<f64> {
+        match f {
+            FuncKind::Round => {
+                check_args(f.name(), f.arity(), |i| f.domain(i), args)?;
+
+                Ok(round_args(args, self.rounding))
+            }
+            FuncKind::Rand => {
+                check_args(f.name(), f.arity(), |i| f.domain(i), args)?;
+
+                Ok(self.random())
+            }
+            FuncKind::Sum | FuncKind::Avg if self.compensated => {
+                check_args(f.name(), f.arity(), |i| f.domain(i), args)?;
+
+                let sum = compensated_sum(args);
+                match f {
+                    FuncKind::Sum => Ok(sum),
+                    _ => Ok(sum / args.len() as f64),
+                }
+            }
+            FuncKind::Sin | FuncKind::Cos | FuncKind::Tan
+                if self.exact_trig =>
+            {
+                check_args(f.name(), f.arity(), |i| f.domain(i), args)?;
+
+                if let Some(angle) = trig_pole(f, args[0]) {
+                    bail!(Code::UndefinedAt.with(&[&f.name(), &angle]))
+                }
+
+                match exact::trig(f, args[0]) {
+                    Some(exact) => Ok(exact.value()),
+                    None => f.call(args),
+                }
+            }
+            _ => f.call(args),
+        }
+    }
+
+    /// Evaluate the function `f`, which takes or returns lists, with `args`
+    /// using the settings of `self`.
+    ///
+    /// This is the same as `FuncKind::call_list`, except that functions
+    /// which depend on the evaluator, such as `stdin`, can be evaluated, and
+    /// the work they do counts towards the step and time limits of `self`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to evaluate.
+    /// * `args` - The arguments to evaluate the function with.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(f = f.name())))]
+    pub fn call_list(&self, f: FuncKind, args: &[Value]) -> Result<Value> {
+        self.step_by(work(f, args))?;
+
+        let value = match f {
+            FuncKind::Stdin if args.is_empty() => {
+                Value::List(self.read_stdin()?)
+            }
+            // the roots are refined for as many rounds as they take to
+            // converge, which cannot be known beforehand
+            FuncKind::PolyRoots if args.len() == 1 => {
+                let coefficients = args[0].clone().into_list();
+                let n = coefficients.len();
+
+                Value::List(poly_roots_with(&coefficients, &mut || {
+                    self.step_by(n)
+                })?)
+            }
+            _ => f.call_list(args)?,
+        };
+
+        // making a list, such as a range, takes a step for each element
+        if let Value::List(list) = &value {
+            self.step_by(list.len())?;
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluate the function `f`, which takes a single string, with `arg`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - A function for which `takes_string` is true.
+    /// * `arg` - The string to evaluate the function with.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(f = f.name())))]
+    pub(crate) fn call_string(&self, f: FuncKind, arg: &str) -> Result<Value> {
+        match f {
+            FuncKind::Env => {
+                if !self.allow_env() {
+                    bail!(Message::new(Code::EnvNotAllowed))
+                }
+
+                let value = match std::env::var(arg) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        bail!(Code::EnvNotSet.with(&[&arg]))
+                    }
+                };
+
+                match value.trim().parse::<f64>() {
+                    Ok(n) => Ok(n.into()),
+                    Err(_) => bail!(Code::EnvNotANumber.with(&[&arg, &value])),
+                }
+            }
+            FuncKind::Load => {
+                if !self.allow_files() {
+                    bail!(Message::new(Code::FilesNotAllowed))
+                }
+
+                let data = match fs::read_to_string(arg) {
+                    Ok(data) => data,
+                    Err(e) => bail!(Code::CouldNotRead.with(&[&arg, &e])),
+                };
+
+                parse_numbers(&data, &format!("'{}'", arg)).map(Value::List)
+            }
+            _ => bail!(Code::DoesNotTakeString.with(&[&f.name()])),
+        }
+    }
+
+    /// Lex, parse and evaluate `input`.
+    ///
+    /// Unlike `parse`, the whole input must make up a single expression, so
+    /// stray tokens such as an unmatched `)` are reported as errors.
+    ///
+    /// Since the input is lexed with `lex_spanned`, errors that point at a
+    /// specific part of the input can be downcast to a `SpanError`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The expression to evaluate.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// assert_eq!(14.0, evaluator.eval("2 + 3 * 4").unwrap());
+    /// assert!(evaluator.eval("(2 + 3)) * 4").is_err());
+    /// ```
+    pub fn eval(&self, input: &str) -> Result<f64> {
+        let value = self.eval_value(input)?;
+        self.check_range(&value)?;
+
+        value.into_number()
+    }
+
+    /// Lex, parse and evaluate `input`, which unlike `eval` may evaluate to a
+    /// list as well as a number.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The expression to evaluate.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// assert_eq!(Value::Number(3.0), evaluator.eval_value("nth([4, 3], 2)").unwrap());
+    /// assert_eq!(
+    ///     Value::List(vec![3.0, 2.0, 1.0]),
+    ///     evaluator.eval_value("reverse(range(1, 3))").unwrap()
+    /// );
+    /// assert!(evaluator.eval("[1, 2]").is_err());
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self))
+    )]
+    pub fn eval_value(&self, input: &str) -> Result<Value> {
+        self.warnings.take();
+
+        let tokens = lex_spanned(&mut input.chars().peekable())?;
+        self.warn_inexact(input, &tokens);
+        self.check_limits(tokens.iter().cloned())?;
+
+        let mut tokens = tokens.into_iter().peekable();
+
+        let value = parse_value_with(self, &mut tokens, 0)?;
+        expect_end(&mut tokens)?;
+
+        self.warn_result(&value);
+        Ok(value)
+    }
+
+    /// Evaluate `input` like `eval_value`, returning the result along with
+    /// the warnings about it, which are given even if evaluating it failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The expression to evaluate.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{error::Severity, evaluator::Evaluator, value::Value};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// let (result, warnings) = evaluator.eval_with_warnings("9007199254740993");
+    /// assert_eq!(Value::Number(9007199254740992.0), result.unwrap());
+    /// assert_eq!(Severity::Warning, warnings[0].severity);
+    /// assert_eq!(
+    ///     "9007199254740993 is too large to be held exactly, so it is \
+    ///     rounded to 9007199254740992",
+    ///     warnings[0].message
+    /// );
+    ///
+    /// let (result, warnings) = evaluator.eval_with_warnings("1 + 2");
+    /// assert_eq!(Value::Number(3.0), result.unwrap());
+    /// assert!(warnings.is_empty());
+    /// ```
+    pub fn eval_with_warnings(
+        &self,
+        input: &str,
+    ) -> (Result<Value>, Vec<Diagnostic>) {
+        let result = self.eval_value(input);
+
+        (result, self.take_warnings())
+    }
+
+    /// Parse and evaluate tokens that have already been lexed into a packed
+    /// stream, like `eval_value`.
+    ///
+    /// This avoids holding a large expression as a vector of `Token`s, and
+    /// lets an expression be lexed once and evaluated many times, e.g. as
+    /// its variables change.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - The tokens of the expression to evaluate.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, packed::PackedTokens, value::Value};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// let tokens = PackedTokens::lex("x^2 + 1").unwrap();
+    ///
+    /// evaluator.set_variable("x", 3.0).unwrap();
+    /// assert_eq!(Value::Number(10.0), evaluator.eval_packed(&tokens).unwrap());
+    ///
+    /// evaluator.set_variable("x", 4.0).unwrap();
+    /// assert_eq!(Value::Number(17.0), evaluator.eval_packed(&tokens).unwrap());
+    /// ```
+    pub fn eval_packed(&self, tokens: &PackedTokens) -> Result<Value> {
+        self.warnings.take();
+        self.check_limits(tokens.iter())?;
+
+        let mut tokens = tokens.iter().peekable();
+
+        let value = parse_value_with(self, &mut tokens, 0)?;
+        expect_end(&mut tokens)?;
+
+        self.warn_result(&value);
+        Ok(value)
+    }
+
+    /// Evaluate the expression tree `expr`, like `eval_value`, so that an
+    /// expression can be parsed once with `Expr::from_str`, inspected or
+    /// transformed, e.g. with `Expr::simplify`, and then evaluated.
+    ///
+    /// Since a tree does not have to come from any input, errors do not
+    /// point at where in it they happened.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The expression to evaluate.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::Expr, evaluator::Evaluator, value::Value};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// let expr: Expr = "x * 1 + 0 + x^2".parse().unwrap();
+    /// let simplified = expr.simplify();
+    /// assert_eq!("x + x ^ 2", simplified.to_string());
+    ///
+    /// evaluator.set_variable("x", 3.0).unwrap();
+    /// let eval = |expr: &Expr| evaluator.eval_expr(expr).unwrap();
+    /// assert_eq!(Value::Number(12.0), eval(&expr));
+    /// assert_eq!(Value::Number(12.0), eval(&simplified));
+    ///
+    /// let list: Expr = "map(x^2, x, [1, 2])".parse().unwrap();
+    /// assert_eq!(Value::List(vec![1.0, 4.0]), eval(&list));
+    /// assert!(evaluator.eval_expr(&"y + 1".parse().unwrap()).is_err());
+    /// ```
+    pub fn eval_expr(&self, expr: &Expr) -> Result<Value> {
+        self.warnings.take();
+        // limits are on the tokens an expression is written with
+        self.check_limits(expr.to_tokens().into_iter())?;
+
+        self.start_metering();
+        let value = Interpreter::without_spans(self).eval(expr)?;
+
+        self.warn_result(&value);
+        Ok(value)
+    }
+
+    /// Evaluate the expression tree `expr` at each of `xs` as the value of
+    /// the variable `var`, returning the results in the same order.
+    ///
+    /// If the settings of `self` do not change how numbers are calculated
+    /// and the expression only refers to `var`, it is compiled once into a
+    /// program for a stack machine, which is then run for each value, making
+    /// this much quicker than evaluating it value by value, e.g. to sample a
+    /// curve for a plot.
+    ///
+    /// Results which cannot be evaluated, such as `sqrt(x)` for negative x,
+    /// are NaN, but exceeding the limits of `self` is an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The expression to evaluate.
+    /// * `var` - The name of the variable.
+    /// * `xs` - The values of the variable to evaluate the expression at.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::Expr, evaluator::Evaluator, math::Rounding};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// let expr: Expr = "round(x) + y".parse().unwrap();
+    ///
+    /// evaluator.set_variable("y", 1.0).unwrap();
+    /// let ys = evaluator.eval_many(&expr, "x", &[2.5, 3.5]).unwrap();
+    /// assert_eq!(vec![4.0, 5.0], ys);
+    ///
+    /// evaluator.set_rounding(Rounding::HalfEven);
+    /// let ys = evaluator.eval_many(&expr, "x", &[2.5, 3.5]).unwrap();
+    /// assert_eq!(vec![3.0, 5.0], ys);
+    ///
+    /// evaluator.set_step_limit(Some(100));
+    /// let squares: Expr = "x^2".parse().unwrap();
+    /// assert!(evaluator.eval_many(&squares, "x", &[0.0; 10]).is_ok());
+    /// assert!(evaluator.eval_many(&squares, "x", &[0.0; 100]).is_err());
+    /// ```
+    pub fn eval_many(
+        &self,
+        expr: &Expr,
+        var: &str,
+        xs: &[f64],
+    ) -> Result<Vec<f64>> {
+        self.warnings.take();
+        self.check_limits(expr.to_tokens().into_iter())?;
+        self.start_metering();
+
+        let interpreter = Interpreter::without_spans(self);
+        let program = Program::compile(expr, &[var]).filter(|_| self.plain());
+        let mut stack = vec![];
+        let mut ys = Vec::with_capacity(xs.len());
+
+        for &x in xs {
+            let y = match &program {
+                Some(program) => program.run(self, &[x], &mut stack)?,
+                None => match interpreter.eval_at(expr, &[(var, x)]) {
+                    Ok(y) => y,
+                    // only failing because of the limits stops evaluating
+                    // the rest
+                    Err(e) if self.step_by(0).is_err() => return Err(e),
+                    Err(_) => f64::NAN,
+                },
+            };
+            ys.push(y);
+        }
+
+        Ok(ys)
+    }
+
+    /// Evaluate `input` as a calculator would as it is being typed, where
+    /// mistakes that have a sensible interpretation are warned about rather
+    /// than stopping it from being evaluated.
+    ///
+    /// Unmatched closing brackets and anything at the end which is missing
+    /// something after it, such as an operator without its right operand,
+    /// are ignored, and brackets left open are closed. The result is `None`
+    /// if the expression still could not be evaluated or is not a number,
+    /// in which case the last diagnostic is the error it failed with. Notes
+    /// the evaluator made, e.g. about autocorrections, are also included as
+    /// warnings.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The expression to evaluate.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{error::Severity, evaluator::Evaluator, token::Span};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// let (n, diagnostics) = evaluator.eval_tolerant("(2 + 3) * 4 +");
+    /// assert_eq!(Some(20.0), n);
+    /// assert_eq!("ignored '+' at the end", diagnostics[0].message);
+    /// assert_eq!(Some(Span::new(12, 13)), diagnostics[0].span);
+    ///
+    /// let (n, diagnostics) = evaluator.eval_tolerant("sqrt(16))");
+    /// assert_eq!(Some(4.0), n);
+    /// assert_eq!("ignored unmatched ')'", diagnostics[0].message);
+    ///
+    /// let (n, diagnostics) = evaluator.eval_tolerant("max(1, 2 * (3");
+    /// assert_eq!(Some(6.0), n);
+    /// assert_eq!(2, diagnostics.len());
+    ///
+    /// let (n, diagnostics) = evaluator.eval_tolerant("1 + y");
+    /// assert_eq!(None, n);
+    /// assert_eq!(Severity::Error, diagnostics[0].severity);
+    ///
+    /// assert_eq!((Some(3.0), vec![]), evaluator.eval_tolerant("1 + 2"));
+    /// ```
+    pub fn eval_tolerant(&self, input: &str) -> (Option<f64>, Vec<Diagnostic>) {
+        self.warnings.take();
+
+        let mut tokens = match lex_spanned(&mut input.chars().peekable()) {
+            Ok(tokens) => tokens,
+            Err(e) => return (None, vec![Diagnostic::error(&e)]),
+        };
+        self.warn_inexact(input, &tokens);
+
+        let mut diagnostics = drop_unmatched(&mut tokens, input);
+        diagnostics.extend(finish(&mut tokens, input));
+        if tokens.is_empty() {
+            return (None, diagnostics);
+        }
+
+        let result = self.check_limits(tokens.iter().cloned()).and_then(|_| {
+            let mut tokens = tokens.into_iter().peekable();
+
+            let value = parse_value_with(self, &mut tokens, 0)?;
+            expect_end(&mut tokens)?;
+
+            self.warn_result(&value);
+            self.check_range(&value)?;
+            value.into_number()
+        });
+
+        diagnostics.extend(
+            self.take_notes()
+                .into_iter()
+                .map(|note| Diagnostic::warning(None, note)),
+        );
+        diagnostics.extend(self.take_warnings());
+
+        match result {
+            Ok(n) => (Some(n), diagnostics),
+            Err(e) => {
+                diagnostics.push(Diagnostic::error(&e));
+                (None, diagnostics)
+            }
+        }
+    }
+
+    /// Evaluate `input` at each value of the variable `var` from `start` to
+    /// `stop` in increments of `step`, returning each value of `var` paired
+    /// with the result of the expression at that value.
+    ///
+    /// Evaluating at one value failing does not stop the rest of the table
+    /// from being evaluated, so that e.g. `sqrt(x)` can be tabulated across
+    /// zero. `var` is bound to each value in turn, then restored to what it
+    /// was bound to before, if anything.
+    ///
+    /// Within an expression, `table(expr, var, start, stop, step)` gives the
+    /// list of the results instead, failing if any of them does.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The expression to tabulate.
+    /// * `var` - The name of the variable to vary.
+    /// * `start` - The first value of the variable.
+    /// * `stop` - The value of the variable to stop at, which is included if
+    ///   it is a whole number of steps from `start`.
+    /// * `step` - The amount to increment the variable by, which is negative
+    ///   to count down from `start` to `stop`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// let table = evaluator.table("x^2", "x", 0.0, 1.0, 0.5).unwrap();
+    /// let rows: Vec<(f64, f64)> =
+    ///     table.into_iter().map(|(x, y)| (x, y.unwrap())).collect();
+    ///
+    /// assert_eq!(vec![(0.0, 0.0), (0.5, 0.25), (1.0, 1.0)], rows);
+    /// assert!(evaluator.variable("x").is_none());
+    /// assert!(evaluator.table("x^2", "x", 0.0, 1.0, -0.5).is_err());
+    ///
+    /// let list = evaluator.eval_value("table(x^2, x, 0, 1, 0.5)").unwrap();
+    /// assert_eq!(Value::List(vec![0.0, 0.25, 1.0]), list);
+    /// assert!(evaluator.eval_value("table(sqrt(x), x, -1, 1)").is_err());
+    /// ```
+    pub fn table(
+        &mut self,
+        input: &str,
+        var: &str,
+        start: f64,
+        stop: f64,
+        step: f64,
+    ) -> Result<Vec<(f64, Result<f64>)>> {
+        // report syntax errors once, rather than on every row
+        input.parse::<Expr>()?;
+
+        let steps = count_steps(start, stop, step, TABLE_ROWS)?;
+        let previous = self.variable(var);
+        let mut rows = Vec::new();
+
+        for i in 0..=steps {
+            // multiplying rather than repeatedly adding avoids accumulating
+            // error in the values of the variable
+            let x = start + i as f64 * step;
+
+            self.set_variable(var, x)?;
+            rows.push((x, self.eval(input)));
+            self.report("table", i + 1, steps + 1);
+        }
+
+        match previous {
+            Some(value) => self.set_variable(var, value)?,
+            None => {
+                self.remove_variable(var);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+/// Get the number of steps calling the function of lists `f` with `args`
+/// takes before it makes its result, which is one for each element of the
+/// lists it is given, times the number of passes it makes over them.
+fn work(f: FuncKind, args: &[Value]) -> usize {
+    let len = |i: usize| elements(args.get(i..=i).unwrap_or_default());
+
+    match f {
+        // elimination works through the matrix once for each row
+        FuncKind::LinSolve => len(0).saturating_mul(len(1)),
+        // a reflection works through the points once for each coefficient
+        FuncKind::Fit => {
+            // casting is safe since a degree which is not a natural number is
+            // rejected, and huge ones saturate
+            let degree = args
+                .get(2)
+                .and_then(|degree| degree.clone().into_number().ok())
+                .map_or(1, |degree| degree as usize);
+
+            len(0).saturating_mul(degree.saturating_add(1))
+        }
+        _ => elements(args),
+    }
+}
+
+/// Get the angle `x` as a multiple of pi if it is a special angle at which
+/// the trigonometric function `f` is undefined, such as `tan` at `pi / 2`.
+fn trig_pole(f: FuncKind, x: f64) -> Option<Symbolic> {
+    let degrees = exact::special_angle(x)?;
+    if exact::trig_degrees(f, degrees).is_some() {
+        return None;
+    }
+
+    Symbolic::from(Rational::new(degrees, 180)?).checked_mul(&Symbolic::pi())
+}
+
+/// Parse the whitespace separated numbers in `data`, which was read from
+/// `source`, e.g. a file.
+fn parse_numbers(data: &str, source: &str) -> Result<Vec<f64>> {
+    let mut list = vec![];
+
+    for (i, line) in data.lines().enumerate() {
+        for word in line.split_whitespace() {
+            if list.len() == MAX_LEN {
+                bail!(Code::TooManyNumbers.with(&[&MAX_LEN, &source]))
+            }
+
+            match word.parse() {
+                Ok(n) => list.push(n),
+                Err(_) => bail!(Code::ExpectedNumberOnLine.with(&[
+                    &(i + 1),
+                    &source,
+                    &word
+                ])),
+            }
+        }
+    }
+
+    Ok(list)
+}
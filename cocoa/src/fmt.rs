@@ -0,0 +1,116 @@
+//! Formatting expressions as text which parses back to them, for programs
+//! which store expressions as text and read them again later.
+
+use anyhow::{bail, Result};
+
+use crate::{
+    ast::{Expr, ExprKind},
+    message::Code,
+    token::OpKind,
+};
+
+/// Format `expr` as text in its canonical form, which is the same as its
+/// `Display` output.
+///
+/// The output is stable: formatting the tree it parses back to gives the
+/// same text again, even for trees which were not made by the parser, such
+/// as those from `Expr::simplify`.
+///
+/// # Arguments
+///
+/// * `expr` - The expression to format.
+///
+/// # Examples
+/// ```
+/// use cocoa::{ast::Expr, fmt::format};
+///
+/// let expr: Expr = "(1+2)*x^-y".parse().unwrap();
+/// assert_eq!("(1 + 2) * x ^ -y", format(&expr));
+///
+/// let simple = "(x + 1) * (0 - 3)".parse::<Expr>().unwrap().simplify();
+/// let text = format(&simple);
+/// assert_eq!(text, format(&text.parse().unwrap()));
+/// ```
+pub fn format(expr: &Expr) -> String {
+    expr.to_string()
+}
+
+/// Check that `expr` parses back to itself once formatted, returning an error
+/// describing the difference if it does not.
+///
+/// Brackets which the formatter adds, and negative numbers which parse back
+/// as a minus applied to a number, do not count as differences, since they
+/// evaluate the same. Trees containing numbers which cannot be written,
+/// such as infinity, fail the check.
+///
+/// # Arguments
+///
+/// * `expr` - The expression to check.
+///
+/// # Examples
+/// ```
+/// use cocoa::{
+///     ast::{Expr, ExprKind},
+///     fmt::roundtrip_check,
+/// };
+///
+/// let expr: Expr = "-2 ^ 2 + f(x, [1, \"a\"])!".parse().unwrap();
+/// assert!(roundtrip_check(&expr).is_ok());
+///
+/// let simple = "(x - 3) * 2".parse::<Expr>().unwrap().simplify();
+/// assert!(roundtrip_check(&simple).is_ok());
+///
+/// let inf = Expr::new(ExprKind::Number(f64::INFINITY));
+/// assert_eq!(
+///     "'inf' parses back as the identifier 'inf'",
+///     roundtrip_check(&inf).unwrap_err().to_string(),
+/// );
+/// ```
+pub fn roundtrip_check(expr: &Expr) -> Result<()> {
+    let formatted = format(expr);
+    let reparsed = match formatted.parse::<Expr>() {
+        Ok(reparsed) => reparsed,
+        Err(e) => bail!(Code::DoesNotParseBack.with(&[&formatted, &e])),
+    };
+
+    if canonical(&reparsed) != canonical(expr) {
+        match reparsed.kind {
+            ExprKind::Ident(name) => {
+                bail!(Code::ParsesBackAsIdentifier.with(&[&formatted, &name]))
+            }
+            _ => bail!(Code::ParsesBackAs.with(&[&formatted, &reparsed])),
+        }
+    }
+
+    Ok(())
+}
+
+/// Get `expr` without the differences `roundtrip_check` ignores, i.e. with
+/// its groups removed and minus signs applied to the numbers they negate.
+fn canonical(expr: &Expr) -> Expr {
+    let kind = match &expr.kind {
+        ExprKind::Group(e) => return canonical(e),
+        ExprKind::Unary(OpKind::Minus, e) => match canonical(e).kind {
+            ExprKind::Number(n) => ExprKind::Number(-n),
+            e => ExprKind::Unary(OpKind::Minus, Box::new(Expr::new(e))),
+        },
+        ExprKind::Unary(op, e) => ExprKind::Unary(*op, Box::new(canonical(e))),
+        ExprKind::Postfix(op, e) => {
+            ExprKind::Postfix(*op, Box::new(canonical(e)))
+        }
+        ExprKind::Binary(op, lhs, rhs) => ExprKind::Binary(
+            *op,
+            Box::new(canonical(lhs)),
+            Box::new(canonical(rhs)),
+        ),
+        ExprKind::Call(callee, args) => {
+            ExprKind::Call(callee.clone(), args.iter().map(canonical).collect())
+        }
+        ExprKind::List(elements) => {
+            ExprKind::List(elements.iter().map(canonical).collect())
+        }
+        kind => kind.clone(),
+    };
+
+    Expr::new(kind)
+}
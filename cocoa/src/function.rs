@@ -0,0 +1,348 @@
+//! Metadata describing the arguments that functions accept, which is checked
+//! before a function is evaluated.
+
+use std::fmt;
+
+use anyhow::{bail, Result};
+
+use crate::message::Code;
+
+/// The number of arguments a function takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    /// Exactly the given number of arguments.
+    Exact(usize),
+    /// The given number of arguments or more.
+    AtLeast(usize),
+    /// Between the given minimum and maximum number of arguments, inclusive.
+    Between(usize, usize),
+}
+
+impl Arity {
+    /// Check whether a function with arity `self` can be called with `n`
+    /// arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of arguments.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::function::Arity;
+    ///
+    /// assert!(Arity::Exact(2).accepts(2));
+    /// assert!(!Arity::Exact(2).accepts(3));
+    /// assert!(Arity::AtLeast(1).accepts(3));
+    /// assert!(!Arity::AtLeast(1).accepts(0));
+    /// assert!(Arity::Between(1, 2).accepts(2));
+    /// ```
+    pub fn accepts(self, n: usize) -> bool {
+        match self {
+            Arity::Exact(arity) => n == arity,
+            Arity::AtLeast(arity) => n >= arity,
+            Arity::Between(min, max) => min <= n && n <= max,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, n) = match *self {
+            Arity::Exact(n) => (String::new(), n),
+            Arity::AtLeast(n) => ("at least ".to_string(), n),
+            Arity::Between(min, max) => (format!("{} to ", min), max),
+        };
+
+        write!(
+            f,
+            "{}{} argument{}",
+            prefix,
+            n,
+            if n == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// The set of values an argument of a function may take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Domain {
+    /// Any real number.
+    Real,
+    /// Numbers in the closed interval `[min, max]`.
+    Closed(f64, f64),
+    /// Numbers strictly greater than the given value.
+    Above(f64),
+    /// Numbers greater than or equal to the given value.
+    AtLeast(f64),
+    /// Any integer.
+    Integer,
+    /// Any non-negative integer.
+    Natural,
+    /// Any integer greater than 0.
+    PositiveInteger,
+    /// Any real number other than the given value.
+    Except(f64),
+    /// Integers in the closed interval `[min, max]`.
+    IntegerIn(f64, f64),
+}
+
+impl Domain {
+    /// Check whether `x` lies within `self`.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The value to check.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::function::Domain;
+    ///
+    /// assert!(Domain::Closed(-1.0, 1.0).contains(1.0));
+    /// assert!(!Domain::Above(0.0).contains(0.0));
+    /// assert!(Domain::AtLeast(0.0).contains(0.0));
+    /// assert!(!Domain::Integer.contains(0.5));
+    /// assert!(!Domain::Natural.contains(-1.0));
+    /// assert!(!Domain::Except(1.0).contains(1.0));
+    /// assert!(!Domain::PositiveInteger.contains(0.0));
+    /// assert!(!Domain::IntegerIn(1.0, 64.0).contains(8.5));
+    /// ```
+    pub fn contains(self, x: f64) -> bool {
+        match self {
+            Domain::Real => true,
+            Domain::Closed(min, max) => min <= x && x <= max,
+            Domain::Above(min) => x > min,
+            Domain::AtLeast(min) => x >= min,
+            Domain::Integer => x.fract() == 0.0,
+            Domain::Natural => x.fract() == 0.0 && x >= 0.0,
+            Domain::PositiveInteger => x.fract() == 0.0 && x > 0.0,
+            Domain::Except(value) => x != value,
+            Domain::IntegerIn(min, max) => {
+                x.fract() == 0.0 && min <= x && x <= max
+            }
+        }
+    }
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Domain::Real => write!(f, "(-inf, inf)"),
+            Domain::Closed(min, max) => write!(f, "[{}, {}]", min, max),
+            Domain::Above(min) => write!(f, "({}, inf)", min),
+            Domain::AtLeast(min) => write!(f, "[{}, inf)", min),
+            Domain::Integer => write!(f, "integers"),
+            Domain::Natural => write!(f, "non-negative integers"),
+            Domain::PositiveInteger => write!(f, "positive integers"),
+            Domain::IntegerIn(min, max) => {
+                write!(f, "integers in [{}, {}]", min, max)
+            }
+            Domain::Except(value) => {
+                write!(f, "(-inf, {}) U ({}, inf)", value, value)
+            }
+        }
+    }
+}
+
+/// The kinds of functions, operators and constants expresso supports, which
+/// they are grouped into in their documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Trignometric functions and conversions between angle units.
+    Trigonometry,
+    /// Exponentials, logarithms and roots.
+    Exponential,
+    /// Functions combining or rounding numbers, such as `max` and `round`.
+    Arithmetic,
+    /// Functions inspecting or manipulating floats, such as `ulp`.
+    FloatingPoint,
+    /// Probability distributions and random numbers.
+    Statistics,
+    /// Special functions, such as `lambertw`.
+    Special,
+    /// Sequences of integers and functions of primes and divisors.
+    NumberTheory,
+    /// Functions of the bits of integers.
+    Bits,
+    /// Functions for calculations to check themselves.
+    Testing,
+    /// Functions reading numbers from outside the expression.
+    Input,
+    /// Functions making and manipulating lists.
+    Lists,
+    /// Functions finding the roots of polynomials and solving equations.
+    Algebra,
+    /// Functions estimating integrals and solving differential equations.
+    Calculus,
+    /// Operators, such as `+`.
+    Operators,
+    /// Named constants, such as `pi`.
+    Constants,
+}
+
+impl Category {
+    /// Every category, in the order they are listed in documentation.
+    pub const ALL: &'static [Category] = &[
+        Category::Trigonometry,
+        Category::Exponential,
+        Category::Arithmetic,
+        Category::FloatingPoint,
+        Category::Statistics,
+        Category::Special,
+        Category::NumberTheory,
+        Category::Bits,
+        Category::Testing,
+        Category::Input,
+        Category::Lists,
+        Category::Algebra,
+        Category::Calculus,
+        Category::Operators,
+        Category::Constants,
+    ];
+
+    /// Iterate over every category, in the order they are listed in
+    /// documentation.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::function::Category;
+    ///
+    /// assert_eq!(Some(Category::Trigonometry), Category::all().next());
+    /// assert_eq!(Some(Category::Constants), Category::all().last());
+    /// ```
+    pub fn all() -> impl Iterator<Item = Category> {
+        Self::ALL.iter().copied()
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Category::Trigonometry => "trigonometry",
+            Category::Exponential => "exponents and logarithms",
+            Category::Arithmetic => "arithmetic",
+            Category::FloatingPoint => "floating point",
+            Category::Statistics => "statistics",
+            Category::Special => "special functions",
+            Category::NumberTheory => "number theory",
+            Category::Bits => "bits",
+            Category::Testing => "testing",
+            Category::Input => "input",
+            Category::Lists => "lists",
+            Category::Algebra => "algebra",
+            Category::Calculus => "calculus",
+            Category::Operators => "operators",
+            Category::Constants => "constants",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Check that `args` are acceptable arguments for the function `name`.
+///
+/// # Arguments
+///
+/// * `name` - The name of the function, used in error messages.
+/// * `arity` - The number of arguments the function takes.
+/// * `domain` - The domain of the function's argument at a given index.
+/// * `args` - The arguments the function is being called with.
+///
+/// # Examples
+/// ```
+/// use cocoa::function::{check_args, Arity, Domain};
+///
+/// let domain = |_| Domain::Closed(-1.0, 1.0);
+///
+/// assert!(check_args("asin", Arity::Exact(1), domain, &[0.5]).is_ok());
+/// assert_eq!(
+///     "asin expects an argument in [-1, 1], got 2",
+///     check_args("asin", Arity::Exact(1), domain, &[2.0])
+///         .unwrap_err()
+///         .to_string()
+/// );
+/// assert_eq!(
+///     "asin expects 1 argument, got 2",
+///     check_args("asin", Arity::Exact(1), domain, &[0.5, 0.5])
+///         .unwrap_err()
+///         .to_string()
+/// );
+/// assert_eq!(
+///     "max expects at least 1 argument, got 0",
+///     check_args("max", Arity::AtLeast(1), |_| Domain::Real, &[])
+///         .unwrap_err()
+///         .to_string()
+/// );
+/// ```
+pub fn check_args(
+    name: &str,
+    arity: Arity,
+    domain: impl Fn(usize) -> Domain,
+    args: &[f64],
+) -> Result<()> {
+    if !arity.accepts(args.len()) {
+        bail!(Code::WrongArity.with(&[&name, &arity, &args.len()]))
+    }
+
+    for (i, &arg) in args.iter().enumerate() {
+        check_arg(name, arity, i, domain(i), arg)?;
+    }
+
+    Ok(())
+}
+
+/// Check that `arg` is an acceptable value for the argument at index `i` of
+/// the function `name`, for functions whose arguments are not all numbers so
+/// cannot be checked at once with `check_args`.
+///
+/// # Arguments
+///
+/// * `name` - The name of the function, used in error messages.
+/// * `arity` - The number of arguments the function takes.
+/// * `i` - The index of the argument.
+/// * `domain` - The domain of the argument.
+/// * `arg` - The value of the argument.
+///
+/// # Examples
+/// ```
+/// use cocoa::function::{check_arg, Arity, Domain};
+///
+/// assert!(check_arg("nth", Arity::Exact(2), 1, Domain::PositiveInteger, 2.0).is_ok());
+/// assert_eq!(
+///     "nth expects argument 2 to be a positive integer, got 0",
+///     check_arg("nth", Arity::Exact(2), 1, Domain::PositiveInteger, 0.0)
+///         .unwrap_err()
+///         .to_string()
+/// );
+/// ```
+pub fn check_arg(
+    name: &str,
+    arity: Arity,
+    i: usize,
+    domain: Domain,
+    arg: f64,
+) -> Result<()> {
+    if domain.contains(arg) {
+        return Ok(());
+    }
+
+    let expected = match domain {
+        Domain::Integer => "to be an integer".to_string(),
+        Domain::Natural => "to be a non-negative integer".to_string(),
+        Domain::PositiveInteger => "to be a positive integer".to_string(),
+        Domain::IntegerIn(min, max) => {
+            format!("to be an integer in [{}, {}]", min, max)
+        }
+        _ => format!("in {}", domain),
+    };
+
+    if arity == Arity::Exact(1) {
+        bail!(Code::ArgumentOutOfDomain.with(&[&name, &expected, &arg]))
+    } else {
+        bail!(Code::NthArgumentOutOfDomain.with(&[
+            &name,
+            &(i + 1),
+            &expected,
+            &arg
+        ]))
+    }
+}
@@ -0,0 +1,158 @@
+//! Classification of the text of an expression into the kinds of tokens it
+//! is made up of, for highlighting it as it is typed.
+
+use crate::{
+    error::SpanError,
+    lexer::lex_token,
+    token::{Span, Token},
+};
+
+/// The kind of a token, as far as highlighting it is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenClass {
+    /// A number.
+    Number,
+    /// An infix, prefix or postfix operator.
+    Operator,
+    /// A function that is called, whether builtin or not.
+    Function,
+    /// A name which stands for a value, such as `pi` or a variable.
+    Constant,
+    /// A string in double quotes.
+    String,
+    /// A bracket or square bracket.
+    Paren,
+    /// A comma separating arguments or the elements of a list.
+    Comma,
+    /// Text which cannot be lexed, or a bracket closing one which was never
+    /// opened.
+    Error,
+}
+
+impl TokenClass {
+    /// Get the name of the class in lowercase, e.g. `number`, such as for
+    /// the class of an element in HTML.
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenClass::Number => "number",
+            TokenClass::Operator => "operator",
+            TokenClass::Function => "function",
+            TokenClass::Constant => "constant",
+            TokenClass::String => "string",
+            TokenClass::Paren => "paren",
+            TokenClass::Comma => "comma",
+            TokenClass::Error => "error",
+        }
+    }
+}
+
+/// Split `input` into the spans of its tokens, each with its class.
+///
+/// Unlike lexing, classifying never fails: text which cannot be lexed is
+/// classed as an error and the tokens after it are still classified, so
+/// that a line can be highlighted while it is being typed. Whitespace is
+/// not part of any span.
+///
+/// # Arguments
+///
+/// * `input` - The text of the expression.
+///
+/// # Examples
+/// ```
+/// use cocoa::{highlight::{classify, TokenClass}, token::Span};
+///
+/// assert_eq!(
+///     vec![
+///         (Span::new(0, 4), TokenClass::Function),
+///         (Span::new(4, 5), TokenClass::Paren),
+///         (Span::new(5, 7), TokenClass::Constant),
+///         (Span::new(8, 9), TokenClass::Operator),
+///         (Span::new(10, 11), TokenClass::Number),
+///         (Span::new(11, 12), TokenClass::Paren),
+///     ],
+///     classify("sqrt(pi * 2)"),
+/// );
+///
+/// let classes: Vec<_> = classify("f(x) # 1)").into_iter().map(|(_, c)| c).collect();
+/// assert_eq!(
+///     vec![
+///         TokenClass::Function,
+///         TokenClass::Paren,
+///         TokenClass::Constant,
+///         TokenClass::Paren,
+///         TokenClass::Error,
+///         TokenClass::Number,
+///         TokenClass::Error,
+///     ],
+///     classes,
+/// );
+/// ```
+pub fn classify(input: &str) -> Vec<(Span, TokenClass)> {
+    let mut cs = input.chars().peekable();
+    let mut pos = 0;
+    // the tokens lexed, or `None` for text that could not be
+    let mut tokens = vec![];
+
+    loop {
+        match lex_token(&mut cs, &mut pos) {
+            Ok(Some((token, span))) => tokens.push((Some(token), span)),
+            Ok(None) => break,
+            // the lexer has consumed the text the error spans, so lexing
+            // carries on after it
+            Err(e) => {
+                let span = e
+                    .downcast_ref::<SpanError>()
+                    .map_or(Span::new(pos, pos), |e| e.span);
+                tokens.push((None, span));
+            }
+        }
+    }
+
+    let chars: Vec<char> = input.chars().collect();
+    // the closing brackets the brackets opened so far are waiting for
+    let mut open = vec![];
+    let mut classes = Vec::with_capacity(tokens.len());
+
+    for (i, (token, span)) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1).and_then(|(t, _)| t.as_ref());
+
+        let class = match token {
+            None => TokenClass::Error,
+            // constants such as `pi` are lexed as their value
+            Some(Token::Number(_))
+                if chars[span.start].is_ascii_alphabetic() =>
+            {
+                TokenClass::Constant
+            }
+            Some(Token::Number(_)) => TokenClass::Number,
+            Some(Token::Op(_)) => TokenClass::Operator,
+            Some(Token::Func(_)) => TokenClass::Function,
+            Some(Token::Ident(_)) if next == Some(&Token::LParen) => {
+                TokenClass::Function
+            }
+            Some(Token::Ident(_)) => TokenClass::Constant,
+            Some(Token::Str(_)) => TokenClass::String,
+            Some(Token::Comma) => TokenClass::Comma,
+            Some(Token::LParen) => {
+                open.push(Token::RParen);
+                TokenClass::Paren
+            }
+            Some(Token::LBracket) => {
+                open.push(Token::RBracket);
+                TokenClass::Paren
+            }
+            Some(t @ (Token::RParen | Token::RBracket)) => {
+                if open.last() == Some(t) {
+                    open.pop();
+                    TokenClass::Paren
+                } else {
+                    TokenClass::Error
+                }
+            }
+        };
+
+        classes.push((*span, class));
+    }
+
+    classes
+}
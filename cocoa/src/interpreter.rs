@@ -0,0 +1,1395 @@
+//! Evaluation of expression trees, which walks a tree once it has been parsed
+//! with the settings, variables and functions of an `Evaluator`.
+
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, Sign};
+#[cfg(feature = "bigint")]
+use num_traits::FromPrimitive;
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    compile::Program,
+    error::spanned,
+    evaluator::{CacheKey, Domain, Evaluator},
+    lexer::lex,
+    math::{bits::wrap, compensated_add, factorial},
+    message::{self, into_message, Code, Message},
+    solve::solve_linear,
+    suggest::{correction, suggestions},
+    symbolic::{Symbolic, MAX_POWER},
+    template::Template,
+    token::{FuncKind, OpKind, Span, Token},
+    value::Value,
+};
+#[cfg(feature = "bigint")]
+use crate::{math::big, token::exact_sequence, value::to_f64};
+
+/// The variables bound by the higher order functions being evaluated, on top
+/// of those bound by the calls they are in, which take precedence over those
+/// of the evaluator.
+#[derive(Debug, Clone, Copy, Default)]
+struct Scope<'s> {
+    bindings: &'s [(&'s str, f64)],
+    outer: Option<&'s Scope<'s>>,
+}
+
+impl<'s> Scope<'s> {
+    /// Get the value of the innermost variable named `name`, if it is bound.
+    fn get(&self, name: &str) -> Option<f64> {
+        let bound = self.bindings.iter().rev().find(|&&(n, _)| n == name);
+
+        match bound {
+            Some(&(_, x)) => Some(x),
+            None => self.outer?.get(name),
+        }
+    }
+
+    /// Add the names of every variable bound to `names`.
+    fn names(&self, names: &mut Vec<&'s str>) {
+        names.extend(self.bindings.iter().map(|&(name, _)| name));
+
+        if let Some(outer) = self.outer {
+            outer.names(names);
+        }
+    }
+}
+
+/// A walk over expression trees, which evaluates each node with the settings
+/// of `evaluator`.
+pub(crate) struct Interpreter<'e> {
+    evaluator: &'e Evaluator,
+    /// Whether the spans of the trees point into the input, which those of
+    /// the bodies of templates do not, since they were parsed from their
+    /// definitions.
+    spanned: bool,
+}
+
+impl<'e> Interpreter<'e> {
+    pub(crate) fn new(evaluator: &'e Evaluator) -> Self {
+        Self {
+            evaluator,
+            spanned: true,
+        }
+    }
+
+    /// Create an interpreter for trees whose spans do not point into the
+    /// input, so errors do not point at where they happened either.
+    pub(crate) fn without_spans(evaluator: &'e Evaluator) -> Self {
+        Self {
+            evaluator,
+            spanned: false,
+        }
+    }
+
+    /// Evaluate the tree `expr`.
+    pub(crate) fn eval(&self, expr: &Expr) -> Result<Value> {
+        self.value(expr, &Scope::default())
+    }
+
+    /// Evaluate the tree `expr` to a number with the variables in `bindings`
+    /// bound.
+    pub(crate) fn eval_at(
+        &self,
+        expr: &Expr,
+        bindings: &[(&str, f64)],
+    ) -> Result<f64> {
+        self.eval_body(expr, bindings, &Scope::default())
+    }
+
+    /// Apply the infix operator `op`, which is not `=`, to the numbers `lhs`
+    /// and `rhs` as it is applied in an expression.
+    pub(crate) fn infix(&self, op: OpKind, lhs: f64, rhs: f64) -> Result<f64> {
+        let mut carry = 0.0;
+        let value = self.operator(
+            op,
+            lhs.into(),
+            (rhs.into(), None),
+            None,
+            &mut carry,
+        )?;
+
+        self.number(&settle(value, &mut carry), None)
+    }
+
+    /// Apply the postfix operator `op` to the number `lhs` as it is applied
+    /// in an expression.
+    pub(crate) fn postfix(&self, op: OpKind, lhs: f64) -> Result<f64> {
+        let value = match op {
+            OpKind::Factorial => self.factorial(lhs, None)?,
+            op => bail!(unexpected(op)),
+        };
+
+        self.number(&value, None)
+    }
+
+    /// Get the span of `expr` in the input, if it is known.
+    fn span(&self, expr: &Expr) -> Option<Span> {
+        expr.span.filter(|_| self.spanned)
+    }
+
+    /// Get the number `value` holds, failing with an error pointing at `span`
+    /// if it is a list or an exact integer which overflows as a number.
+    fn number(&self, value: &Value, span: Option<Span>) -> Result<f64> {
+        self.evaluator
+            .check_range(value)
+            .map_err(|e| spanned(span, into_message(e)))?;
+
+        number(value, span)
+    }
+
+    /// Evaluate `expr` with the variables in `scope` bound.
+    fn value(&self, expr: &Expr, scope: &Scope) -> Result<Value> {
+        self.evaluator.step()?;
+        let span = self.span(expr);
+
+        let value = match &expr.kind {
+            &ExprKind::Number(n) => self.literal(n),
+            ExprKind::Str(_) => {
+                return Err(spanned(span, Code::UnexpectedString))
+            }
+            ExprKind::Ident(name) => self.ident(name, span, scope)?,
+            ExprKind::Call(Callee::Builtin(f), args) => {
+                self.warn_deprecated(f.name(), name_span(span, f.name()));
+                self.builtin(*f, args, span, scope)?
+            }
+            ExprKind::Call(Callee::Named(name), args) => {
+                let resolved =
+                    self.resolve(name, true, name_span(span, name), scope);
+
+                match &resolved {
+                    None => self.named(name, args, span, scope)?,
+                    Some(Token::Ident(name)) => {
+                        self.named(name, args, span, scope)?
+                    }
+                    &Some(Token::Func(f)) => {
+                        self.builtin(f, args, span, scope)?
+                    }
+                    Some(_) => {
+                        bail!(Code::UnexpectedToken.with(&[&Token::LParen]))
+                    }
+                }
+            }
+            ExprKind::Unary(op, e) => self.unary(*op, e, scope)?,
+            ExprKind::Postfix(OpKind::Factorial, e) => {
+                let lhs = self.value(e, scope)?;
+                let n = self.number(&lhs, self.span(e))?;
+
+                self.factorial(n, span)?
+            }
+            ExprKind::Binary(..) => self.chain(expr, scope)?,
+            ExprKind::Group(e) => self.value(e, scope)?,
+            ExprKind::List(elements) => self.list(elements, scope)?,
+            ExprKind::Postfix(op, _) => bail!(unexpected(*op)),
+        };
+
+        Ok(value)
+    }
+
+    /// Get the value of the number `n` as written in an expression.
+    fn literal(&self, n: f64) -> Value {
+        match n {
+            // `pi` is lexed as the number closest to it
+            PI if self.evaluator.symbolic() => Value::Symbolic(Symbolic::pi()),
+            n if n != PI && self.evaluator.domain() == Domain::Rational => {
+                Symbolic::from_f64(n).map_or(Value::Number(n), Value::from)
+            }
+            n => Value::Number(n),
+        }
+    }
+
+    /// Get the value of the name `name`, which is not called and is at `span`
+    /// in the input.
+    fn ident(
+        &self,
+        name: &str,
+        span: Option<Span>,
+        scope: &Scope,
+    ) -> Result<Value> {
+        let resolved = self.resolve(name, false, span, scope);
+        let name = match &resolved {
+            None => name,
+            Some(Token::Ident(name)) => name,
+            &Some(Token::Number(n)) => return Ok(self.literal(n)),
+            Some(Token::Func(f)) => {
+                bail!(Code::ExpectedParen.with(&[&f.name()]))
+            }
+            Some(t) => {
+                bail!(Code::UnexpectedToken.with(&[t]))
+            }
+        };
+
+        let value = scope
+            .get(name)
+            .or_else(|| self.evaluator.constant(name))
+            .or_else(|| self.evaluator.variable(name));
+
+        match value {
+            Some(n) => Ok(n.into()),
+            None if self.evaluator.symbolic() && name == "e" => {
+                bail!(Message::new(Code::EulerVariable))
+            }
+            None if self.evaluator.symbolic() => {
+                Ok(Value::Symbolic(Symbolic::variable(name)))
+            }
+            None => bail!(Code::UnknownIdentifier
+                .with(&[&name])
+                .with_hint(self.hint(name, false, scope))),
+        }
+    }
+
+    /// Get every name that may be called if `call` is true, or that holds a
+    /// value otherwise, including those bound by higher order functions.
+    fn names<'a>(&'a self, call: bool, scope: &'a Scope<'a>) -> Vec<&'a str> {
+        let mut names = self.evaluator.names(call);
+        if !call {
+            scope.names(&mut names);
+        }
+
+        names
+    }
+
+    /// Get the token for the name `name` stands for if it is an alias, or for
+    /// the name it is an obvious typo of if it is unknown and the evaluator
+    /// corrects typos, warning about it if it is deprecated where `span` is
+    /// where it is in the input. This is `None` if `name` stands for itself.
+    fn resolve(
+        &self,
+        name: &str,
+        call: bool,
+        span: Option<Span>,
+        scope: &Scope,
+    ) -> Option<Token> {
+        // variables bound by higher order functions shadow everything else
+        if scope.get(name).is_some() {
+            return None;
+        }
+        self.warn_deprecated(name, span);
+
+        if let Some(target) = self.evaluator.alias(name) {
+            return token_for(target);
+        }
+
+        if !self.evaluator.autocorrect() {
+            return None;
+        }
+
+        let known = if call {
+            self.evaluator.function(name).is_some()
+                || self.evaluator.template(name).is_some()
+        } else {
+            self.evaluator.constant(name).is_some()
+                || self.evaluator.variable(name).is_some()
+        };
+        if known {
+            return None;
+        }
+
+        let fixed = correction(name, self.names(call, scope))?.to_string();
+
+        // a typo of an alias is resolved to what the alias stands for
+        let target = self.evaluator.alias(&fixed).unwrap_or(&fixed);
+        let token = token_for(target)?;
+        self.evaluator
+            .note(format!("assuming '{}' for '{}'", fixed, name));
+
+        Some(token)
+    }
+
+    /// Warn about `name`, where `span` is where it is in the input, if it is
+    /// deprecated.
+    fn warn_deprecated(&self, name: &str, span: Option<Span>) {
+        if let Some(replacement) = self.evaluator.deprecation(name) {
+            self.evaluator
+                .warn(span, Code::Deprecated.with(&[&name, &replacement]));
+        }
+    }
+
+    /// Suggest what the unknown name `name` may have been meant to be, as a
+    /// hint to add to its error.
+    fn hint(&self, name: &str, call: bool, scope: &Scope) -> Option<Message> {
+        let suggestions = suggestions(name, self.names(call, scope));
+
+        Message::did_you_mean(&suggestions[..suggestions.len().min(3)])
+    }
+
+    /// Evaluate each of `args` in order.
+    fn args(&self, args: &[Expr], scope: &Scope) -> Result<Vec<Value>> {
+        args.iter().map(|arg| self.value(arg, scope)).collect()
+    }
+
+    /// Call the builtin `f` with `args`, where the call is at `span` in the
+    /// input.
+    fn builtin(
+        &self,
+        f: FuncKind,
+        args: &[Expr],
+        span: Option<Span>,
+        scope: &Scope,
+    ) -> Result<Value> {
+        let domain = self.evaluator.domain();
+
+        if f.takes_string() {
+            let arg = match args {
+                [Expr {
+                    kind: ExprKind::Str(s),
+                    ..
+                }] => s,
+                _ => bail!(Code::ExpectedString.with(&[&f.name()])),
+            };
+            let value = self
+                .evaluator
+                .call_string(f, arg)
+                .map_err(|e| spanned(span, into_message(e)))?;
+
+            return Ok(truncate(domain, value));
+        }
+
+        if f.is_higher_order() {
+            return Ok(truncate(domain, self.higher_order(f, args, scope)?));
+        }
+
+        let args = self.args(args, scope)?;
+        if f.is_list_function() {
+            args.iter().try_for_each(|arg| bound(self.evaluator, arg))?;
+            let value = self.evaluator.call_list(f, &args)?;
+
+            return Ok(truncate(domain, value));
+        }
+
+        if self.evaluator.symbolic() {
+            if let Some(s) = symbolic_call(f, &args) {
+                return Ok(s.into());
+            }
+        }
+
+        // working through a list takes a step for each element
+        self.evaluator.step_by(elements(&args))?;
+        let args = spread(self.evaluator, args)?;
+        let evaluator = self.evaluator;
+        let compute = || {
+            #[cfg(feature = "bigint")]
+            if let Some(n) = exact_sequence(f, &args)? {
+                return Ok(n.into());
+            }
+
+            evaluator.call(f, &args).map(Value::from)
+        };
+
+        let value = if f.is_expensive() {
+            evaluator.memoize(CacheKey::call(f, &args), compute)?
+        } else {
+            match compute() {
+                // point at the assertion that failed
+                Err(e)
+                    if matches!(f, FuncKind::Assert | FuncKind::AssertEq) =>
+                {
+                    return Err(spanned(span, into_message(e)))
+                }
+                value => value?,
+            }
+        };
+
+        // exact integers are never infinite or out of range
+        let n = match truncate(domain, value) {
+            Value::Number(n) => n,
+            value => return Ok(value),
+        };
+
+        if self.evaluator.detect_overflow() {
+            if let Some(lost) = call_lost_range(f, &args, n) {
+                let args: Vec<String> =
+                    args.iter().map(|arg| arg.to_string()).collect();
+                let call = format!("{}({})", f.name(), args.join(", "));
+
+                return Err(spanned(span, lost.with(&[&call])));
+            }
+        }
+
+        self.evaluator
+            .check_finite(n, || describe_call(f.name(), &args, n))
+            .map(Value::from)
+    }
+
+    /// Call the template or function which is not builtin named `name` with
+    /// `args`, where the call is at `span` in the input.
+    fn named(
+        &self,
+        name: &str,
+        args: &[Expr],
+        span: Option<Span>,
+        scope: &Scope,
+    ) -> Result<Value> {
+        if let Some(template) = self.evaluator.template(name) {
+            let args = spread(self.evaluator, self.args(args, scope)?)?;
+            let n = self
+                .template(template, &args)
+                .map_err(|e| spanned(span, into_message(e)))?;
+
+            return Ok(n.into());
+        }
+
+        let f = match self.evaluator.function(name) {
+            Some(f) => f,
+            None => bail!(Code::UnknownFunction
+                .with(&[&name])
+                .with_hint(self.hint(name, true, scope))),
+        };
+
+        let args = spread(self.evaluator, self.args(args, scope)?)?;
+        let n = f.call(name, &args)?;
+
+        self.evaluator
+            .check_finite(n, || describe_call(name, &args, n))
+            .map(Value::from)
+    }
+
+    /// Evaluate the body of `template` with its parameters bound to `args`.
+    ///
+    /// Only the parameters are bound, since the body of a template does not
+    /// see the variables bound where it is called.
+    fn template(&self, template: &Template, args: &[f64]) -> Result<f64> {
+        let params = template.params();
+        if params.len() != args.len() {
+            bail!(Code::WrongArity.with(&[
+                &template.name(),
+                &template.arity(),
+                &args.len()
+            ]))
+        }
+
+        let bindings: Vec<(&str, f64)> = params
+            .iter()
+            .map(String::as_str)
+            .zip(args.iter().copied())
+            .collect();
+        let scope = Scope {
+            bindings: &bindings,
+            outer: None,
+        };
+        let body = Interpreter::without_spans(self.evaluator);
+
+        let value = body.value(template.body(), &scope)?;
+        body.number(&value, None)
+    }
+
+    /// Apply the unary operator `op` to `e`.
+    fn unary(&self, op: OpKind, e: &Expr, scope: &Scope) -> Result<Value> {
+        let rhs = self.value(e, scope)?;
+
+        let value = match (op, rhs) {
+            #[cfg(feature = "bigint")]
+            (OpKind::Plus, rhs @ Value::Integer(_)) => rhs,
+            #[cfg(feature = "bigint")]
+            (OpKind::Minus, Value::Integer(n)) => Value::Integer(-n),
+            (OpKind::Plus, rhs @ Value::Symbolic(_)) => rhs,
+            (OpKind::Minus, Value::Symbolic(s)) => match s.checked_neg() {
+                Some(neg) => neg.into(),
+                None => (-s.value()).into(),
+            },
+            (OpKind::Plus, rhs) => self.number(&rhs, self.span(e))?.into(),
+            (OpKind::Minus, rhs) => (-self.number(&rhs, self.span(e))?).into(),
+            (op, _) => bail!(unexpected(op)),
+        };
+
+        Ok(value)
+    }
+
+    /// Evaluate the chain of infix operators `expr`, i.e. the operators along
+    /// the left of its tree, from left to right.
+    ///
+    /// Walking the chain rather than descending into it keeps long chains
+    /// such as `1 + 1 + ... + 1` from overflowing the stack, and lets sums be
+    /// compensated over the whole chain.
+    fn chain(&self, expr: &Expr, scope: &Scope) -> Result<Value> {
+        let mut links = vec![];
+        let mut first = expr;
+        while let ExprKind::Binary(op, lhs, rhs) = &first.kind {
+            links.push((*op, lhs, rhs, first.span));
+            first = lhs;
+        }
+
+        let mut lhs = self.value(first, scope)?;
+
+        // the rounding error of the chain of + and - so far when sums are
+        // compensated, which is added back once the chain ends
+        let mut carry = 0.0;
+
+        for (op, lhs_expr, rhs, span) in links.into_iter().rev() {
+            let span = span.filter(|_| self.spanned);
+
+            if op == OpKind::Equals {
+                return Err(spanned(span, Code::EqualsOutsideSolve));
+            }
+            if op == OpKind::Factorial {
+                bail!(unexpected(op))
+            }
+
+            // a chain of sums is an operand of any other operator as a whole
+            if !matches!(op, OpKind::Plus | OpKind::Minus) {
+                lhs = settle(lhs, &mut carry);
+            }
+
+            // operators only apply to numbers, except that those which keep
+            // exact values exact also apply to variables without a value
+            if !matches!(lhs, Value::Symbolic(_)) {
+                self.number(&lhs, self.span(lhs_expr))?;
+            }
+
+            let rhs_value = self.value(rhs, scope)?;
+            lhs = self.operator(
+                op,
+                lhs,
+                (rhs_value, self.span(rhs)),
+                span,
+                &mut carry,
+            )?;
+        }
+
+        Ok(settle(lhs, &mut carry))
+    }
+
+    /// Apply the infix operator `op` to `lhs` and `rhs`, which is paired with
+    /// its span, where the operation is at `span` in the input.
+    ///
+    /// With the `bigint` feature, operators applied to integers are
+    /// calculated exactly when their result is too large to be held exactly
+    /// by a number, or when either operand already is.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(op = %op))
+    )]
+    fn operator(
+        &self,
+        op: OpKind,
+        lhs: Value,
+        (rhs, rhs_span): (Value, Option<Span>),
+        span: Option<Span>,
+        carry: &mut f64,
+    ) -> Result<Value> {
+        // machine arithmetic wraps within its width rather than growing
+        let exact = self.evaluator.width().is_none();
+        if exact && self.evaluator.symbolic() {
+            if let Some(s) = symbolic_op(op, &lhs, &rhs) {
+                return Ok(s.into());
+            }
+            if let Some(message) = too_large_power(op, &lhs, &rhs) {
+                return Err(spanned(span, message));
+            }
+        }
+        if exact && self.evaluator.domain() == Domain::Rational {
+            if let Some(s) = rational_op(op, &lhs, &rhs) {
+                return Ok(s.into());
+            }
+        }
+        #[cfg(feature = "bigint")]
+        if exact {
+            if let Some(n) = exact_op(op, &lhs, &rhs) {
+                return Ok(n.into());
+            }
+        }
+
+        // lhs has already been checked not to be a list
+        let lhs = self.number(&lhs, span)?;
+        let rhs = self.number(&rhs, rhs_span)?;
+
+        if self.evaluator.strict_division()
+            && matches!(op, OpKind::Slash | OpKind::Modulo)
+            && rhs == 0.0
+        {
+            return Err(spanned(span, Code::DivisionByZero));
+        }
+
+        if matches!(op, OpKind::Shl | OpKind::Shr) {
+            if lhs.fract() != 0.0 || rhs.fract() != 0.0 {
+                return Err(spanned(span, Code::NonIntegerShift));
+            } else if rhs < 0.0 {
+                return Err(spanned(span, Code::NegativeShift));
+            }
+        }
+
+        let wrapped = self
+            .evaluator
+            .width()
+            .and_then(|width| wrapping_op(op, lhs, rhs, width));
+
+        let n = match wrapped {
+            Some(n) => n,
+            None if self.evaluator.compensated()
+                && matches!(op, OpKind::Plus | OpKind::Minus) =>
+            {
+                let rhs = if op == OpKind::Minus { -rhs } else { rhs };
+                compensated_add(lhs, rhs, carry)
+            }
+            None => apply_op(op, lhs, rhs),
+        };
+        let n = match self.evaluator.domain() {
+            Domain::Int => n.trunc(),
+            _ => n,
+        };
+
+        // wrapping around is the whole point of machine arithmetic, so it
+        // is not treated as having lost the result
+        if self.evaluator.detect_overflow() && wrapped.is_none() {
+            if let Some(lost) = op_lost_range(op, lhs, rhs, n) {
+                let operation = format!(
+                    "{} {} {}",
+                    message::number(lhs),
+                    op,
+                    message::number(rhs)
+                );
+
+                return Err(spanned(span, lost.with(&[&operation])));
+            }
+        }
+
+        self.evaluator
+            .check_finite(n, || describe_op(op, lhs, rhs, n))
+            .map(Value::from)
+    }
+
+    /// Calculate the factorial of `lhs`, where the factorial is at `span` in
+    /// the input.
+    ///
+    /// With the `bigint` feature, factorials are calculated exactly.
+    fn factorial(&self, lhs: f64, span: Option<Span>) -> Result<Value> {
+        if lhs.is_sign_negative() {
+            bail!(Message::new(Code::NegativeFactorial))
+        } else if lhs.fract() != 0.0 {
+            bail!(Message::new(Code::NonIntegerFactorial))
+        }
+
+        // casting is safe since lhs is clearly positive and has no fractional
+        // part if this bit of code is executed, and huge values saturate,
+        // which is harmless since their factorial is infinite anyway
+        let k = lhs as u64;
+        let compute = || {
+            #[cfg(feature = "bigint")]
+            if let Some(n) = big::factorial(k) {
+                return Ok(n.into());
+            }
+
+            Ok(factorial(k).into())
+        };
+
+        // small factorials are quicker to calculate than to look up
+        let value = match k {
+            0..=20 => compute()?,
+            _ => self.evaluator.memoize(CacheKey::Factorial(k), compute)?,
+        };
+
+        if self.evaluator.detect_overflow()
+            && value == Value::Number(f64::INFINITY)
+        {
+            return Err(spanned(
+                span,
+                Code::Overflowed.with(&[&format!("{}!", lhs)]),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluate the elements of a list, where elements which are themselves
+    /// lists are spread into the list, so `[[1, 2], 3]` is `[1, 2, 3]`.
+    fn list(&self, elements: &[Expr], scope: &Scope) -> Result<Value> {
+        let mut list = vec![];
+
+        for e in elements {
+            let element = self.value(e, scope)?;
+            bound(self.evaluator, &element)
+                .map_err(|err| spanned(self.span(e), into_message(err)))?;
+            list.extend(element.into_list());
+        }
+
+        Ok(Value::List(list))
+    }
+
+    /// Evaluate a call to the higher order function `f` with `args`, the
+    /// first of which is the body evaluated with the names following it
+    /// bound.
+    fn higher_order(
+        &self,
+        f: FuncKind,
+        args: &[Expr],
+        scope: &Scope,
+    ) -> Result<Value> {
+        let body = match args {
+            [body, _, ..] => body,
+            _ => bail!(Message::new(Code::ExpectedExpressionThenComma)),
+        };
+
+        let names = match f {
+            // the body of odesolve always refers to x and y
+            FuncKind::OdeSolve => vec![],
+            // the unknown of solve comes last, so is not followed by a comma
+            FuncKind::Solve => match args {
+                [_, Expr {
+                    kind: ExprKind::Ident(name),
+                    ..
+                }] => vec![name.as_str()],
+                [_, Expr {
+                    kind: ExprKind::Ident(_),
+                    ..
+                }, ..] => {
+                    bail!(Code::Expects.with(&[&f.name(), &f.arity()]))
+                }
+                _ => bail!(Message::new(Code::ExpectedUnknownName)),
+            },
+            FuncKind::Reduce => self.bindings(&args[1..], 2)?,
+            _ => self.bindings(&args[1..], 1)?,
+        };
+        let rest = self.args(&args[1 + names.len()..], scope)?;
+
+        self.apply(f, body, &names, rest, scope)
+    }
+
+    /// Get the `n` names of the variables bound by a higher order function,
+    /// which are the first of `args`, each of which must be followed by
+    /// another argument.
+    fn bindings<'a>(&self, args: &'a [Expr], n: usize) -> Result<Vec<&'a str>> {
+        let mut names = vec![];
+
+        for (i, arg) in args.iter().take(n).enumerate() {
+            let name = match &arg.kind {
+                ExprKind::Ident(name)
+                    if self.evaluator.constant(name).is_none() =>
+                {
+                    name
+                }
+                ExprKind::Ident(name) => bail!(Code::CannotBind.with(&[&name])),
+                _ => bail!(Message::new(Code::ExpectedVariableName)),
+            };
+
+            if i + 1 == args.len() {
+                bail!(Code::ExpectedCommaAfter.with(&[&name]))
+            }
+            names.push(name.as_str());
+        }
+
+        Ok(names)
+    }
+
+    /// Apply the higher order function `f` to the body `body`, which is
+    /// evaluated with `names` bound, on top of those bound in `scope`, and
+    /// the rest of its arguments `args`.
+    fn apply(
+        &self,
+        f: FuncKind,
+        body: &Expr,
+        names: &[&str],
+        args: Vec<Value>,
+        scope: &Scope,
+    ) -> Result<Value> {
+        if !f.arity().accepts(names.len() + args.len() + 1) {
+            bail!(Code::Expects.with(&[&f.name(), &f.arity()]))
+        }
+        match f {
+            FuncKind::MonteCarlo => {
+                return self.montecarlo(body, names[0], &args, scope)
+            }
+            FuncKind::OdeSolve => return self.odesolve(body, &args, scope),
+            FuncKind::Solve => return self.solve(body, names[0], scope),
+            _ => (),
+        }
+
+        let list = match f {
+            // the values table binds its variable to are those of a range
+            FuncKind::Table => self
+                .evaluator
+                .call_list(FuncKind::Range, &args)?
+                .into_list(),
+            _ => spread(self.evaluator, args)?,
+        };
+        let total = list.len();
+        let report = |i: usize| self.evaluator.report(f.name(), i + 1, total);
+
+        let value = match f {
+            FuncKind::Map | FuncKind::Table => Value::List(
+                list.into_iter()
+                    .enumerate()
+                    .map(|(i, x)| {
+                        let y = self.eval_body(body, &[(names[0], x)], scope);
+                        report(i);
+                        y
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            FuncKind::Filter => {
+                let mut kept = vec![];
+                for (i, x) in list.into_iter().enumerate() {
+                    let cond = self.eval_body(body, &[(names[0], x)], scope)?;
+                    report(i);
+
+                    // NaN is not a true condition, even though it is not 0
+                    if cond != 0.0 && !cond.is_nan() {
+                        kept.push(x);
+                    }
+                }
+
+                Value::List(kept)
+            }
+            FuncKind::Reduce => {
+                let (first, rest) = match list.split_first() {
+                    Some(split) => split,
+                    None => bail!(Message::new(Code::EmptyReduce)),
+                };
+
+                let mut acc = *first;
+                for (i, &x) in rest.iter().enumerate() {
+                    let bindings = [(names[0], acc), (names[1], x)];
+                    acc = self.eval_body(body, &bindings, scope)?;
+                    // the first element is taken as done
+                    self.evaluator.report(f.name(), i + 2, total);
+                }
+
+                Value::Number(acc)
+            }
+            _ => unreachable!("{} is not a higher order function", f.name()),
+        };
+
+        Ok(value)
+    }
+
+    /// Estimate the integral of the body `body` from `a` to `b` by evaluating
+    /// it at `n` points drawn uniformly from between them, where `args` are
+    /// `a`, `b` and `n`, as the list of the estimate and its standard error.
+    fn montecarlo(
+        &self,
+        body: &Expr,
+        var: &str,
+        args: &[Value],
+        scope: &Scope,
+    ) -> Result<Value> {
+        let args = args
+            .iter()
+            .map(|arg| self.number(arg, None))
+            .collect::<Result<Vec<_>>>()?;
+        let (a, b, n) = (args[0], args[1], args[2]);
+
+        if !a.is_finite() || !b.is_finite() {
+            bail!(Code::MonteCarloBounds.with(&[&a, &b]))
+        }
+        if n < 2.0 || n.fract() != 0.0 {
+            bail!(Code::MonteCarloSamples.with(&[&n]))
+        }
+        // casting is safe since n is a positive integer
+        let n = n as usize;
+
+        let program = self.compile(body, &[var]);
+        let mut stack = vec![];
+
+        // the mean and the sum of squared differences from it, which are
+        // updated with each sample so that the variance is found stably
+        let (mut mean, mut m2) = (0.0, 0.0);
+        for i in 0..n {
+            self.evaluator.step()?;
+            let x = a + (b - a) * self.evaluator.random();
+            let y = self.eval_compiled(
+                body,
+                program.as_ref(),
+                &[(var, x)],
+                &mut stack,
+                scope,
+            )?;
+
+            let delta = y - mean;
+            mean += delta / (i + 1) as f64;
+            m2 += delta * (y - mean);
+
+            self.evaluator.report(FuncKind::MonteCarlo.name(), i + 1, n);
+        }
+
+        let error = (m2 / (n - 1) as f64 / n as f64).sqrt();
+
+        Ok(Value::List(vec![(b - a) * mean, (b - a).abs() * error]))
+    }
+
+    /// Solve dy/dx = `body` from `x0` to `x1` with the classic fourth order
+    /// Runge-Kutta method, where `args` are `x0`, `y0`, `x1`, the number of
+    /// steps and whether to give every value of y rather than the last.
+    fn odesolve(
+        &self,
+        body: &Expr,
+        args: &[Value],
+        scope: &Scope,
+    ) -> Result<Value> {
+        let args = args
+            .iter()
+            .map(|arg| self.number(arg, None))
+            .collect::<Result<Vec<_>>>()?;
+        let (x0, y0, x1, steps) = (args[0], args[1], args[2], args[3]);
+        let every = args.get(4).is_some_and(|&n| n != 0.0);
+
+        if !x0.is_finite() || !y0.is_finite() || !x1.is_finite() {
+            bail!(Message::new(Code::OdeSolveBounds))
+        }
+        if steps < 1.0 || steps.fract() != 0.0 {
+            bail!(Code::OdeSolveSteps.with(&[&steps]))
+        }
+        // casting is safe since steps is a positive integer
+        let steps = steps as usize;
+
+        let program = self.compile(body, &["x", "y"]);
+        let mut stack = vec![];
+        let mut slope = |x: f64, y: f64| {
+            self.eval_compiled(
+                body,
+                program.as_ref(),
+                &[("x", x), ("y", y)],
+                &mut stack,
+                scope,
+            )
+        };
+
+        let h = (x1 - x0) / steps as f64;
+        let mut y = y0;
+        let mut ys = vec![y0];
+
+        for i in 0..steps {
+            self.evaluator.step()?;
+            // each x is found from the start, so that rounding errors in h
+            // do not build up over the steps
+            let x = x0 + i as f64 * h;
+
+            let k1 = slope(x, y)?;
+            let k2 = slope(x + h / 2.0, y + h / 2.0 * k1)?;
+            let k3 = slope(x + h / 2.0, y + h / 2.0 * k2)?;
+            let k4 = slope(x + h, y + h * k3)?;
+            y += h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+
+            if every {
+                ys.push(y);
+            }
+            self.evaluator
+                .report(FuncKind::OdeSolve.name(), i + 1, steps);
+        }
+
+        if every {
+            Ok(Value::List(ys))
+        } else {
+            Ok(Value::Number(y))
+        }
+    }
+
+    /// Solve the equation `body` for the unknown `var`, which it must be
+    /// linear in.
+    fn solve(&self, body: &Expr, var: &str, scope: &Scope) -> Result<Value> {
+        let (lhs, rhs) = match &body.kind {
+            ExprKind::Binary(OpKind::Equals, lhs, rhs) => (lhs, rhs),
+            _ => return Err(spanned(self.span(body), Code::ExpectedEquation)),
+        };
+
+        // the parts of the equation without the unknown are evaluated as
+        // usual, with the variables bound so far
+        let value = |e: &Expr| self.eval_body(e, &[], scope);
+
+        solve_linear(lhs, rhs, var, &value).map(Value::Number)
+    }
+
+    /// Compile the body `body` as a function of the variables `vars`, unless
+    /// the settings of the evaluator change how operators and builtins apply
+    /// to numbers, which only walking its tree takes into account.
+    fn compile(&self, body: &Expr, vars: &[&str]) -> Option<Program> {
+        if !self.evaluator.plain() {
+            return None;
+        }
+
+        Program::compile(body, vars)
+    }
+
+    /// Evaluate the body `body` with the variables in `bindings` bound, by
+    /// running `program` if the body could be compiled with them.
+    fn eval_compiled(
+        &self,
+        body: &Expr,
+        program: Option<&Program>,
+        bindings: &[(&str, f64)],
+        stack: &mut Vec<f64>,
+        scope: &Scope,
+    ) -> Result<f64> {
+        if let Some(program) = program {
+            let values: Vec<f64> = bindings.iter().map(|&(_, x)| x).collect();
+            let y = program.run(self.evaluator, &values, stack)?;
+
+            // compiled programs only fail as NaN, so the body is evaluated
+            // again to find out why
+            if y.is_finite() {
+                return Ok(y);
+            }
+        }
+
+        self.eval_body(body, bindings, scope)
+    }
+
+    /// Evaluate the body `body` of a higher order function with the
+    /// variables in `bindings` bound, on top of those bound in `scope`.
+    fn eval_body(
+        &self,
+        body: &Expr,
+        bindings: &[(&str, f64)],
+        scope: &Scope,
+    ) -> Result<f64> {
+        let scope = Scope {
+            bindings,
+            outer: Some(scope),
+        };
+
+        let value = self.value(body, &scope)?;
+        self.number(&value, self.span(body))
+    }
+}
+
+/// Get the span of the name `name` at the start of the call at `span`.
+fn name_span(span: Option<Span>, name: &str) -> Option<Span> {
+    span.map(|span| Span::new(span.start, span.start + name.chars().count()))
+}
+
+/// Describe the error for an operator where it cannot be, such as a unary
+/// operator which is only infix.
+fn unexpected(op: OpKind) -> Message {
+    Code::UnexpectedOperator.with(&[&format!("{:?}", op)])
+}
+
+/// Get the token for the known name `name`, which is always a single token,
+/// e.g. a builtin for `sqrt`.
+fn token_for(name: &str) -> Option<Token> {
+    match lex(&mut name.chars().peekable()) {
+        Ok(tokens) if tokens.len() == 1 => tokens.into_iter().next(),
+        _ => None,
+    }
+}
+
+/// Get the number `value` holds, failing with an error pointing at `span` if
+/// it is a list.
+fn number(value: &Value, span: Option<Span>) -> Result<f64> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        Value::List(_) => Err(spanned(span, Code::ExpectedNumber)),
+        #[cfg(feature = "bigint")]
+        Value::Integer(n) => Ok(to_f64(n)),
+        Value::Symbolic(s) => match s.variables().first() {
+            Some(name) => Err(spanned(span, Code::NoValue.with(&[name]))),
+            None => Ok(s.value()),
+        },
+    }
+}
+
+/// Get the exact value of `value`, if it is a number which can be held
+/// exactly or is already symbolic.
+fn symbolic(value: &Value) -> Option<Symbolic> {
+    match value {
+        Value::Number(n) => Symbolic::from_f64(*n),
+        Value::Symbolic(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Apply the infix operator `op` to `lhs` and `rhs` exactly, returning `None`
+/// if either cannot be held exactly, or if the result cannot be either.
+fn symbolic_op(op: OpKind, lhs: &Value, rhs: &Value) -> Option<Symbolic> {
+    apply_symbolic(op, &symbolic(lhs)?, &symbolic(rhs)?)
+}
+
+/// Apply the infix operator `op` to `a` and `b`, returning `None` if the
+/// result cannot be held exactly.
+fn apply_symbolic(op: OpKind, a: &Symbolic, b: &Symbolic) -> Option<Symbolic> {
+    match op {
+        OpKind::Plus => a.checked_add(b),
+        OpKind::Minus => a.checked_sub(b),
+        OpKind::Star => a.checked_mul(b),
+        OpKind::Slash => a.checked_div(b),
+        OpKind::Caret => a.checked_pow(&b.to_rational()?),
+        _ => None,
+    }
+}
+
+/// Describe the error for raising a variable without a value to a whole power
+/// too large to expand exactly, if `op` does that to `lhs` and `rhs`.
+fn too_large_power(op: OpKind, lhs: &Value, rhs: &Value) -> Option<Message> {
+    let name = match lhs {
+        Value::Symbolic(s) if op == OpKind::Caret => *s.variables().first()?,
+        _ => return None,
+    };
+    let power = symbolic(rhs)?.to_rational()?;
+
+    (power.is_integer() && power.num().abs() > MAX_POWER)
+        .then(|| Code::PowerTooLarge.with(&[&name, &MAX_POWER, &power.num()]))
+}
+
+/// Apply the infix operator `op` to `lhs` and `rhs` as fractions, returning
+/// `None` if either is not a fraction or an integer, or if the result is not
+/// a fraction either.
+fn rational_op(op: OpKind, lhs: &Value, rhs: &Value) -> Option<Symbolic> {
+    let rational = |value: &Value| match value {
+        Value::Number(n) if n.fract() == 0.0 => Symbolic::from_f64(*n),
+        Value::Symbolic(s) => s.to_rational().map(Symbolic::from),
+        _ => None,
+    };
+    apply_symbolic(op, &rational(lhs)?, &rational(rhs)?)
+        .filter(|s| s.to_rational().is_some())
+}
+
+/// Truncate `value` towards zero if it was calculated in the int domain.
+fn truncate(domain: Domain, value: Value) -> Value {
+    match (domain, value) {
+        (Domain::Int, Value::Number(n)) => Value::Number(n.trunc()),
+        (Domain::Int, Value::List(list)) => {
+            Value::List(list.iter().map(|n| n.trunc()).collect())
+        }
+        (_, value) => value,
+    }
+}
+
+/// Call the builtin `f` with `args` exactly, returning `None` if it cannot be
+/// called exactly with them.
+fn symbolic_call(f: FuncKind, args: &[Value]) -> Option<Symbolic> {
+    let [arg] = args else { return None };
+    let arg = symbolic(arg)?;
+
+    match f {
+        FuncKind::Sqrt => Symbolic::sqrt(arg.to_rational()?),
+        FuncKind::Exp => Symbolic::exp(arg.to_rational()?),
+        FuncKind::Sin | FuncKind::Cos | FuncKind::Tan => arg.trig(f),
+        _ => None,
+    }
+}
+
+/// Apply the infix operator `op` to `lhs` and `rhs` exactly, returning `None`
+/// if either is not an integer, if the result is not one either, or if it
+/// does not need to be calculated exactly, so `op` should be applied to them
+/// as numbers.
+#[cfg(feature = "bigint")]
+fn exact_op(op: OpKind, lhs: &Value, rhs: &Value) -> Option<BigInt> {
+    let either_exact =
+        matches!(lhs, Value::Integer(_)) || matches!(rhs, Value::Integer(_));
+    let (a, b) = (integer(lhs)?, integer(rhs)?);
+
+    match op {
+        OpKind::Plus if either_exact => big::limit(a + b),
+        OpKind::Minus if either_exact => big::limit(a - b),
+        OpKind::Star if either_exact => big::limit(a * b),
+        // division is only exact when there is no remainder
+        OpKind::Slash if either_exact && b.sign() != Sign::NoSign => {
+            let (q, r) = (&a / &b, &a % &b);
+            (r.sign() == Sign::NoSign).then_some(q)
+        }
+        OpKind::Modulo if either_exact && b.sign() != Sign::NoSign => {
+            Some(a % b)
+        }
+        // small powers are held exactly by a number anyway, and negative ones
+        // are not integers
+        OpKind::Caret => {
+            let exp = u64::try_from(&b).ok()?;
+            let n = big::pow(&a, exp)?;
+
+            (either_exact || n.bits() > 53).then_some(n)
+        }
+        _ => None,
+    }
+}
+
+/// Get the integer `value` holds, if it is one.
+#[cfg(feature = "bigint")]
+fn integer(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 => BigInt::from_f64(*n),
+        Value::Integer(n) => Some(n.clone()),
+        _ => None,
+    }
+}
+
+/// Spread the elements of any lists in `args` into the arguments, for
+/// functions which only take numbers.
+fn spread(evaluator: &Evaluator, args: Vec<Value>) -> Result<Vec<f64>> {
+    args.iter().try_for_each(|arg| bound(evaluator, arg))?;
+
+    Ok(args.into_iter().flat_map(Value::into_list).collect())
+}
+
+/// Count the elements of the lists among `args`.
+pub(crate) fn elements(args: &[Value]) -> usize {
+    args.iter()
+        .map(|arg| match arg {
+            Value::List(list) => list.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Fail if `value` is an exact value with a variable, which has no value to
+/// use as a number, or an exact integer which overflows when used as one.
+fn bound(evaluator: &Evaluator, value: &Value) -> Result<()> {
+    evaluator.check_range(value)?;
+
+    match value {
+        Value::Symbolic(s) => match s.variables().first() {
+            Some(name) => bail!(Code::NoValue.with(&[name])),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Add the rounding error `carry` of a compensated chain of sums back to the
+/// total `sum` of the chain, leaving `carry` at 0 for the next chain.
+fn settle(sum: Value, carry: &mut f64) -> Value {
+    match sum {
+        Value::Number(n) if *carry != 0.0 => {
+            Value::Number(n + std::mem::take(carry))
+        }
+        sum => sum,
+    }
+}
+
+/// Apply the infix operator `op` to `lhs` and `rhs`.
+pub(crate) fn apply_op(op: OpKind, lhs: f64, rhs: f64) -> f64 {
+    match op {
+        OpKind::Plus => lhs + rhs,
+        OpKind::Minus => lhs - rhs,
+        OpKind::Star => lhs * rhs,
+        OpKind::Slash => lhs / rhs,
+        OpKind::Modulo => lhs.rem_euclid(rhs),
+        OpKind::Caret => lhs.powf(rhs),
+        OpKind::Shl => lhs * 2f64.powf(rhs),
+        OpKind::Shr => (lhs / 2f64.powf(rhs)).floor(),
+        // an equation is not a value
+        OpKind::Equals => f64::NAN,
+        // factorial is a postfix operator
+        OpKind::Factorial => unreachable!("factorial is not an infix operator"),
+    }
+}
+
+/// Apply the infix operator `op` to `lhs` and `rhs` as machine arithmetic on
+/// `width` bit two's complement integers, which wraps around on overflow.
+///
+/// Only addition, subtraction, multiplication and shifts of integers wrap, so
+/// `None` is returned for any other operation.
+fn wrapping_op(op: OpKind, lhs: f64, rhs: f64, width: u32) -> Option<f64> {
+    // 2^100, comfortably within the range of an i128
+    const LIMIT: f64 = 1267650600228229401496703205376.0;
+
+    let integer = |x: f64| x.fract() == 0.0 && x.abs() < LIMIT;
+    if !integer(lhs) || !integer(rhs) {
+        return None;
+    }
+
+    // casting is safe since both operands are integers within the range of
+    // an i128, and the operands are wrapped first so that the operation
+    // itself cannot overflow an i128
+    let l = wrap(lhs as i128, width) as i128;
+    let r = wrap(rhs as i128, width) as i128;
+
+    let n = match op {
+        OpKind::Plus => l + r,
+        OpKind::Minus => l - r,
+        OpKind::Star => l * r,
+        // shifting by the width or more shifts every bit out, and the shift
+        // amount is the unwrapped right hand side since it is not a value
+        // with the width itself
+        OpKind::Shl if rhs >= width as f64 => 0,
+        OpKind::Shl => l << rhs as u32,
+        OpKind::Shr => l >> rhs.min(127.0) as u32,
+        _ => return None,
+    };
+
+    Some(wrap(n, width) as f64)
+}
+
+/// Check whether applying the infix operator `op` to `lhs` and `rhs` resulted
+/// in `n` because the actual result is out of the range of an `f64`, returning
+/// the code of how the value was lost if so.
+fn op_lost_range(op: OpKind, lhs: f64, rhs: f64, n: f64) -> Option<Code> {
+    if !lhs.is_finite() || !rhs.is_finite() {
+        return None;
+    }
+
+    if n.is_infinite() {
+        // dividing by zero or raising zero to a negative power is infinite
+        // since there is a pole there, not because the value is too large
+        let pole = match op {
+            OpKind::Slash => rhs == 0.0,
+            OpKind::Caret => lhs == 0.0,
+            _ => false,
+        };
+
+        if !pole {
+            return Some(Code::Overflowed);
+        }
+    } else if n == 0.0 && lhs != 0.0 {
+        let underflow = match op {
+            OpKind::Star => rhs != 0.0,
+            OpKind::Slash | OpKind::Caret => true,
+            _ => false,
+        };
+
+        if underflow {
+            return Some(Code::Underflowed);
+        }
+    }
+
+    None
+}
+
+/// Check whether calling the builtin `f` with `args` resulted in `n` because
+/// the actual result is out of the range of an `f64`, returning the code of
+/// how the value was lost if so.
+fn call_lost_range(f: FuncKind, args: &[f64], n: f64) -> Option<Code> {
+    if !args.iter().all(|arg| arg.is_finite()) {
+        return None;
+    }
+
+    // the domains of builtins exclude their poles, so an infinite result of
+    // finite arguments always means the value is too large
+    if n.is_infinite() {
+        Some(Code::Overflowed)
+    } else if n == 0.0 && f == FuncKind::Exp {
+        Some(Code::Underflowed)
+    } else {
+        None
+    }
+}
+
+/// Describe why applying the infix operator `op` to `lhs` and `rhs` resulted in
+/// `n`, which is not a finite number.
+fn describe_op(op: OpKind, lhs: f64, rhs: f64, n: f64) -> Message {
+    match op {
+        OpKind::Slash if rhs == 0.0 => Message::new(Code::DivisionByZero),
+        OpKind::Modulo if rhs == 0.0 => Message::new(Code::RemainderByZero),
+        OpKind::Caret if lhs == 0.0 && rhs < 0.0 => {
+            Message::new(Code::ZeroToNegativePower)
+        }
+        OpKind::Caret if lhs < 0.0 && rhs.fract() != 0.0 => {
+            Message::new(Code::NegativeToFractionalPower)
+        }
+        _ => {
+            let operation = format!(
+                "{} {} {}",
+                message::number(lhs),
+                op,
+                message::number(rhs)
+            );
+
+            describe_non_finite(n).with(&[&operation])
+        }
+    }
+}
+
+/// Describe why calling the function `name` with `args` resulted in `n`, which
+/// is not a finite number.
+fn describe_call(name: &str, args: &[f64], n: f64) -> Message {
+    let args: Vec<String> =
+        args.iter().map(|&arg| message::number(arg)).collect();
+
+    describe_non_finite(n).with(&[&format!("{}({})", name, args.join(", "))])
+}
+
+fn describe_non_finite(n: f64) -> Code {
+    if n.is_nan() {
+        Code::IsNotANumber
+    } else {
+        Code::IsInfinite
+    }
+}
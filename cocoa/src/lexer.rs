@@ -1,11 +1,21 @@
 //! Functions that expresso uses to perform lexical analysis of it's input to
 //! convert it into tokens that it understands.
 
-use std::{iter::Peekable, str::Chars};
+use std::{iter::Peekable, ops::Range, str::Chars};
 
 use anyhow::{bail, Result};
 
-use crate::token::{FuncKind, OpKind, Token};
+use crate::{
+    error::SpanError,
+    message::{into_message, Code, Message},
+    token::{Constant, FuncKind, OpKind, Span, Token},
+};
+
+/// The powers of ten which can be held exactly by a float.
+const POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13,
+    1e14, 1e15, 1e16, 1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
 
 /// A lexer that turns an iterator over characters into a vector of `Token`s.
 ///
@@ -31,26 +41,326 @@ use crate::token::{FuncKind, OpKind, Token};
 /// ```
 pub fn lex(cs: &mut Peekable<Chars>) -> Result<Vec<Token>> {
     let mut tokens = vec![];
+    lex_each(cs, |token, _| tokens.push(token))?;
 
-    while let Some(&c) = cs.peek() {
-        if c.is_ascii_whitespace() {
-            cs.next();
-            continue;
-        }
-        else if c.is_ascii_digit() || c == '.' {
-            tokens.push(lex_number(cs)?);
-        } else if c.is_ascii_alphabetic() {
-            tokens.push(lex_ident(cs)?);
-        } else {
-            tokens.push(lex_op(c)?);
-            cs.next();
+    Ok(tokens)
+}
+
+/// A lexer that turns `input` into `Token`s like `lex`, but stores them in
+/// `tokens` rather than a new vector.
+///
+/// `tokens` is cleared first, so that one vector can be reused to lex many
+/// expressions without allocating for each of them. If lexing fails, it holds
+/// the tokens lexed before the error.
+///
+/// # Arguments
+///
+/// * `input` - The text to lex.
+/// * `tokens` - The vector to store the tokens in.
+///
+/// # Examples
+/// ```
+/// use cocoa::{token::{Token, OpKind}, lexer::lex_into};
+///
+/// let mut tokens = vec![];
+///
+/// lex_into("2 + 2", &mut tokens).unwrap();
+/// assert_eq!(
+///     vec![Token::Number(2.0), Token::Op(OpKind::Plus), Token::Number(2.0)],
+///     tokens
+/// );
+///
+/// lex_into("3", &mut tokens).unwrap();
+/// assert_eq!(vec![Token::Number(3.0)], tokens);
+///
+/// assert!(lex_into("1 $ 2", &mut tokens).is_err());
+/// assert_eq!(vec![Token::Number(1.0)], tokens);
+/// ```
+pub fn lex_into(input: &str, tokens: &mut Vec<Token>) -> Result<()> {
+    tokens.clear();
+
+    lex_each(&mut input.chars().peekable(), |token, _| tokens.push(token))
+}
+
+/// A lexer that turns an iterator over characters into a vector of `Token`s,
+/// along with the span of characters each token was lexed from.
+///
+/// See `lex` for details on which characters are recognized. Errors are
+/// `SpanError`s pointing at the characters that could not be lexed.
+///
+/// # Arguments
+///
+/// * `cs` - A peekable character iterator which will be lexed.
+///
+/// # Examples
+/// ```
+/// use cocoa::{error::SpanError, token::{Token, OpKind, Span}, lexer::lex_spanned};
+///
+/// let mut input = "12 * 3".chars().peekable();
+/// let expected = vec![
+///     (Token::Number(12.0), Span::new(0, 2)),
+///     (Token::Op(OpKind::Star), Span::new(3, 4)),
+///     (Token::Number(3.0), Span::new(5, 6)),
+/// ];
+///
+/// assert_eq!(expected, lex_spanned(&mut input).unwrap());
+///
+/// let shift = lex_spanned(&mut "1 << 4".chars().peekable()).unwrap();
+/// assert_eq!((Token::Op(OpKind::Shl), Span::new(2, 4)), shift[1]);
+///
+/// let e = lex_spanned(&mut "1 $ 2".chars().peekable()).unwrap_err();
+/// assert_eq!(Some(Span::new(2, 3)), e.downcast_ref::<SpanError>().map(|e| e.span));
+///
+/// let e = lex_spanned(&mut "1 + 2.5.1".chars().peekable()).unwrap_err();
+/// assert_eq!(Some(Span::new(4, 8)), e.downcast_ref::<SpanError>().map(|e| e.span));
+/// assert_eq!(
+///     "number cannot contain more than one decimal point",
+///     e.to_string()
+/// );
+///
+/// // a decimal point needs a digit on at least one side of it
+/// let e = lex_spanned(&mut "1 + .".chars().peekable()).unwrap_err();
+/// let span = e.downcast_ref::<SpanError>().map(|e| e.span);
+/// assert_eq!(Some(Span::new(4, 5)), span);
+/// assert_eq!("malformed number '.', which has no digits", e.to_string());
+/// assert!(lex_spanned(&mut ".5 + 1.".chars().peekable()).is_ok());
+///
+/// let env = lex_spanned(&mut r#"env("HOME")"#.chars().peekable()).unwrap();
+/// assert_eq!((Token::Str("HOME".to_string()), Span::new(4, 10)), env[2]);
+/// ```
+pub fn lex_spanned(cs: &mut Peekable<Chars>) -> Result<Vec<(Token, Span)>> {
+    let mut tokens = vec![];
+    lex_each(cs, |token, span| tokens.push((token, span)))?;
+
+    Ok(tokens)
+}
+
+/// A change to the text of an expression, replacing the characters in a span
+/// of the old text with some new text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// The characters of the old text that are replaced.
+    pub span: Span,
+    /// The text they are replaced with.
+    pub text: String,
+}
+
+/// Update `tokens`, which were lexed from some text by `lex_spanned`, to be
+/// the tokens of `input`, the text after `edit` was made to it, returning the
+/// range of indices of the tokens which were replaced.
+///
+/// Only the tokens around the edit are lexed again. Tokens before it are
+/// kept, and so are those after it once lexing reaches a token that starts
+/// where one did before the edit, with their spans moved to account for the
+/// change in length. This keeps long lines quick to update as they are
+/// typed, e.g. for highlighting them in an editor.
+///
+/// If `input` cannot be lexed, `tokens` is left unchanged.
+///
+/// # Arguments
+///
+/// * `tokens` - The tokens of the text before the edit, with their spans.
+/// * `edit` - The change that was made to the text.
+/// * `input` - The text after the edit.
+///
+/// # Examples
+/// ```
+/// use cocoa::{lexer::{lex_spanned, relex, Edit}, token::{Span, Token}};
+///
+/// let mut tokens = lex_spanned(&mut "1 + 2 * 3".chars().peekable()).unwrap();
+///
+/// // "2" becomes "sin(20)"
+/// let edit = Edit { span: Span::new(4, 5), text: "sin(20)".to_string() };
+/// let replaced = relex(&mut tokens, &edit, "1 + sin(20) * 3").unwrap();
+///
+/// assert_eq!(2..6, replaced);
+/// assert_eq!(lex_spanned(&mut "1 + sin(20) * 3".chars().peekable()).unwrap(), tokens);
+/// assert_eq!((Token::Number(3.0), Span::new(14, 15)), tokens[7]);
+///
+/// // joining two numbers replaces both of them
+/// let edit = Edit { span: Span::new(1, 3), text: String::new() };
+/// let mut tokens = lex_spanned(&mut "1 + 2".chars().peekable()).unwrap();
+///
+/// assert_eq!(0..1, relex(&mut tokens, &edit, "12").unwrap());
+/// assert_eq!(vec![(Token::Number(12.0), Span::new(0, 2))], tokens);
+/// ```
+pub fn relex(
+    tokens: &mut Vec<(Token, Span)>,
+    edit: &Edit,
+    input: &str,
+) -> Result<Range<usize>> {
+    // the lexer looks one character past the end of a token, so the tokens
+    // ending before the edit would still be lexed the same way
+    let first = tokens
+        .iter()
+        .position(|(_, span)| span.end >= edit.span.start)
+        .unwrap_or(tokens.len());
+    let start = first.checked_sub(1).map_or(0, |i| tokens[i].1.end);
+
+    // the first character after the edit, in the edited text
+    let (removed, inserted) =
+        (edit.span.end - edit.span.start, edit.text.chars().count());
+    let end = edit.span.start + inserted;
+    // tokens after the edit keep their length, only moving
+    let moved = |span: Span| {
+        Span::new(
+            span.start + inserted - removed,
+            span.end + inserted - removed,
+        )
+    };
+
+    // the characters from `start` onwards
+    let rest = input
+        .char_indices()
+        .nth(start)
+        .map_or("", |(i, _)| &input[i..]);
+    let mut cs = rest.chars().peekable();
+    let mut pos = start;
+
+    let mut lexed = vec![];
+    // the first old token after the edit which no new token starts after
+    let mut old = first;
+
+    let last = loop {
+        let Some((token, span)) = lex_token(&mut cs, &mut pos)? else {
+            break tokens.len();
+        };
+
+        // lexing from the same text always gives the same tokens, so the old
+        // tokens can be kept from the first one after the edit that a new
+        // token starts at
+        if span.start >= end {
+            while old < tokens.len()
+                && (tokens[old].1.start < edit.span.end
+                    || moved(tokens[old].1).start < span.start)
+            {
+                old += 1;
+            }
+
+            if old < tokens.len() && moved(tokens[old].1).start == span.start {
+                break old;
+            }
         }
+
+        lexed.push((token, span));
+    };
+
+    for (_, span) in &mut tokens[last..] {
+        *span = moved(*span);
     }
+    let replaced = first..first + lexed.len();
+    tokens.splice(first..last, lexed);
 
-    Ok(tokens)
+    Ok(replaced)
+}
+
+/// Lex `cs`, passing each token to `emit` along with the span of characters
+/// it was lexed from, so that the tokens can be stored however the caller
+/// needs.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "lex", level = "debug", skip_all)
+)]
+pub(crate) fn lex_each(
+    cs: &mut Peekable<Chars>,
+    mut emit: impl FnMut(Token, Span),
+) -> Result<()> {
+    // the index of the next character in `cs`
+    let mut pos = 0;
+
+    while let Some((token, span)) = lex_token(cs, &mut pos)? {
+        emit(token, span);
+    }
+
+    Ok(())
 }
 
-fn lex_op(c: char) -> Result<Token> {
+/// Lex the next token in `cs` along with its span, skipping any whitespace
+/// before it, where `pos` is the index of the next character in `cs`.
+pub(crate) fn lex_token(
+    cs: &mut Peekable<Chars>,
+    pos: &mut usize,
+) -> Result<Option<(Token, Span)>> {
+    while cs.next_if(char::is_ascii_whitespace).is_some() {
+        *pos += 1;
+    }
+
+    let Some(&c) = cs.peek() else {
+        return Ok(None);
+    };
+    let start = *pos;
+
+    let token = if c.is_ascii_digit() || c == '.' {
+        lex_number(cs, pos)
+    } else if c.is_ascii_alphabetic() || c == '_' {
+        lex_ident(cs, pos)
+    } else if c == '"' {
+        lex_string(cs, pos)
+    } else {
+        lex_op(cs, pos)
+    };
+
+    // point at the characters consumed before the error was found, or at
+    // least the character it was found at
+    let token = token.map_err(|e| {
+        SpanError::new(Span::new(start, (*pos).max(start + 1)), into_message(e))
+    })?;
+
+    Ok(Some((token, Span::new(start, *pos))))
+}
+
+/// Lex a string in double quotes, in which `\"` is a quote and `\\` is a
+/// backslash.
+fn lex_string(cs: &mut Peekable<Chars>, pos: &mut usize) -> Result<Token> {
+    // the opening quote
+    cs.next();
+    *pos += 1;
+
+    let mut buf = String::new();
+
+    loop {
+        let c = match cs.next() {
+            Some(c) => c,
+            None => bail!(Message::new(Code::UnterminatedString)),
+        };
+        *pos += 1;
+
+        match c {
+            '"' => return Ok(Token::Str(buf)),
+            '\\' => {
+                match cs.next() {
+                    Some(c @ ('"' | '\\')) => buf.push(c),
+                    Some(c) => bail!(Code::UnknownEscape.with(&[&c])),
+                    None => bail!(Message::new(Code::UnterminatedString)),
+                }
+                *pos += 1;
+            }
+            c => buf.push(c),
+        }
+    }
+}
+
+fn lex_op(cs: &mut Peekable<Chars>, pos: &mut usize) -> Result<Token> {
+    let c = match cs.next() {
+        Some(c) => c,
+        None => bail!(Message::new(Code::UnexpectedEndOfInput)),
+    };
+    *pos += 1;
+
+    // shifts are the only operators made up of two characters
+    if c == '<' || c == '>' {
+        if cs.peek() != Some(&c) {
+            let doubled = format!("{}{}", c, c);
+            bail!(Code::UnrecognizedCharacter
+                .with(&[&c])
+                .with_hint(Message::did_you_mean(&[&doubled])))
+        }
+        cs.next();
+        *pos += 1;
+
+        return Ok(Token::Op(if c == '<' { OpKind::Shl } else { OpKind::Shr }));
+    }
+
     match c {
         '+' => Ok(Token::Op(OpKind::Plus)),
         '-' => Ok(Token::Op(OpKind::Minus)),
@@ -59,62 +369,104 @@ fn lex_op(c: char) -> Result<Token> {
         '^' => Ok(Token::Op(OpKind::Caret)),
         '%' => Ok(Token::Op(OpKind::Modulo)),
         '!' => Ok(Token::Op(OpKind::Factorial)),
+        '=' => Ok(Token::Op(OpKind::Equals)),
         '(' => Ok(Token::LParen),
         ')' => Ok(Token::RParen),
-        _ => bail!("unrecognized character '{}'", c),
+        '[' => Ok(Token::LBracket),
+        ']' => Ok(Token::RBracket),
+        ',' => Ok(Token::Comma),
+        _ => bail!(Code::UnrecognizedCharacter.with(&[&c])),
     }
 }
 
-fn lex_number(cs: &mut Peekable<Chars>) -> Result<Token> {
+fn lex_number(cs: &mut Peekable<Chars>, pos: &mut usize) -> Result<Token> {
     let mut dot = false;
-    let mut buf = String::new();
+    let mut digits = false;
+    // the digits lexed so far, as long as they fit in a u64
+    let mut mantissa: u64 = 0;
+    // every digit lexed so far, once there are too many for the mantissa
+    let mut long: Option<String> = None;
+    // the power of ten the digits are scaled by, i.e. minus the number of
+    // digits after the decimal point
+    let mut scale: i32 = 0;
 
-    // insert a 0 in the buffer is the number being parsed is a decimal
-    // beginning with a '.'
-    //
-    // the `parse` function that will parse the buf into an f64 is not happy if
-    // the buf begins with a '.' as opposed to '0.'
-    if let Some(&c) = cs.peek() {
+    while let Some(&c) = cs.peek() {
         if c == '.' {
-            buf.push('0');
-        }
-    }
+            cs.next();
+            *pos += 1;
 
-    while let Some(&c) = cs.peek() {
-        if c.is_ascii_digit() || c == '.' {
-            if c == '.' {
-                if !dot {
-                    dot = true;
-                } else {
-                    bail!("number cannot contain more than one decimal point")
-                }
+            if dot {
+                bail!(Message::new(Code::MultipleDecimalPoints))
             }
-            buf.push(c);
+            dot = true;
+        } else if let Some(d) = c.to_digit(10) {
             cs.next();
+            *pos += 1;
+            digits = true;
+
+            match &mut long {
+                Some(digits) => digits.push(c),
+                None if mantissa <= (u64::MAX - 9) / 10 => {
+                    mantissa = mantissa * 10 + u64::from(d);
+                }
+                None => {
+                    let mut digits = String::with_capacity(32);
+                    digits.push_str(&mantissa.to_string());
+                    digits.push(c);
+                    long = Some(digits);
+                }
+            }
+            if dot {
+                scale = scale.saturating_sub(1);
+            }
         } else {
             break;
         }
     }
 
-    Ok(Token::Number(buf.parse()?))
+    // a decimal point on its own is not a number
+    if !digits {
+        bail!(Message::new(Code::NumberWithoutDigits))
+    }
+
+    // integers up to 2^53 and powers of ten up to 10^22 are exact as floats,
+    // so dividing one by the other rounds correctly, see Clinger's "How to
+    // read floating point numbers accurately"
+    let n = match long {
+        None if mantissa <= 1 << 53 && scale >= -22 => {
+            mantissa as f64 / POWERS_OF_TEN[scale.unsigned_abs() as usize]
+        }
+        None => format!("{}e{}", mantissa, scale).parse()?,
+        Some(mut digits) => {
+            digits.push('e');
+            digits.push_str(&scale.to_string());
+            digits.parse()?
+        }
+    };
+
+    Ok(Token::Number(n))
 }
 
 // note: a trie is more efficient for the purposes of this function, but the
 // current implementation is easier to extend and makes for some nicer error
 // messages
-fn lex_ident(cs: &mut Peekable<Chars>) -> Result<Token> {
+fn lex_ident(cs: &mut Peekable<Chars>, pos: &mut usize) -> Result<Token> {
     let mut buf = String::new();
 
     while let Some(&c) = cs.peek() {
-        if c.is_ascii_alphabetic() {
+        // the first character is always alphabetic or an underscore, as in
+        // the results `_1` of a session, but plugin names such as `log2` or
+        // `std_dev` may contain digits or underscores after it
+        if c.is_ascii_alphanumeric() || c == '_' {
             buf.push(c);
             cs.next();
+            *pos += 1;
         } else {
             break;
         }
     }
 
-    match buf.as_str().into() {
+    match buf.as_str() {
         "sin" => Ok(Token::Func(FuncKind::Sin)),
         "cos" => Ok(Token::Func(FuncKind::Cos)),
         "tan" => Ok(Token::Func(FuncKind::Tan)),
@@ -127,8 +479,82 @@ fn lex_ident(cs: &mut Peekable<Chars>) -> Result<Token> {
         "ln" => Ok(Token::Func(FuncKind::Ln)),
         "log" => Ok(Token::Func(FuncKind::Log)),
         "sqrt" => Ok(Token::Func(FuncKind::Sqrt)),
+        "max" => Ok(Token::Func(FuncKind::Max)),
+        "min" => Ok(Token::Func(FuncKind::Min)),
+        "sum" => Ok(Token::Func(FuncKind::Sum)),
+        "avg" => Ok(Token::Func(FuncKind::Avg)),
+        "gcd" => Ok(Token::Func(FuncKind::Gcd)),
+        "round" => Ok(Token::Func(FuncKind::Round)),
+        "pctchange" => Ok(Token::Func(FuncKind::PctChange)),
+        "relchange" => Ok(Token::Func(FuncKind::RelChange)),
+        "ulp" => Ok(Token::Func(FuncKind::Ulp)),
+        "nextafter" => Ok(Token::Func(FuncKind::NextAfter)),
+        "fma" => Ok(Token::Func(FuncKind::Fma)),
+        "erf" => Ok(Token::Func(FuncKind::Erf)),
+        "erfc" => Ok(Token::Func(FuncKind::Erfc)),
+        "normpdf" => Ok(Token::Func(FuncKind::NormPdf)),
+        "normcdf" => Ok(Token::Func(FuncKind::NormCdf)),
+        "norminv" => Ok(Token::Func(FuncKind::NormInv)),
+        "binompdf" => Ok(Token::Func(FuncKind::BinomPdf)),
+        "binomcdf" => Ok(Token::Func(FuncKind::BinomCdf)),
+        "poissonpdf" => Ok(Token::Func(FuncKind::PoissonPdf)),
+        "tcdf" => Ok(Token::Func(FuncKind::TCdf)),
+        #[cfg(feature = "special-functions")]
+        "besselj" => Ok(Token::Func(FuncKind::BesselJ)),
+        #[cfg(feature = "special-functions")]
+        "zeta" => Ok(Token::Func(FuncKind::Zeta)),
+        #[cfg(feature = "special-functions")]
+        "beta" => Ok(Token::Func(FuncKind::Beta)),
+        "lambertw" => Ok(Token::Func(FuncKind::LambertW)),
+        "lgamma" => Ok(Token::Func(FuncKind::LGamma)),
+        "lnfact" => Ok(Token::Func(FuncKind::LnFact)),
+        "fib" => Ok(Token::Func(FuncKind::Fib)),
+        "tri" => Ok(Token::Func(FuncKind::Tri)),
+        "catalan" => Ok(Token::Func(FuncKind::Catalan)),
+        "primepi" => Ok(Token::Func(FuncKind::PrimePi)),
+        "prime" => Ok(Token::Func(FuncKind::Prime)),
+        "totient" => Ok(Token::Func(FuncKind::Totient)),
+        "divisors" => Ok(Token::Func(FuncKind::Divisors)),
+        "sigma" => Ok(Token::Func(FuncKind::Sigma)),
+        "cfrac" => Ok(Token::Func(FuncKind::Cfrac)),
+        "popcount" => Ok(Token::Func(FuncKind::Popcount)),
+        "rotl" => Ok(Token::Func(FuncKind::Rotl)),
+        "rotr" => Ok(Token::Func(FuncKind::Rotr)),
+        "bit" => Ok(Token::Func(FuncKind::Bit)),
+        "mantissa" => Ok(Token::Func(FuncKind::Mantissa)),
+        "exponent" => Ok(Token::Func(FuncKind::Exponent)),
+        "bits" => Ok(Token::Func(FuncKind::Bits)),
+        "frombits" => Ok(Token::Func(FuncKind::FromBits)),
+        "rand" => Ok(Token::Func(FuncKind::Rand)),
+        "assert" => Ok(Token::Func(FuncKind::Assert)),
+        "assert_eq" => Ok(Token::Func(FuncKind::AssertEq)),
+        "approx" => Ok(Token::Func(FuncKind::Approx)),
+        "env" => Ok(Token::Func(FuncKind::Env)),
+        "load" => Ok(Token::Func(FuncKind::Load)),
+        "stdin" => Ok(Token::Func(FuncKind::Stdin)),
+        "sort" => Ok(Token::Func(FuncKind::Sort)),
+        "reverse" => Ok(Token::Func(FuncKind::Reverse)),
+        "nth" => Ok(Token::Func(FuncKind::Nth)),
+        "head" => Ok(Token::Func(FuncKind::Head)),
+        "tail" => Ok(Token::Func(FuncKind::Tail)),
+        "unique" => Ok(Token::Func(FuncKind::Unique)),
+        "range" => Ok(Token::Func(FuncKind::Range)),
+        "quadroots" => Ok(Token::Func(FuncKind::QuadRoots)),
+        "cubicroots" => Ok(Token::Func(FuncKind::CubicRoots)),
+        "polyroots" => Ok(Token::Func(FuncKind::PolyRoots)),
+        "linsolve" => Ok(Token::Func(FuncKind::LinSolve)),
+        "fit" => Ok(Token::Func(FuncKind::Fit)),
+        "map" => Ok(Token::Func(FuncKind::Map)),
+        "table" => Ok(Token::Func(FuncKind::Table)),
+        "filter" => Ok(Token::Func(FuncKind::Filter)),
+        "reduce" => Ok(Token::Func(FuncKind::Reduce)),
+        "solve" => Ok(Token::Func(FuncKind::Solve)),
+        "montecarlo" => Ok(Token::Func(FuncKind::MonteCarlo)),
+        "odesolve" => Ok(Token::Func(FuncKind::OdeSolve)),
         // `pi` is treated as a regular floating point number
-        "pi" => Ok(Token::Number(std::f64::consts::PI)),
-        _ => bail!("unrecognized identifier '{}'", buf),
+        "pi" => Ok(Token::Number(Constant::Pi.value())),
+        // anything else is left for the evaluator to resolve, since it may
+        // have been provided by a plugin
+        _ => Ok(Token::Ident(buf)),
     }
 }
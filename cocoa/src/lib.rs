@@ -6,7 +6,338 @@
 //! It contains an implementation for a lexer and a pratt parser which is used
 //! to evaluate mathematical expressions.
 
+pub mod algebra;
+pub mod analysis;
+pub mod arena;
+pub mod ast;
+pub mod autodiff;
+mod compile;
+pub mod error;
+pub mod evaluator;
+pub mod fmt;
+pub mod function;
+pub mod highlight;
+mod interpreter;
 pub mod lexer;
 pub mod math;
+pub mod message;
+pub mod packed;
 pub mod parser;
+pub mod partial;
+pub mod plugin;
+pub mod prelude;
+pub mod render;
+pub mod rpn;
+pub mod script;
+pub mod session;
+mod solve;
+pub mod suggest;
+pub mod symbolic;
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod token;
+pub mod value;
+
+use std::io::BufRead;
+
+use anyhow::Result;
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    error::{Diagnostic, Severity},
+    evaluator::Evaluator,
+    message::{Code, Message},
+    suggest::{correction, suggestions},
+    token::{FuncKind, Span},
+};
+
+/// Evaluate `input` with the variables in `bindings` bound, using the default
+/// settings of an `Evaluator`.
+///
+/// This is a shorthand for creating an `Evaluator` and calling
+/// `set_variable` for each binding, for programs which only need to evaluate
+/// an expression once.
+///
+/// # Arguments
+///
+/// * `input` - The expression to evaluate.
+/// * `bindings` - The names of variables paired with their values. Later
+///   bindings of the same name replace earlier ones.
+///
+/// # Examples
+/// ```
+/// assert_eq!(10.0, cocoa::eval_with("x^2 + y", &[("x", 3.0), ("y", 1.0)]).unwrap());
+/// assert!(cocoa::eval_with("x + z", &[("x", 1.0)]).is_err());
+/// assert!(cocoa::eval_with("pi", &[("pi", 3.0)]).is_err());
+/// ```
+pub fn eval_with(input: &str, bindings: &[(&str, f64)]) -> Result<f64> {
+    let mut evaluator = Evaluator::new();
+
+    for (name, value) in bindings {
+        evaluator.set_variable(name, *value)?;
+    }
+
+    evaluator.eval(input)
+}
+
+/// Evaluate each line read from `reader` as an expression, using the default
+/// settings of an `Evaluator`, yielding each line along with its result.
+///
+/// Lines are read and evaluated one at a time as the iterator is advanced,
+/// so input of any size can be processed without holding all of it in
+/// memory. Blank lines are skipped. If a line cannot be read, e.g. since it
+/// is not UTF-8, the error is yielded with an empty line and the iterator
+/// ends, since reading any further may fail forever.
+///
+/// # Arguments
+///
+/// * `reader` - The input to read lines of expressions from.
+///
+/// # Examples
+/// ```
+/// let input = "1 + 2\n\nsqrt(16)\n2 +\n";
+/// let results: Vec<(String, Option<f64>)> = cocoa::eval_lines(input.as_bytes())
+///     .map(|(line, result)| (line, result.ok()))
+///     .collect();
+///
+/// assert_eq!(
+///     vec![
+///         ("1 + 2".to_string(), Some(3.0)),
+///         ("sqrt(16)".to_string(), Some(4.0)),
+///         ("2 +".to_string(), None),
+///     ],
+///     results
+/// );
+/// ```
+pub fn eval_lines(
+    reader: impl BufRead,
+) -> impl Iterator<Item = (String, Result<f64>)> {
+    eval_lines_with(reader, Evaluator::new())
+}
+
+/// Evaluate each line read from `reader` like `eval_lines`, with `evaluator`
+/// rather than one with the default settings.
+///
+/// # Arguments
+///
+/// * `reader` - The input to read lines of expressions from.
+/// * `evaluator` - The evaluator to evaluate the lines with.
+///
+/// # Examples
+/// ```
+/// use cocoa::evaluator::Evaluator;
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.set_prelude(false);
+///
+/// let mut results = cocoa::eval_lines_with("sec(0)\n".as_bytes(), evaluator);
+/// assert!(results.next().unwrap().1.is_err());
+/// ```
+pub fn eval_lines_with(
+    reader: impl BufRead,
+    evaluator: Evaluator,
+) -> impl Iterator<Item = (String, Result<f64>)> {
+    let mut failed = false;
+
+    reader
+        .lines()
+        .map_while(move |line| {
+            if failed {
+                return None;
+            }
+            failed = line.is_err();
+
+            Some(line)
+        })
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(move |line| match line {
+            Ok(line) => {
+                let result = evaluator.eval(&line);
+                (line, result)
+            }
+            Err(e) => (String::new(), Err(e.into())),
+        })
+}
+
+/// Check that `input` is a well formed expression without evaluating it,
+/// returning the syntax error it has if it is not.
+///
+/// Since nothing is evaluated, this is quick and cannot have side effects
+/// such as reading environment variables, so it is suited to checking
+/// input as it is entered, e.g. into a form.
+///
+/// # Arguments
+///
+/// * `input` - The expression to check.
+///
+/// # Examples
+/// ```
+/// use cocoa::token::Span;
+///
+/// assert!(cocoa::validate("sqrt(x) + 2").is_ok());
+///
+/// let diagnostics = cocoa::validate("1 + * 2").unwrap_err();
+/// assert_eq!(1, diagnostics.len());
+/// assert_eq!(Some(Span::new(4, 5)), diagnostics[0].span);
+/// ```
+pub fn validate(input: &str) -> Result<(), Vec<Diagnostic>> {
+    input
+        .parse::<Expr>()
+        .map(|_| ())
+        .map_err(|e| vec![Diagnostic::error(&e)])
+}
+
+/// Check that `input` is a well formed expression which only uses names
+/// known to `evaluator`, without evaluating it.
+///
+/// Every unknown name is reported, along with any names it may have been
+/// meant to be. Names which `evaluator` would correct as typos, and names
+/// bound by functions such as `map`, are known.
+///
+/// # Arguments
+///
+/// * `input` - The expression to check.
+/// * `evaluator` - The evaluator whose variables, constants, functions and
+///   aliases are known.
+///
+/// # Examples
+/// ```
+/// use cocoa::{evaluator::Evaluator, token::Span};
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.set_variable("rate", 0.05).unwrap();
+///
+/// assert!(cocoa::validate_with("100 * (1 + rate)^2", &evaluator).is_ok());
+/// assert!(cocoa::validate_with("map(x * rate, x, [1, 2])", &evaluator).is_ok());
+/// assert!(cocoa::validate_with("odesolve(rate * y, 0, 1, 10, 100)", &evaluator).is_ok());
+///
+/// let diagnostics = cocoa::validate_with("rates * years + f(1)", &evaluator).unwrap_err();
+/// assert_eq!(
+///     vec![
+///         "unknown identifier 'rates', did you mean 'rate'?",
+///         "unknown identifier 'years'",
+///         "unknown function 'f'",
+///     ],
+///     diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>(),
+/// );
+/// assert_eq!(Some(Span::new(16, 17)), diagnostics[2].span);
+///
+/// assert!(cocoa::validate_with("1 +", &evaluator).is_err());
+/// ```
+pub fn validate_with(
+    input: &str,
+    evaluator: &Evaluator,
+) -> Result<(), Vec<Diagnostic>> {
+    let expr: Expr = input.parse().map_err(|e| vec![Diagnostic::error(&e)])?;
+
+    let mut diagnostics = vec![];
+    check_names(&expr, evaluator, &mut vec![], &mut diagnostics);
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Add an error to `diagnostics` for each name in `expr` which is not known
+/// to `evaluator` or bound in `scope`, in the order they appear.
+fn check_names<'a>(
+    expr: &'a Expr,
+    evaluator: &Evaluator,
+    scope: &mut Vec<&'a str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut check = |name: &str, call: bool, span: Option<Span>| {
+        if scope.contains(&name) || evaluator.alias(name).is_some() {
+            return;
+        }
+
+        let known = if call {
+            evaluator.function(name).is_some()
+                || evaluator.template(name).is_some()
+        } else {
+            evaluator.constant(name).is_some()
+                || evaluator.variable(name).is_some()
+        };
+        let names = evaluator.names(call);
+        if known
+            || evaluator.autocorrect()
+                && correction(name, names.clone()).is_some()
+        {
+            return;
+        }
+
+        let suggestions = suggestions(name, names);
+        let code = if call {
+            Code::UnknownFunction
+        } else {
+            Code::UnknownIdentifier
+        };
+        let message = code.with(&[&name]).with_hint(Message::did_you_mean(
+            &suggestions[..suggestions.len().min(3)],
+        ));
+        diagnostics.push(Diagnostic::new(Severity::Error, span, message));
+    };
+
+    match &expr.kind {
+        ExprKind::Number(_) | ExprKind::Str(_) => (),
+        ExprKind::Ident(name) => check(name, false, expr.span),
+        ExprKind::Unary(_, e)
+        | ExprKind::Postfix(_, e)
+        | ExprKind::Group(e) => check_names(e, evaluator, scope, diagnostics),
+        ExprKind::Binary(_, lhs, rhs) => {
+            check_names(lhs, evaluator, scope, diagnostics);
+            check_names(rhs, evaluator, scope, diagnostics);
+        }
+        ExprKind::List(elements) => {
+            for e in elements {
+                check_names(e, evaluator, scope, diagnostics);
+            }
+        }
+        ExprKind::Call(callee, args) => {
+            let bound = match callee {
+                Callee::Named(name) => {
+                    // the span of the name, rather than the whole call
+                    let span = expr.span.map(|span| {
+                        Span::new(span.start, span.start + name.chars().count())
+                    });
+                    check(name, true, span);
+                    0
+                }
+                Callee::Builtin(FuncKind::Reduce) => 2,
+                Callee::Builtin(FuncKind::OdeSolve) => 0,
+                Callee::Builtin(f) if f.is_higher_order() => 1,
+                Callee::Builtin(_) => 0,
+            };
+
+            // the body of a higher order function comes before the names it
+            // binds, which are known within it
+            let mut names: Vec<&str> = args
+                .iter()
+                .skip(1)
+                .take(bound)
+                .filter_map(|arg| match &arg.kind {
+                    ExprKind::Ident(name) => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let implicit = *callee == Callee::Builtin(FuncKind::OdeSolve);
+            if implicit {
+                names = vec!["x", "y"];
+            }
+
+            for (i, arg) in args.iter().enumerate() {
+                if i == 0 && !names.is_empty() {
+                    let len = scope.len();
+                    scope.extend(&names);
+                    check_names(arg, evaluator, scope, diagnostics);
+                    scope.truncate(len);
+                } else if implicit || !(1..=names.len()).contains(&i) {
+                    check_names(arg, evaluator, scope, diagnostics);
+                }
+            }
+        }
+    }
+}
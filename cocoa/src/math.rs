@@ -1,5 +1,22 @@
 //! Helper functions which handle some of the arithmetic that expresso supports.
 
+#[cfg(feature = "bigint")]
+pub mod big;
+pub mod bits;
+pub mod exact;
+pub mod linalg;
+pub mod nt;
+pub mod poly;
+pub mod random;
+pub mod special;
+pub mod stats;
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Error, Result};
+
+use crate::message::Code;
+
 /// Calculate the factorial of n where n is an integer that is greater
 /// than or equal to 0.
 ///
@@ -26,3 +43,583 @@ pub fn ufactorial(n: u64) -> u64 {
 
     n * ufactorial(n - 1)
 }
+
+/// Calculate the factorial of n as a floating point number, where n is an
+/// integer that is greater than or equal to 0.
+///
+/// The result is exact up to `20!`, the largest factorial that fits in a
+/// `u64`, and infinite from `171!` onwards since it is too large for an `f64`.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::factorial;
+///
+/// assert_eq!(factorial(5), 120.0);
+/// assert_eq!(factorial(21), 51090942171709440000.0);
+/// assert_eq!(factorial(171), f64::INFINITY);
+/// ```
+pub fn factorial(n: u64) -> f64 {
+    match n {
+        0..=20 => ufactorial(n) as f64,
+        21..=170 => {
+            (21..=n).fold(ufactorial(20) as f64, |acc, k| acc * k as f64)
+        }
+        _ => f64::INFINITY,
+    }
+}
+
+/// Calculate the greatest common divisor of a and b using the euclidean
+/// algorithm.
+///
+/// The greatest common divisor of 0 and 0 is taken to be 0.
+///
+/// # Arguments
+///
+/// * `a` - An integer >= 0.
+/// * `b` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::gcd;
+///
+/// assert_eq!(gcd(12, 18), 6);
+/// assert_eq!(gcd(7, 0), 7);
+/// assert_eq!(gcd(0, 0), 0);
+/// ```
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        return a;
+    }
+
+    gcd(b, a % b)
+}
+
+/// Calculate the nth fibonacci number, where `fibonacci(0)` is 0 and
+/// `fibonacci(1)` is 1, returning `None` if it overflows a `u64`.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::fibonacci;
+///
+/// assert_eq!(fibonacci(0), Some(0));
+/// assert_eq!(fibonacci(10), Some(55));
+/// assert_eq!(fibonacci(93), Some(12200160415121876738));
+/// assert_eq!(fibonacci(94), None);
+/// ```
+pub fn fibonacci(n: u64) -> Option<u64> {
+    if n == 0 {
+        return Some(0);
+    }
+
+    let (mut a, mut b) = (0u64, 1u64);
+
+    for _ in 1..n {
+        (a, b) = (b, a.checked_add(b)?);
+    }
+
+    Some(b)
+}
+
+/// Calculate the nth triangular number, i.e. the sum of the integers from 1 to
+/// n, returning `None` if it overflows a `u64`.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::triangular;
+///
+/// assert_eq!(triangular(0), Some(0));
+/// assert_eq!(triangular(4), Some(10));
+/// assert_eq!(triangular(u64::MAX), None);
+/// ```
+pub fn triangular(n: u64) -> Option<u64> {
+    // one of n and n + 1 is even, so halve that one before multiplying
+    if n.is_multiple_of(2) {
+        (n / 2).checked_mul(n.checked_add(1)?)
+    } else {
+        n.checked_mul(n / 2 + 1)
+    }
+}
+
+/// Calculate the nth catalan number, returning `None` if it overflows a
+/// `u64`.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::catalan;
+///
+/// assert_eq!(catalan(0), Some(1));
+/// assert_eq!(catalan(5), Some(42));
+/// assert_eq!(catalan(36), Some(11959798385860453492));
+/// assert_eq!(catalan(37), None);
+/// ```
+pub fn catalan(n: u64) -> Option<u64> {
+    let mut c: u64 = 1;
+
+    // C(k + 1) = C(k) * 2(2k + 1) / (k + 2), where the division is exact, and
+    // the product is done in a u128 so that it can't overflow before dividing
+    for k in 0..n {
+        let next = c as u128 * (2 * (2 * k as u128 + 1)) / (k as u128 + 2);
+        c = u64::try_from(next).ok()?;
+    }
+
+    Some(c)
+}
+
+/// The ways a number can be rounded to a given number of digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Round to the nearest value, with halfway cases rounded away from zero,
+    /// e.g. `2.5` becomes `3` and `-2.5` becomes `-3`.
+    #[default]
+    HalfUp,
+    /// Round to the nearest value, with halfway cases rounded to the nearest
+    /// even digit, e.g. `2.5` becomes `2` and `3.5` becomes `4`.
+    ///
+    /// This is also known as banker's rounding.
+    HalfEven,
+    /// Discard any digits that are not kept, e.g. `2.7` becomes `2` and `-2.7`
+    /// becomes `-2`.
+    TowardZero,
+}
+
+impl FromStr for Rounding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "half-up" => Ok(Rounding::HalfUp),
+            "half-even" => Ok(Rounding::HalfEven),
+            "toward-zero" => Ok(Rounding::TowardZero),
+            _ => bail!(Code::UnknownRoundingMode.with(&[&s])),
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::HalfUp => write!(f, "half-up"),
+            Rounding::HalfEven => write!(f, "half-even"),
+            Rounding::TowardZero => write!(f, "toward-zero"),
+        }
+    }
+}
+
+/// Round `x` to `digits` digits after the decimal point using `mode`.
+///
+/// A negative number of digits rounds to the left of the decimal point, e.g.
+/// rounding to -2 digits rounds to the nearest hundred.
+///
+/// Since `x` is a binary floating point number, a decimal that looks like a
+/// halfway case may be stored as slightly less or more than it, e.g. `2.675`
+/// is stored as `2.67499999...`, so is rounded down to `2.67`.
+///
+/// # Arguments
+///
+/// * `x` - The number to round.
+/// * `digits` - The number of digits after the decimal point to keep.
+/// * `mode` - How to round the digits that are not kept.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::{round, Rounding};
+///
+/// assert_eq!(round(2.5, 0, Rounding::HalfUp), 3.0);
+/// assert_eq!(round(2.5, 0, Rounding::HalfEven), 2.0);
+/// assert_eq!(round(-2.7, 0, Rounding::TowardZero), -2.0);
+/// assert_eq!(round(0.125, 2, Rounding::HalfEven), 0.12);
+/// assert_eq!(round(1250.0, -2, Rounding::HalfUp), 1300.0);
+/// ```
+pub fn round(x: f64, digits: i32, mode: Rounding) -> f64 {
+    let round = |y: f64| match mode {
+        Rounding::HalfUp => y.round(),
+        Rounding::HalfEven => y.round_ties_even(),
+        Rounding::TowardZero => y.trunc(),
+    };
+
+    // dividing by a power of 10 is exact for more inputs than multiplying by
+    // its reciprocal, which cannot be represented exactly
+    let scale = 10f64.powi(digits.abs());
+    let scaled = if digits < 0 { x / scale } else { x * scale };
+
+    if !scaled.is_finite() {
+        // x has no digits this far after the decimal point
+        return x;
+    }
+
+    if digits < 0 {
+        round(scaled) * scale
+    } else {
+        round(scaled) / scale
+    }
+}
+
+/// Get the number with the fewest digits after the decimal point which is
+/// within `epsilon` of `x`, such as `0.3` for `0.30000000000000004`, or `x`
+/// itself if it has no such neighbour.
+///
+/// The tolerance grows with the magnitude of `x` once it is larger than 1,
+/// so that `epsilon` is relative for large numbers and absolute for small
+/// ones, which snap to 0 when they are smaller than it.
+///
+/// # Arguments
+///
+/// * `x` - The number to snap.
+/// * `epsilon` - How far from `x` the number it is snapped to may be.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::snap;
+///
+/// assert_eq!(0.3, snap(0.1 + 0.2, 1e-9));
+/// assert_eq!(3.0, snap(2.9999999999999996, 1e-9));
+/// assert_eq!(0.0, snap(std::f64::consts::PI.sin(), 1e-9));
+/// assert_eq!(0.1234, snap(0.1234, 1e-9));
+/// assert_eq!(0.333333333, snap(1.0 / 3.0, 1e-9));
+/// assert_eq!(0.333, snap(1.0 / 3.0, 1e-3));
+/// ```
+pub fn snap(x: f64, epsilon: f64) -> f64 {
+    // a float has at most 17 significant digits, so no more digits after the
+    // decimal point than that are needed for numbers around 1
+    const MAX_DIGITS: i32 = 17;
+
+    if !x.is_finite() {
+        return x;
+    }
+
+    let tolerance = epsilon * x.abs().max(1.0);
+    (0..=MAX_DIGITS)
+        .map(|digits| round(x, digits, Rounding::HalfEven))
+        .find(|n| (n - x).abs() <= tolerance)
+        .unwrap_or(x)
+}
+
+/// The tolerance `approx_eq` is used with by default, e.g. by `approx(a, b)`.
+pub const DEFAULT_EPSILON: f64 = 1e-9;
+
+/// Check whether `a` and `b` are equal to within `epsilon`, which is relative
+/// to their magnitude once it is larger than 1 and absolute otherwise, so
+/// that results with rounding errors can be compared.
+///
+/// Infinities are only equal to themselves, and NaN is equal to nothing.
+///
+/// # Arguments
+///
+/// * `a` - The first number to compare.
+/// * `b` - The second number to compare.
+/// * `epsilon` - How far apart `a` and `b` may be.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::{approx_eq, DEFAULT_EPSILON};
+///
+/// assert!(approx_eq(0.1 + 0.2, 0.3, DEFAULT_EPSILON));
+/// assert!(approx_eq(1e20 + 1e5, 1e20, DEFAULT_EPSILON));
+/// assert!(!approx_eq(1.001, 1.0, DEFAULT_EPSILON));
+/// assert!(approx_eq(1.001, 1.0, 1e-2));
+/// assert!(approx_eq(f64::INFINITY, f64::INFINITY, 0.0));
+/// assert!(!approx_eq(f64::NAN, f64::NAN, 1.0));
+/// ```
+pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    // comparing directly first means equal infinities are equal
+    a == b || (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.0)
+}
+
+/// Calculate the change from `from` to `to` relative to the size of `from`,
+/// so that a rise is positive and a fall is negative even when `from` is
+/// negative.
+///
+/// # Arguments
+///
+/// * `from` - The value before the change, which should be non-zero.
+/// * `to` - The value after the change.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::relative_change;
+///
+/// assert_eq!(0.25, relative_change(80.0, 100.0));
+/// assert_eq!(-0.2, relative_change(100.0, 80.0));
+/// assert_eq!(0.5, relative_change(-2.0, -1.0));
+/// ```
+pub fn relative_change(from: f64, to: f64) -> f64 {
+    (to - from) / from.abs()
+}
+
+/// Calculate the unit in the last place of x, i.e. the gap between x and the
+/// next representable `f64` further away from zero.
+///
+/// # Arguments
+///
+/// * `x` - Any floating point number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::ulp;
+///
+/// assert_eq!(ulp(1.0), f64::EPSILON);
+/// assert_eq!(ulp(-1.0), f64::EPSILON);
+/// assert_eq!(ulp(0.0), f64::from_bits(1));
+/// assert_eq!(ulp(f64::INFINITY), f64::INFINITY);
+/// ```
+pub fn ulp(x: f64) -> f64 {
+    let x = x.abs();
+
+    if !x.is_finite() {
+        // infinity has no next float and NaN propagates
+        x
+    } else if x == f64::MAX {
+        x - x.next_down()
+    } else {
+        x.next_up() - x
+    }
+}
+
+/// Get the next representable `f64` after `from` in the direction of `to`.
+///
+/// If `from` and `to` are equal, `to` is returned.
+///
+/// # Arguments
+///
+/// * `from` - The float to start from.
+/// * `to` - The float to move towards.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nextafter;
+///
+/// assert_eq!(nextafter(1.0, 2.0), 1.0 + f64::EPSILON);
+/// assert_eq!(nextafter(1.0, 0.0), 1.0 - f64::EPSILON / 2.0);
+/// assert_eq!(nextafter(1.0, 1.0), 1.0);
+/// ```
+pub fn nextafter(from: f64, to: f64) -> f64 {
+    if from.is_nan() || to.is_nan() {
+        f64::NAN
+    } else if from < to {
+        from.next_up()
+    } else if from > to {
+        from.next_down()
+    } else {
+        to
+    }
+}
+
+/// Add x to the running total `sum`, accumulating the rounding error of the
+/// addition into `carry` rather than losing it.
+///
+/// This is one step of Neumaier's improvement of Kahan summation. The total
+/// with its error compensated for is the returned sum plus `carry` once every
+/// number has been added.
+///
+/// # Arguments
+///
+/// * `sum` - The total of the numbers added so far, without `carry`.
+/// * `x` - The number to add.
+/// * `carry` - The rounding error of the additions so far, which starts at 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::compensated_add;
+///
+/// let mut carry = 0.0;
+/// let sum = compensated_add(1.0, 1e100, &mut carry);
+/// let sum = compensated_add(sum, 1.0, &mut carry);
+/// let sum = compensated_add(sum, -1e100, &mut carry);
+///
+/// assert_eq!(sum + carry, 2.0);
+/// ```
+pub fn compensated_add(sum: f64, x: f64, carry: &mut f64) -> f64 {
+    let total = sum + x;
+
+    // infinities have no rounding error, and would make the carry NaN
+    if !total.is_finite() {
+        return total;
+    }
+
+    // the low order digits of whichever operand is smaller are lost
+    if sum.abs() >= x.abs() {
+        *carry += (sum - total) + x;
+    } else {
+        *carry += (x - total) + sum;
+    }
+
+    total
+}
+
+/// Sum `xs` with their rounding errors compensated for, so that the result
+/// does not drift from the true sum as more numbers are added.
+///
+/// # Arguments
+///
+/// * `xs` - The numbers to sum.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::compensated_sum;
+///
+/// assert_eq!(compensated_sum(&[1.0, 1e100, 1.0, -1e100]), 2.0);
+/// assert_eq!(compensated_sum(&[0.1; 10]), 1.0);
+/// assert_ne!([0.1; 10].iter().sum::<f64>(), 1.0);
+/// assert_eq!(compensated_sum(&[]), 0.0);
+/// assert_eq!(compensated_sum(&[f64::INFINITY, 1.0]), f64::INFINITY);
+/// ```
+pub fn compensated_sum(xs: &[f64]) -> f64 {
+    let mut carry = 0.0;
+    let sum = xs
+        .iter()
+        .fold(0.0, |sum, &x| compensated_add(sum, x, &mut carry));
+
+    sum + carry
+}
+
+/// Calculate the principal branch of the Lambert W function of x, i.e. the
+/// value w >= -1 such that `w * e^w = x`.
+///
+/// The value is found with Halley's method, starting from a closed form
+/// approximation.
+///
+/// # Arguments
+///
+/// * `x` - A number >= -1/e.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::lambertw;
+/// use std::f64::consts::E;
+///
+/// assert!((lambertw(1.0) - 0.5671432904097838).abs() < 1e-15);
+/// assert!((lambertw(E) - 1.0).abs() < 1e-15);
+/// assert_eq!(lambertw(0.0), 0.0);
+/// assert_eq!(lambertw(-1.0 / E), -1.0);
+/// assert!(lambertw(-1.0).is_nan());
+/// ```
+pub fn lambertw(x: f64) -> f64 {
+    use std::f64::consts::E;
+
+    let branch_point = -1.0 / E;
+
+    if x.is_nan() || x < branch_point {
+        return f64::NAN;
+    } else if x == branch_point {
+        return -1.0;
+    } else if x == f64::INFINITY {
+        return x;
+    }
+
+    let mut w = if x < -0.25 {
+        // series around the branch point, where W is not differentiable
+        let p = (2.0 * (E * x + 1.0)).sqrt();
+        -1.0 + p - p * p / 3.0 + 11.0 / 72.0 * p * p * p
+    } else {
+        // winitzki's approximation
+        let l = x.ln_1p();
+        l * (1.0 - l.ln_1p() / (2.0 + l))
+    };
+
+    for _ in 0..64 {
+        let ew = w.exp();
+        let f = w * ew - x;
+
+        if f == 0.0 {
+            break;
+        }
+
+        let step = f / (ew * (w + 1.0) - (w + 2.0) * f / (2.0 * w + 2.0));
+        w -= step;
+
+        if step.abs() <= 4.0 * f64::EPSILON * w.abs() {
+            break;
+        }
+    }
+
+    w
+}
+
+/// Calculate up to the first n terms of the simple continued fraction
+/// expansion of x, `[a0; a1, a2, ...]`, such that
+/// `x = a0 + 1 / (a1 + 1 / (a2 + ...))`.
+///
+/// The expansion stops early once it represents x exactly, since any further
+/// terms would only describe the rounding error of x.
+///
+/// # Arguments
+///
+/// * `x` - A finite number.
+/// * `n` - The maximum number of terms.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::cfrac;
+///
+/// assert_eq!(cfrac(std::f64::consts::PI, 5), vec![3, 7, 15, 1, 292]);
+/// assert_eq!(cfrac(0.75, 10), vec![0, 1, 3]);
+/// assert_eq!(cfrac(-1.5, 10), vec![-2, 2]);
+/// ```
+pub fn cfrac(x: f64, n: usize) -> Vec<i64> {
+    let mut terms = vec![];
+    let mut y = x;
+
+    while terms.len() < n && y.is_finite() {
+        let a = y.floor();
+        // casting saturates for terms too large for an i64, which can only be
+        // the first term, or the last one just before the expansion ends
+        terms.push(a as i64);
+
+        if convergent(&terms).is_some_and(|(p, q)| p as f64 / q as f64 == x) {
+            break;
+        }
+
+        y = 1.0 / (y - a);
+    }
+
+    terms
+}
+
+/// Calculate the fraction `p / q` a continued fraction expansion is equal to,
+/// returning `None` if the numerator or denominator overflows an `i64`.
+///
+/// # Arguments
+///
+/// * `terms` - The terms of the expansion, `[a0; a1, a2, ...]`.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::convergent;
+///
+/// assert_eq!(convergent(&[3, 7, 15, 1]), Some((355, 113)));
+/// assert_eq!(convergent(&[0, 1, 3]), Some((3, 4)));
+/// assert_eq!(convergent(&[]), Some((0, 1)));
+/// ```
+pub fn convergent(terms: &[i64]) -> Option<(i64, i64)> {
+    // h(n) = a(n) h(n - 1) + h(n - 2), and likewise for k, starting from
+    // h(-1) = 1, h(-2) = 0, k(-1) = 0 and k(-2) = 1
+    let (mut h, mut h_prev) = (1i64, 0i64);
+    let (mut k, mut k_prev) = (0i64, 1i64);
+
+    if terms.is_empty() {
+        return Some((0, 1));
+    }
+
+    for &a in terms {
+        (h, h_prev) = (a.checked_mul(h)?.checked_add(h_prev)?, h);
+        (k, k_prev) = (a.checked_mul(k)?.checked_add(k_prev)?, k);
+    }
+
+    Some((h, k))
+}
@@ -0,0 +1,164 @@
+//! Exact integer arithmetic on integers too large to be held exactly by an
+//! `f64`, which is only available with the `bigint` feature.
+
+use num_bigint::BigInt;
+
+/// The most bits an exact result may have, which is about 39000 digits, so
+/// that a mistyped calculation cannot hang or exhaust memory.
+pub const MAX_BITS: u64 = 1 << 17;
+
+/// The largest number `factorial` will calculate the factorial of, whose
+/// factorial has 35660 digits, just within `MAX_BITS`.
+pub const MAX_FACTORIAL: u64 = 10_000;
+
+/// Get `n` if it has at most `MAX_BITS` bits.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::big::{limit, MAX_BITS};
+/// use num_bigint::BigInt;
+///
+/// assert!(limit(BigInt::from(1) << (MAX_BITS - 1)).is_some());
+/// assert!(limit(BigInt::from(1) << MAX_BITS).is_none());
+/// ```
+pub fn limit(n: BigInt) -> Option<BigInt> {
+    (n.bits() <= MAX_BITS).then_some(n)
+}
+
+/// Calculate the factorial of n exactly, where n is an integer that is at
+/// most `MAX_FACTORIAL`, returning `None` if n is larger.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::big::factorial;
+///
+/// assert_eq!("120", factorial(5).unwrap().to_string());
+/// assert_eq!(
+///     "30414093201713378043612608166064768844377641568960512000000000000",
+///     factorial(50).unwrap().to_string()
+/// );
+/// assert!(factorial(10_001).is_none());
+/// ```
+pub fn factorial(n: u64) -> Option<BigInt> {
+    if n > MAX_FACTORIAL {
+        return None;
+    }
+
+    Some((2..=n).fold(BigInt::from(1), |acc, k| acc * k))
+}
+
+/// Calculate `base` to the power of `exp` exactly, returning `None` if the
+/// result could have more than `MAX_BITS` bits.
+///
+/// # Arguments
+///
+/// * `base` - An integer.
+/// * `exp` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::big::pow;
+/// use num_bigint::BigInt;
+///
+/// assert_eq!("1267650600228229401496703205376", pow(&BigInt::from(2), 100).unwrap().to_string());
+/// assert_eq!("-27", pow(&BigInt::from(-3), 3).unwrap().to_string());
+/// assert!(pow(&BigInt::from(10), 1_000_000).is_none());
+/// ```
+pub fn pow(base: &BigInt, exp: u64) -> Option<BigInt> {
+    // the result has at most as many bits as the base has times exp
+    if base.bits().saturating_mul(exp) > MAX_BITS {
+        return None;
+    }
+
+    // exp fits in a u32 unless the base is 0 or 1, whose powers are small
+    Some(base.pow(u32::try_from(exp).ok()?))
+}
+
+/// Calculate the nth fibonacci number exactly, where `fibonacci(0)` is 0 and
+/// `fibonacci(1)` is 1, returning `None` if it has more than `MAX_BITS` bits.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::big::fibonacci;
+///
+/// assert_eq!("55", fibonacci(10).unwrap().to_string());
+/// assert_eq!("354224848179261915075", fibonacci(100).unwrap().to_string());
+/// assert!(fibonacci(1_000_000).is_none());
+/// ```
+pub fn fibonacci(n: u64) -> Option<BigInt> {
+    // fibonacci(n) has about n * log2(phi) bits
+    if n as f64 * 0.6942419136306174 > MAX_BITS as f64 {
+        return None;
+    }
+
+    // fast doubling, where (a, b) is (fibonacci(k), fibonacci(k + 1)) for k
+    // made up of the bits of n read so far
+    let (mut a, mut b) = (BigInt::from(0), BigInt::from(1));
+
+    for i in (0..u64::BITS - n.leading_zeros()).rev() {
+        let (double, double_next) = (&a * (&b * 2 - &a), &a * &a + &b * &b);
+
+        (a, b) = if n >> i & 1 == 0 {
+            (double, double_next)
+        } else {
+            let next = &double + &double_next;
+            (double_next, next)
+        };
+    }
+
+    Some(a)
+}
+
+/// Calculate the nth triangular number exactly, i.e. the sum of the integers
+/// from 1 to n.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::big::triangular;
+///
+/// assert_eq!("10", triangular(4).to_string());
+/// assert_eq!(
+///     "170141183460469231722463931679029329920",
+///     triangular(u64::MAX).to_string()
+/// );
+/// ```
+pub fn triangular(n: u64) -> BigInt {
+    BigInt::from(n) * (BigInt::from(n) + 1) / 2
+}
+
+/// Calculate the nth catalan number exactly, returning `None` if it has more
+/// than `MAX_BITS` bits.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::big::catalan;
+///
+/// assert_eq!("42", catalan(5).unwrap().to_string());
+/// assert_eq!("896519947090131496687170070074100632420837521538745909320", catalan(100).unwrap().to_string());
+/// assert!(catalan(1_000_000).is_none());
+/// ```
+pub fn catalan(n: u64) -> Option<BigInt> {
+    // catalan(n) has fewer than 2n bits
+    if n.saturating_mul(2) > MAX_BITS {
+        return None;
+    }
+
+    // C(k + 1) = C(k) * 2(2k + 1) / (k + 2), where the division is exact
+    Some((0..n).fold(BigInt::from(1), |c, k| c * (2 * (2 * k + 1)) / (k + 2)))
+}
@@ -0,0 +1,260 @@
+//! Bit manipulation of integers, which are treated as two's complement
+//! numbers the way they would be by machine code.
+
+/// Reinterpret the integer x as the 64 bits of its two's complement
+/// representation, so that negative numbers have their high bits set.
+///
+/// Integers too large for a `u64` or too small for an `i64` saturate.
+///
+/// # Arguments
+///
+/// * `x` - An integer.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::to_bits;
+///
+/// assert_eq!(to_bits(5.0), 5);
+/// assert_eq!(to_bits(-1.0), u64::MAX);
+/// ```
+pub fn to_bits(x: f64) -> u64 {
+    if x < 0.0 {
+        x as i64 as u64
+    } else {
+        x as u64
+    }
+}
+
+/// Wrap the integer x around to the range of a two's complement integer
+/// with `width` bits, the way machine arithmetic overflows.
+///
+/// # Arguments
+///
+/// * `x` - An integer.
+/// * `width` - The number of bits in the integer, from 1 to 64.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::wrap;
+///
+/// assert_eq!(wrap(127, 8), 127);
+/// assert_eq!(wrap(128, 8), -128);
+/// assert_eq!(wrap(-129, 8), 127);
+/// assert_eq!(wrap(1 << 64, 64), 0);
+/// ```
+pub fn wrap(x: i128, width: u32) -> i64 {
+    let modulus = 1i128 << width;
+    let x = x.rem_euclid(modulus);
+
+    // casting is safe since the result is in the range of a `width` bit
+    // signed integer
+    if x >= modulus / 2 {
+        (x - modulus) as i64
+    } else {
+        x as i64
+    }
+}
+
+/// Get the mask of the lowest `width` bits.
+fn mask(width: u32) -> u64 {
+    u64::MAX >> (64 - width)
+}
+
+/// Count the number of set bits in the two's complement representation of x.
+///
+/// # Arguments
+///
+/// * `x` - An integer.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::popcount;
+///
+/// assert_eq!(popcount(0b1011 as f64), 3);
+/// assert_eq!(popcount(-1.0), 64);
+/// ```
+pub fn popcount(x: f64) -> u32 {
+    to_bits(x).count_ones()
+}
+
+/// Rotate the lowest `width` bits of x left by n places, so that bits shifted
+/// out of the top come back in at the bottom.
+///
+/// # Arguments
+///
+/// * `x` - An integer, of which only the lowest `width` bits are used.
+/// * `n` - The number of places to rotate by, rotating right if negative.
+/// * `width` - The number of bits in the integer, from 1 to 64.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::rotl;
+///
+/// assert_eq!(rotl(0b1001_0000 as f64, 1, 8), 0b0010_0001);
+/// assert_eq!(rotl(1.0, -1, 8), 0b1000_0000);
+/// assert_eq!(rotl(-1.0, 3, 16), 0xffff);
+/// ```
+pub fn rotl(x: f64, n: i64, width: u32) -> u64 {
+    let x = to_bits(x) & mask(width);
+    // casting is safe since the remainder is less than width
+    let n = n.rem_euclid(width as i64) as u32;
+
+    if n == 0 {
+        x
+    } else {
+        ((x << n) | (x >> (width - n))) & mask(width)
+    }
+}
+
+/// Rotate the lowest `width` bits of x right by n places, so that bits
+/// shifted out of the bottom come back in at the top.
+///
+/// # Arguments
+///
+/// * `x` - An integer, of which only the lowest `width` bits are used.
+/// * `n` - The number of places to rotate by, rotating left if negative.
+/// * `width` - The number of bits in the integer, from 1 to 64.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::rotr;
+///
+/// assert_eq!(rotr(0b0000_0011 as f64, 1, 8), 0b1000_0001);
+/// ```
+pub fn rotr(x: f64, n: i64, width: u32) -> u64 {
+    // rotating right by n is rotating left by the rest of the width
+    rotl(x, -(n.rem_euclid(width as i64)), width)
+}
+
+/// Get the bit of x at index i, where bit 0 is the least significant bit.
+///
+/// Bits beyond the 64th are those of the sign extension of x, i.e. they are
+/// set only if x is negative.
+///
+/// # Arguments
+///
+/// * `x` - An integer.
+/// * `i` - The index of the bit.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::bit;
+///
+/// assert!(bit(0b100 as f64, 2));
+/// assert!(!bit(0b100 as f64, 1));
+/// assert!(bit(-1.0, 100));
+/// ```
+pub fn bit(x: f64, i: u64) -> bool {
+    if i >= 64 {
+        x < 0.0
+    } else {
+        to_bits(x) >> i & 1 == 1
+    }
+}
+
+/// Get the exponent of x, such that `x = mantissa(x) * 2^exponent(x)`.
+///
+/// This is the unbiased exponent of the IEEE-754 representation of x, except
+/// that zero and subnormal numbers have an exponent of -1022 rather than
+/// -1023, and infinity and NaN have an exponent of 1024.
+///
+/// # Arguments
+///
+/// * `x` - Any floating point number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::exponent;
+///
+/// assert_eq!(exponent(1.0), 0);
+/// assert_eq!(exponent(-6.0), 2);
+/// assert_eq!(exponent(0.1), -4);
+/// assert_eq!(exponent(f64::MIN_POSITIVE / 2.0), -1022);
+/// ```
+pub fn exponent(x: f64) -> i32 {
+    // casting is safe since the exponent field is 11 bits
+    let biased = ((x.to_bits() >> 52) & 0x7ff) as i32;
+
+    if biased == 0 {
+        -1022
+    } else {
+        biased - 1023
+    }
+}
+
+/// Get the mantissa of x, such that `x = mantissa(x) * 2^exponent(x)`.
+///
+/// The mantissa has the sign of x, and its magnitude is in [1, 2) for normal
+/// numbers or in [0, 1) for zero and subnormal numbers. Infinity and NaN are
+/// their own mantissa.
+///
+/// # Arguments
+///
+/// * `x` - Any floating point number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::mantissa;
+///
+/// assert_eq!(mantissa(1.0), 1.0);
+/// assert_eq!(mantissa(-6.0), -1.5);
+/// assert_eq!(mantissa(0.1), 1.6);
+/// assert_eq!(mantissa(f64::MIN_POSITIVE / 2.0), 0.5);
+/// ```
+pub fn mantissa(x: f64) -> f64 {
+    if !x.is_finite() {
+        return x;
+    }
+
+    // dividing by a power of two only changes the exponent, so is exact
+    x / 2f64.powi(exponent(x))
+}
+
+/// Split the IEEE-754 representation of x into its sign, biased exponent and
+/// mantissa fields, the fraction bits without the implicit leading one.
+///
+/// # Arguments
+///
+/// * `x` - Any floating point number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::fields;
+///
+/// assert_eq!(fields(1.0), [0, 1023, 0]);
+/// assert_eq!(fields(-1.5), [1, 1023, 1 << 51]);
+/// assert_eq!(fields(f64::INFINITY), [0, 2047, 0]);
+/// ```
+pub fn fields(x: f64) -> [u64; 3] {
+    let bits = x.to_bits();
+
+    [bits >> 63, (bits >> 52) & 0x7ff, bits & mask(52)]
+}
+
+/// Build the float whose IEEE-754 representation has the given sign, biased
+/// exponent and mantissa fields, the inverse of `fields`.
+///
+/// Bits beyond the width of each field are ignored.
+///
+/// # Arguments
+///
+/// * `sign` - The sign bit, 1 for negative numbers.
+/// * `exponent` - The 11 bit biased exponent.
+/// * `mantissa` - The 52 bit fraction.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::bits::{fields, from_fields};
+///
+/// assert_eq!(from_fields(0, 1023, 0), 1.0);
+/// assert_eq!(from_fields(1, 1024, 1 << 51), -3.0);
+/// assert_eq!(from_fields(0, 0, 1), 5e-324);
+///
+/// let [sign, exponent, mantissa] = fields(0.1);
+/// assert_eq!(from_fields(sign, exponent, mantissa), 0.1);
+/// ```
+pub fn from_fields(sign: u64, exponent: u64, mantissa: u64) -> f64 {
+    f64::from_bits(
+        (sign & 1) << 63 | (exponent & 0x7ff) << 52 | mantissa & mask(52),
+    )
+}
@@ -0,0 +1,467 @@
+//! Exact values, such as those which sin, cos and tan take at special angles,
+//! which can be displayed exactly rather than as the float closest to them.
+
+use std::{f64::consts::PI, fmt};
+
+use super::gcd;
+use crate::token::FuncKind;
+
+/// The values other than integers and halves which sin, cos and tan take at
+/// multiples of 30 and 45 degrees, as `(numerator, denominator, radicand)`.
+const SPECIAL: [(i64, u64, u64); 8] = [
+    (1, 2, 2),
+    (-1, 2, 2),
+    (1, 2, 3),
+    (-1, 2, 3),
+    (1, 3, 3),
+    (-1, 3, 3),
+    (1, 1, 3),
+    (-1, 1, 3),
+];
+
+/// The most multiples of 15 degrees which an angle is checked for being, since
+/// the rounding error of larger angles is too large to tell them apart.
+const MAX_STEPS: f64 = 1e6;
+
+/// An exact number of the form `num / den * sqrt(radicand)`, such as
+/// `sqrt(2) / 2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Surd {
+    num: i64,
+    den: u64,
+    radicand: u64,
+}
+
+impl Surd {
+    /// Create the surd `num / den * sqrt(radicand)`, in lowest terms.
+    ///
+    /// The radicand is not simplified, so it should have no square factors.
+    ///
+    /// # Arguments
+    ///
+    /// * `num` - The numerator of the coefficient.
+    /// * `den` - The denominator of the coefficient, which is not 0.
+    /// * `radicand` - The number whose square root is taken.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::exact::Surd;
+    ///
+    /// assert_eq!(Surd::new(1, 2, 3), Surd::new(2, 4, 3));
+    /// assert_eq!(Surd::new(0, 1, 1), Surd::new(0, 5, 2));
+    /// assert_eq!("sqrt(3) / 2", Surd::new(2, 4, 3).to_string());
+    /// ```
+    pub fn new(num: i64, den: u64, radicand: u64) -> Self {
+        if num == 0 || radicand == 0 {
+            return Self {
+                num: 0,
+                den: 1,
+                radicand: 1,
+            };
+        }
+
+        let divisor = gcd(num.unsigned_abs(), den);
+        Self {
+            // casting is safe since the divisor divides num
+            num: num / divisor as i64,
+            den: den / divisor,
+            radicand,
+        }
+    }
+
+    /// Create the rational number `num / den`, in lowest terms.
+    ///
+    /// # Arguments
+    ///
+    /// * `num` - The numerator.
+    /// * `den` - The denominator, which is not 0.
+    pub fn rational(num: i64, den: u64) -> Self {
+        Self::new(num, den, 1)
+    }
+
+    /// Get the rational number which the square root of `self` is multiplied
+    /// by.
+    pub fn coefficient(&self) -> Rational {
+        // the denominator is not 0, and is small enough to fit in an i64 for
+        // every surd that is created in practice
+        Rational::new(self.num, self.den as i64).unwrap_or_default()
+    }
+
+    /// Get the number whose square root is taken, which is 1 for rational
+    /// numbers.
+    pub fn radicand(&self) -> u64 {
+        self.radicand
+    }
+
+    /// Get whether `self` is rational, which it is when its radicand is 1.
+    pub fn is_rational(&self) -> bool {
+        self.radicand == 1
+    }
+
+    /// Get the float closest to `self`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::exact::Surd;
+    ///
+    /// assert_eq!(0.5, Surd::rational(1, 2).value());
+    /// assert_eq!(
+    ///     std::f64::consts::FRAC_1_SQRT_2,
+    ///     Surd::new(1, 2, 2).value()
+    /// );
+    /// ```
+    pub fn value(&self) -> f64 {
+        if self.is_rational() {
+            self.num as f64 / self.den as f64
+        } else {
+            self.num as f64 * (self.radicand as f64).sqrt() / self.den as f64
+        }
+    }
+
+    /// Recognize `x` as one of the irrational values which sin, cos and tan
+    /// take at special angles, such as `sqrt(2) / 2`, returning `None` if it
+    /// is not the float closest to one of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The number to recognize.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::exact::Surd;
+    ///
+    /// let half_sqrt_2 = 2f64.sqrt() / 2.0;
+    /// assert_eq!(Some(Surd::new(1, 2, 2)), Surd::recognize(half_sqrt_2));
+    /// assert_eq!(Some(Surd::new(-1, 1, 3)), Surd::recognize(-(3f64.sqrt())));
+    /// assert_eq!(None, Surd::recognize(0.7));
+    /// ```
+    pub fn recognize(x: f64) -> Option<Self> {
+        SPECIAL
+            .iter()
+            .map(|&(num, den, radicand)| Self::new(num, den, radicand))
+            .find(|s| s.value() == x)
+    }
+}
+
+impl fmt::Display for Surd {
+    /// Format `self` as an expression which evaluates to it, such as
+    /// `-sqrt(3) / 3`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.num, self.radicand) {
+            (num, 1) => write!(f, "{}", num)?,
+            (1, radicand) => write!(f, "sqrt({})", radicand)?,
+            (-1, radicand) => write!(f, "-sqrt({})", radicand)?,
+            (num, radicand) => write!(f, "{} * sqrt({})", num, radicand)?,
+        }
+
+        if self.den != 1 {
+            write!(f, " / {}", self.den)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An exact rational number, whose numerator and denominator are small enough
+/// to fit in an `i64`.
+///
+/// Arithmetic on rational numbers is checked, returning `None` when the
+/// result does not fit, so that it can be approximated instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i64,
+    // always positive, and coprime with num
+    den: i64,
+}
+
+impl Rational {
+    /// Create the rational number `num / den`, in lowest terms, returning
+    /// `None` if `den` is 0 or the result does not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `num` - The numerator.
+    /// * `den` - The denominator.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::exact::Rational;
+    ///
+    /// let half = Rational::new(-2, -4).unwrap();
+    /// assert_eq!((1, 2), (half.num(), half.den()));
+    /// assert_eq!("-1 / 3", Rational::new(3, -9).unwrap().to_string());
+    /// assert_eq!(None, Rational::new(1, 0));
+    /// ```
+    pub fn new(num: i64, den: i64) -> Option<Self> {
+        Self::reduce(num.into(), den.into())
+    }
+
+    /// Create the integer `n` as a rational number.
+    pub fn integer(n: i64) -> Self {
+        Self { num: n, den: 1 }
+    }
+
+    /// Reduce `num / den` to lowest terms, returning `None` if `den` is 0 or
+    /// the result does not fit.
+    fn reduce(num: i128, den: i128) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+
+        let divisor = gcd_wide(num.unsigned_abs(), den.unsigned_abs());
+        // casting is safe since the divisor is at most |den|, which fits
+        let divisor = divisor as i128 * den.signum();
+
+        Some(Self {
+            num: i64::try_from(num / divisor).ok()?,
+            den: i64::try_from(den / divisor).ok()?,
+        })
+    }
+
+    /// Get the exact rational number `n` is written as, such as `1 / 10` for
+    /// `0.1`, returning `None` if it is not finite or its digits do not fit.
+    ///
+    /// Numbers are taken to be what they are written as, rather than the
+    /// binary fraction they are rounded to, so `0.1` is `1 / 10`.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number to convert.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::exact::Rational;
+    ///
+    /// assert_eq!(Rational::new(1, 10), Rational::from_f64(0.1));
+    /// assert_eq!(Rational::new(-5, 4), Rational::from_f64(-1.25));
+    /// assert_eq!(Some(Rational::integer(3)), Rational::from_f64(3.0));
+    /// assert_eq!(None, Rational::from_f64(f64::INFINITY));
+    /// assert_eq!(None, Rational::from_f64(1e300));
+    /// ```
+    pub fn from_f64(n: f64) -> Option<Self> {
+        if !n.is_finite() {
+            return None;
+        }
+
+        // floats are displayed as the shortest decimal which rounds to them,
+        // without an exponent
+        let text = n.to_string();
+        let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+        let digits: i64 = format!("{}{}", whole, fraction).parse().ok()?;
+        let den = 10i64.checked_pow(u32::try_from(fraction.len()).ok()?)?;
+
+        Self::new(digits, den)
+    }
+
+    /// Get the numerator of `self`, which has the sign of `self`.
+    pub fn num(&self) -> i64 {
+        self.num
+    }
+
+    /// Get the denominator of `self`, which is always positive.
+    pub fn den(&self) -> i64 {
+        self.den
+    }
+
+    /// Get whether `self` is an integer.
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    /// Get the float closest to `self`.
+    pub fn value(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Add `other` to `self`, returning `None` if the result does not fit.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let (a, b) = (i128::from(self.num), i128::from(other.num));
+        let (c, d) = (i128::from(self.den), i128::from(other.den));
+
+        Self::reduce(a * d + b * c, c * d)
+    }
+
+    /// Multiply `self` by `other`, returning `None` if the result does not
+    /// fit.
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        Self::reduce(
+            i128::from(self.num) * i128::from(other.num),
+            i128::from(self.den) * i128::from(other.den),
+        )
+    }
+
+    /// Get the reciprocal of `self`, returning `None` if `self` is 0.
+    pub fn recip(self) -> Option<Self> {
+        Self::reduce(self.den.into(), self.num.into())
+    }
+
+    /// Negate `self`, returning `None` if the result does not fit.
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Self {
+            num: self.num.checked_neg()?,
+            den: self.den,
+        })
+    }
+}
+
+impl Default for Rational {
+    /// The rational number 0.
+    fn default() -> Self {
+        Self::integer(0)
+    }
+}
+
+impl fmt::Display for Rational {
+    /// Format `self` as an integer, or as a division such as `1 / 3`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.den {
+            1 => write!(f, "{}", self.num),
+            den => write!(f, "{} / {}", self.num, den),
+        }
+    }
+}
+
+/// Calculate the greatest common divisor of a and b, which may be too large
+/// for `math::gcd`.
+fn gcd_wide(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// Get the angle in degrees which `x` radians is a rounded multiple of 30 or
+/// 45 degrees of, returning `None` if it is not one.
+///
+/// # Arguments
+///
+/// * `x` - The angle in radians.
+///
+/// # Examples
+/// ```
+/// use std::f64::consts::PI;
+///
+/// use cocoa::math::exact::special_angle;
+///
+/// assert_eq!(Some(30), special_angle(PI / 6.0));
+/// assert_eq!(Some(-135), special_angle(-3.0 * PI / 4.0));
+/// assert_eq!(Some(720), special_angle(4.0 * PI));
+/// assert_eq!(None, special_angle(PI / 12.0));
+/// assert_eq!(None, special_angle(1.0));
+/// ```
+pub fn special_angle(x: f64) -> Option<i64> {
+    // the multiples of 15 degrees are checked, since pi / 12 is a divisor of
+    // every special angle
+    let steps = (x / (PI / 12.0)).round();
+    // the rounding error of x grows with its magnitude
+    let tolerance = 8.0 * f64::EPSILON * x.abs().max(1.0);
+
+    if steps.is_nan()
+        || steps.abs() > MAX_STEPS
+        || (x - steps * (PI / 12.0)).abs() > tolerance
+    {
+        return None;
+    }
+
+    // casting is safe since steps is an integer no larger than MAX_STEPS
+    let degrees = steps as i64 * 15;
+    (degrees % 30 == 0 || degrees % 45 == 0).then_some(degrees)
+}
+
+/// Get the exact sine of `degrees` degrees, which is a multiple of 30 or 45.
+fn sin_degrees(degrees: i64) -> Surd {
+    let degrees = degrees.rem_euclid(360);
+    if degrees >= 180 {
+        let Surd { num, den, radicand } = sin_degrees(degrees - 180);
+        return Surd::new(-num, den, radicand);
+    }
+
+    match degrees.min(180 - degrees) {
+        0 => Surd::rational(0, 1),
+        30 => Surd::rational(1, 2),
+        45 => Surd::new(1, 2, 2),
+        60 => Surd::new(1, 2, 3),
+        _ => Surd::rational(1, 1),
+    }
+}
+
+/// Get the exact tangent of `degrees` degrees, which is a multiple of 30 or
+/// 45, returning `None` if it is undefined.
+fn tan_degrees(degrees: i64) -> Option<Surd> {
+    match degrees.rem_euclid(180) {
+        0 => Some(Surd::rational(0, 1)),
+        30 => Some(Surd::new(1, 3, 3)),
+        45 => Some(Surd::rational(1, 1)),
+        60 => Some(Surd::new(1, 1, 3)),
+        120 => Some(Surd::new(-1, 1, 3)),
+        135 => Some(Surd::rational(-1, 1)),
+        150 => Some(Surd::new(-1, 3, 3)),
+        _ => None,
+    }
+}
+
+/// Get the exact value of the trigonometric function `f` at `x` radians when
+/// `x` is a special angle, a multiple of `pi / 6` or `pi / 4`, returning
+/// `None` if it is not, or if `f` is not `sin`, `cos` or `tan`.
+///
+/// The tangent of odd multiples of `pi / 2` is undefined, so it is `None`.
+///
+/// # Arguments
+///
+/// * `f` - The trigonometric function.
+/// * `x` - The angle in radians.
+///
+/// # Examples
+/// ```
+/// use std::f64::consts::PI;
+///
+/// use cocoa::{
+///     math::exact::{trig, Surd},
+///     token::FuncKind,
+/// };
+///
+/// assert_eq!(0.49999999999999994, (PI / 6.0).sin());
+/// assert_eq!(Some(Surd::rational(1, 2)), trig(FuncKind::Sin, PI / 6.0));
+/// assert_eq!(Some(Surd::new(1, 2, 2)), trig(FuncKind::Cos, PI / 4.0));
+/// assert_eq!(Some(Surd::rational(0, 1)), trig(FuncKind::Sin, PI));
+/// assert_eq!(Some(Surd::new(-1, 1, 3)), trig(FuncKind::Tan, 2.0 * PI / 3.0));
+/// assert_eq!(None, trig(FuncKind::Tan, PI / 2.0));
+/// assert_eq!(None, trig(FuncKind::Sin, 1.0));
+/// ```
+pub fn trig(f: FuncKind, x: f64) -> Option<Surd> {
+    trig_degrees(f, special_angle(x)?)
+}
+
+/// Get the exact value of the trigonometric function `f` at `degrees`
+/// degrees, returning `None` if it is not a multiple of 30 or 45, or if `f`
+/// is not `sin`, `cos` or `tan`.
+///
+/// # Arguments
+///
+/// * `f` - The trigonometric function.
+/// * `degrees` - The angle in degrees.
+///
+/// # Examples
+/// ```
+/// use cocoa::{
+///     math::exact::{trig_degrees, Surd},
+///     token::FuncKind,
+/// };
+///
+/// assert_eq!(Some(Surd::new(1, 2, 3)), trig_degrees(FuncKind::Cos, -30));
+/// assert_eq!(Some(Surd::rational(-1, 1)), trig_degrees(FuncKind::Sin, 270));
+/// assert_eq!(None, trig_degrees(FuncKind::Sin, 20));
+/// ```
+pub fn trig_degrees(f: FuncKind, degrees: i64) -> Option<Surd> {
+    if degrees % 30 != 0 && degrees % 45 != 0 {
+        return None;
+    }
+
+    match f {
+        FuncKind::Sin => Some(sin_degrees(degrees)),
+        FuncKind::Cos => Some(sin_degrees(degrees + 90)),
+        FuncKind::Tan => tan_degrees(degrees),
+        _ => None,
+    }
+}
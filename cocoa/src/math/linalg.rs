@@ -0,0 +1,90 @@
+//! Linear algebra on matrices stored as flat lists of numbers, row by row.
+
+use anyhow::{bail, Result};
+
+use crate::message::{Code, Message};
+
+/// Solve the system of linear equations `ax = b` for x, where `a` is a
+/// square matrix given row by row, so that `a[i * n + j]` is the entry in
+/// row i and column j of the n by n matrix.
+///
+/// The system is solved by Gaussian elimination with partial pivoting,
+/// which picks the largest entry of each column as the pivot to keep the
+/// rounding errors small. This fails if the matrix is singular, since then
+/// the equations have either no solution or infinitely many.
+///
+/// # Arguments
+///
+/// * `a` - The coefficients of the equations, row by row, which must be n^2
+///   numbers.
+/// * `b` - The right hand sides of the n equations.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::linalg::linsolve;
+///
+/// // x + y = 3 and x - y = 1
+/// assert_eq!(vec![2.0, 1.0], linsolve(&[1.0, 1.0, 1.0, -1.0], &[3.0, 1.0]).unwrap());
+///
+/// // the first pivot is 0, which partial pivoting swaps away
+/// let x = linsolve(&[0.0, 2.0, 1.0, 1.0, 1.0, 1.0, 2.0, 1.0, 0.0], &[3.0, 3.0, 3.0]).unwrap();
+/// assert_eq!(vec![1.0, 1.0, 1.0], x);
+///
+/// assert_eq!(
+///     "the matrix is singular, so the equations do not have a unique solution",
+///     linsolve(&[1.0, 2.0, 2.0, 4.0], &[1.0, 2.0]).unwrap_err().to_string()
+/// );
+/// assert!(linsolve(&[1.0, 2.0, 3.0], &[1.0, 2.0]).is_err());
+/// ```
+pub fn linsolve(a: &[f64], b: &[f64]) -> Result<Vec<f64>> {
+    let n = b.len();
+    if n == 0 {
+        bail!(Message::new(Code::NoEquations))
+    }
+    if a.len() != n * n {
+        bail!(Code::MatrixSize.with(&[&(n * n), &n, &a.len()]))
+    }
+    if a.iter().chain(b).any(|x| !x.is_finite()) {
+        bail!(Message::new(Code::NotFiniteEquations))
+    }
+
+    // the matrix augmented with b, so that b is transformed along with it
+    let mut rows: Vec<Vec<f64>> = a
+        .chunks(n)
+        .zip(b)
+        .map(|(row, &b)| row.iter().copied().chain([b]).collect())
+        .collect();
+
+    // pivots smaller than this are taken to be zero, since they are within
+    // the rounding error of eliminating the entries of the matrix
+    let largest = a.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+    let tolerance = n as f64 * f64::EPSILON * largest;
+
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| rows[i][k].abs().total_cmp(&rows[j][k].abs()))
+            .unwrap_or(k);
+        if rows[pivot][k].abs() <= tolerance {
+            bail!(Message::new(Code::SingularMatrix))
+        }
+        rows.swap(k, pivot);
+
+        let (above, below) = rows.split_at_mut(k + 1);
+        let pivot = &above[k];
+        for row in below {
+            let factor = row[k] / pivot[k];
+            for (x, p) in row[k..].iter_mut().zip(&pivot[k..]) {
+                *x -= factor * p;
+            }
+        }
+    }
+
+    // solve the triangular system by back substitution
+    let mut x = vec![0.0; n];
+    for k in (0..n).rev() {
+        let rest: f64 = (k + 1..n).map(|j| rows[k][j] * x[j]).sum();
+        x[k] = (rows[k][n] - rest) / rows[k][k];
+    }
+
+    Ok(x)
+}
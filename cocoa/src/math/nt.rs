@@ -0,0 +1,228 @@
+//! Number theoretic functions, such as counting and finding primes.
+
+use anyhow::{bail, Result};
+
+use crate::message::{Code, Message};
+
+/// The largest number the prime functions sieve up to, which keeps the memory
+/// a sieve uses to around 10MB.
+pub const SIEVE_LIMIT: u64 = 10_000_000;
+
+/// Find which numbers up to and including `n` are prime using the sieve of
+/// eratosthenes.
+fn sieve(n: usize) -> Vec<bool> {
+    let mut is_prime = vec![true; n + 1];
+    is_prime[0] = false;
+    if n >= 1 {
+        is_prime[1] = false;
+    }
+
+    let mut i = 2;
+    while i * i <= n {
+        if is_prime[i] {
+            for multiple in (i * i..=n).step_by(i) {
+                is_prime[multiple] = false;
+            }
+        }
+        i += 1;
+    }
+
+    is_prime
+}
+
+/// Count the number of primes less than or equal to n.
+///
+/// # Arguments
+///
+/// * `n` - An integer <= `SIEVE_LIMIT`.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nt::primepi;
+///
+/// assert_eq!(primepi(1).unwrap(), 0);
+/// assert_eq!(primepi(10).unwrap(), 4);
+/// assert_eq!(primepi(1_000_000).unwrap(), 78498);
+/// assert!(primepi(100_000_000).is_err());
+/// ```
+pub fn primepi(n: u64) -> Result<u64> {
+    if n > SIEVE_LIMIT {
+        bail!(Code::PrimepiTooLarge.with(&[&SIEVE_LIMIT, &n]))
+    }
+
+    // casting is safe since n is at most SIEVE_LIMIT
+    Ok(sieve(n as usize).into_iter().filter(|&p| p).count() as u64)
+}
+
+/// Find the kth prime, where the 1st prime is 2.
+///
+/// # Arguments
+///
+/// * `k` - An integer >= 1 such that the kth prime is <= `SIEVE_LIMIT`.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nt::prime;
+///
+/// assert_eq!(prime(1).unwrap(), 2);
+/// assert_eq!(prime(5).unwrap(), 11);
+/// assert_eq!(prime(78498).unwrap(), 999983);
+/// assert!(prime(0).is_err());
+/// assert!(prime(1_000_000).is_err());
+/// ```
+pub fn prime(k: u64) -> Result<u64> {
+    if k == 0 {
+        bail!(Message::new(Code::ZerothPrime))
+    }
+
+    // the kth prime is less than k(ln k + ln ln k) for k >= 6, which avoids
+    // sieving far beyond it for small k
+    let bound = if k < 6 {
+        13
+    } else {
+        let k = k as f64;
+        (k * (k.ln() + k.ln().ln())) as u64
+    };
+
+    // casting is safe since the bound is at most SIEVE_LIMIT
+    let n = bound.min(SIEVE_LIMIT) as usize;
+    let kth = sieve(n)
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, p)| p)
+        .nth((k - 1) as usize);
+
+    match kth {
+        Some((p, _)) => Ok(p as u64),
+        None => bail!(Code::PrimeTooLarge.with(&[&SIEVE_LIMIT, &k])),
+    }
+}
+
+/// Factorize n into its prime factors along with their multiplicities, in
+/// ascending order, using trial division.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 1.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nt::factorize;
+///
+/// assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+/// assert_eq!(factorize(97), vec![(97, 1)]);
+/// assert_eq!(factorize(1), vec![]);
+/// ```
+pub fn factorize(mut n: u64) -> Vec<(u64, u32)> {
+    let mut factors = vec![];
+
+    let mut divide_out = |n: &mut u64, p: u64| {
+        let mut e = 0;
+        while n.is_multiple_of(p) {
+            *n /= p;
+            e += 1;
+        }
+        if e > 0 {
+            factors.push((p, e));
+        }
+    };
+
+    divide_out(&mut n, 2);
+    divide_out(&mut n, 3);
+
+    // every prime above 3 is of the form 6k - 1 or 6k + 1
+    let mut p = 5;
+    while p * p <= n {
+        divide_out(&mut n, p);
+        divide_out(&mut n, p + 2);
+        p += 6;
+    }
+
+    // whatever is left has no factors below its square root
+    if n > 1 {
+        factors.push((n, 1));
+    }
+
+    factors
+}
+
+/// Count the integers from 1 to n that are coprime to n, i.e. euler's totient
+/// function of n.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 1.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nt::totient;
+///
+/// assert_eq!(totient(1), 1);
+/// assert_eq!(totient(9), 6);
+/// assert_eq!(totient(36), 12);
+/// ```
+pub fn totient(n: u64) -> u64 {
+    factorize(n)
+        .into_iter()
+        .fold(n, |acc, (p, _)| acc / p * (p - 1))
+}
+
+/// Find all the positive divisors of n in ascending order.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 1.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nt::divisors;
+///
+/// assert_eq!(divisors(12), vec![1, 2, 3, 4, 6, 12]);
+/// assert_eq!(divisors(1), vec![1]);
+/// ```
+pub fn divisors(n: u64) -> Vec<u64> {
+    let mut divisors = vec![1];
+
+    for (p, e) in factorize(n) {
+        let mut multiplied = vec![];
+        let mut power = 1;
+
+        for _ in 0..e {
+            power *= p;
+            multiplied.extend(divisors.iter().map(|d| d * power));
+        }
+
+        divisors.extend(multiplied);
+    }
+
+    divisors.sort_unstable();
+    divisors
+}
+
+/// Sum the kth powers of the positive divisors of n, so that `sigma(n, 0)` is
+/// the number of divisors of n and `sigma(n, 1)` is their sum.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 1.
+/// * `k` - The power the divisors are raised to.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::nt::sigma;
+///
+/// assert_eq!(sigma(12, 0), 6.0);
+/// assert_eq!(sigma(12, 1), 28.0);
+/// assert_eq!(sigma(12, 2), 210.0);
+/// ```
+pub fn sigma(n: u64, k: u32) -> f64 {
+    // sigma is multiplicative, so it is the product of sigma over the prime
+    // powers of n, which is 1 + p^k + p^2k + ... + p^ek
+    factorize(n)
+        .into_iter()
+        .map(|(p, e)| {
+            let pk = (p as f64).powi(k as i32);
+            (0..=e).map(|i| pk.powi(i as i32)).sum::<f64>()
+        })
+        .product()
+}
@@ -0,0 +1,493 @@
+//! Functions for finding the roots of polynomials.
+
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+use crate::message::{Code, Message};
+
+/// Find the distinct real roots of `ax^2 + bx + c` in ascending order.
+///
+/// The roots are found without the cancellation the textbook formula
+/// suffers from when `b^2` is much larger than `4ac`. If `a` is 0 the
+/// polynomial is linear, and it fails if every coefficient is 0, since
+/// then every number is a root.
+///
+/// # Arguments
+///
+/// * `a` - The coefficient of x^2.
+/// * `b` - The coefficient of x.
+/// * `c` - The constant term.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::poly::quadratic_roots;
+///
+/// assert_eq!(vec![2.0, 3.0], quadratic_roots(1.0, -5.0, 6.0).unwrap());
+/// assert_eq!(vec![-1.0], quadratic_roots(1.0, 2.0, 1.0).unwrap());
+/// assert!(quadratic_roots(1.0, 0.0, 1.0).unwrap().is_empty());
+/// assert_eq!(vec![-2.0], quadratic_roots(0.0, 2.0, 4.0).unwrap());
+///
+/// // the smaller root is not lost to cancellation
+/// assert_eq!(1e-8, quadratic_roots(1.0, -1e8, 1.0).unwrap()[0]);
+/// assert!(quadratic_roots(0.0, 0.0, 0.0).is_err());
+/// ```
+pub fn quadratic_roots(a: f64, b: f64, c: f64) -> Result<Vec<f64>> {
+    if a == 0.0 {
+        return match (b, c) {
+            (0.0, 0.0) => bail!(Message::new(Code::ZeroPolynomial)),
+            (0.0, _) => Ok(vec![]),
+            _ => Ok(vec![-c / b]),
+        };
+    }
+
+    let disc = b * b - 4.0 * a * c;
+    let mut roots = if disc < 0.0 {
+        vec![]
+    } else if disc == 0.0 {
+        vec![-b / (2.0 * a)]
+    } else {
+        // b and the square root are added with the same sign, so that they
+        // never cancel, and the other root follows from their product c/a
+        let q = -0.5 * (b + disc.sqrt().copysign(b));
+        vec![q / a, c / q]
+    };
+    roots.sort_by(f64::total_cmp);
+
+    Ok(roots)
+}
+
+/// Find the distinct real roots of `ax^3 + bx^2 + cx + d` in ascending
+/// order.
+///
+/// The roots are found with Cardano's formula, or with trigonometry when
+/// there are three of them, then refined with Newton's method. If `a` is 0
+/// the polynomial is a quadratic, which is solved with `quadratic_roots`.
+///
+/// # Arguments
+///
+/// * `a` - The coefficient of x^3.
+/// * `b` - The coefficient of x^2.
+/// * `c` - The coefficient of x.
+/// * `d` - The constant term.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::poly::cubic_roots;
+///
+/// assert_eq!(vec![1.0, 2.0, 3.0], cubic_roots(1.0, -6.0, 11.0, -6.0).unwrap());
+/// assert_eq!(vec![-2.0], cubic_roots(1.0, 0.0, 0.0, 8.0).unwrap());
+/// // (x - 1)^2 (x + 2) has a double root at 1
+/// assert_eq!(vec![-2.0, 1.0], cubic_roots(1.0, 0.0, -3.0, 2.0).unwrap());
+/// assert_eq!(vec![0.0], cubic_roots(2.0, 0.0, 0.0, 0.0).unwrap());
+/// assert_eq!(vec![2.0, 3.0], cubic_roots(0.0, 1.0, -5.0, 6.0).unwrap());
+/// ```
+pub fn cubic_roots(a: f64, b: f64, c: f64, d: f64) -> Result<Vec<f64>> {
+    if a == 0.0 {
+        return quadratic_roots(b, c, d);
+    }
+
+    // x = t - b/3 turns the cubic into t^3 + pt + q
+    let (b, c, d) = (b / a, c / a, d / a);
+    let shift = b / 3.0;
+    let p = c - b * shift;
+    let q = 2.0 * shift.powi(3) - c * shift + d;
+
+    // the sign of the discriminant tells how many real roots there are, but
+    // p and q lose digits to cancellation, so a repeated root leaves it off
+    // zero by up to about as much as that error moves it
+    let (half, third) = (q / 2.0, p / 3.0);
+    let disc = half * half + third.powi(3);
+    let p_error = f64::EPSILON * (c.abs() + (b * shift).abs());
+    let q_error = f64::EPSILON
+        * (2.0 * shift.powi(3).abs() + (c * shift).abs() + d.abs());
+    let tolerance = 8.0 * (half.abs() * q_error + third * third * p_error);
+
+    // each root of the depressed cubic along with how many times it is
+    // repeated
+    let ts = if p == 0.0 && q == 0.0 {
+        vec![(0.0, 3)]
+    } else if disc.abs() <= tolerance {
+        vec![(3.0 * q / p, 1), (-1.5 * q / p, 2)]
+    } else if disc > 0.0 {
+        let root = disc.sqrt();
+        vec![((-half + root).cbrt() + (-half - root).cbrt(), 1)]
+    } else {
+        let r = 2.0 * (-third).sqrt();
+        let phi = (half / third * (-1.0 / third).sqrt()).clamp(-1.0, 1.0);
+        let phi = phi.acos() / 3.0;
+
+        (0..3)
+            .map(|k| (r * (phi - 2.0 * PI * k as f64 / 3.0).cos(), 1))
+            .collect()
+    };
+
+    let mut roots: Vec<f64> = ts
+        .into_iter()
+        .map(|(t, m)| polish(&[1.0, b, c, d], t - shift, m))
+        .collect();
+    roots.sort_by(f64::total_cmp);
+    roots.dedup();
+
+    Ok(roots)
+}
+
+/// Refine `x`, which is close to a root repeated `m` times of the
+/// polynomial with the coefficients `a`, from that of the highest power
+/// down, with a few steps of Newton's method.
+fn polish(a: &[f64], mut x: f64, m: usize) -> f64 {
+    // the polynomial is flat around a repeated root, so that rounding
+    // errors swamp it, but the root is a simple root of its (m - 1)th
+    // derivative, which Newton's method finds as precisely as any other
+    let mut g = a.to_vec();
+    for _ in 1..m {
+        g = derivative(&g);
+    }
+    let dg = derivative(&g);
+    let (g, dg) = (|x| horner(&g, x), |x| horner(&dg, x));
+
+    // steps are only kept while they help, and only small ones so that a
+    // root is never swapped for another
+    for _ in 0..8 {
+        let next = x - g(x) / dg(x);
+        if !next.is_finite()
+            || (next - x).abs() > 1e-3 * x.abs().max(1.0)
+            || g(next).abs() >= g(x).abs()
+        {
+            break;
+        }
+        x = next;
+    }
+
+    // rounding may leave a root such as 1 just off it, so a nearby value
+    // with fewer digits is taken if it is at least as close
+    let snapped = snap(x);
+    if g(snapped).abs() <= g(x).abs() {
+        snapped
+    } else {
+        x
+    }
+}
+
+/// Get the coefficients of the derivative of the polynomial with the
+/// coefficients `a`, from that of the highest power down.
+fn derivative(a: &[f64]) -> Vec<f64> {
+    let n = a.len().saturating_sub(1);
+
+    a[..n]
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (n - i) as f64 * a)
+        .collect()
+}
+
+/// Evaluate the polynomial with the coefficients `a`, from that of the
+/// highest power down, at `x`.
+fn horner(a: &[f64], x: f64) -> f64 {
+    a.iter().fold(0.0, |y, a| y * x + a)
+}
+
+/// Round `x` to 12 significant digits.
+fn snap(x: f64) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        return x;
+    }
+
+    let scale = 10f64.powi(11 - x.abs().log10().floor() as i32);
+    let snapped = (x * scale).round() / scale;
+
+    if snapped.is_finite() {
+        snapped
+    } else {
+        x
+    }
+}
+
+/// A complex number, which the roots of a real polynomial may be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    fn div(self, other: Self) -> Self {
+        let norm = other.re * other.re + other.im * other.im;
+
+        Self::new(
+            (self.re * other.re + self.im * other.im) / norm,
+            (self.im * other.re - self.re * other.im) / norm,
+        )
+    }
+
+    fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+}
+
+/// Find the distinct real roots of the polynomial with the coefficients
+/// `coefficients` in descending order, where the first is the coefficient of
+/// the highest power of x and the last is the constant term.
+///
+/// Every root, real or complex, is found at once with the Aberth method,
+/// then those which are real are refined with Newton's method. Leading zeros
+/// are ignored, and polynomials of degree 3 or less are solved with
+/// `cubic_roots`. This fails if every coefficient is 0, since then every
+/// number is a root, or if the roots cannot be found to full precision.
+///
+/// # Arguments
+///
+/// * `coefficients` - The coefficients, from that of the highest power down.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::poly::poly_roots;
+///
+/// // (x - 1)(x - 2)(x - 3)(x - 4)
+/// assert_eq!(vec![1.0, 2.0, 3.0, 4.0], poly_roots(&[1.0, -10.0, 35.0, -50.0, 24.0]).unwrap());
+/// // x^4 - 1 has the complex roots i and -i as well
+/// assert_eq!(vec![-1.0, 1.0], poly_roots(&[1.0, 0.0, 0.0, 0.0, -1.0]).unwrap());
+/// // x^5 - x^3 = x^3 (x - 1)(x + 1)
+/// assert_eq!(vec![-1.0, 0.0, 1.0], poly_roots(&[0.0, 1.0, 0.0, -1.0, 0.0, 0.0]).unwrap());
+///
+/// // x^199 + 2x^198 + ... + 200, whose values are far too large to hold
+/// // at most of the points its roots are looked for at
+/// let coefficients: Vec<f64> = (1..=200).map(f64::from).collect();
+/// let roots = poly_roots(&coefficients).unwrap();
+/// assert_eq!(1, roots.len());
+/// assert!((roots[0] + 1.0303471791155128).abs() < 1e-12);
+///
+/// assert_eq!(vec![-0.5], poly_roots(&[2.0, 1.0]).unwrap());
+/// assert!(poly_roots(&[0.0, 0.0]).is_err());
+/// ```
+pub fn poly_roots(coefficients: &[f64]) -> Result<Vec<f64>> {
+    poly_roots_with(coefficients, &mut || Ok(()))
+}
+
+/// Find the distinct real roots of a polynomial like `poly_roots`, calling
+/// `step` before each round of refining every root, so that the caller can
+/// stop the search early by failing.
+///
+/// # Arguments
+///
+/// * `coefficients` - The coefficients, from that of the highest power down.
+/// * `step` - Called before each round, whose error is returned if it fails.
+///
+/// # Examples
+/// ```
+/// use anyhow::bail;
+/// use cocoa::math::poly::poly_roots_with;
+///
+/// let coefficients: Vec<f64> = (1..=50).map(f64::from).collect();
+/// let mut rounds = 0;
+/// poly_roots_with(&coefficients, &mut || Ok(rounds += 1)).unwrap();
+/// assert!(rounds > 1);
+///
+/// let mut stop = || bail!("stopped");
+/// assert!(poly_roots_with(&coefficients, &mut stop).is_err());
+/// ```
+pub fn poly_roots_with(
+    coefficients: &[f64],
+    step: &mut dyn FnMut() -> Result<()>,
+) -> Result<Vec<f64>> {
+    let start = coefficients.iter().position(|&a| a != 0.0);
+    let coefficients = match start {
+        Some(start) => &coefficients[start..],
+        None => bail!(Message::new(Code::ZeroPolynomial)),
+    };
+
+    match *coefficients {
+        [_] => return Ok(vec![]),
+        [b, c] => return quadratic_roots(0.0, b, c),
+        [a, b, c] => return quadratic_roots(a, b, c),
+        [a, b, c, d] => return cubic_roots(a, b, c, d),
+        _ => (),
+    }
+
+    // x divides the polynomial once for each trailing zero, so 0 is a root
+    // and the rest are those of the polynomial left after dividing
+    let zeros = coefficients.iter().rev().take_while(|&&a| a == 0.0).count();
+    if zeros > 0 {
+        let mut roots =
+            poly_roots_with(&coefficients[..coefficients.len() - zeros], step)?;
+        roots.push(0.0);
+        roots.sort_by(f64::total_cmp);
+        roots.dedup();
+
+        return Ok(roots);
+    }
+
+    let a: Vec<f64> =
+        coefficients.iter().map(|a| a / coefficients[0]).collect();
+
+    // the copies of a root repeated m times are found around it rather
+    // than on it, by as much as the mth root of the rounding error, so
+    // roots close together are taken as one repeated root
+    let mut clusters: Vec<(Complex, usize)> = vec![];
+    for z in aberth(&a, step)? {
+        let cluster = clusters.iter_mut().find(|(sum, m)| {
+            let mean = Complex::new(sum.re / *m as f64, sum.im / *m as f64);
+            mean.sub(z).abs() <= 1e-3 * z.abs().max(1.0)
+        });
+
+        match cluster {
+            Some((sum, m)) => {
+                *sum = sum.add(z);
+                *m += 1;
+            }
+            None => clusters.push((z, 1)),
+        }
+    }
+
+    let mut roots = vec![];
+    for (sum, m) in clusters {
+        let z = Complex::new(sum.re / m as f64, sum.im / m as f64);
+        if z.im.abs() > 1e-3 * z.abs().max(1.0) {
+            continue;
+        }
+
+        // a root is only real if the polynomial is zero there
+        let x = polish(&a, z.re, m);
+        if is_root(&a, x) {
+            roots.push(x);
+        }
+    }
+    roots.sort_by(f64::total_cmp);
+    roots.dedup();
+
+    Ok(roots)
+}
+
+/// Check whether the polynomial with the coefficients `a`, from that of the
+/// highest power down, is zero at `x` up to the rounding error in evaluating
+/// it.
+///
+/// Beyond 1, the polynomial with its coefficients reversed is evaluated at
+/// `1 / x` instead, which is zero exactly where this one is, so that neither
+/// the value nor its rounding error overflow however large `x` is.
+fn is_root(a: &[f64], x: f64) -> bool {
+    let reversed: Vec<f64>;
+    let (a, x) = if x.abs() > 1.0 {
+        reversed = a.iter().rev().copied().collect();
+        (&reversed[..], 1.0 / x)
+    } else {
+        (a, x)
+    };
+
+    let size = a.iter().fold(0.0, |y, a| y * x.abs() + a.abs());
+    horner(a, x).abs() <= 64.0 * f64::EPSILON * size
+}
+
+/// The most steps `aberth` takes before giving up on the roots converging.
+const MAX_STEPS: usize = 500;
+
+/// Find every root of the monic polynomial with the coefficients `a`, from
+/// that of the highest power down, with the Aberth method, failing if they do
+/// not converge or `step`, which is called before each step, fails.
+fn aberth(
+    a: &[f64],
+    step: &mut dyn FnMut() -> Result<()>,
+) -> Result<Vec<Complex>> {
+    let n = a.len() - 1;
+    let one = Complex::new(1.0, 0.0);
+
+    // the product of the sizes of the roots is that of the constant term, so
+    // the guesses start on a circle of their geometric mean, and at angles
+    // that do not line up with the real axis, which keeps them from being
+    // symmetric, which they would never break out of
+    let radius = a[n].abs().powf(1.0 / n as f64);
+    let mut z: Vec<Complex> = (0..n)
+        .map(|k| {
+            let angle = 2.0 * PI * k as f64 / n as f64 + 0.4;
+            Complex::new(radius * angle.cos(), radius * angle.sin())
+        })
+        .collect();
+
+    // the value of the polynomial with the coefficients `a` at x, that of
+    // its derivative, and whether the value is within the rounding error of
+    // evaluating it, so that x is as close to a root as it can be found
+    let eval = |a: &[f64], x: Complex| {
+        let (mut y, mut dy) = (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0));
+        let mut size = 0.0;
+        for &a in a {
+            dy = dy.mul(x).add(y);
+            y = y.mul(x).add(Complex::new(a, 0.0));
+            size = size * x.abs() + a.abs();
+        }
+
+        (y, dy, y.abs() <= 8.0 * f64::EPSILON * size)
+    };
+
+    // the Newton step y / y' at x, or `None` if x is a root, where beyond 1
+    // the polynomial with its coefficients reversed, q(w) = w^n p(1 / w), is
+    // evaluated at w = 1 / x instead so that neither overflows, since
+    // p'(x) / p(x) = n w - w^2 q'(w) / q(w)
+    let reversed: Vec<f64> = a.iter().rev().copied().collect();
+    let newton = |x: Complex| {
+        if x.abs() <= 1.0 {
+            let (y, dy, root) = eval(a, x);
+            return (!root).then(|| y.div(dy));
+        }
+
+        let w = one.div(x);
+        let (y, dy, root) = eval(&reversed, w);
+        if root {
+            return None;
+        }
+
+        let n = Complex::new(n as f64, 0.0);
+        Some(one.div(n.mul(w).sub(w.mul(w).mul(dy.div(y)))))
+    };
+
+    for _ in 0..MAX_STEPS {
+        step()?;
+        let mut converged = true;
+
+        for k in 0..n {
+            let ratio = match newton(z[k]) {
+                Some(ratio) => ratio,
+                None => continue,
+            };
+            let repulsion = (0..n)
+                .filter(|&j| j != k)
+                .map(|j| Complex::new(1.0, 0.0).div(z[k].sub(z[j])))
+                .fold(Complex::new(0.0, 0.0), Complex::add);
+            let step =
+                ratio.div(Complex::new(1.0, 0.0).sub(ratio.mul(repulsion)));
+
+            if !step.re.is_finite() || !step.im.is_finite() {
+                converged = false;
+                continue;
+            }
+            if step.abs() > 4.0 * f64::EPSILON * z[k].abs().max(1.0) {
+                converged = false;
+            }
+            z[k] = z[k].sub(step);
+        }
+
+        if converged {
+            return Ok(z);
+        }
+    }
+
+    bail!(Code::RootsDidNotConverge.with(&[&MAX_STEPS]))
+}
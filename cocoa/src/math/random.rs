@@ -0,0 +1,68 @@
+//! Pseudo-random numbers, which are reproducible from the seed they start
+//! from.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// A small xorshift pseudo-random number generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a generator which starts from `seed`, so that generators
+    /// created with the same seed generate the same numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Any number.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::random::Rng;
+    ///
+    /// let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+    ///
+    /// assert_eq!(a.next_f64(), b.next_f64());
+    /// assert_ne!(Rng::new(1).next_f64(), Rng::new(2).next_f64());
+    /// ```
+    pub fn new(seed: u64) -> Self {
+        // scramble the seed with splitmix64, so that similar seeds such as 1
+        // and 2 do not start out generating similar numbers
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        // xorshift never leaves 0, so it is replaced with an arbitrary seed
+        Self(if z == 0 { 0x2545_f491_4f6c_dd1d } else { z })
+    }
+
+    /// Create a generator with a seed that is different every time one is
+    /// created.
+    pub fn from_entropy() -> Self {
+        // the hasher is keyed randomly for each process, and differently for
+        // each `RandomState` within one, without needing a clock
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+
+    /// Get a uniformly distributed number in [0, 1).
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::math::random::Rng;
+    ///
+    /// let mut rng = Rng::new(0);
+    ///
+    /// assert!((0..1000).map(|_| rng.next_f64()).all(|x| (0.0..1.0).contains(&x)));
+    /// ```
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        // the top 53 bits fill the mantissa of an f64
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,735 @@
+//! Special functions, such as the error function, and the functions of the
+//! probability distributions that are built on them.
+//!
+//! The Bessel function of the first kind, the Riemann zeta function and the
+//! beta function are only available with the `special-functions` feature.
+
+use std::f64::consts::{FRAC_2_SQRT_PI, PI, SQRT_2};
+
+use super::factorial;
+
+/// Chebyshev coefficients used by `erfc_cheb`, from Numerical Recipes (3rd
+/// edition, section 6.2.2).
+const ERFC_COEFFICIENTS: [f64; 28] = [
+    -1.3026537197817094,
+    6.419697923564902e-1,
+    1.9476473204185836e-2,
+    -9.56151478680863e-3,
+    -9.46595344482036e-4,
+    3.66839497852761e-4,
+    4.2523324806907e-5,
+    -2.0278578112534e-5,
+    -1.624290004647e-6,
+    1.303655835580e-6,
+    1.5626441722e-8,
+    -8.5238095915e-8,
+    6.529054439e-9,
+    5.059343495e-9,
+    -9.91364156e-10,
+    -2.27365122e-10,
+    9.6467911e-11,
+    2.394038e-12,
+    -6.886027e-12,
+    8.94487e-13,
+    3.13092e-13,
+    -1.12708e-13,
+    3.81e-16,
+    7.106e-15,
+    -1.523e-15,
+    -9.4e-17,
+    1.21e-16,
+    -2.8e-17,
+];
+
+/// Calculate the complementary error function of z, where z is >= 0, using a
+/// Chebyshev approximation accurate to about 1.2e-16.
+fn erfc_cheb(z: f64) -> f64 {
+    let t = 2.0 / (2.0 + z);
+    let ty = 4.0 * t - 2.0;
+
+    let (mut d, mut dd) = (0.0, 0.0);
+    for &c in ERFC_COEFFICIENTS[1..].iter().rev() {
+        (d, dd) = (ty * d - dd + c, d);
+    }
+
+    t * (-z * z + 0.5 * (ERFC_COEFFICIENTS[0] + ty * d) - dd).exp()
+}
+
+/// Calculate the error function of x.
+///
+/// # Arguments
+///
+/// * `x` - Any real number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::erf;
+///
+/// assert!((erf(1.0) - 0.8427007929497149).abs() < 1e-15);
+/// assert!((erf(-0.5) + 0.5204998778130465).abs() < 1e-15);
+/// assert_eq!(erf(0.0), 0.0);
+/// ```
+pub fn erf(x: f64) -> f64 {
+    // 1 - erfc(x) loses most of its precision for small x, where the taylor
+    // series converges quickly instead
+    if x.abs() < 0.5 {
+        let mut term = x;
+        let mut sum = x;
+
+        for n in 1..30 {
+            let n = n as f64;
+            term *= -x * x / n;
+            sum += term / (2.0 * n + 1.0);
+        }
+
+        return FRAC_2_SQRT_PI * sum;
+    }
+
+    if x >= 0.0 {
+        1.0 - erfc_cheb(x)
+    } else {
+        erfc_cheb(-x) - 1.0
+    }
+}
+
+/// Calculate the complementary error function of x, i.e. `1 - erf(x)`, without
+/// losing precision when `erf(x)` is close to 1.
+///
+/// # Arguments
+///
+/// * `x` - Any real number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::erfc;
+///
+/// assert!((erfc(3.0) - 2.209049699858544e-5).abs() < 1e-19);
+/// assert!((erfc(-1.0) - 1.8427007929497148).abs() < 1e-15);
+/// ```
+pub fn erfc(x: f64) -> f64 {
+    if x.abs() < 0.5 {
+        1.0 - erf(x)
+    } else if x >= 0.0 {
+        erfc_cheb(x)
+    } else {
+        2.0 - erfc_cheb(-x)
+    }
+}
+
+/// Calculate the probability density of a normal distribution with mean `mu`
+/// and standard deviation `sigma` at x.
+///
+/// # Arguments
+///
+/// * `x` - The point to evaluate the density at.
+/// * `mu` - The mean of the distribution.
+/// * `sigma` - The standard deviation of the distribution, which is > 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::normpdf;
+///
+/// assert!((normpdf(0.0, 0.0, 1.0) - 0.3989422804014327).abs() < 1e-15);
+/// assert_eq!(normpdf(1.0, 0.0, 1.0), normpdf(-1.0, 0.0, 1.0));
+/// ```
+pub fn normpdf(x: f64, mu: f64, sigma: f64) -> f64 {
+    let z = (x - mu) / sigma;
+
+    (-0.5 * z * z).exp() / (sigma * (2.0 * PI).sqrt())
+}
+
+/// Calculate the cumulative distribution function of a normal distribution
+/// with mean `mu` and standard deviation `sigma` at x, i.e. the probability
+/// that a value drawn from the distribution is at most x.
+///
+/// # Arguments
+///
+/// * `x` - The point to evaluate the distribution function at.
+/// * `mu` - The mean of the distribution.
+/// * `sigma` - The standard deviation of the distribution, which is > 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::normcdf;
+///
+/// assert_eq!(normcdf(0.0, 0.0, 1.0), 0.5);
+/// assert!((normcdf(1.96, 0.0, 1.0) - 0.9750021048517795).abs() < 1e-15);
+/// assert!((normcdf(110.0, 100.0, 10.0) - 0.8413447460685429).abs() < 1e-15);
+/// ```
+pub fn normcdf(x: f64, mu: f64, sigma: f64) -> f64 {
+    0.5 * erfc(-(x - mu) / (sigma * SQRT_2))
+}
+
+/// Calculate the inverse of the cumulative distribution function of a normal
+/// distribution with mean `mu` and standard deviation `sigma`, i.e. the value
+/// which a value drawn from the distribution is at most with probability p.
+///
+/// This uses Peter Acklam's rational approximation, refined with a step of
+/// Halley's method to close to full double precision.
+///
+/// # Arguments
+///
+/// * `p` - A probability in the interval [0, 1].
+/// * `mu` - The mean of the distribution.
+/// * `sigma` - The standard deviation of the distribution, which is > 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::norminv;
+///
+/// assert_eq!(norminv(0.5, 0.0, 1.0), 0.0);
+/// assert!((norminv(0.975, 0.0, 1.0) - 1.959963984540054).abs() < 1e-14);
+/// assert!((norminv(0.025, 100.0, 10.0) - 80.40036015459946).abs() < 1e-12);
+/// assert_eq!(norminv(1.0, 0.0, 1.0), f64::INFINITY);
+/// ```
+pub fn norminv(p: f64, mu: f64, sigma: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    // the boundary between the central and the tail approximations
+    const P_LOW: f64 = 0.02425;
+
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    } else if p == 1.0 {
+        return f64::INFINITY;
+    } else if !(0.0..1.0).contains(&p) {
+        return f64::NAN;
+    }
+
+    let poly = |coefficients: &[f64], x: f64| {
+        coefficients.iter().fold(0.0, |acc, &c| acc * x + c)
+    };
+    let tail = |q: f64| {
+        let q = (-2.0 * q.ln()).sqrt();
+        poly(&C, q) / (poly(&D, q) * q + 1.0)
+    };
+
+    let mut z = if p < P_LOW {
+        tail(p)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        poly(&A, r) * q / (poly(&B, r) * r + 1.0)
+    } else {
+        -tail(1.0 - p)
+    };
+
+    // a single step of halley's method
+    let e = 0.5 * erfc(-z / SQRT_2) - p;
+    let u = e * (2.0 * PI).sqrt() * (z * z / 2.0).exp();
+    z -= u / (1.0 + z * u / 2.0);
+
+    mu + sigma * z
+}
+
+/// Lanczos coefficients used by `ln_gamma`, for g = 7.
+const LANCZOS_COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Calculate the natural logarithm of the absolute value of the gamma function
+/// of x using the Lanczos approximation.
+///
+/// Unlike the gamma function itself, this does not overflow until x is about
+/// 1e305, so can be used to work with the gamma function of large numbers.
+/// The gamma function has poles at 0 and the negative integers, where this is
+/// infinite.
+///
+/// # Arguments
+///
+/// * `x` - Any real number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::ln_gamma;
+///
+/// assert!((ln_gamma(0.5) - 0.5723649429247004).abs() < 1e-14);
+/// assert!((ln_gamma(-2.5) + 0.05624371649767457).abs() < 1e-14);
+/// assert!((ln_gamma(1e300) - 6.897755278982137e302).abs() < 1e288);
+/// assert_eq!(ln_gamma(-3.0), f64::INFINITY);
+/// ```
+pub fn ln_gamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::INFINITY;
+    } else if x < 0.5 {
+        // reflection formula, since the approximation only holds for x >= 0.5
+        return (PI / (PI * x).sin().abs()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = LANCZOS_COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS_COEFFICIENTS[0], |acc, (i, &c)| {
+            acc + c / (x + i as f64 + 1.0)
+        });
+
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Calculate the natural logarithm of the factorial of n, where n is an
+/// integer that is greater than or equal to 0.
+///
+/// Unlike the factorial itself, this does not overflow for n above 170, so
+/// can be used to work with the factorials of large numbers.
+///
+/// # Arguments
+///
+/// * `n` - An integer >= 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::ln_factorial;
+///
+/// assert_eq!(ln_factorial(0.0), 0.0);
+/// assert!((ln_factorial(5.0) - 120f64.ln()).abs() < 1e-15);
+/// assert!((ln_factorial(1000.0) - 5912.128178488163).abs() < 1e-11);
+/// ```
+pub fn ln_factorial(n: f64) -> f64 {
+    // the factorial is as accurate as it gets where it does not overflow
+    if n <= 170.0 {
+        // casting is safe since n is a non-negative integer below 171
+        factorial(n as u64).ln()
+    } else {
+        ln_gamma(n + 1.0)
+    }
+}
+
+/// Calculate the regularized incomplete beta function `I_x(a, b)`, where x is
+/// in [0, 1] and a and b are > 0.
+pub(crate) fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    } else if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b)
+        + a * x.ln()
+        + b * (1.0 - x).ln())
+    .exp();
+
+    // the continued fraction converges quickly only on one side of the mean,
+    // the other side is found through the symmetry I_x(a, b) = 1 - I_1-x(b, a)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Evaluate the continued fraction for the incomplete beta function using the
+/// modified Lentz method.
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..300 {
+        let m = m as f64;
+
+        // each iteration applies an even and an odd step of the fraction
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+
+        if (d * c - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+/// The most trials whose binomial coefficients are calculated by multiplying
+/// rather than from their logarithms, since the coefficients of more trials
+/// are too large for a float.
+const MAX_EXACT_TRIALS: f64 = 1030.0;
+
+/// Calculate the binomial coefficient `n choose k` by multiplying, which is
+/// exact while it is below 2^53.
+fn choose(n: f64, k: f64) -> f64 {
+    let k = k.min(n - k);
+
+    // each partial product is itself a binomial coefficient, so the division
+    // has no remainder
+    (1..=k as u64).fold(1.0, |c, i| {
+        let i = i as f64;
+        c * (n - k + i) / i
+    })
+}
+
+/// Calculate the natural logarithm of the binomial coefficient `n choose k`.
+fn ln_choose(n: f64, k: f64) -> f64 {
+    ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0)
+}
+
+/// Calculate the probability of exactly k successes in n independent trials
+/// which each succeed with probability p.
+///
+/// # Arguments
+///
+/// * `n` - The number of trials, a non-negative integer.
+/// * `p` - The probability of each trial succeeding, in [0, 1].
+/// * `k` - The number of successes, an integer.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::binompdf;
+///
+/// assert!((binompdf(10.0, 0.5, 5.0) - 0.24609375).abs() < 1e-15);
+/// assert_eq!(binompdf(10.0, 0.5, 3.0), 0.1171875);
+/// assert_eq!(binompdf(10.0, 0.5, 11.0), 0.0);
+///
+/// // large numbers of trials are calculated with logarithms
+/// assert!((binompdf(2000.0, 0.5, 1000.0) - 0.017839).abs() < 1e-6);
+/// assert_eq!(binompdf(3.0, 1.0, 3.0), 1.0);
+/// ```
+pub fn binompdf(n: f64, p: f64, k: f64) -> f64 {
+    if k < 0.0 || k > n {
+        return 0.0;
+    }
+
+    // the logarithms below are undefined when p is 0 or 1, where every trial
+    // has the same outcome
+    if p == 0.0 {
+        return if k == 0.0 { 1.0 } else { 0.0 };
+    } else if p == 1.0 {
+        return if k == n { 1.0 } else { 0.0 };
+    }
+
+    if n <= MAX_EXACT_TRIALS {
+        let (c, successes, failures) =
+            (choose(n, k), p.powf(k), (1.0 - p).powf(n - k));
+
+        // the product loses precision if a factor is too large or small to
+        // be held as a normal float
+        if [c, successes, failures].iter().all(|x| x.is_normal()) {
+            return c * successes * failures;
+        }
+    }
+
+    (ln_choose(n, k) + k * p.ln() + (n - k) * (1.0 - p).ln()).exp()
+}
+
+/// Calculate the probability of at most k successes in n independent trials
+/// which each succeed with probability p.
+///
+/// # Arguments
+///
+/// * `n` - The number of trials, a non-negative integer.
+/// * `p` - The probability of each trial succeeding, in [0, 1].
+/// * `k` - The maximum number of successes, an integer.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::binomcdf;
+///
+/// assert_eq!(binomcdf(10.0, 0.5, 5.0), 0.623046875);
+/// assert!((binomcdf(2000.0, 0.5, 1000.0) - 0.5089195).abs() < 1e-6);
+/// assert_eq!(binomcdf(10.0, 0.5, 10.0), 1.0);
+/// assert_eq!(binomcdf(10.0, 0.5, -1.0), 0.0);
+/// ```
+pub fn binomcdf(n: f64, p: f64, k: f64) -> f64 {
+    if k < 0.0 {
+        0.0
+    } else if k >= n {
+        1.0
+    } else if n <= MAX_EXACT_TRIALS {
+        // casting is safe since k is a non-negative integer below n
+        (0..=k as u64).map(|i| binompdf(n, p, i as f64)).sum()
+    } else {
+        incomplete_beta(1.0 - p, n - k, k + 1.0)
+    }
+}
+
+/// Calculate the probability of exactly k events occurring in a Poisson
+/// process where lambda events are expected.
+///
+/// # Arguments
+///
+/// * `lambda` - The expected number of events, which is >= 0.
+/// * `k` - The number of events, a non-negative integer.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::poissonpdf;
+///
+/// assert!((poissonpdf(3.0, 2.0) - 0.22404180765538775).abs() < 1e-15);
+/// assert_eq!(poissonpdf(0.0, 0.0), 1.0);
+/// ```
+pub fn poissonpdf(lambda: f64, k: f64) -> f64 {
+    if lambda == 0.0 {
+        return if k == 0.0 { 1.0 } else { 0.0 };
+    }
+
+    (k * lambda.ln() - lambda - ln_gamma(k + 1.0)).exp()
+}
+
+/// Calculate the cumulative distribution function of Student's t-distribution
+/// with nu degrees of freedom at x.
+///
+/// # Arguments
+///
+/// * `x` - The point to evaluate the distribution function at.
+/// * `nu` - The degrees of freedom, which is > 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::tcdf;
+///
+/// assert_eq!(tcdf(0.0, 5.0), 0.5);
+/// assert!((tcdf(2.015, 5.0) - 0.95).abs() < 1e-4);
+/// assert!((tcdf(-1.0, 1.0) - 0.25).abs() < 1e-15);
+/// ```
+pub fn tcdf(x: f64, nu: f64) -> f64 {
+    let tail = 0.5 * incomplete_beta(nu / (nu + x * x), nu / 2.0, 0.5);
+
+    if x > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Calculate the beta function of a and b.
+///
+/// # Arguments
+///
+/// * `a` - A number > 0.
+/// * `b` - A number > 0.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::beta;
+///
+/// assert!((beta(2.0, 3.0) - 1.0 / 12.0).abs() < 1e-15);
+/// assert!((beta(0.5, 0.5) - std::f64::consts::PI).abs() < 1e-14);
+/// ```
+#[cfg(feature = "special-functions")]
+pub fn beta(a: f64, b: f64) -> f64 {
+    (ln_gamma(a) + ln_gamma(b) - ln_gamma(a + b)).exp()
+}
+
+/// Calculate the Riemann zeta function of s.
+///
+/// Arguments > 0 use Borwein's acceleration of the alternating Dirichlet
+/// series, and arguments < 0 use the reflection formula.
+///
+/// # Arguments
+///
+/// * `s` - Any real number other than 1, where the function has a pole.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::zeta;
+/// use std::f64::consts::PI;
+///
+/// assert!((zeta(2.0) - PI * PI / 6.0).abs() < 1e-15);
+/// assert!((zeta(0.0) + 0.5).abs() < 1e-15);
+/// assert!((zeta(-1.0) + 1.0 / 12.0).abs() < 1e-15);
+/// assert_eq!(zeta(-2.0), 0.0);
+/// assert!(zeta(1.0).is_infinite());
+/// ```
+#[cfg(feature = "special-functions")]
+pub fn zeta(s: f64) -> f64 {
+    // the number of terms used by borwein's algorithm, which has an error of
+    // roughly 5.8^-n
+    const N: usize = 40;
+
+    if s == 1.0 {
+        return f64::INFINITY;
+    } else if s < 0.0 {
+        // the trivial zeros at the negative even integers, which the
+        // reflection formula only gets approximately
+        if s % 2.0 == 0.0 {
+            return 0.0;
+        }
+
+        return 2f64.powf(s)
+            * PI.powf(s - 1.0)
+            * (PI * s / 2.0).sin()
+            * ln_gamma(1.0 - s).exp()
+            * zeta(1.0 - s);
+    } else if s == 0.0 {
+        return -0.5;
+    }
+
+    // d[k] = n * sum_{i=0}^{k} (n + i - 1)! 4^i / ((n - i)! (2i)!)
+    let mut d = [0.0; N + 1];
+    let n = N as f64;
+    let mut term = 1.0 / n;
+    let mut sum = term;
+    d[0] = n * sum;
+    for (i, d) in d.iter_mut().enumerate().skip(1) {
+        let i = i as f64;
+        term *=
+            (n + i - 1.0) * (n - i + 1.0) * 4.0 / ((2.0 * i - 1.0) * (2.0 * i));
+        sum += term;
+        *d = n * sum;
+    }
+
+    let series: f64 = (0..N)
+        .map(|k| {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            sign * (d[k] - d[N]) / (k as f64 + 1.0).powf(s)
+        })
+        .sum();
+
+    -series / (d[N] * (1.0 - 2f64.powf(1.0 - s)))
+}
+
+/// Calculate the Bessel function of the first kind of integer order n at x.
+///
+/// # Arguments
+///
+/// * `n` - The order of the function, an integer.
+/// * `x` - Any real number.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::special::besselj;
+///
+/// assert!((besselj(0.0, 1.0) - 0.7651976865579666).abs() < 1e-15);
+/// assert!((besselj(1.0, 10.0) - 0.04347274616886144).abs() < 1e-15);
+/// assert!((besselj(-2.0, 3.0) - 0.48609126058589107).abs() < 1e-15);
+/// assert!((besselj(20.0, 1.0) - 3.873503008524658e-25).abs() < 1e-38);
+/// assert_eq!(besselj(3.0, 0.0), 0.0);
+/// ```
+#[cfg(feature = "special-functions")]
+pub fn besselj(n: f64, x: f64) -> f64 {
+    // J_-n(x) = (-1)^n J_n(x) = J_n(-x)
+    let sign = if n < 0.0 && n % 2.0 != 0.0 { -1.0 } else { 1.0 };
+    let sign = if x < 0.0 && n % 2.0 != 0.0 {
+        -sign
+    } else {
+        sign
+    };
+    let (n, x) = (n.abs(), x.abs());
+
+    let value = match bessel_series(n, x) {
+        Some(value) => value,
+        None if x > 1e4 && x > 10.0 * n * n => bessel_asymptotic(n, x),
+        None => bessel_integral(n, x),
+    };
+
+    sign * value
+}
+
+/// Sum the power series of `J_n(x)`, where n and x are >= 0, giving up if
+/// the terms cancel out so much that the result would be inaccurate.
+#[cfg(feature = "special-functions")]
+fn bessel_series(n: f64, x: f64) -> Option<f64> {
+    let half = x / 2.0;
+    // (x / 2)^n / n!, computed through logarithms so that it doesn't overflow
+    let mut term = if n == 0.0 {
+        1.0
+    } else {
+        (n * half.ln() - ln_gamma(n + 1.0)).exp()
+    };
+    let mut sum = term;
+    let mut largest = term.abs();
+
+    for m in 1..500 {
+        let m = m as f64;
+        term *= -half * half / (m * (m + n));
+        sum += term;
+        largest = largest.max(term.abs());
+
+        if term.abs() <= f64::EPSILON * sum.abs() {
+            break;
+        }
+    }
+
+    // more than 3 digits lost to cancellation
+    if sum.is_finite() && largest <= 1e3 * sum.abs() {
+        Some(sum)
+    } else {
+        None
+    }
+}
+
+/// Evaluate Bessel's integral `J_n(x) = 1/2pi int_0^2pi cos(n t - x sin t) dt`
+/// with the trapezoidal rule, which converges exponentially for periodic
+/// integrands once there are more points than oscillations.
+#[cfg(feature = "special-functions")]
+fn bessel_integral(n: f64, x: f64) -> f64 {
+    // casting is safe since n and x are finite and non-negative here
+    let points = (n + x) as usize + 64;
+    let step = 2.0 * PI / points as f64;
+
+    (0..points)
+        .map(|j| {
+            let t = j as f64 * step;
+            (n * t - x * t.sin()).cos()
+        })
+        .sum::<f64>()
+        / points as f64
+}
+
+/// Evaluate the asymptotic expansion of `J_n(x)` for x much larger than n.
+#[cfg(feature = "special-functions")]
+fn bessel_asymptotic(n: f64, x: f64) -> f64 {
+    let mu = 4.0 * n * n;
+    let z = 8.0 * x;
+    let p = 1.0 - (mu - 1.0) * (mu - 9.0) / (2.0 * z * z);
+    let q = (mu - 1.0) / z
+        - (mu - 1.0) * (mu - 9.0) * (mu - 25.0) / (6.0 * z * z * z);
+    let omega = x - (n / 2.0 + 0.25) * PI;
+
+    (2.0 / (PI * x)).sqrt() * (p * omega.cos() - q * omega.sin())
+}
@@ -0,0 +1,208 @@
+//! Statistical functions that work on data sets rather than single numbers,
+//! such as fitting lines and polynomials to points.
+
+use anyhow::{bail, Result};
+
+use crate::message::{Code, Message};
+
+/// A line fitted to some points by least squares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    /// The gradient of the line.
+    pub slope: f64,
+    /// The value of the line at x = 0.
+    pub intercept: f64,
+    /// The coefficient of determination, the fraction of the variation in
+    /// the y values that the line accounts for, where 1 is a perfect fit.
+    pub r_squared: f64,
+}
+
+/// A polynomial fitted to some points by least squares.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolyFit {
+    /// The coefficients of the polynomial, starting with the constant term,
+    /// so that `coefficients[i]` is the coefficient of x^i.
+    pub coefficients: Vec<f64>,
+    /// The coefficient of determination, the fraction of the variation in
+    /// the y values that the polynomial accounts for, where 1 is a perfect
+    /// fit.
+    pub r_squared: f64,
+}
+
+/// Check that `xs` and `ys` make up at least `min` points.
+fn check_points(xs: &[f64], ys: &[f64], min: usize) -> Result<()> {
+    if xs.len() != ys.len() {
+        bail!(Code::PointsMismatched.with(&[&xs.len(), &ys.len()]))
+    }
+    if xs.len() < min {
+        bail!(Code::TooFewPoints.with(&[&min, &xs.len()]))
+    }
+
+    Ok(())
+}
+
+/// Calculate the coefficient of determination of a fit which predicts
+/// `predicted` where `ys` were observed.
+fn r_squared(ys: &[f64], predicted: impl Iterator<Item = f64>) -> f64 {
+    let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+    let total: f64 = ys.iter().map(|y| (y - mean).powi(2)).sum();
+    let residual: f64 =
+        ys.iter().zip(predicted).map(|(y, p)| (y - p).powi(2)).sum();
+
+    // when every y is the same there is no variation to account for, so the
+    // fit is only perfect if it passes through every point
+    match total {
+        0.0 if residual == 0.0 => 1.0,
+        0.0 => 0.0,
+        _ => 1.0 - residual / total,
+    }
+}
+
+/// Fit a line to the points `(xs[i], ys[i])` by least squares.
+///
+/// # Arguments
+///
+/// * `xs` - The x values of the points, which must not all be the same.
+/// * `ys` - The y values of the points, which must be as many as `xs`.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::stats::fit;
+///
+/// let line = fit(&[0.0, 1.0, 2.0, 3.0], &[1.0, 3.0, 5.0, 7.0]).unwrap();
+///
+/// assert_eq!(2.0, line.slope);
+/// assert_eq!(1.0, line.intercept);
+/// assert_eq!(1.0, line.r_squared);
+/// assert!(fit(&[1.0, 1.0], &[2.0, 3.0]).is_err());
+/// ```
+pub fn fit(xs: &[f64], ys: &[f64]) -> Result<Fit> {
+    check_points(xs, ys, 2)?;
+
+    let n = xs.len() as f64;
+    let (mean_x, mean_y) =
+        (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+
+    // centring on the means avoids the cancellation of the textbook formula
+    // which sums x^2 directly
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+
+    if sxx == 0.0 {
+        bail!(Message::new(Code::SameX))
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+
+    Ok(Fit {
+        slope,
+        intercept,
+        r_squared: r_squared(ys, xs.iter().map(|x| slope * x + intercept)),
+    })
+}
+
+/// Evaluate the polynomial with `coefficients`, starting with the constant
+/// term, at `x`.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::stats::polyval;
+///
+/// // 1 + 2x + 3x^2
+/// assert_eq!(17.0, polyval(&[1.0, 2.0, 3.0], 2.0));
+/// ```
+pub fn polyval(coefficients: &[f64], x: f64) -> f64 {
+    // horner's method
+    coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// Fit a polynomial of `degree` to the points `(xs[i], ys[i])` by least
+/// squares.
+///
+/// The least squares problem is solved with a QR decomposition rather than
+/// the normal equations, which square the condition number of the problem.
+///
+/// # Arguments
+///
+/// * `xs` - The x values of the points, which must have more than `degree`
+///   distinct values.
+/// * `ys` - The y values of the points, which must be as many as `xs`.
+/// * `degree` - The degree of the polynomial.
+///
+/// # Examples
+/// ```
+/// use cocoa::math::stats::fitpoly;
+///
+/// let xs = [-1.0, 0.0, 1.0, 2.0];
+/// let ys: Vec<f64> = xs.iter().map(|x| 2.0 * x * x - x + 3.0).collect();
+/// let poly = fitpoly(&xs, &ys, 2).unwrap();
+///
+/// for (expected, c) in [3.0, -1.0, 2.0].iter().zip(&poly.coefficients) {
+///     assert!((expected - c).abs() < 1e-12);
+/// }
+/// assert!(fitpoly(&[1.0, 1.0, 2.0], &[1.0, 2.0, 3.0], 2).is_err());
+/// ```
+pub fn fitpoly(xs: &[f64], ys: &[f64], degree: usize) -> Result<PolyFit> {
+    check_points(xs, ys, degree + 1)?;
+
+    let mut distinct = xs.to_vec();
+    distinct.sort_by(f64::total_cmp);
+    distinct.dedup();
+    if distinct.len() <= degree {
+        bail!(Code::TooFewDistinctX.with(&[
+            &degree,
+            &(degree + 1),
+            &distinct.len()
+        ]))
+    }
+
+    let (m, n) = (xs.len(), degree + 1);
+    // the vandermonde matrix, whose rows are the powers of each x, augmented
+    // with the y values so that they are transformed along with it
+    let mut a: Vec<Vec<f64>> = xs
+        .iter()
+        .zip(ys)
+        .map(|(&x, &y)| (0..n).map(|j| x.powi(j as i32)).chain([y]).collect())
+        .collect();
+
+    // reduce a to upper triangular form with householder reflections
+    for k in 0..n {
+        let norm = (k..m).map(|i| a[i][k].powi(2)).sum::<f64>().sqrt();
+        let alpha = if a[k][k] > 0.0 { -norm } else { norm };
+
+        let mut v: Vec<f64> = (k..m).map(|i| a[i][k]).collect();
+        v[0] -= alpha;
+        let v_norm = v.iter().map(|x| x * x).sum::<f64>();
+        if v_norm == 0.0 {
+            continue;
+        }
+
+        let rows = &mut a[k..];
+        for j in k..=n {
+            let dot: f64 = rows.iter().zip(&v).map(|(row, v)| v * row[j]).sum();
+            for (row, v) in rows.iter_mut().zip(&v) {
+                row[j] -= 2.0 * dot / v_norm * v;
+            }
+        }
+    }
+
+    // solve the triangular system by back substitution
+    let mut coefficients = vec![0.0; n];
+    for k in (0..n).rev() {
+        let rest: f64 = (k + 1..n).map(|j| a[k][j] * coefficients[j]).sum();
+        coefficients[k] = (a[k][n] - rest) / a[k][k];
+    }
+
+    let r_squared =
+        r_squared(ys, xs.iter().map(|&x| polyval(&coefficients, x)));
+
+    Ok(PolyFit {
+        coefficients,
+        r_squared,
+    })
+}
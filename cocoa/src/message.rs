@@ -0,0 +1,1230 @@
+//! The messages of errors, kept in a catalog keyed by what went wrong so that
+//! frontends can present them in other languages.
+//!
+//! Errors returned by cocoa hold a `Message`, which is a `Code` along with
+//! the names and numbers the message mentions. Formatting an error gives its
+//! message in English, and `localize` gives it in any other `Locale`, such as
+//! a `Catalog` of translations.
+
+use std::{collections::HashMap, error, fmt};
+
+use anyhow::Error;
+
+use crate::{error::SpanError, script::ScriptError};
+
+/// What went wrong, which keys the message of an error in a catalog.
+///
+/// The English message of each code is a template in which `{0}`, `{1}` and
+/// so on are replaced by the arguments of the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Code {
+    /// A message which is not in the catalog, such as one from a plugin,
+    /// which is its only argument.
+    Other,
+    /// A hint suggesting one name the input may have meant.
+    DidYouMean,
+    /// A hint suggesting some names the input may have meant, all but the
+    /// last of them joined by commas, then the last.
+    DidYouMeanOneOf,
+    /// A string with no closing quote.
+    UnterminatedString,
+    /// An escape other than `\"` or `\\` in a string.
+    UnknownEscape,
+    /// The input ended in the middle of a token.
+    UnexpectedEndOfInput,
+    /// A character which does not start any token.
+    UnrecognizedCharacter,
+    /// A number with two decimal points.
+    MultipleDecimalPoints,
+    /// A decimal point with no digits on either side of it.
+    NumberWithoutDigits,
+    /// An expression nested more deeply than the parser allows.
+    NestedTooDeep,
+    /// A builtin function which is not called.
+    ExpectedParen,
+    /// An operator where an operand was expected.
+    UnexpectedOperator,
+    /// A bracket which is never closed, or never opened.
+    UnmatchedDelimiter,
+    /// A token which cannot appear where it does.
+    UnexpectedToken,
+    /// The input ended in the middle of an expression.
+    UnexpectedEndOfStatement,
+    /// A string which is not the argument of a function taking one.
+    UnexpectedString,
+    /// A call to a function which is not defined.
+    UnknownFunction,
+    /// A name which is not defined.
+    UnknownIdentifier,
+    /// An equals sign outside of an equation passed to `solve`.
+    EqualsOutsideSolve,
+    /// The factorial of a negative number.
+    NegativeFactorial,
+    /// The factorial of a number with a fractional part.
+    NonIntegerFactorial,
+    /// A result too large to be held by a float.
+    Overflowed,
+    /// A result too small to be held by a float.
+    Underflowed,
+    /// A division by zero.
+    DivisionByZero,
+    /// The remainder of a division by zero.
+    RemainderByZero,
+    /// Zero raised to a negative power.
+    ZeroToNegativePower,
+    /// A negative number raised to a power with a fractional part.
+    NegativeToFractionalPower,
+    /// A result which is not a number.
+    IsNotANumber,
+    /// A result which is infinite.
+    IsInfinite,
+    /// A shift of or by a number with a fractional part.
+    NonIntegerShift,
+    /// A shift by a negative amount.
+    NegativeShift,
+    /// A function called with the wrong number of arguments.
+    WrongArity,
+    /// A higher order function called with the wrong number of arguments.
+    Expects,
+    /// A function called with an argument outside of its domain, when it
+    /// takes one argument.
+    ArgumentOutOfDomain,
+    /// A function called with an argument outside of its domain, when it
+    /// takes more than one argument.
+    NthArgumentOutOfDomain,
+    /// A list passed to a function which takes numbers.
+    ArgumentIsList,
+    /// A list where a number was expected.
+    ExpectedNumber,
+    /// Something other than a string passed to a function taking one.
+    ExpectedString,
+    /// A string passed to a function which does not take one.
+    DoesNotTakeString,
+    /// A function taking a string called without one.
+    NeedsString,
+    /// A function taking an expression called with a value.
+    NeedsExpression,
+    /// A function which only an evaluator can call.
+    NeedsEvaluator,
+    /// A higher order function whose name for the unknown is missing.
+    ExpectedUnknownName,
+    /// A higher order function binding a name which cannot be bound.
+    CannotBind,
+    /// A higher order function whose name to bind is missing.
+    ExpectedVariableName,
+    /// A name to bind which is not followed by a comma.
+    ExpectedCommaAfter,
+    /// An argument of a higher order function which is missing its comma.
+    ExpectedExpressionThenComma,
+    /// A comma with no argument of a higher order function before it.
+    ExpectedExpressionBeforeComma,
+    /// `reduce` called with an empty list.
+    EmptyReduce,
+    /// `montecarlo` called with infinite bounds.
+    MonteCarloBounds,
+    /// `montecarlo` called with too few samples.
+    MonteCarloSamples,
+    /// `odesolve` called with an infinite start or end.
+    OdeSolveBounds,
+    /// `odesolve` called with too few steps.
+    OdeSolveSteps,
+    /// `solve` called with something other than an equation.
+    ExpectedEquation,
+    /// An equation with more than one equals sign.
+    MultipleEquals,
+    /// An equation which is not linear in its unknown.
+    NotLinear,
+    /// An equation which every value of its unknown solves.
+    EverySolution,
+    /// An equation which no value of its unknown solves.
+    NoSolution,
+    /// An equation which cannot be solved.
+    Unsolvable,
+    /// `nth` called with an index past the end of its list.
+    IndexOutOfRange,
+    /// `head` called with an empty list.
+    EmptyHead,
+    /// `tail` called with an empty list.
+    EmptyTail,
+    /// An exact integer too large to be calculated.
+    TooLargeToComputeExactly,
+    /// `assert` called with zero.
+    AssertNonZero,
+    /// `assert_eq` called with different values.
+    AssertNotEqual,
+    /// `assert_eq` called with values further apart than its tolerance.
+    AssertDiffer,
+    /// A range with an infinite bound or step.
+    RangeNotFinite,
+    /// A range whose step goes away from its end.
+    RangeNeverReaches,
+    /// A range with more values than are allowed.
+    TooManyValues,
+    /// An expression with more tokens than are allowed.
+    TooManyTokens,
+    /// Calls nested more deeply than is allowed.
+    NestedCalls,
+    /// An evaluation which took more steps than are allowed.
+    StepBudgetExceeded,
+    /// An evaluation which took longer than is allowed.
+    TimeBudgetExceeded,
+    /// `env` called when environment variables cannot be read.
+    EnvNotAllowed,
+    /// `env` called with a variable which is not set.
+    EnvNotSet,
+    /// `env` called with a variable which is not a number.
+    EnvNotANumber,
+    /// A file read when files cannot be read.
+    FilesNotAllowed,
+    /// A file which could not be read.
+    CouldNotRead,
+    /// `stdin` called when standard input cannot be read.
+    StdinNotAllowed,
+    /// Standard input which could not be read.
+    CouldNotReadStdin,
+    /// A file of numbers with more numbers than are allowed.
+    TooManyNumbers,
+    /// A word in a file of numbers which is not a number.
+    ExpectedNumberOnLine,
+    /// A variable whose name is not an identifier.
+    InvalidVariableName,
+    /// An assignment to a name which is not a variable.
+    CannotAssign,
+    /// An alias whose name is not an identifier.
+    InvalidAliasName,
+    /// An alias for a name which is already defined.
+    AliasAlreadyDefined,
+    /// An alias of something which is not a function or constant.
+    AliasUnknown,
+    /// A base other than those numbers can be written in.
+    UnknownBase,
+    /// A width of integers which is not supported.
+    UnsupportedWidth,
+    /// A rounding mode which does not exist.
+    UnknownRoundingMode,
+    /// A plugin providing a name which is not an identifier.
+    PluginInvalidName,
+    /// A plugin providing a name which is already defined.
+    PluginRedefines,
+    /// A definition which does not start with `template`.
+    ExpectedTemplate,
+    /// A template without `:=` after its parameters.
+    ExpectedDefinition,
+    /// A template without a name and parameters.
+    ExpectedTemplateHead,
+    /// A template whose parameters are not all names.
+    ExpectedParameterNames,
+    /// A template named after a builtin function.
+    CannotRedefineBuiltin,
+    /// A template whose name is not an identifier.
+    InvalidTemplateName,
+    /// A template parameter which is not an identifier.
+    InvalidParameterName,
+    /// A template naming a parameter more than once.
+    DuplicateParameter,
+    /// A template whose name is already defined.
+    TemplateAlreadyDefined,
+    /// A template which calls itself directly.
+    TemplateCallsItself,
+    /// A template which calls itself through another template.
+    TemplateCallsItselfThrough,
+    /// A script which imports itself, directly or not.
+    AlreadyImporting,
+    /// A function which cannot be differentiated exactly.
+    CannotDifferentiate,
+    /// An operator which an algebra does not support.
+    AlgebraUnsupported,
+    /// A string evaluated over an algebra.
+    AlgebraString,
+    /// A list evaluated over an algebra.
+    AlgebraList,
+    /// A formatted expression which does not parse.
+    DoesNotParseBack,
+    /// A formatted number which parses back as a name.
+    ParsesBackAsIdentifier,
+    /// A formatted expression which parses back differently.
+    ParsesBackAs,
+    /// A variable without a value used where a number is needed.
+    NoValue,
+    /// A variable without a value raised to a whole power too large to
+    /// expand exactly.
+    PowerTooLarge,
+    /// `e` used as a variable without a value, which would be shown the same
+    /// as Euler's number.
+    EulerVariable,
+    /// A list of points with a different number of x and y values.
+    PointsMismatched,
+    /// Too few points for what is done with them.
+    TooFewPoints,
+    /// Points through which no line can be fit, since they share an x value.
+    SameX,
+    /// Points with too few distinct x values to fit a polynomial to.
+    TooFewDistinctX,
+    /// A system of no linear equations.
+    NoEquations,
+    /// A matrix whose size does not match the number of equations.
+    MatrixSize,
+    /// A system of linear equations with a NaN or infinite number.
+    NotFiniteEquations,
+    /// A system of linear equations without a unique solution.
+    SingularMatrix,
+    /// A number past the largest that `primepi` supports.
+    PrimepiTooLarge,
+    /// The 0th prime, when primes are counted from 1.
+    ZerothPrime,
+    /// A prime past the largest that `prime` supports.
+    PrimeTooLarge,
+    /// The roots of the zero polynomial, which are every number.
+    ZeroPolynomial,
+    /// Roots of a polynomial which could not be found precisely.
+    RootsDidNotConverge,
+    /// A function at a pole, such as `tan` at `pi / 2`, when special angles
+    /// are exact.
+    UndefinedAt,
+    /// A domain of numbers which does not exist.
+    UnknownDomain,
+    /// A domain of numbers which exists but cannot be calculated in.
+    UnsupportedDomain,
+    /// An operator, function or word of reverse Polish notation with fewer
+    /// values on the stack than it takes.
+    StackUnderflow,
+    /// Something which cannot be written in reverse Polish notation, such as
+    /// a bracket.
+    NotInRpn,
+    /// A warning that an operator or comma ending the input was left out.
+    IgnoredAtEnd,
+    /// A warning that a bracket left open was closed.
+    ClosedLeftOpen,
+    /// A warning that a bracket which was never opened was left out.
+    IgnoredUnmatched,
+    /// A warning that an integer was too large to be held exactly.
+    InexactInteger,
+    /// A warning that a result is so large that calculations with it may
+    /// overflow.
+    NearOverflow,
+    /// A warning that a name which is going away was used.
+    Deprecated,
+}
+
+impl Code {
+    /// Every code, for checking that a catalog translates all of them.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::message::Code;
+    ///
+    /// assert!(Code::ALL.contains(&Code::UnknownIdentifier));
+    /// ```
+    pub const ALL: &'static [Code] = &[
+        Code::Other,
+        Code::DidYouMean,
+        Code::DidYouMeanOneOf,
+        Code::UnterminatedString,
+        Code::UnknownEscape,
+        Code::UnexpectedEndOfInput,
+        Code::UnrecognizedCharacter,
+        Code::MultipleDecimalPoints,
+        Code::NumberWithoutDigits,
+        Code::NestedTooDeep,
+        Code::ExpectedParen,
+        Code::UnexpectedOperator,
+        Code::UnmatchedDelimiter,
+        Code::UnexpectedToken,
+        Code::UnexpectedEndOfStatement,
+        Code::UnexpectedString,
+        Code::UnknownFunction,
+        Code::UnknownIdentifier,
+        Code::EqualsOutsideSolve,
+        Code::NegativeFactorial,
+        Code::NonIntegerFactorial,
+        Code::Overflowed,
+        Code::Underflowed,
+        Code::DivisionByZero,
+        Code::RemainderByZero,
+        Code::ZeroToNegativePower,
+        Code::NegativeToFractionalPower,
+        Code::IsNotANumber,
+        Code::IsInfinite,
+        Code::NonIntegerShift,
+        Code::NegativeShift,
+        Code::WrongArity,
+        Code::Expects,
+        Code::ArgumentOutOfDomain,
+        Code::NthArgumentOutOfDomain,
+        Code::ArgumentIsList,
+        Code::ExpectedNumber,
+        Code::ExpectedString,
+        Code::DoesNotTakeString,
+        Code::NeedsString,
+        Code::NeedsExpression,
+        Code::NeedsEvaluator,
+        Code::ExpectedUnknownName,
+        Code::CannotBind,
+        Code::ExpectedVariableName,
+        Code::ExpectedCommaAfter,
+        Code::ExpectedExpressionThenComma,
+        Code::ExpectedExpressionBeforeComma,
+        Code::EmptyReduce,
+        Code::MonteCarloBounds,
+        Code::MonteCarloSamples,
+        Code::OdeSolveBounds,
+        Code::OdeSolveSteps,
+        Code::ExpectedEquation,
+        Code::MultipleEquals,
+        Code::NotLinear,
+        Code::EverySolution,
+        Code::NoSolution,
+        Code::Unsolvable,
+        Code::IndexOutOfRange,
+        Code::EmptyHead,
+        Code::EmptyTail,
+        Code::TooLargeToComputeExactly,
+        Code::AssertNonZero,
+        Code::AssertNotEqual,
+        Code::AssertDiffer,
+        Code::RangeNotFinite,
+        Code::RangeNeverReaches,
+        Code::TooManyValues,
+        Code::TooManyTokens,
+        Code::NestedCalls,
+        Code::StepBudgetExceeded,
+        Code::TimeBudgetExceeded,
+        Code::EnvNotAllowed,
+        Code::EnvNotSet,
+        Code::EnvNotANumber,
+        Code::FilesNotAllowed,
+        Code::CouldNotRead,
+        Code::StdinNotAllowed,
+        Code::CouldNotReadStdin,
+        Code::TooManyNumbers,
+        Code::ExpectedNumberOnLine,
+        Code::InvalidVariableName,
+        Code::CannotAssign,
+        Code::InvalidAliasName,
+        Code::AliasAlreadyDefined,
+        Code::AliasUnknown,
+        Code::UnknownBase,
+        Code::UnsupportedWidth,
+        Code::UnknownRoundingMode,
+        Code::PluginInvalidName,
+        Code::PluginRedefines,
+        Code::ExpectedTemplate,
+        Code::ExpectedDefinition,
+        Code::ExpectedTemplateHead,
+        Code::ExpectedParameterNames,
+        Code::CannotRedefineBuiltin,
+        Code::InvalidTemplateName,
+        Code::InvalidParameterName,
+        Code::DuplicateParameter,
+        Code::TemplateAlreadyDefined,
+        Code::TemplateCallsItself,
+        Code::TemplateCallsItselfThrough,
+        Code::AlreadyImporting,
+        Code::CannotDifferentiate,
+        Code::AlgebraUnsupported,
+        Code::AlgebraString,
+        Code::AlgebraList,
+        Code::DoesNotParseBack,
+        Code::ParsesBackAsIdentifier,
+        Code::ParsesBackAs,
+        Code::NoValue,
+        Code::PowerTooLarge,
+        Code::EulerVariable,
+        Code::PointsMismatched,
+        Code::TooFewPoints,
+        Code::SameX,
+        Code::TooFewDistinctX,
+        Code::NoEquations,
+        Code::MatrixSize,
+        Code::NotFiniteEquations,
+        Code::SingularMatrix,
+        Code::PrimepiTooLarge,
+        Code::ZerothPrime,
+        Code::PrimeTooLarge,
+        Code::ZeroPolynomial,
+        Code::RootsDidNotConverge,
+        Code::UndefinedAt,
+        Code::UnknownDomain,
+        Code::UnsupportedDomain,
+        Code::StackUnderflow,
+        Code::NotInRpn,
+        Code::IgnoredAtEnd,
+        Code::ClosedLeftOpen,
+        Code::IgnoredUnmatched,
+        Code::InexactInteger,
+        Code::NearOverflow,
+        Code::Deprecated,
+    ];
+
+    /// Get the stable identifier of `self`, such as `E014` for an unknown
+    /// identifier, which frontends can match on or link to documentation
+    /// for.
+    ///
+    /// Errors are numbered from `E001`, warnings from `W001` and hints from
+    /// `H001`, and a code keeps its identifier once it has one. Messages
+    /// which are not in the catalog are `E000`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::HashSet;
+    ///
+    /// use cocoa::message::Code;
+    ///
+    /// assert_eq!("E001", Code::UnmatchedDelimiter.id());
+    /// assert_eq!("E014", Code::UnknownIdentifier.id());
+    ///
+    /// let ids: HashSet<_> = Code::ALL.iter().map(|code| code.id()).collect();
+    /// assert_eq!(Code::ALL.len(), ids.len());
+    /// ```
+    pub fn id(self) -> &'static str {
+        match self {
+            Code::Other => "E000",
+            Code::UnmatchedDelimiter => "E001",
+            Code::UnexpectedToken => "E002",
+            Code::UnexpectedEndOfStatement => "E003",
+            Code::UnexpectedOperator => "E004",
+            Code::ExpectedParen => "E005",
+            Code::UnterminatedString => "E006",
+            Code::UnknownEscape => "E007",
+            Code::UnexpectedEndOfInput => "E008",
+            Code::UnrecognizedCharacter => "E009",
+            Code::MultipleDecimalPoints => "E010",
+            Code::NestedTooDeep => "E011",
+            Code::UnexpectedString => "E012",
+            Code::UnknownFunction => "E013",
+            Code::UnknownIdentifier => "E014",
+            Code::EqualsOutsideSolve => "E015",
+            Code::NegativeFactorial => "E016",
+            Code::NonIntegerFactorial => "E017",
+            Code::Overflowed => "E018",
+            Code::Underflowed => "E019",
+            Code::DivisionByZero => "E020",
+            Code::RemainderByZero => "E021",
+            Code::ZeroToNegativePower => "E022",
+            Code::NegativeToFractionalPower => "E023",
+            Code::IsNotANumber => "E024",
+            Code::IsInfinite => "E025",
+            Code::NonIntegerShift => "E026",
+            Code::NegativeShift => "E027",
+            Code::WrongArity => "E028",
+            Code::Expects => "E029",
+            Code::ArgumentOutOfDomain => "E030",
+            Code::NthArgumentOutOfDomain => "E031",
+            Code::ArgumentIsList => "E032",
+            Code::ExpectedNumber => "E033",
+            Code::ExpectedString => "E034",
+            Code::DoesNotTakeString => "E035",
+            Code::NeedsString => "E036",
+            Code::NeedsExpression => "E037",
+            Code::NeedsEvaluator => "E038",
+            Code::ExpectedUnknownName => "E039",
+            Code::CannotBind => "E040",
+            Code::ExpectedVariableName => "E041",
+            Code::ExpectedCommaAfter => "E042",
+            Code::ExpectedExpressionThenComma => "E043",
+            Code::ExpectedExpressionBeforeComma => "E044",
+            Code::EmptyReduce => "E045",
+            Code::MonteCarloBounds => "E046",
+            Code::MonteCarloSamples => "E047",
+            Code::OdeSolveBounds => "E048",
+            Code::OdeSolveSteps => "E049",
+            Code::ExpectedEquation => "E050",
+            Code::MultipleEquals => "E051",
+            Code::NotLinear => "E052",
+            Code::EverySolution => "E053",
+            Code::NoSolution => "E054",
+            Code::Unsolvable => "E055",
+            Code::IndexOutOfRange => "E056",
+            Code::EmptyHead => "E057",
+            Code::EmptyTail => "E058",
+            Code::TooLargeToComputeExactly => "E059",
+            Code::AssertNonZero => "E060",
+            Code::AssertNotEqual => "E061",
+            Code::AssertDiffer => "E062",
+            Code::RangeNotFinite => "E063",
+            Code::RangeNeverReaches => "E064",
+            Code::TooManyValues => "E065",
+            Code::TooManyTokens => "E066",
+            Code::NestedCalls => "E067",
+            Code::StepBudgetExceeded => "E068",
+            Code::TimeBudgetExceeded => "E069",
+            Code::EnvNotAllowed => "E070",
+            Code::EnvNotSet => "E071",
+            Code::EnvNotANumber => "E072",
+            Code::FilesNotAllowed => "E073",
+            Code::CouldNotRead => "E074",
+            Code::StdinNotAllowed => "E075",
+            Code::CouldNotReadStdin => "E076",
+            Code::TooManyNumbers => "E077",
+            Code::ExpectedNumberOnLine => "E078",
+            Code::InvalidVariableName => "E079",
+            Code::CannotAssign => "E080",
+            Code::InvalidAliasName => "E081",
+            Code::AliasAlreadyDefined => "E082",
+            Code::AliasUnknown => "E083",
+            Code::UnknownBase => "E084",
+            Code::UnsupportedWidth => "E085",
+            Code::UnknownRoundingMode => "E086",
+            Code::PluginInvalidName => "E087",
+            Code::PluginRedefines => "E088",
+            Code::ExpectedTemplate => "E089",
+            Code::ExpectedDefinition => "E090",
+            Code::ExpectedTemplateHead => "E091",
+            Code::ExpectedParameterNames => "E092",
+            Code::CannotRedefineBuiltin => "E093",
+            Code::InvalidTemplateName => "E094",
+            Code::InvalidParameterName => "E095",
+            Code::DuplicateParameter => "E096",
+            Code::TemplateAlreadyDefined => "E097",
+            Code::TemplateCallsItself => "E098",
+            Code::TemplateCallsItselfThrough => "E099",
+            Code::AlreadyImporting => "E100",
+            Code::CannotDifferentiate => "E101",
+            Code::AlgebraUnsupported => "E102",
+            Code::AlgebraString => "E103",
+            Code::AlgebraList => "E104",
+            Code::DoesNotParseBack => "E105",
+            Code::ParsesBackAsIdentifier => "E106",
+            Code::ParsesBackAs => "E107",
+            Code::NoValue => "E108",
+            Code::UnknownDomain => "E109",
+            Code::UnsupportedDomain => "E110",
+            Code::StackUnderflow => "E111",
+            Code::NotInRpn => "E112",
+            Code::PowerTooLarge => "E113",
+            Code::EulerVariable => "E114",
+            Code::PointsMismatched => "E115",
+            Code::TooFewPoints => "E116",
+            Code::SameX => "E117",
+            Code::TooFewDistinctX => "E118",
+            Code::NoEquations => "E119",
+            Code::MatrixSize => "E120",
+            Code::NotFiniteEquations => "E121",
+            Code::SingularMatrix => "E122",
+            Code::PrimepiTooLarge => "E123",
+            Code::ZerothPrime => "E124",
+            Code::PrimeTooLarge => "E125",
+            Code::ZeroPolynomial => "E126",
+            Code::RootsDidNotConverge => "E127",
+            Code::UndefinedAt => "E128",
+            Code::NumberWithoutDigits => "E129",
+            Code::IgnoredAtEnd => "W001",
+            Code::ClosedLeftOpen => "W002",
+            Code::IgnoredUnmatched => "W003",
+            Code::InexactInteger => "W004",
+            Code::NearOverflow => "W005",
+            Code::Deprecated => "W006",
+            Code::DidYouMean => "H001",
+            Code::DidYouMeanOneOf => "H002",
+        }
+    }
+
+    /// Get the code whose stable identifier is `id`, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The identifier of a code, such as `E014`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::message::Code;
+    ///
+    /// assert_eq!(Some(Code::UnknownIdentifier), Code::from_id("E014"));
+    /// assert_eq!(None, Code::from_id("E999"));
+    /// ```
+    pub fn from_id(id: &str) -> Option<Code> {
+        Code::ALL.iter().copied().find(|code| code.id() == id)
+    }
+
+    /// Get the template of the English message for `self`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::message::Code;
+    ///
+    /// assert_eq!("unknown identifier '{0}'", Code::UnknownIdentifier.english());
+    /// ```
+    pub fn english(self) -> &'static str {
+        match self {
+            Code::Other => "{0}",
+            Code::DidYouMean => "did you mean {0}?",
+            Code::DidYouMeanOneOf => "did you mean {0} or {1}?",
+            Code::UnterminatedString => "unterminated string",
+            Code::UnknownEscape => "unknown escape '\\{0}' in string",
+            Code::UnexpectedEndOfInput => "unexpected end of input",
+            Code::UnrecognizedCharacter => "unrecognized character '{0}'",
+            Code::MultipleDecimalPoints => {
+                "number cannot contain more than one decimal point"
+            }
+            Code::NumberWithoutDigits => {
+                "malformed number '.', which has no digits"
+            }
+            Code::NestedTooDeep => {
+                "expression is nested more than {0} levels deep"
+            }
+            Code::ExpectedParen => "expected '(' after function '{0}'",
+            Code::UnexpectedOperator => "unexpected operator token '{0}'",
+            Code::UnmatchedDelimiter => "unmatched delimiter '{0}'",
+            Code::UnexpectedToken => "unexpected token '{0}'",
+            Code::UnexpectedEndOfStatement => "unexpected end of statement",
+            Code::UnexpectedString => {
+                "unexpected string, strings can only be passed to functions \
+                such as env"
+            }
+            Code::UnknownFunction => "unknown function '{0}'",
+            Code::UnknownIdentifier => "unknown identifier '{0}'",
+            Code::EqualsOutsideSolve => {
+                "'=' can only be used in an equation passed to solve"
+            }
+            Code::NegativeFactorial => {
+                "cannot calculate factorial of negative numbers"
+            }
+            Code::NonIntegerFactorial => {
+                "cannot calculate factorial of non integers"
+            }
+            Code::Overflowed => "{0} overflowed to infinity",
+            Code::Underflowed => "{0} underflowed to zero",
+            Code::DivisionByZero => "division by zero",
+            Code::RemainderByZero => "remainder of division by zero",
+            Code::ZeroToNegativePower => "zero raised to a negative power",
+            Code::NegativeToFractionalPower => {
+                "negative number raised to a non integer power"
+            }
+            Code::IsNotANumber => "{0} is not a number",
+            Code::IsInfinite => "{0} is infinite",
+            Code::NonIntegerShift => "cannot shift non integers",
+            Code::NegativeShift => "cannot shift by a negative amount",
+            Code::WrongArity => "{0} expects {1}, got {2}",
+            Code::Expects => "{0} expects {1}",
+            Code::ArgumentOutOfDomain => "{0} expects an argument {1}, got {2}",
+            Code::NthArgumentOutOfDomain => {
+                "{0} expects argument {1} {2}, got {3}"
+            }
+            Code::ArgumentIsList => {
+                "{0} expects argument {1} to be a number, got a list"
+            }
+            Code::ExpectedNumber => "expected a number, got a list",
+            Code::ExpectedString => {
+                "expected a single string, e.g. {0}(\"...\")"
+            }
+            Code::DoesNotTakeString => "{0} does not take a string",
+            Code::NeedsString => "{0} must be called with a string",
+            Code::NeedsExpression => {
+                "{0} takes an expression rather than a value, so can only be \
+                called in an expression"
+            }
+            Code::NeedsEvaluator => "{0} can only be called by an evaluator",
+            Code::ExpectedUnknownName => "expected the name of the unknown",
+            Code::CannotBind => "cannot bind '{0}'",
+            Code::ExpectedVariableName => "expected the name of a variable",
+            Code::ExpectedCommaAfter => "expected ',' after '{0}'",
+            Code::ExpectedExpressionThenComma => {
+                "expected an expression followed by ','"
+            }
+            Code::ExpectedExpressionBeforeComma => {
+                "expected an expression before ','"
+            }
+            Code::EmptyReduce => "cannot reduce an empty list",
+            Code::MonteCarloBounds => {
+                "montecarlo expects finite bounds, got {0} and {1}"
+            }
+            Code::MonteCarloSamples => {
+                "montecarlo expects at least 2 samples, got {0}"
+            }
+            Code::OdeSolveBounds => "odesolve expects a finite start and end",
+            Code::OdeSolveSteps => "odesolve expects at least 1 step, got {0}",
+            Code::ExpectedEquation => {
+                "solve expects an equation such as 2 * x + 3 = 11"
+            }
+            Code::MultipleEquals => "an equation can only have one '='",
+            Code::NotLinear => "the equation is not linear in {0}",
+            Code::EverySolution => "every value of {0} solves the equation",
+            Code::NoSolution => "no value of {0} solves the equation",
+            Code::Unsolvable => "the equation cannot be solved for {0}",
+            Code::IndexOutOfRange => "nth expects an index up to {0}, got {1}",
+            Code::EmptyHead => "cannot take the head of an empty list",
+            Code::EmptyTail => "cannot take the tail of an empty list",
+            Code::TooLargeToComputeExactly => {
+                "{0}({1}) is too large to compute exactly"
+            }
+            Code::AssertNonZero => {
+                "assertion failed: expected a non-zero value, got {0}"
+            }
+            Code::AssertNotEqual => "assertion failed: {0} != {1}",
+            Code::AssertDiffer => {
+                "assertion failed: {0} and {1} differ by {2}, more than {3}"
+            }
+            Code::RangeNotFinite => "the bounds and step must be finite",
+            Code::RangeNeverReaches => {
+                "a step of {0} never reaches {1} from {2}"
+            }
+            Code::TooManyValues => "expected at most {0} values, got {1}",
+            Code::TooManyTokens => "expression has more than {0} tokens",
+            Code::NestedCalls => "functions are nested more than {0} deep",
+            Code::StepBudgetExceeded => {
+                "budget exceeded: took more than {0} steps"
+            }
+            Code::TimeBudgetExceeded => {
+                "budget exceeded: took longer than {0}ms"
+            }
+            Code::EnvNotAllowed => {
+                "reading environment variables is not allowed"
+            }
+            Code::EnvNotSet => "environment variable '{0}' is not set",
+            Code::EnvNotANumber => {
+                "expected environment variable '{0}' to be a number, got '{1}'"
+            }
+            Code::FilesNotAllowed => "reading files is not allowed",
+            Code::CouldNotRead => "could not read '{0}': {1}",
+            Code::StdinNotAllowed => "reading standard input is not allowed",
+            Code::CouldNotReadStdin => "could not read standard input: {0}",
+            Code::TooManyNumbers => "expected at most {0} numbers in {1}",
+            Code::ExpectedNumberOnLine => {
+                "expected a number on line {0} of {1}, got '{2}'"
+            }
+            Code::InvalidVariableName => "invalid variable name '{0}'",
+            Code::CannotAssign => "cannot assign to '{0}'",
+            Code::InvalidAliasName => "invalid alias name '{0}'",
+            Code::AliasAlreadyDefined => {
+                "cannot alias '{0}', which is already defined"
+            }
+            Code::AliasUnknown => {
+                "cannot alias unknown function or constant '{0}'"
+            }
+            Code::UnknownBase => {
+                "unknown base '{0}', expected one of dec, hex, oct or bin"
+            }
+            Code::UnsupportedWidth => {
+                "unsupported width {0}, expected one of 8, 16, 32 or 64"
+            }
+            Code::UnknownRoundingMode => {
+                "unknown rounding mode '{0}', expected one of half-up, \
+                half-even or toward-zero"
+            }
+            Code::PluginInvalidName => {
+                "plugin '{0}' provides invalid name '{1}'"
+            }
+            Code::PluginRedefines => "plugin '{0}' redefines '{1}'",
+            Code::ExpectedTemplate => {
+                "expected a template such as template area(r) := pi * r^2"
+            }
+            Code::ExpectedDefinition => "expected ':=' after the parameters",
+            Code::ExpectedTemplateHead => {
+                "expected the name of the template and its parameters, e.g. \
+                area(r)"
+            }
+            Code::ExpectedParameterNames => {
+                "expected the parameters of '{0}' to be names separated by ','"
+            }
+            Code::CannotRedefineBuiltin => "cannot redefine builtin '{0}'",
+            Code::InvalidTemplateName => "invalid template name '{0}'",
+            Code::InvalidParameterName => "invalid parameter name '{0}'",
+            Code::DuplicateParameter => {
+                "parameter '{0}' is named more than once"
+            }
+            Code::TemplateAlreadyDefined => {
+                "cannot define template '{0}', which is already defined"
+            }
+            Code::TemplateCallsItself => "template '{0}' calls itself",
+            Code::TemplateCallsItselfThrough => {
+                "template '{0}' calls itself through '{1}'"
+            }
+            Code::AlreadyImporting => {
+                "cannot import '{0}', which is already being imported"
+            }
+            Code::CannotDifferentiate => "cannot differentiate '{0}' exactly",
+            Code::AlgebraUnsupported => "the algebra does not support '{0}'",
+            Code::AlgebraString => {
+                "strings cannot be evaluated over an algebra"
+            }
+            Code::AlgebraList => "lists cannot be evaluated over an algebra",
+            Code::DoesNotParseBack => "'{0}' does not parse back: {1}",
+            Code::ParsesBackAsIdentifier => {
+                "'{0}' parses back as the identifier '{1}'"
+            }
+            Code::ParsesBackAs => "'{0}' parses back as '{1}'",
+            Code::NoValue => {
+                "'{0}' has no value, so it can only be used in sums, \
+                 products, exact quotients and whole powers"
+            }
+            Code::PowerTooLarge => {
+                "'{0}' has no value, so it can only be raised to powers from \
+                 -{1} to {1}, got {2}"
+            }
+            Code::EulerVariable => {
+                "'e' cannot be a variable without a value, since it is how \
+                 Euler's number is shown, which is written as exp(1)"
+            }
+            Code::PointsMismatched => {
+                "expected as many y values as x values, got {0} x values and \
+                 {1} y values"
+            }
+            Code::TooFewPoints => "expected at least {0} points, got {1}",
+            Code::SameX => {
+                "cannot fit a line to points which all have the same x value"
+            }
+            Code::TooFewDistinctX => {
+                "fitting a polynomial of degree {0} needs at least {1} \
+                 distinct x values, got {2}"
+            }
+            Code::NoEquations => "expected at least one equation",
+            Code::MatrixSize => {
+                "expected a matrix of {0} numbers for {1} equations, got {2}"
+            }
+            Code::NotFiniteEquations => {
+                "expected the matrix and right hand sides to be finite"
+            }
+            Code::SingularMatrix => {
+                "the matrix is singular, so the equations do not have a \
+                 unique solution"
+            }
+            Code::PrimepiTooLarge => {
+                "primepi only supports n up to {0}, got {1}"
+            }
+            Code::ZerothPrime => {
+                "there is no 0th prime, primes are counted from 1"
+            }
+            Code::PrimeTooLarge => {
+                "prime only supports primes up to {0}, prime({1}) is larger"
+            }
+            Code::ZeroPolynomial => "every number is a root of 0",
+            Code::RootsDidNotConverge => {
+                "the roots did not converge within {0} steps, so they cannot \
+                 be found precisely"
+            }
+            Code::UndefinedAt => "{0} is undefined at {1}",
+            Code::UnknownDomain => {
+                "unknown domain '{0}', expected one of float, int, rational, \
+                 complex or bigfloat"
+            }
+            Code::UnsupportedDomain => {
+                "the {0} domain is not supported yet, since there are no {0} \
+                 numbers to calculate with"
+            }
+            Code::StackUnderflow => {
+                "not enough values on the stack for '{0}', which takes {1}"
+            }
+            Code::NotInRpn => "'{0}' cannot be used in reverse Polish notation",
+            Code::IgnoredAtEnd => "ignored '{0}' at the end",
+            Code::ClosedLeftOpen => "closed '{0}' which was left open",
+            Code::IgnoredUnmatched => "ignored unmatched '{0}'",
+            Code::InexactInteger => {
+                "{0} is too large to be held exactly, so it is rounded to {1}"
+            }
+            Code::NearOverflow => {
+                "{0} is close to the largest number that can be held, so \
+                calculations with it may overflow"
+            }
+            Code::Deprecated => "'{0}' is deprecated, use '{1}' instead",
+        }
+    }
+
+    /// Create a message for `self` which mentions `args`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments of the message, which replace `{0}`, `{1}`
+    ///   and so on in its template.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::message::Code;
+    ///
+    /// let message = Code::WrongArity.with(&[&"sin", &"1 argument", &2]);
+    /// assert_eq!("sin expects 1 argument, got 2", message.to_string());
+    /// ```
+    pub fn with(self, args: &[&dyn fmt::Display]) -> Message {
+        Message {
+            code: self,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            hint: None,
+        }
+    }
+}
+
+/// The message of an error, which can be formatted in any locale.
+///
+/// Formatting a message gives it in English.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// What went wrong.
+    pub code: Code,
+    /// The names, numbers and so on which the message mentions, in the order
+    /// of their placeholders.
+    pub args: Vec<String>,
+    /// A suggestion of how to fix what went wrong, which is added to the end
+    /// of the message.
+    pub hint: Option<Box<Message>>,
+}
+
+impl Message {
+    /// Create a message for `code` which does not mention anything.
+    pub fn new(code: Code) -> Self {
+        code.with(&[])
+    }
+
+    /// Add the hint `hint` to `self`, if there is one.
+    pub fn with_hint(mut self, hint: Option<Message>) -> Self {
+        self.hint = hint.map(Box::new);
+        self
+    }
+
+    /// Format `self` in `locale`, falling back to English for messages which
+    /// `locale` does not translate.
+    ///
+    /// # Arguments
+    ///
+    /// * `locale` - The locale to format the message in.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::message::{Catalog, Code};
+    ///
+    /// let mut french = Catalog::new();
+    /// french.insert(Code::UnknownIdentifier, "identifiant inconnu « {0} »");
+    ///
+    /// let message = Code::UnknownIdentifier.with(&[&"y"]);
+    /// assert_eq!("identifiant inconnu « y »", message.localize(&french));
+    /// assert_eq!("unknown identifier 'y'", message.to_string());
+    /// ```
+    pub fn localize(&self, locale: &dyn Locale) -> String {
+        let template = locale
+            .template(self.code)
+            .unwrap_or_else(|| self.code.english());
+        let message = fill(template, &self.args);
+
+        match &self.hint {
+            Some(hint) => format!("{}, {}", message, hint.localize(locale)),
+            None => message,
+        }
+    }
+
+    /// Suggest that the input may have meant one of `names`, or `None` if
+    /// there are no names to suggest.
+    pub(crate) fn did_you_mean(names: &[&str]) -> Option<Self> {
+        let quoted: Vec<String> =
+            names.iter().map(|name| format!("'{}'", name)).collect();
+
+        match quoted.as_slice() {
+            [] => None,
+            [only] => Some(Code::DidYouMean.with(&[only])),
+            [rest @ .., last] => {
+                Some(Code::DidYouMeanOneOf.with(&[&rest.join(", "), last]))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.localize(&English))
+    }
+}
+
+impl error::Error for Message {}
+
+impl From<Code> for Message {
+    fn from(code: Code) -> Self {
+        Message::new(code)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(message: &str) -> Self {
+        Code::Other.with(&[&message])
+    }
+}
+
+impl From<String> for Message {
+    fn from(message: String) -> Self {
+        Code::Other.with(&[&message])
+    }
+}
+
+/// A language that messages can be formatted in.
+pub trait Locale {
+    /// Get the template of the message for `code` in this locale, in which
+    /// `{0}`, `{1}` and so on are replaced by the arguments of the message,
+    /// or `None` if it is not translated.
+    fn template(&self, code: Code) -> Option<&str>;
+}
+
+/// The locale that messages are written in, which translates every code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct English;
+
+impl Locale for English {
+    fn template(&self, code: Code) -> Option<&str> {
+        Some(code.english())
+    }
+}
+
+/// A locale made up of templates for some codes, such as a translation
+/// loaded by a frontend.
+///
+/// # Examples
+/// ```
+/// use cocoa::{
+///     evaluator::Evaluator,
+///     message::{localize, Catalog, Code},
+/// };
+///
+/// let mut german = Catalog::new();
+/// german.insert(Code::UnknownIdentifier, "unbekannter Bezeichner '{0}'");
+/// german.insert(Code::DidYouMean, "meinten Sie {0}?");
+///
+/// let e = Evaluator::new().eval("pii + 1").unwrap_err();
+///
+/// assert_eq!(
+///     "unbekannter Bezeichner 'pii', meinten Sie 'pi'?",
+///     localize(&e, &german)
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    templates: HashMap<Code, String>,
+}
+
+impl Catalog {
+    /// Create a catalog which does not translate anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Translate the message for `code` to `template`, returning the template
+    /// it replaces, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code of the message.
+    /// * `template` - The translated message, in which `{0}`, `{1}` and so on
+    ///   are replaced by the arguments of the message.
+    pub fn insert(
+        &mut self,
+        code: Code,
+        template: impl Into<String>,
+    ) -> Option<String> {
+        self.templates.insert(code, template.into())
+    }
+}
+
+impl Locale for Catalog {
+    fn template(&self, code: Code) -> Option<&str> {
+        self.templates.get(&code).map(String::as_str)
+    }
+}
+
+/// Get the message of the error `e`, if it has one.
+///
+/// # Examples
+/// ```
+/// use cocoa::{evaluator::Evaluator, message::{message, Code}};
+///
+/// let e = Evaluator::new().eval("(1 + 2").unwrap_err();
+/// assert_eq!(Some(Code::UnmatchedDelimiter), message(&e).map(|m| m.code));
+/// ```
+///
+/// Every error of a builtin function has a code of its own, rather than
+/// falling back to `Code::Other`.
+/// ```
+/// use cocoa::{
+///     evaluator::Evaluator,
+///     message::{message, Code},
+///     token::FuncKind,
+/// };
+///
+/// let arguments = [
+///     "", "0", "-1", "100000000000", "[]", "[0]", "[1]", "[], []",
+///     "[1], [1]", "[1, 1], [1, 2]", "0, 0", "1, 2, 3", "0, 0, 0, 0",
+/// ];
+/// for f in FuncKind::all() {
+///     for arguments in arguments {
+///         let input = format!("{}({})", f.name(), arguments);
+///         if let Err(e) = Evaluator::new().eval(&input) {
+///             let code = message(&e).map(|m| m.code);
+///             assert!(code.is_some_and(|c| c != Code::Other), "{}", input);
+///         }
+///     }
+/// }
+/// ```
+pub fn message(e: &Error) -> Option<&Message> {
+    if let Some(e) = e.downcast_ref::<SpanError>() {
+        return Some(&e.message);
+    }
+    if let Some(e) = e.downcast_ref::<ScriptError>() {
+        return Some(&e.message);
+    }
+
+    e.downcast_ref::<Message>()
+}
+
+/// Format the error `e` in `locale`, falling back to English for messages
+/// which `locale` does not translate, and for errors which do not come from
+/// cocoa.
+///
+/// # Arguments
+///
+/// * `e` - The error to format.
+/// * `locale` - The locale to format it in.
+pub fn localize(e: &Error, locale: &dyn Locale) -> String {
+    if let Some(e) = e.downcast_ref::<ScriptError>() {
+        return e.localize(locale);
+    }
+
+    match message(e) {
+        Some(message) => message.localize(locale),
+        None => e.to_string(),
+    }
+}
+
+/// Turn the error `e` into a message, keeping its message if it has one.
+pub(crate) fn into_message(e: Error) -> Message {
+    message(&e).cloned().unwrap_or_else(|| e.to_string().into())
+}
+
+/// Format the number `n` to be mentioned in a message, in scientific notation
+/// if it is too large or small to write out in full, so that a message about
+/// a huge number is not hundreds of digits long.
+pub(crate) fn number(n: f64) -> String {
+    let magnitude = n.abs();
+
+    if magnitude.is_finite()
+        && magnitude != 0.0
+        && !(1e-6..1e16).contains(&magnitude)
+    {
+        format!("{:e}", n)
+    } else {
+        n.to_string()
+    }
+}
+
+/// Replace `{0}`, `{1}` and so on in `template` with the argument at that
+/// index in `args`, leaving any placeholder without an argument as it is.
+fn fill(template: &str, args: &[String]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let arg = rest.find('}').and_then(|close| {
+            let i: usize = rest[1..close].parse().ok()?;
+            Some((args.get(i)?, close))
+        });
+
+        match arg {
+            Some((arg, close)) => {
+                filled.push_str(arg);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    filled.push_str(rest);
+    filled
+}
@@ -0,0 +1,263 @@
+//! A compact representation of a stream of tokens, for very large
+//! expressions where a vector of `Token`s would take several times the memory
+//! of the input.
+
+use anyhow::Result;
+
+use crate::{
+    lexer::lex_each,
+    token::{FuncKind, OpKind, Span, Token},
+};
+
+// the tag of each kind of token, where operators take one tag each
+const NUMBER: u8 = 0;
+const IDENT: u8 = 1;
+const STR: u8 = 2;
+const FUNC: u8 = 3;
+const LPAREN: u8 = 4;
+const RPAREN: u8 = 5;
+const LBRACKET: u8 = 6;
+const RBRACKET: u8 = 7;
+const COMMA: u8 = 8;
+const OP: u8 = 16;
+
+/// Every operator, in the order of their tags after `OP`.
+const OPS: [OpKind; 10] = [
+    OpKind::Plus,
+    OpKind::Minus,
+    OpKind::Star,
+    OpKind::Slash,
+    OpKind::Modulo,
+    OpKind::Caret,
+    OpKind::Factorial,
+    OpKind::Shl,
+    OpKind::Shr,
+    OpKind::Equals,
+];
+
+/// Tokens stored as one byte tags, with the numbers, functions and names they
+/// carry kept in separate tables, along with the span each was lexed from.
+///
+/// A token takes 9 bytes plus the size of what it carries, rather than the
+/// 48 bytes of a `(Token, Span)`, and tokens are stored next to each other
+/// rather than scattered around the heap. Iterating yields `(Token, Span)`
+/// pairs, so packed tokens can be parsed like the output of `lex_spanned`,
+/// though names are allocated as they are yielded.
+///
+/// # Examples
+/// ```
+/// use cocoa::{packed::PackedTokens, token::{OpKind, Span, Token}};
+///
+/// let tokens = PackedTokens::lex("x * 2").unwrap();
+///
+/// assert_eq!(3, tokens.len());
+/// assert_eq!(
+///     vec![
+///         (Token::Ident("x".to_string()), Span::new(0, 1)),
+///         (Token::Op(OpKind::Star), Span::new(2, 3)),
+///         (Token::Number(2.0), Span::new(4, 5)),
+///     ],
+///     tokens.iter().collect::<Vec<_>>()
+/// );
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct PackedTokens {
+    tags: Vec<u8>,
+    /// The start and end of the span of each token.
+    spans: Vec<[u32; 2]>,
+    numbers: Vec<f64>,
+    funcs: Vec<FuncKind>,
+    /// The start and end in `text` of each name and string.
+    names: Vec<[u32; 2]>,
+    text: String,
+}
+
+impl PackedTokens {
+    /// Create an empty stream of tokens.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lex `input` into a packed stream of tokens, failing with the same
+    /// errors as `lex_spanned`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text to lex.
+    pub fn lex(input: &str) -> Result<Self> {
+        let mut tokens = Self::new();
+        tokens.relex(input)?;
+
+        Ok(tokens)
+    }
+
+    /// Replace the tokens in `self` with those lexed from `input`, reusing
+    /// the memory already allocated for them.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text to lex.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::packed::PackedTokens;
+    ///
+    /// let mut tokens = PackedTokens::lex("sqrt(2)").unwrap();
+    /// tokens.relex("[1, 2]").unwrap();
+    ///
+    /// assert_eq!(5, tokens.len());
+    /// ```
+    pub fn relex(&mut self, input: &str) -> Result<()> {
+        self.clear();
+
+        lex_each(&mut input.chars().peekable(), |token, span| {
+            self.push(token, span)
+        })
+    }
+
+    /// Add `token`, which was lexed from `span`, to the end of the stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the end of the span does not fit in 32 bits.
+    pub fn push(&mut self, token: Token, span: Span) {
+        let tag = match token {
+            Token::Number(n) => {
+                self.numbers.push(n);
+                NUMBER
+            }
+            Token::Ident(name) => {
+                self.push_text(&name);
+                IDENT
+            }
+            Token::Str(s) => {
+                self.push_text(&s);
+                STR
+            }
+            Token::Func(f) => {
+                self.funcs.push(f);
+                FUNC
+            }
+            Token::LParen => LPAREN,
+            Token::RParen => RPAREN,
+            Token::LBracket => LBRACKET,
+            Token::RBracket => RBRACKET,
+            Token::Comma => COMMA,
+            Token::Op(op) => {
+                let i = OPS.iter().position(|&o| o == op);
+                OP + i.expect("every operator has a tag") as u8
+            }
+        };
+
+        self.tags.push(tag);
+        self.spans.push([offset(span.start), offset(span.end)]);
+    }
+
+    /// Remove every token, keeping the memory allocated for them.
+    pub fn clear(&mut self) {
+        self.tags.clear();
+        self.spans.clear();
+        self.numbers.clear();
+        self.funcs.clear();
+        self.names.clear();
+        self.text.clear();
+    }
+
+    /// Get the number of tokens in the stream.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Check whether the stream has no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Iterate over the tokens in the stream along with their spans.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            tokens: self,
+            next: 0,
+            number: 0,
+            func: 0,
+            name: 0,
+        }
+    }
+
+    fn push_text(&mut self, s: &str) {
+        let start = offset(self.text.len());
+        self.text.push_str(s);
+        self.names.push([start, offset(self.text.len())]);
+    }
+}
+
+impl<'a> IntoIterator for &'a PackedTokens {
+    type Item = (Token, Span);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the tokens of a `PackedTokens` and their spans.
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    tokens: &'a PackedTokens,
+    /// The index of the next token, and of the next entry in each table.
+    next: usize,
+    number: usize,
+    func: usize,
+    name: usize,
+}
+
+impl Iter<'_> {
+    fn text(&mut self) -> String {
+        let [start, end] = self.tokens.names[self.name];
+        self.name += 1;
+
+        self.tokens.text[start as usize..end as usize].to_string()
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<(Token, Span)> {
+        let tag = *self.tokens.tags.get(self.next)?;
+        let [start, end] = self.tokens.spans[self.next];
+        self.next += 1;
+
+        let token = match tag {
+            NUMBER => {
+                self.number += 1;
+                Token::Number(self.tokens.numbers[self.number - 1])
+            }
+            IDENT => Token::Ident(self.text()),
+            STR => Token::Str(self.text()),
+            FUNC => {
+                self.func += 1;
+                Token::Func(self.tokens.funcs[self.func - 1])
+            }
+            LPAREN => Token::LParen,
+            RPAREN => Token::RParen,
+            LBRACKET => Token::LBracket,
+            RBRACKET => Token::RBracket,
+            COMMA => Token::Comma,
+            op => Token::Op(OPS[(op - OP) as usize]),
+        };
+
+        Some((token, Span::new(start as usize, end as usize)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.tokens.len() - self.next;
+
+        (left, Some(left))
+    }
+}
+
+/// Convert an offset into the input or text to the width it is stored with.
+fn offset(i: usize) -> u32 {
+    u32::try_from(i).expect("packed tokens are indexed with 32 bits")
+}
@@ -1,16 +1,49 @@
 //! Functions that convert a stream of tokens that are generated by the lexical
-//! analyser into an output.
+//! analyser into an expression tree, or into the value of that tree.
 
 use std::iter::Peekable;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Error, Result};
 
 use crate::{
-    math::ufactorial,
-    token::{Bindable, OpKind, Token},
+    arena::{Children, ExprArena, NodeCallee, NodeId, NodeKind},
+    ast::Expr,
+    error::{spanned, SpanError},
+    evaluator::Evaluator,
+    interpreter::Interpreter,
+    message::{Code, Message},
+    token::{Bindable, OpKind, Span, Spanned, Token},
+    value::Value,
 };
 
-/// A parser which turns an iterator over `Token`s into an output.
+/// The most levels deep the parser descends into an expression, so that
+/// deeply nested input such as `((((1))))` with thousands of brackets is an
+/// error rather than overflowing the stack.
+///
+/// # Examples
+/// ```
+/// use cocoa::{lexer::lex, parser::{parse, MAX_DEPTH}};
+///
+/// let nested = |depth| {
+///     let input = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+///     let tokens = lex(&mut input.chars().peekable()).unwrap();
+///     parse(&mut tokens.into_iter().peekable(), 0)
+/// };
+///
+/// assert!(nested(MAX_DEPTH - 1).is_ok());
+/// assert_eq!(
+///     format!("expression is nested more than {} levels deep", MAX_DEPTH),
+///     nested(5000).unwrap_err().to_string()
+/// );
+///
+/// let negated = format!("{}1", "-".repeat(5000));
+/// let tokens = lex(&mut negated.chars().peekable()).unwrap();
+/// assert!(parse(&mut tokens.into_iter().peekable(), 0).is_err());
+/// ```
+pub const MAX_DEPTH: usize = 256;
+
+/// A parser which turns an iterator over `Token`s into an expression tree,
+/// which `eval` evaluates.
 ///
 /// The parser is an implementation of the Pratt parsing algorithm, all
 /// operators have a binding power, and the binding power of an operator
@@ -23,11 +56,11 @@ use crate::{
 ///
 /// * `tokens` - A peekable iterator over some tokens.
 /// * `bp` - The minimum binding power the next operator should have in order
-///          to be evaluated.
+///   to be part of the expression.
 ///
 /// # Examples
 /// ```
-/// use cocoa::{token::{Token, OpKind}, parser::parse};
+/// use cocoa::{token::{Token, OpKind}, parser::{eval, parse}};
 ///
 /// let mut tokens = vec![
 ///     Token::Number(2.0),
@@ -37,116 +70,464 @@ use crate::{
 ///
 /// // the binding power initially is always 0 so that the first operator
 /// // in the expression is not skipped over
-/// assert_eq!(4.0, parse(&mut tokens, 0).unwrap());
+/// let expr = parse(&mut tokens, 0).unwrap();
+/// assert_eq!("2 + 2", expr.to_string());
+/// assert_eq!(4.0, eval(&expr).unwrap());
 /// ```
-pub fn parse<I: Iterator<Item = Token>>(
+pub fn parse<T: Spanned, I: Iterator<Item = T>>(
+    tokens: &mut Peekable<I>,
+    bp: u8,
+) -> Result<Expr> {
+    parse_expr(tokens, bp)
+}
+
+/// Evaluate the expression tree `expr` to a number, with an evaluator made
+/// by `Evaluator::new`.
+///
+/// See `Evaluator::eval_expr` for evaluating it with other settings,
+/// variables or functions.
+///
+/// # Arguments
+///
+/// * `expr` - The expression to evaluate.
+///
+/// # Examples
+/// ```
+/// use cocoa::{lexer::lex, parser::{eval, parse}};
+///
+/// let tokens = lex(&mut "2 ^ 3 ^ 2".chars().peekable()).unwrap();
+/// let expr = parse(&mut tokens.into_iter().peekable(), 0).unwrap();
+///
+/// assert_eq!(512.0, eval(&expr).unwrap());
+/// ```
+pub fn eval(expr: &Expr) -> Result<f64> {
+    let evaluator = Evaluator::new();
+    let value = evaluator.eval_expr(expr)?;
+    evaluator.check_range(&value)?;
+
+    value.into_number()
+}
+
+/// Parse `tokens` and evaluate them to a number, which is what `parse` did
+/// before it returned the tree.
+///
+/// # Arguments
+///
+/// * `tokens` - A peekable iterator over some tokens.
+/// * `bp` - The minimum binding power the next operator should have in order
+///   to be evaluated.
+#[deprecated(
+    since = "0.1.0",
+    note = "use `parse` to get the tree and `eval` to evaluate it"
+)]
+pub fn parse_and_eval<T: Spanned, I: Iterator<Item = T>>(
     tokens: &mut Peekable<I>,
     bp: u8,
 ) -> Result<f64> {
-    let mut lhs = match tokens.next() {
-        Some(t) => match t {
-            Token::Number(n) => n,
-            Token::Func(f) => {
-                // not the best but it gets the job done
-                if tokens.next() != Some(Token::LParen) {
-                    bail!("expected '(' after token '{:?}'", f)
+    parse_with(&Evaluator::new(), tokens, bp)
+}
+
+/// A parser which turns an iterator over `Token`s into an output, resolving
+/// any identifiers that are not builtins using `evaluator`.
+///
+/// See `parse` for details on how the tokens are parsed. If the tokens come
+/// with spans, e.g. from `lex_spanned`, some errors are `SpanError`s pointing
+/// at the part of the input that caused them.
+///
+/// # Arguments
+///
+/// * `evaluator` - The environment used to look up identifiers.
+/// * `tokens` - A peekable iterator over some tokens.
+/// * `bp` - The minimum binding power the next operator should have in order
+///   to be evaluated.
+///
+/// # Examples
+/// ```
+/// use cocoa::{evaluator::Evaluator, lexer::lex_spanned, parser::parse_with};
+///
+/// let evaluator = Evaluator::new();
+/// let error = |input: &str| {
+///     let tokens = lex_spanned(&mut input.chars().peekable()).unwrap();
+///     parse_with(&evaluator, &mut tokens.into_iter().peekable(), 0)
+///         .unwrap_err()
+///         .to_string()
+/// };
+///
+/// assert_eq!("unexpected token '2'", error("1 2"));
+/// assert_eq!("unexpected token ')'", error("1 + )"));
+/// ```
+pub fn parse_with<T: Spanned, I: Iterator<Item = T>>(
+    evaluator: &Evaluator,
+    tokens: &mut Peekable<I>,
+    bp: u8,
+) -> Result<f64> {
+    let value = parse_value_with(evaluator, tokens, bp)?;
+    evaluator.check_range(&value)?;
+
+    value.into_number()
+}
+
+/// A parser which turns an iterator over `Token`s into a value, which unlike
+/// `parse_with` may be a list as well as a number.
+///
+/// # Arguments
+///
+/// * `evaluator` - The environment used to look up identifiers.
+/// * `tokens` - A peekable iterator over some tokens.
+/// * `bp` - The minimum binding power the next operator should have in order
+///   to be evaluated.
+///
+/// # Examples
+/// ```
+/// use cocoa::{evaluator::Evaluator, lexer::lex, parser::parse_value_with, value::Value};
+///
+/// let tokens = lex(&mut "sort([3, 1, 2])".chars().peekable()).unwrap();
+/// let value =
+///     parse_value_with(&Evaluator::new(), &mut tokens.into_iter().peekable(), 0);
+///
+/// assert_eq!(Value::List(vec![1.0, 2.0, 3.0]), value.unwrap());
+///
+/// // long chains of operators do not overflow the stack
+/// let sum = vec!["1"; 100_000].join(" + ");
+/// let tokens = lex(&mut sum.chars().peekable()).unwrap();
+/// let value =
+///     parse_value_with(&Evaluator::new(), &mut tokens.into_iter().peekable(), 0);
+///
+/// assert_eq!(Value::Number(100_000.0), value.unwrap());
+/// ```
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn parse_value_with<T: Spanned, I: Iterator<Item = T>>(
+    evaluator: &Evaluator,
+    tokens: &mut Peekable<I>,
+    bp: u8,
+) -> Result<Value> {
+    let expr = parse_expr(tokens, bp)?;
+
+    evaluator.start_metering();
+    let value = Interpreter::new(evaluator).eval(&expr);
+    expr.dismantle();
+
+    value
+}
+
+/// A parser which turns an iterator over `Token`s into an expression tree
+/// rather than evaluating it.
+///
+/// Expressions are parsed with the same precedence as `parse`, and each node
+/// of the tree has the span of the tokens it was parsed from, if the tokens
+/// come with spans. Expressions nested more than `MAX_DEPTH` levels deep are
+/// an error.
+///
+/// # Arguments
+///
+/// * `tokens` - A peekable iterator over some tokens.
+/// * `bp` - The minimum binding power the next operator should have in order
+///   to be part of the expression.
+///
+/// # Examples
+/// ```
+/// use cocoa::{lexer::lex, parser::parse_expr};
+///
+/// let tokens = lex(&mut "2 + 3 * 4".chars().peekable()).unwrap();
+/// let expr = parse_expr(&mut tokens.into_iter().peekable(), 0).unwrap();
+///
+/// assert_eq!("2 + 3 * 4", expr.to_string());
+/// ```
+pub fn parse_expr<T: Spanned, I: Iterator<Item = T>>(
+    tokens: &mut Peekable<I>,
+    bp: u8,
+) -> Result<Expr> {
+    let mut arena = ExprArena::new();
+    let root = parse_into(&mut arena, tokens, bp)?;
+
+    Ok(arena.to_expr(root))
+}
+
+/// A parser which turns an iterator over `Token`s into a tree of nodes in
+/// `arena`, returning the root of the tree.
+///
+/// Expressions are parsed the same way as `parse_expr`, but without
+/// allocating for each node of the tree.
+///
+/// # Arguments
+///
+/// * `arena` - The arena to add the nodes of the tree to.
+/// * `tokens` - A peekable iterator over some tokens.
+/// * `bp` - The minimum binding power the next operator should have in order
+///   to be part of the expression.
+///
+/// # Examples
+/// ```
+/// use cocoa::{arena::ExprArena, lexer::lex, parser::parse_into};
+///
+/// let mut arena = ExprArena::new();
+/// let tokens = lex(&mut "2 + 3 * 4".chars().peekable()).unwrap();
+/// let root = parse_into(&mut arena, &mut tokens.into_iter().peekable(), 0).unwrap();
+///
+/// assert_eq!(5, arena.len());
+/// assert_eq!("2 + 3 * 4", arena.to_expr(root).to_string());
+/// ```
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn parse_into<T: Spanned, I: Iterator<Item = T>>(
+    arena: &mut ExprArena,
+    tokens: &mut Peekable<I>,
+    bp: u8,
+) -> Result<NodeId> {
+    let mut parser = TreeParser {
+        arena,
+        tokens,
+        last: None,
+        depth: 0,
+        limit: MAX_DEPTH,
+    };
+
+    parser.expr(bp)
+}
+
+/// Parse `tokens` into a tree in `arena` like `parse_into`, but without
+/// descending more than `limit` levels into it, or `MAX_DEPTH` if that is
+/// fewer.
+///
+/// This fails if the tree is deeper than `limit`, though it may be deeper
+/// when this succeeds since not every level of a tree is parsed by
+/// descending into it. The root of the tree is `None` if the tokens could not
+/// be parsed for any other reason.
+pub(crate) fn parse_limited<T: Spanned, I: Iterator<Item = T>>(
+    arena: &mut ExprArena,
+    tokens: &mut Peekable<I>,
+    limit: usize,
+) -> Result<Option<NodeId>> {
+    let limit = limit.min(MAX_DEPTH);
+    let mut parser = TreeParser {
+        arena,
+        tokens,
+        last: None,
+        depth: 0,
+        limit,
+    };
+
+    match parser.expr(0) {
+        Ok(root) => Ok(Some(root)),
+        Err(e) if parser.depth > limit => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The error for an expression nested more than `limit` levels deep, which
+/// points at where the nesting went too deep.
+pub(crate) fn too_deep(span: Option<Span>, limit: usize) -> Error {
+    spanned(span, Code::NestedTooDeep.with(&[&limit]))
+}
+
+/// The state of a single parse into a tree, which keeps track of the span of
+/// the last token consumed so that the span of each node is known.
+struct TreeParser<'a, I: Iterator> {
+    arena: &'a mut ExprArena,
+    tokens: &'a mut Peekable<I>,
+    last: Option<Span>,
+    /// How many levels of the tree are being parsed.
+    depth: usize,
+    /// The most levels deep the parser may descend.
+    limit: usize,
+}
+
+impl<'a, T: Spanned + 'a, I: Iterator<Item = T>> TreeParser<'a, I> {
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.next()?;
+        self.last = t.span();
+
+        Some(t.into_token())
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek().map(Spanned::token)
+    }
+
+    /// Get the span from the start of `start` up to the end of the last token
+    /// consumed.
+    fn since(&self, start: Option<Span>) -> Option<Span> {
+        Some(start?.to(self.last?))
+    }
+
+    fn expr(&mut self, bp: u8) -> Result<NodeId> {
+        let start = self.tokens.peek().and_then(Spanned::span);
+
+        // each level parsed is a level deeper in the tree
+        self.depth += 1;
+        if self.depth > self.limit {
+            return Err(too_deep(start, self.limit));
+        }
+
+        let root = self.node(start, bp)?;
+        self.depth -= 1;
+
+        Ok(root)
+    }
+
+    /// Parse an expression starting at `start`, returning its root.
+    fn node(&mut self, start: Option<Span>, bp: u8) -> Result<NodeId> {
+        let kind = match self.next() {
+            Some(t) => match t {
+                Token::Number(n) => NodeKind::Number(n),
+                Token::Str(s) => NodeKind::Str(self.arena.push_text(&s)),
+                Token::Func(f) => {
+                    if self.next() != Some(Token::LParen) {
+                        return Err(spanned(
+                            self.since(start),
+                            Code::ExpectedParen.with(&[&f.name()]),
+                        ));
+                    }
+
+                    NodeKind::Call(NodeCallee::Builtin(f), self.args()?)
                 }
+                Token::Ident(name) => {
+                    let name = self.arena.push_text(&name);
 
-                let rhs = parse(tokens, f.bp())?;
-                f.eval(rhs)
-            }
-            // unary plus and minus
-            Token::Op(o) => match o {
-                OpKind::Plus | OpKind::Minus => {
+                    if self.peek() == Some(&Token::LParen) {
+                        self.next();
+                        NodeKind::Call(NodeCallee::Named(name), self.args()?)
+                    } else {
+                        NodeKind::Ident(name)
+                    }
+                }
+                // unary plus and minus
+                Token::Op(o @ (OpKind::Plus | OpKind::Minus)) => {
                     // the binding power of unary plus/minus is 15 more than
                     // their infix binding power
-                    let rhs = parse(tokens, o.bp() + 15)?;
-                    match o {
-                        OpKind::Plus => rhs,
-                        OpKind::Minus => -rhs,
-                        _ => unreachable!(),
+                    NodeKind::Unary(o, self.expr(o.bp() + 15)?)
+                }
+                Token::Op(o) => {
+                    return Err(spanned(
+                        start,
+                        Code::UnexpectedOperator.with(&[&format!("{:?}", o)]),
+                    ))
+                }
+                Token::LParen => {
+                    let inner = self.expr(0)?;
+
+                    // point at the bracket that was left open
+                    if self.next() != Some(Token::RParen) {
+                        return Err(spanned(
+                            start,
+                            Code::UnmatchedDelimiter.with(&[&'(']),
+                        ));
                     }
+
+                    NodeKind::Group(inner)
+                }
+                Token::LBracket => NodeKind::List(self.list()?),
+                _ => {
+                    return Err(spanned(
+                        start,
+                        Code::UnexpectedToken.with(&[&t]),
+                    ))
                 }
-                _ => bail!("unexpected operator token '{:?}'", o),
             },
-            Token::LParen => {
-                let lhs = parse(tokens, 0)?;
+            None => bail!(Message::new(Code::UnexpectedEndOfStatement)),
+        };
+        let mut lhs = self.arena.push(kind, self.since(start));
 
-                let next = tokens.next();
-                if next != Some(Token::RParen) {
-                    bail!("unmatched delimeter '('")
+        loop {
+            let span = self.tokens.peek().and_then(Spanned::span);
+            let op = match self.peek() {
+                Some(&Token::Op(o)) => o,
+                Some(Token::RParen | Token::RBracket | Token::Comma) | None => {
+                    break
+                }
+                Some(t) => {
+                    return Err(spanned(span, Code::UnexpectedToken.with(&[t])))
                 }
+            };
 
-                lhs
+            if op.bp() <= bp {
+                break;
             }
-            _ => bail!("unexpected token {:?}", t),
-        },
-        None => bail!("unexpected end of statement"),
-    };
 
-    loop {
-        let &op = match tokens.peek() {
-            Some(Token::Op(o)) => o,
-            // an issue with this approach is that expressions such as
-            // `(2 + 3)))) * 4` are valid as the extra RParens are simply consumed
-            //
-            // the ideal solution is a stack to keep track of delimeters
-            Some(Token::RParen) => break,
-            None => break,
-            _ => bail!("unexpected token '{:?}'", tokens.peek()),
-        };
+            self.next();
 
-        // postfix operators such as factorial need to be handled differently
-        match op {
-            OpKind::Factorial => {
-                if op.bp() <= bp {
-                    break;
+            let kind = match op {
+                OpKind::Factorial => NodeKind::Postfix(op, lhs),
+                // caret is right associative, so its right hand side is
+                // parsed with a lower binding power to take in any carets
+                // after it, e.g. `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`
+                OpKind::Caret => {
+                    NodeKind::Binary(op, lhs, self.expr(op.bp() - 1)?)
                 }
+                _ => NodeKind::Binary(op, lhs, self.expr(op.bp())?),
+            };
+            lhs = self.arena.push(kind, self.since(start));
+        }
 
-                tokens.next();
+        Ok(lhs)
+    }
 
-                if lhs.is_sign_negative() {
-                    bail!("cannot calculate factorial of negative numbers")
-                } else if lhs.fract() != 0.0 {
-                    bail!("cannot calculate factorial of non integers")
-                } else {
-                    // casting is safe since lhs is clearly positive and has no
-                    // fractional part if this bit of code is executed
-                    lhs = ufactorial(lhs as u64) as f64;
-                }
+    /// Parse the comma separated elements of a list up to and including the
+    /// closing bracket, the opening bracket having already been consumed.
+    fn list(&mut self) -> Result<Children> {
+        let mark = self.arena.pending();
+
+        if self.peek() == Some(&Token::RBracket) {
+            self.next();
+            return Ok(self.arena.finish_children(mark));
+        }
 
-                continue;
+        loop {
+            let element = self.expr(0)?;
+            self.arena.push_pending(element);
+
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RBracket) => break,
+                _ => bail!(Code::UnmatchedDelimiter.with(&[&'['])),
             }
-            _ => (),
-        };
+        }
+
+        Ok(self.arena.finish_children(mark))
+    }
 
-        if op.bp() <= bp {
-            break;
+    /// Parse the comma separated arguments of a function call up to and
+    /// including the closing bracket, the opening bracket having already been
+    /// consumed.
+    fn args(&mut self) -> Result<Children> {
+        let mark = self.arena.pending();
+
+        if self.peek() == Some(&Token::RParen) {
+            self.next();
+            return Ok(self.arena.finish_children(mark));
         }
 
-        tokens.next();
+        loop {
+            let arg = self.expr(0)?;
+            self.arena.push_pending(arg);
 
-        let rhs = match op {
-            // caret is right associative, 2^3^4 should be parsed as 2^(3^4)
-            //
-            // the effective binding power of right associative operators is
-            // reduced by one so that the loop does not break if the next
-            // operator is also the same right associative operator
-            OpKind::Caret => parse(tokens, op.bp() - 1)?,
-            _ => parse(tokens, op.bp())?,
-        };
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RParen) => break,
+                _ => bail!(Code::UnmatchedDelimiter.with(&[&'('])),
+            }
+        }
 
-        lhs = match op {
-            OpKind::Plus => lhs + rhs,
-            OpKind::Minus => lhs - rhs,
-            OpKind::Star => lhs * rhs,
-            OpKind::Slash => lhs / rhs,
-            OpKind::Modulo => lhs.rem_euclid(rhs),
-            OpKind::Caret => lhs.powf(rhs),
-            // factorial is handled in the postfix operator implementation
-            _ => unreachable!(),
-        };
+        Ok(self.arena.finish_children(mark))
     }
+}
 
-    Ok(lhs)
+/// Check that every token has been consumed, reporting the first leftover
+/// token as an error otherwise.
+pub(crate) fn expect_end<I: Iterator<Item = (Token, Span)>>(
+    tokens: &mut I,
+) -> Result<()> {
+    match tokens.next() {
+        Some((Token::RParen, span)) => {
+            Err(SpanError::new(span, Code::UnmatchedDelimiter.with(&[&')']))
+                .into())
+        }
+        Some((Token::RBracket, span)) => {
+            Err(SpanError::new(span, Code::UnmatchedDelimiter.with(&[&']']))
+                .into())
+        }
+        Some((t, span)) => {
+            Err(SpanError::new(span, Code::UnexpectedToken.with(&[&t])).into())
+        }
+        None => Ok(()),
+    }
 }
@@ -0,0 +1,356 @@
+//! Parsing of expressions which are still being written, such as `sin(2 +`,
+//! to find what could come next in them, e.g. to complete them in an editor.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::{
+    ast::Expr,
+    error::Diagnostic,
+    lexer::lex_spanned,
+    message::Code,
+    parser::{expect_end, parse_expr},
+    token::{OpKind, Span, Token},
+};
+
+/// Something that could come next in an unfinished expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expected {
+    /// A number.
+    Number,
+    /// A builtin function, such as `sin`.
+    Function,
+    /// A name that is not builtin, such as a variable or constant.
+    Name,
+    /// A string in double quotes, for the functions that take one.
+    String,
+    /// A unary plus or minus.
+    Sign,
+    /// An infix or postfix operator.
+    Operator,
+    /// A left bracket, opening a group or the arguments of a call.
+    LParen,
+    /// A left square bracket, opening a list.
+    LBracket,
+    /// A comma, separating arguments or the elements of a list.
+    Comma,
+    /// A right bracket, closing a group or the arguments of a call.
+    RParen,
+    /// A right square bracket, closing a list.
+    RBracket,
+    /// The end of the expression.
+    End,
+}
+
+/// The result of parsing an expression which may be unfinished.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partial {
+    /// The expression parsed from as much of the input as is finished,
+    /// leaving out any operator or comma at the end which is missing an
+    /// operand and closing any brackets left open. This is `None` if there
+    /// is nothing finished to parse.
+    pub expr: Option<Expr>,
+    /// What could come next in the input.
+    pub expected: BTreeSet<Expected>,
+}
+
+impl Partial {
+    /// Check whether the input is a whole expression as it is.
+    pub fn complete(&self) -> bool {
+        self.expected.contains(&Expected::End)
+    }
+}
+
+/// A bracket which has been opened but not yet closed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Open {
+    /// A bracket grouping an expression.
+    Group,
+    /// The bracket starting the arguments of a call, which is to a function
+    /// taking a string if `string` is true.
+    Call { string: bool },
+    /// A square bracket starting a list.
+    List,
+}
+
+/// What the tokens so far leave the parser waiting for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// An operand, such as a number or a bracketed expression.
+    Operand,
+    /// An operator after a complete operand, or the end of the operand's
+    /// list, call or group.
+    Operator,
+    /// The bracket after a builtin function.
+    Call,
+}
+
+/// Parse `input`, which may be an unfinished expression such as `sin(2 +`,
+/// into as much of an expression as it holds along with what could come
+/// next.
+///
+/// Input which could never be finished, such as `2 3` or `1 + 2)`, fails
+/// with the same errors as parsing a whole expression.
+///
+/// # Arguments
+///
+/// * `input` - The text of the expression so far.
+///
+/// # Examples
+/// ```
+/// use cocoa::partial::{parse_partial, Expected};
+///
+/// let partial = parse_partial("sin(2 +").unwrap();
+///
+/// assert!(!partial.complete());
+/// assert!(partial.expected.contains(&Expected::Number));
+/// assert!(partial.expected.contains(&Expected::LParen));
+/// assert_eq!("sin(2)", partial.expr.unwrap().to_string());
+///
+/// let partial = parse_partial("[1, 2").unwrap();
+/// assert_eq!("[1, 2]", partial.expr.unwrap().to_string());
+/// assert!(partial.expected.contains(&Expected::RBracket));
+/// assert!(partial.expected.contains(&Expected::Comma));
+///
+/// let partial = parse_partial("sqrt").unwrap();
+/// assert_eq!(None, partial.expr);
+/// assert_eq!(vec![Expected::LParen], partial.expected.into_iter().collect::<Vec<_>>());
+///
+/// assert!(parse_partial("2 * 3").unwrap().complete());
+/// assert!(parse_partial("env(").unwrap().expected.contains(&Expected::String));
+/// assert!(parse_partial("2 3 +").is_err());
+/// assert!(parse_partial("2(").is_err());
+/// ```
+pub fn parse_partial(input: &str) -> Result<Partial> {
+    let mut tokens = lex_spanned(&mut input.chars().peekable())?;
+    let expected = expected(&tokens);
+
+    finish(&mut tokens, input);
+
+    let expr = if tokens.is_empty() {
+        None
+    } else {
+        let mut tokens = tokens.into_iter().peekable();
+
+        let expr = parse_expr(&mut tokens, 0)?;
+        expect_end(&mut tokens)?;
+
+        Some(expr)
+    };
+
+    Ok(Partial { expr, expected })
+}
+
+/// Make the tokens of an unfinished expression a whole expression, by leaving
+/// out whatever is missing something after it at the end, such as an
+/// operator without its right operand, then closing any brackets left open.
+///
+/// A warning describing each change is returned, where `input` is the text
+/// the tokens were lexed from.
+pub(crate) fn finish(
+    tokens: &mut Vec<(Token, Span)>,
+    input: &str,
+) -> Vec<Diagnostic> {
+    let text = |span: Span| -> String {
+        input
+            .chars()
+            .skip(span.start)
+            .take(span.end - span.start)
+            .collect()
+    };
+    let mut warnings = vec![];
+
+    while let Some(&(ref t, span)) = tokens.last() {
+        let before = tokens.len().checked_sub(2).map(|i| &tokens[i].0);
+        let dangling = match t {
+            Token::Op(OpKind::Factorial) => false,
+            Token::Op(_) | Token::Comma | Token::Func(_) => true,
+            // an empty group, unlike an empty call, is not an expression
+            Token::LParen => matches!(
+                before,
+                None | Some(
+                    Token::Op(_)
+                        | Token::Comma
+                        | Token::LParen
+                        | Token::LBracket
+                )
+            ),
+            _ => false,
+        };
+
+        if !dangling {
+            break;
+        }
+        tokens.pop();
+        warnings.push(Diagnostic::warning(
+            Some(span),
+            Code::IgnoredAtEnd.with(&[&text(span)]),
+        ));
+    }
+
+    let mut open = vec![];
+    for &(ref t, span) in tokens.iter() {
+        match t {
+            Token::LParen => open.push((Token::RParen, span)),
+            Token::LBracket => open.push((Token::RBracket, span)),
+            Token::RParen | Token::RBracket => {
+                open.pop();
+            }
+            _ => (),
+        }
+    }
+
+    // the innermost bracket is closed first
+    let end = input.chars().count();
+    for (closer, span) in open.into_iter().rev() {
+        tokens.push((closer, Span::new(end, end)));
+        warnings.push(Diagnostic::warning(
+            Some(span),
+            Code::ClosedLeftOpen.with(&[&text(span)]),
+        ));
+    }
+
+    warnings
+}
+
+/// Leave out the brackets in `tokens` which close a bracket that was never
+/// opened, returning a warning for each of them, where `input` is the text
+/// the tokens were lexed from.
+pub(crate) fn drop_unmatched(
+    tokens: &mut Vec<(Token, Span)>,
+    input: &str,
+) -> Vec<Diagnostic> {
+    let mut open = vec![];
+    let mut warnings = vec![];
+
+    tokens.retain(|&(ref t, span)| {
+        let closes = match t {
+            Token::LParen | Token::LBracket => {
+                open.push(t.clone());
+                return true;
+            }
+            Token::RParen => Token::LParen,
+            Token::RBracket => Token::LBracket,
+            _ => return true,
+        };
+
+        if open.last() == Some(&closes) {
+            open.pop();
+            return true;
+        }
+
+        let c: String = input.chars().skip(span.start).take(1).collect();
+        warnings.push(Diagnostic::warning(
+            Some(span),
+            Code::IgnoredUnmatched.with(&[&c]),
+        ));
+        false
+    });
+
+    warnings
+}
+
+/// Find what could come after `tokens`.
+fn expected(tokens: &[(Token, Span)]) -> BTreeSet<Expected> {
+    let mut open = vec![];
+    let mut state = State::Operand;
+    // whether the last token opened a list or the arguments of a call, which
+    // may be empty
+    let mut opened = false;
+
+    for (i, (t, _)) in tokens.iter().enumerate() {
+        opened = false;
+
+        state = match t {
+            Token::Number(_) | Token::Str(_) | Token::Ident(_) => {
+                State::Operator
+            }
+            Token::Func(_) => State::Call,
+            Token::LParen => {
+                let before = i.checked_sub(1).map(|i| &tokens[i].0);
+
+                match before {
+                    Some(Token::Func(f)) => open.push(Open::Call {
+                        string: f.takes_string(),
+                    }),
+                    // names which are not builtins can be called too
+                    Some(Token::Ident(_)) => {
+                        open.push(Open::Call { string: false })
+                    }
+                    _ => open.push(Open::Group),
+                }
+                opened = open.last() != Some(&Open::Group);
+
+                State::Operand
+            }
+            Token::LBracket => {
+                open.push(Open::List);
+                opened = true;
+                State::Operand
+            }
+            Token::RParen | Token::RBracket => {
+                open.pop();
+                State::Operator
+            }
+            Token::Comma => State::Operand,
+            Token::Op(OpKind::Factorial) => State::Operator,
+            Token::Op(_) => State::Operand,
+        };
+    }
+
+    let mut expected = BTreeSet::new();
+    match state {
+        State::Call => {
+            expected.insert(Expected::LParen);
+        }
+        State::Operand => {
+            expected.extend([
+                Expected::Number,
+                Expected::Function,
+                Expected::Name,
+                Expected::Sign,
+                Expected::LParen,
+                Expected::LBracket,
+            ]);
+
+            match open.last() {
+                Some(Open::Call { string }) if opened => {
+                    expected.insert(Expected::RParen);
+                    if *string {
+                        expected.insert(Expected::String);
+                    }
+                }
+                Some(Open::List) if opened => {
+                    expected.insert(Expected::RBracket);
+                }
+                _ => (),
+            }
+        }
+        State::Operator => {
+            expected.insert(Expected::Operator);
+
+            match open.last() {
+                Some(Open::Group) => {
+                    expected.insert(Expected::RParen);
+                }
+                Some(Open::Call { .. }) => {
+                    expected.extend([Expected::Comma, Expected::RParen]);
+                }
+                Some(Open::List) => {
+                    expected.extend([Expected::Comma, Expected::RBracket]);
+                }
+                None => {
+                    expected.insert(Expected::End);
+                }
+            }
+
+            // a name may be called rather than used as a value
+            if let Some((Token::Ident(_), _)) = tokens.last() {
+                expected.insert(Expected::LParen);
+            }
+        }
+    }
+
+    expected
+}
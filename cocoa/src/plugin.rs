@@ -0,0 +1,175 @@
+//! Extension points which let other crates bundle functions and constants that
+//! expresso does not provide out of the box.
+
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::function::{check_args, Arity, Domain};
+
+/// The implementation of a function provided by a plugin, which may hold
+/// state of its own, such as a rate it was created with.
+pub type Body = Box<dyn Fn(&[f64]) -> Result<f64> + Send + Sync>;
+
+/// A function provided by a plugin.
+pub struct Function {
+    /// The number of arguments the function takes.
+    pub arity: Arity,
+    /// The domains of the function's arguments, in order.
+    ///
+    /// Arguments without an entry may be any real number.
+    pub domains: Vec<Domain>,
+    /// The implementation of the function, which is only called with
+    /// arguments that satisfy `arity` and `domains`, and may fail for
+    /// arguments which they cannot describe.
+    pub body: Body,
+}
+
+impl Function {
+    /// Create a function taking `arity` arguments which may be any real
+    /// numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `arity` - The number of arguments the function takes.
+    /// * `body` - The implementation of the function.
+    pub fn new(
+        arity: Arity,
+        body: impl Fn(&[f64]) -> Result<f64> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            arity,
+            domains: vec![],
+            body: Box::new(body),
+        }
+    }
+
+    /// Restrict the values the arguments of `self` may take.
+    ///
+    /// # Arguments
+    ///
+    /// * `domains` - The domains of the function's arguments, in order.
+    pub fn with_domains(mut self, domains: Vec<Domain>) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// Get the domain of the argument at index `arg`.
+    pub fn domain(&self, arg: usize) -> Domain {
+        self.domains.get(arg).copied().unwrap_or(Domain::Real)
+    }
+
+    /// Check `args` against the arity and domains of `self`, then evaluate
+    /// `self` with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name the function was called by, used in errors.
+    /// * `args` - The arguments to evaluate the function with.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, args))
+    )]
+    pub fn call(&self, name: &str, args: &[f64]) -> Result<f64> {
+        check_args(name, self.arity, |i| self.domain(i), args)?;
+
+        (self.body)(args)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("arity", &self.arity)
+            .field("domains", &self.domains)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A pack of named functions and constants that can be loaded into an
+/// `Evaluator`.
+///
+/// Names must be valid identifiers, i.e. they start with an ascii letter
+/// followed by ascii letters, digits or underscores, and must not clash with a
+/// builtin or with anything the evaluator already knows about.
+///
+/// Functions are closures, so they may capture state of the plugin, such as
+/// the rate below, and fail with errors of their own.
+///
+/// # Examples
+/// ```
+/// use anyhow::bail;
+/// use cocoa::{
+///     evaluator::Evaluator,
+///     function::{Arity, Domain},
+///     plugin::{Function, Plugin},
+/// };
+///
+/// struct Finance {
+///     rate: f64,
+/// }
+///
+/// impl Plugin for Finance {
+///     fn name(&self) -> &str {
+///         "finance"
+///     }
+///
+///     fn functions(&self) -> Vec<(&'static str, Function)> {
+///         let rate = self.rate;
+///
+///         vec![
+///             (
+///                 "compound",
+///                 Function::new(Arity::Exact(1), move |xs| {
+///                     Ok(xs[0] * (1.0 + rate).powi(12))
+///                 }),
+///             ),
+///             (
+///                 "pv",
+///                 Function::new(Arity::Exact(2), |xs| {
+///                     Ok(xs[0] / (1.0 + xs[1]))
+///                 })
+///                 .with_domains(vec![Domain::Real, Domain::Above(-1.0)]),
+///             ),
+///             (
+///                 "payback",
+///                 Function::new(Arity::Exact(2), |xs| match xs {
+///                     [_, flow] if *flow <= 0.0 => bail!("never paid back"),
+///                     [cost, flow] => Ok((cost / flow).ceil()),
+///                     _ => unreachable!(),
+///                 }),
+///             ),
+///         ]
+///     }
+///
+///     fn constants(&self) -> Vec<(&'static str, f64)> {
+///         vec![("rate", self.rate)]
+///     }
+/// }
+///
+/// let mut evaluator = Evaluator::new();
+/// evaluator.load_plugin(&Finance { rate: 0.25 }).unwrap();
+///
+/// assert_eq!(2.0 * 1.25_f64.powi(12), evaluator.eval("compound(2)").unwrap());
+/// assert_eq!(100.0, evaluator.eval("pv(125, rate)").unwrap());
+/// assert!(evaluator.eval("pv(125, -2)").is_err());
+/// assert_eq!(3.0, evaluator.eval("payback(250, 100)").unwrap());
+/// assert_eq!(
+///     "never paid back",
+///     evaluator.eval("payback(250, 0)").unwrap_err().to_string()
+/// );
+/// ```
+pub trait Plugin {
+    /// A short name for the plugin, used in error messages.
+    fn name(&self) -> &str;
+
+    /// The functions the plugin provides, along with their names.
+    fn functions(&self) -> Vec<(&'static str, Function)> {
+        vec![]
+    }
+
+    /// The constants the plugin provides, along with their names.
+    fn constants(&self) -> Vec<(&'static str, f64)> {
+        vec![]
+    }
+}
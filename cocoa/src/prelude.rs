@@ -0,0 +1,40 @@
+//! The prelude, a script of templates for common helpers such as `sec` and
+//! `logb` which every evaluator made with `Evaluator::new` starts with.
+//!
+//! The prelude is written in expresso itself, as templates rather than
+//! builtins, so anything else of the same name takes its place.
+
+use std::sync::OnceLock;
+
+use crate::template::Template;
+
+/// The source of the prelude, one template per line.
+///
+/// # Examples
+/// ```
+/// use cocoa::prelude::PRELUDE;
+///
+/// assert!(PRELUDE.lines().any(|line| line.starts_with("template sec(x) := ")));
+/// ```
+pub const PRELUDE: &str = include_str!("prelude.xp");
+
+/// Get the templates the prelude defines, in the order they are written.
+///
+/// # Examples
+/// ```
+/// use cocoa::prelude::templates;
+///
+/// let logb = templates().iter().find(|t| t.name() == "logb").unwrap();
+/// assert_eq!(vec!["x", "b"], logb.params());
+/// ```
+pub fn templates() -> &'static [Template] {
+    static TEMPLATES: OnceLock<Vec<Template>> = OnceLock::new();
+
+    TEMPLATES.get_or_init(|| {
+        PRELUDE
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse().expect("the prelude is well formed"))
+            .collect()
+    })
+}
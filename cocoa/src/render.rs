@@ -0,0 +1,271 @@
+//! Rendering of expressions for showing them on the web or in documentation,
+//! either as they were written with their tokens marked up by class, or
+//! typeset as LaTeX.
+
+use std::f64::consts::PI;
+
+use crate::{
+    ast::{Callee, Expr, ExprKind, UNARY_BP},
+    highlight::classify,
+    token::{Bindable, FuncKind, OpKind},
+};
+
+/// The names of greek letters which have a command of the same name in
+/// LaTeX, so that a variable such as `theta` is typeset as the letter.
+const GREEK: &[&str] = &[
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta",
+    "iota", "kappa", "lambda", "mu", "nu", "xi", "rho", "sigma", "tau",
+    "upsilon", "phi", "chi", "psi", "omega", "Gamma", "Delta", "Theta",
+    "Lambda", "Xi", "Sigma", "Upsilon", "Phi", "Psi", "Omega",
+];
+
+/// Render `input` as HTML in which each token is wrapped in a `span` whose
+/// class is the name of its class from `highlight::classify`, e.g.
+/// `<span class="number">2</span>`.
+///
+/// The text between tokens is kept as it is, and characters which are
+/// special in HTML are escaped, so the result can be put straight into an
+/// element such as `code` and styled with CSS.
+///
+/// # Arguments
+///
+/// * `input` - The text of the expression.
+///
+/// # Examples
+/// ```
+/// use cocoa::render::to_html;
+///
+/// assert_eq!(
+///     "<span class=\"function\">sqrt</span><span class=\"paren\">(</span>\
+///      <span class=\"number\">2</span><span class=\"paren\">)</span> \
+///      <span class=\"operator\">*</span> <span class=\"constant\">pi</span>",
+///     to_html("sqrt(2) * pi"),
+/// );
+///
+/// assert_eq!(
+///     "<span class=\"number\">1</span> <span class=\"error\">&lt;</span> \
+///      <span class=\"number\">2</span>",
+///     to_html("1 < 2"),
+/// );
+/// ```
+pub fn to_html(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut html = String::with_capacity(input.len() * 4);
+    // the index of the first character not yet rendered
+    let mut pos = 0;
+
+    for (span, class) in classify(input) {
+        escape(&chars[pos..span.start], &mut html);
+
+        html.push_str("<span class=\"");
+        html.push_str(class.name());
+        html.push_str("\">");
+        escape(&chars[span.start..span.end], &mut html);
+        html.push_str("</span>");
+
+        pos = span.end;
+    }
+    escape(&chars[pos..], &mut html);
+
+    html
+}
+
+/// Add `chars` to `html`, escaping the characters which are special in HTML.
+fn escape(chars: &[char], html: &mut String) {
+    for &c in chars {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+}
+
+/// Typeset `expr` as LaTeX math, e.g. `\frac{1}{2} \cdot \pi \cdot r^{2}`
+/// for `1 / 2 * pi * r^2`, to be put between `$` signs or in an equation.
+///
+/// Division is written as a fraction and powers as superscripts, so only
+/// the brackets the structure of the expression needs are kept, along with
+/// those it was written with elsewhere. Functions which LaTeX has commands
+/// for, such as `sin` and `sqrt`, use them, and others are written upright.
+///
+/// # Arguments
+///
+/// * `expr` - The expression to typeset.
+///
+/// # Examples
+/// ```
+/// use cocoa::{ast::Expr, render::to_latex};
+///
+/// let latex = |s: &str| to_latex(&s.parse::<Expr>().unwrap());
+///
+/// assert_eq!("\\pi \\cdot r^{2}", latex("pi * r^2"));
+/// assert_eq!("\\frac{a + b}{2}", latex("(a + b) / 2"));
+/// assert_eq!("\\sqrt{x} + \\sin\\left(\\theta\\right)", latex("sqrt(x) + sin(theta)"));
+/// assert_eq!("\\left(-x\\right)^{n - 1}", latex("(-x)^(n - 1)"));
+/// assert_eq!("e^{-t} \\cdot \\mathrm{rate}", latex("exp(-t) * rate"));
+/// assert_eq!("\\operatorname{round}\\left(a, 2\\right)", latex("round(a, 2)"));
+/// assert_eq!("\\frac{1}{2} \\cdot \\pi", latex("1 / 2 * pi"));
+/// ```
+pub fn to_latex(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Number(n) => latex_number(*n),
+        ExprKind::Ident(name) => latex_name(name),
+        ExprKind::Str(s) => format!("\\text{{\"{}\"}}", latex_escape(s)),
+        ExprKind::Unary(op, e) => {
+            format!("{}{}", op, latex_operand(e, UNARY_BP, false))
+        }
+        ExprKind::Postfix(op, e) => {
+            format!("{}{}", latex_operand(e, u8::MAX, false), op)
+        }
+        // the bar of a fraction and the raising of an exponent group their
+        // operands, so neither needs brackets
+        ExprKind::Binary(OpKind::Slash, lhs, rhs) => format!(
+            "\\frac{{{}}}{{{}}}",
+            to_latex(ungrouped(lhs)),
+            to_latex(ungrouped(rhs))
+        ),
+        ExprKind::Binary(OpKind::Caret, lhs, rhs) => format!(
+            "{}^{{{}}}",
+            latex_operand(lhs, OpKind::Caret.bp(), true),
+            to_latex(ungrouped(rhs))
+        ),
+        ExprKind::Binary(op, lhs, rhs) => {
+            let symbol = match op {
+                OpKind::Star => "\\cdot".to_string(),
+                OpKind::Modulo => "\\bmod".to_string(),
+                OpKind::Shl => "\\ll".to_string(),
+                OpKind::Shr => "\\gg".to_string(),
+                op => op.to_string(),
+            };
+
+            format!(
+                "{} {} {}",
+                latex_operand(lhs, op.bp(), false),
+                symbol,
+                latex_operand(rhs, op.bp(), true)
+            )
+        }
+        ExprKind::Call(callee, args) => latex_call(callee, args),
+        ExprKind::Group(e) => format!("\\left({}\\right)", to_latex(e)),
+        ExprKind::List(elements) => {
+            let elements: Vec<String> = elements.iter().map(to_latex).collect();
+
+            format!("\\left[{}\\right]", elements.join(", "))
+        }
+    }
+}
+
+/// Typeset `e` as the operand of an operator which binds with `needed`,
+/// wrapping it in brackets if it binds looser, or as loose when `strict` is
+/// set, like `Expr`'s `Display` implementation.
+fn latex_operand(e: &Expr, needed: u8, strict: bool) -> String {
+    let bp = match &e.kind {
+        // a fraction only needs brackets when it is raised to a power or is
+        // followed by a postfix operator
+        ExprKind::Binary(OpKind::Slash, _, _) if needed <= UNARY_BP => None,
+        _ => e.bp(),
+    };
+
+    match bp {
+        Some(bp) if bp < needed || (strict && bp == needed) => {
+            format!("\\left({}\\right)", to_latex(e))
+        }
+        _ => to_latex(e),
+    }
+}
+
+/// Typeset a call to `callee` with `args`.
+fn latex_call(callee: &Callee, args: &[Expr]) -> String {
+    let args: Vec<String> =
+        args.iter().map(|arg| to_latex(ungrouped(arg))).collect();
+    let args = args.join(", ");
+
+    let command = match callee {
+        Callee::Builtin(FuncKind::Sqrt) => {
+            return format!("\\sqrt{{{}}}", args)
+        }
+        Callee::Builtin(FuncKind::Exp) => return format!("e^{{{}}}", args),
+        Callee::Builtin(FuncKind::Sin) => "\\sin".to_string(),
+        Callee::Builtin(FuncKind::Cos) => "\\cos".to_string(),
+        Callee::Builtin(FuncKind::Tan) => "\\tan".to_string(),
+        Callee::Builtin(FuncKind::Asin) => "\\arcsin".to_string(),
+        Callee::Builtin(FuncKind::Acos) => "\\arccos".to_string(),
+        Callee::Builtin(FuncKind::Atan) => "\\arctan".to_string(),
+        Callee::Builtin(FuncKind::Ln) => "\\ln".to_string(),
+        Callee::Builtin(FuncKind::Log) => "\\log".to_string(),
+        Callee::Builtin(FuncKind::Max) => "\\max".to_string(),
+        Callee::Builtin(FuncKind::Min) => "\\min".to_string(),
+        Callee::Builtin(FuncKind::Gcd) => "\\gcd".to_string(),
+        callee => {
+            format!("\\operatorname{{{}}}", latex_escape(callee.name()))
+        }
+    };
+
+    format!("{}\\left({}\\right)", command, args)
+}
+
+/// Typeset the number `n`, writing pi and infinity as symbols and numbers
+/// too large or small to write out in full in scientific notation.
+fn latex_number(n: f64) -> String {
+    let sign = if n.is_sign_negative() { "-" } else { "" };
+    let magnitude = n.abs();
+
+    if magnitude == PI {
+        format!("{}\\pi", sign)
+    } else if magnitude.is_infinite() {
+        format!("{}\\infty", sign)
+    } else if magnitude != 0.0 && !(1e-6..1e16).contains(&magnitude) {
+        let scientific = format!("{:e}", n);
+        let (mantissa, exponent) = scientific
+            .split_once('e')
+            .expect("numbers in scientific notation have an exponent");
+
+        format!("{} \\times 10^{{{}}}", mantissa, exponent)
+    } else {
+        n.to_string()
+    }
+}
+
+/// Typeset the name of a variable, constant or template parameter, as a
+/// greek letter if it is the name of one, and otherwise upright unless it is
+/// a single letter, so that it is not read as a product of letters.
+pub(crate) fn latex_name(name: &str) -> String {
+    if GREEK.contains(&name) {
+        format!("\\{}", name)
+    } else if name.chars().count() == 1 {
+        name.to_string()
+    } else {
+        format!("\\mathrm{{{}}}", latex_escape(name))
+    }
+}
+
+/// Get the expression inside any brackets around `e`.
+fn ungrouped(mut e: &Expr) -> &Expr {
+    while let ExprKind::Group(inner) = &e.kind {
+        e = inner;
+    }
+
+    e
+}
+
+/// Escape the characters which are special in LaTeX text.
+fn latex_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '_' | '#' | '$' | '%' | '&' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
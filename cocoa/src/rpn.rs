@@ -0,0 +1,230 @@
+//! A stack machine evaluating expressions written in reverse Polish notation,
+//! where operators and functions follow the values they apply to, e.g.
+//! `2 3 + 4 *` is `(2 + 3) * 4`.
+
+use anyhow::{Error, Result};
+
+use crate::{
+    error::spanned,
+    evaluator::Evaluator,
+    function::Arity,
+    interpreter::apply_op,
+    lexer::lex_spanned,
+    math::factorial,
+    message::{into_message, Code, Message},
+    token::{OpKind, Span, Token},
+};
+
+/// The values entered in reverse Polish notation so far, which the operators,
+/// functions and words entered after them are applied to.
+///
+/// Besides numbers, operators, builtins, constants and variables, a line may
+/// hold the words `neg`, which negates the top of the stack, `dup`, which
+/// pushes it again, `drop`, which pops it, `swap`, which swaps it with the
+/// value below it, and `clear`, which empties the stack.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stack {
+    values: Vec<f64>,
+}
+
+impl Stack {
+    /// Create an empty stack.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the values on the stack, from the bottom to the top.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Get the value on the top of the stack, which was entered or calculated
+    /// last.
+    pub fn top(&self) -> Option<f64> {
+        self.values.last().copied()
+    }
+
+    /// Push each number of `input` onto the stack in turn, applying each
+    /// operator, function and word to the values on top of it.
+    ///
+    /// Functions taking a varying number of arguments, such as `round`, take
+    /// the fewest they can, except for those taking any number of them, such
+    /// as `sum` and `max`, which take the whole stack. If any of `input`
+    /// fails, the stack is left as it was before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `evaluator` - What functions are called with and names looked up
+    ///   in.
+    /// * `input` - The numbers, operators, functions and words to enter.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, rpn::Stack};
+    ///
+    /// let evaluator = Evaluator::new();
+    /// let mut stack = Stack::new();
+    ///
+    /// stack.eval(&evaluator, "2 3 + 4 *").unwrap();
+    /// assert_eq!(&[20.0], stack.values());
+    ///
+    /// stack.eval(&evaluator, "1 2 swap - 3!").unwrap();
+    /// assert_eq!(&[20.0, 1.0, 6.0], stack.values());
+    ///
+    /// stack.eval(&evaluator, "sum sqrt neg").unwrap();
+    /// assert_eq!(Some(-27f64.sqrt()), stack.top());
+    ///
+    /// // nothing is entered from a line that fails
+    /// assert!(stack.eval(&evaluator, "1 + +").is_err());
+    /// assert_eq!(1, stack.values().len());
+    /// ```
+    pub fn eval(&mut self, evaluator: &Evaluator, input: &str) -> Result<()> {
+        let tokens = lex_spanned(&mut input.chars().peekable())?;
+        let mut values = self.values.clone();
+
+        for (token, span) in tokens {
+            let text: String = input
+                .chars()
+                .skip(span.start)
+                .take(span.end - span.start)
+                .collect();
+            apply(evaluator, &mut values, token, Entry { span, text: &text })?;
+        }
+        self.values = values;
+
+        Ok(())
+    }
+
+    /// Remove every value from the stack.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// Where a token was entered, for pointing errors at it.
+struct Entry<'a> {
+    span: Span,
+    /// The text the token was lexed from.
+    text: &'a str,
+}
+
+impl Entry<'_> {
+    /// Pop the top `count` values off `values`, bottom first, failing if
+    /// there are not that many for the token.
+    fn pop_n(&self, values: &mut Vec<f64>, count: usize) -> Result<Vec<f64>> {
+        let Some(start) = values.len().checked_sub(count) else {
+            let message = Code::StackUnderflow.with(&[&self.text, &count]);
+            return Err(self.error(message));
+        };
+
+        Ok(values.split_off(start))
+    }
+
+    /// Pop the top `N` values off `values` like `pop_n`.
+    fn pop<const N: usize>(&self, values: &mut Vec<f64>) -> Result<[f64; N]> {
+        let popped = self.pop_n(values, N)?;
+
+        Ok(popped.try_into().expect("exactly N values were popped"))
+    }
+
+    /// Create an error with the description `message` pointing at the token.
+    fn error(&self, message: impl Into<Message>) -> Error {
+        spanned(Some(self.span), message)
+    }
+}
+
+/// Apply `token`, which was entered at `entry`, to `values`.
+fn apply(
+    evaluator: &Evaluator,
+    values: &mut Vec<f64>,
+    token: Token,
+    entry: Entry,
+) -> Result<()> {
+    let n = match token {
+        Token::Number(n) => n,
+        Token::Op(OpKind::Factorial) => {
+            let [n] = entry.pop(values)?;
+
+            if n.is_sign_negative() {
+                return Err(entry.error(Code::NegativeFactorial));
+            } else if n.fract() != 0.0 {
+                return Err(entry.error(Code::NonIntegerFactorial));
+            }
+            // casting is safe since n is a positive integer, and huge values
+            // saturate to an infinite factorial anyway
+            factorial(n as u64)
+        }
+        Token::Op(OpKind::Equals) => {
+            return Err(entry.error(Code::NotInRpn.with(&[&entry.text])))
+        }
+        Token::Op(op) => {
+            let [lhs, rhs] = entry.pop(values)?;
+
+            if matches!(op, OpKind::Shl | OpKind::Shr) {
+                if lhs.fract() != 0.0 || rhs.fract() != 0.0 {
+                    return Err(entry.error(Code::NonIntegerShift));
+                } else if rhs < 0.0 {
+                    return Err(entry.error(Code::NegativeShift));
+                }
+            }
+            apply_op(op, lhs, rhs)
+        }
+        // lists, strings and expressions cannot be put on the stack
+        Token::Func(f)
+            if f.takes_string()
+                || f.is_list_function()
+                || f.is_higher_order() =>
+        {
+            return Err(entry.error(Code::NotInRpn.with(&[&entry.text])));
+        }
+        Token::Func(f) => {
+            let count = match f.arity() {
+                Arity::Exact(n) | Arity::Between(n, _) => n,
+                Arity::AtLeast(n) => values.len().max(n),
+            };
+            let args = entry.pop_n(values, count)?;
+
+            evaluator
+                .call(f, &args)
+                .map_err(|e| entry.error(into_message(e)))?
+        }
+        Token::Ident(name) => match name.as_str() {
+            "neg" => -entry.pop::<1>(values)?[0],
+            "dup" => {
+                let [n] = entry.pop(values)?;
+                values.push(n);
+                n
+            }
+            "drop" => {
+                entry.pop::<1>(values)?;
+                return Ok(());
+            }
+            "swap" => {
+                let [a, b] = entry.pop(values)?;
+                values.push(b);
+                a
+            }
+            "clear" => {
+                values.clear();
+                return Ok(());
+            }
+            _ => evaluator
+                .constant(&name)
+                .or_else(|| evaluator.variable(&name))
+                .ok_or_else(|| {
+                    entry.error(Code::UnknownIdentifier.with(&[&name]))
+                })?,
+        },
+        Token::Str(_)
+        | Token::LParen
+        | Token::RParen
+        | Token::LBracket
+        | Token::RBracket
+        | Token::Comma => {
+            return Err(entry.error(Code::NotInRpn.with(&[&entry.text])))
+        }
+    };
+    values.push(n);
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! Scripts, which are files of expressions and definitions such as
+//! templates, one per line, that can be imported into an evaluator.
+
+use std::{
+    error, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Error, Result};
+
+use crate::{
+    error::SpanError,
+    evaluator::Evaluator,
+    lexer::lex,
+    message::{message, Code, English, Locale, Message},
+    template::Template,
+    token::{Span, Token},
+};
+
+/// An error in a line of a script, which says which file and line it is in
+/// since that may not be the file which was imported, but one it imported.
+///
+/// # Examples
+/// ```
+/// use cocoa::{evaluator::Evaluator, script::{import, ScriptError}};
+///
+/// let path = std::env::temp_dir().join("expresso-script-error.xp");
+/// std::fs::write(&path, "template double(x) := 2 * x\n\ndouble(y)\n").unwrap();
+///
+/// let e = import(&mut Evaluator::new(), &path).unwrap_err();
+/// let e = e.downcast_ref::<ScriptError>().unwrap();
+///
+/// assert_eq!(3, e.line);
+/// assert!(e.to_string().ends_with("expresso-script-error.xp:3: unknown identifier 'y'"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    /// The file the error is in.
+    pub path: PathBuf,
+    /// The line the error is on, counted from 1.
+    pub line: usize,
+    /// The part of the line that caused the error, if known.
+    pub span: Option<Span>,
+    /// A description of the error.
+    pub message: Message,
+}
+
+impl ScriptError {
+    /// Create an error for the error `e` on line `line` of the file at
+    /// `path`, pointing at the span it was caused by if it is a `SpanError`.
+    fn new(path: &Path, line: usize, e: &Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            line,
+            span: e.downcast_ref::<SpanError>().map(|e| e.span),
+            message: message(e)
+                .cloned()
+                .unwrap_or_else(|| e.to_string().into()),
+        }
+    }
+
+    /// Format the error like a compiler does in `locale`, with its column
+    /// counted from 1 if it is known, e.g. `stats.xp:3:5: unknown
+    /// identifier 'y'`.
+    pub(crate) fn localize(&self, locale: &dyn Locale) -> String {
+        let column = match self.span {
+            Some(span) => format!(":{}", span.start + 1),
+            None => String::new(),
+        };
+
+        format!(
+            "{}:{}{}: {}",
+            self.path.display(),
+            self.line,
+            column,
+            self.message.localize(locale)
+        )
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.localize(&English))
+    }
+}
+
+impl error::Error for ScriptError {}
+
+/// Get the path of the file imported by `line` if it is an import, such as
+/// `import "stats.xp"`, or `None` if it is not.
+///
+/// # Arguments
+///
+/// * `line` - The line of a script.
+///
+/// # Examples
+/// ```
+/// use cocoa::script::import_path;
+///
+/// assert_eq!(Some("stats.xp".to_string()), import_path(r#"import "stats.xp""#));
+/// assert_eq!(None, import_path("1 + 2"));
+/// ```
+pub fn import_path(line: &str) -> Option<String> {
+    match lex(&mut line.chars().peekable()).ok()?.as_slice() {
+        [Token::Ident(keyword), Token::Str(path)] if keyword == "import" => {
+            Some(path.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Run the script at `path` in `evaluator`, so that the templates it defines
+/// can be used afterwards, returning how many it defined.
+///
+/// Each line is a template definition, an import of another script such as
+/// `import "stats.xp"`, or an expression, which is evaluated and its result
+/// discarded, so that scripts can check themselves with `assert`. Blank
+/// lines and REPL commands, which start with `:`, are skipped. Imports are
+/// found relative to the script importing them, and a script cannot import
+/// itself, whether directly or through other scripts.
+///
+/// The script stops at the first line which fails, with a `ScriptError`
+/// saying where it is, and the definitions before it are kept.
+///
+/// # Arguments
+///
+/// * `evaluator` - The evaluator to define the templates in, which must
+///   allow reading files.
+/// * `path` - The path of the script.
+///
+/// # Examples
+/// ```
+/// use cocoa::{evaluator::Evaluator, script::import};
+///
+/// let dir = std::env::temp_dir().join("expresso-import-example");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("geometry.xp"), "template area(r) := pi * r^2\n").unwrap();
+/// std::fs::write(
+///     dir.join("main.xp"),
+///     "import \"geometry.xp\"\ntemplate ring(a, b) := area(b) - area(a)\nassert_eq(ring(0, 1), area(1), 0)\n",
+/// )
+/// .unwrap();
+///
+/// let mut evaluator = Evaluator::new();
+/// assert_eq!(2, import(&mut evaluator, dir.join("main.xp")).unwrap());
+/// assert_eq!(std::f64::consts::PI * 3.0, evaluator.eval("ring(1, 2)").unwrap());
+///
+/// // geometry.xp importing main.xp would make a cycle
+/// std::fs::write(dir.join("geometry.xp"), "import \"main.xp\"\n").unwrap();
+/// let e = import(&mut evaluator, dir.join("main.xp")).unwrap_err();
+/// assert!(e.to_string().contains("which is already being imported"));
+/// ```
+pub fn import(
+    evaluator: &mut Evaluator,
+    path: impl AsRef<Path>,
+) -> Result<usize> {
+    import_from(evaluator, path.as_ref(), &mut vec![])
+}
+
+/// Run the script at `path` like `import`, where `stack` holds the scripts
+/// being imported which led to it, outermost first.
+fn import_from(
+    evaluator: &mut Evaluator,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<usize> {
+    if !evaluator.allow_files() {
+        bail!(Message::new(Code::FilesNotAllowed))
+    }
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => bail!(Code::CouldNotRead.with(&[&path.display(), &e])),
+    };
+    // the same file may be reached by different paths
+    let canonical = path.canonicalize()?;
+    stack.push(canonical);
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut defined = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(':') {
+            continue;
+        }
+
+        let error = |e: Error| Error::new(ScriptError::new(path, i + 1, &e));
+
+        if let Some(import) = import_path(line) {
+            let import = dir.join(import);
+            if let Ok(canonical) = import.canonicalize() {
+                if stack.contains(&canonical) {
+                    return Err(error(Error::new(
+                        Code::AlreadyImporting.with(&[&import.display()]),
+                    )));
+                }
+            }
+
+            // errors in the imported script already say where they are
+            defined += import_from(evaluator, &import, stack).map_err(|e| {
+                match e.downcast_ref::<ScriptError>() {
+                    Some(_) => e,
+                    None => error(e),
+                }
+            })?;
+        } else if trimmed.starts_with("template ") {
+            let template = line.parse::<Template>().map_err(error)?;
+            evaluator.define_template(template).map_err(error)?;
+            defined += 1;
+        } else {
+            evaluator.eval_value(line).map_err(error)?;
+        }
+    }
+
+    stack.pop();
+
+    Ok(defined)
+}
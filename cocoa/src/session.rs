@@ -0,0 +1,657 @@
+//! The state kept between the lines entered into an interactive frontend,
+//! such as the REPL, so that each frontend runs lines the same way.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    evaluator::Evaluator,
+    script::{import, import_path},
+    template::Template,
+    value::Value,
+};
+
+/// The name the result of the last line is bound to.
+pub const ANS: &str = "ans";
+
+/// The most changes to the environment a session remembers, so that they can
+/// be undone.
+pub const UNDO_LIMIT: usize = 100;
+
+/// The most results a session keeps, so that a long session does not hold on
+/// to every result it ever had, along with a variable for each.
+pub const RESULT_LIMIT: usize = 1000;
+
+/// What running a line of a `Session` did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The line was an expression, which evaluated to the value.
+    Value(Value),
+    /// The line defined the template with the name.
+    Defined(String),
+    /// The line imported a script.
+    Imported {
+        /// The path of the script, as it was written.
+        path: String,
+        /// How many templates the script defined.
+        defined: usize,
+    },
+    /// The line was blank.
+    Nothing,
+}
+
+/// A change to one name in the environment of a session, which is undone by
+/// setting the name back to the value it had before.
+#[derive(Debug, Clone)]
+enum Change {
+    /// The template with the name, or no template.
+    Template(String, Option<Template>),
+    /// The value of the variable with the name, or no variable.
+    Variable(String, Option<f64>),
+    /// The target of the alias with the name, or no alias.
+    Alias(String, Option<String>),
+}
+
+/// The changes made by one line or command, which are undone together.
+#[derive(Debug, Clone)]
+struct Entry {
+    /// The line or command which made the changes, e.g. `unalias r`.
+    description: String,
+    /// The values the names changed had before, in the order they changed.
+    changes: Vec<Change>,
+}
+
+/// An evaluator along with the lines run in it and their results, which
+/// later lines can refer to.
+///
+/// Each result is numbered from 1, and bound to `_N` for its number `N` as
+/// well as to `ans`, which holds the last of them. Results which are not
+/// numbers, such as lists, are numbered but cannot be referred to. Only the
+/// last `RESULT_LIMIT` results are kept, so older ones cannot be referred to
+/// either.
+///
+/// Changes to the templates, variables and aliases made through a session,
+/// rather than through `evaluator_mut`, are kept in a journal so that they
+/// can be undone with `undo` and redone with `redo`.
+#[derive(Debug, Default)]
+pub struct Session {
+    evaluator: Evaluator,
+    /// The lines run so far, oldest first.
+    history: Vec<String>,
+    /// The last `RESULT_LIMIT` results, oldest first, each of which is bound
+    /// to `_N` for its number `N`.
+    results: Vec<Value>,
+    /// The number of results which are no longer kept.
+    dropped: usize,
+    /// The changes which can be undone, oldest first.
+    undo: Vec<Entry>,
+    /// The changes which were undone and can be redone, the last undone
+    /// last.
+    redo: Vec<Entry>,
+}
+
+impl Session {
+    /// Create a session evaluating lines with an `Evaluator::new`.
+    ///
+    /// Like `Evaluator::default`, `Session::default` does not use the
+    /// prelude.
+    pub fn new() -> Self {
+        Self::with_evaluator(Evaluator::new())
+    }
+
+    /// Create a session evaluating lines with `evaluator`, whose settings
+    /// and definitions are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `evaluator` - The evaluator to run lines in.
+    pub fn with_evaluator(evaluator: Evaluator) -> Self {
+        Self {
+            evaluator,
+            history: vec![],
+            results: vec![],
+            dropped: 0,
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    /// Get the evaluator lines are run in, e.g. to format their results.
+    pub fn evaluator(&self) -> &Evaluator {
+        &self.evaluator
+    }
+
+    /// Get the evaluator lines are run in, e.g. to change its settings.
+    ///
+    /// Changes made through it cannot be undone.
+    pub fn evaluator_mut(&mut self) -> &mut Evaluator {
+        &mut self.evaluator
+    }
+
+    /// Run `line`, which is a template definition such as
+    /// `template area(r) := pi * r^2`, an import of a script such as
+    /// `import "stats.xp"`, or an expression, whose value becomes the next
+    /// result.
+    ///
+    /// The line is added to the history whether or not it succeeds, unless
+    /// it is blank. Warnings about an expression are left in the evaluator
+    /// for `Evaluator::take_warnings`.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to run.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{
+    ///     session::{Outcome, Session},
+    ///     value::Value,
+    /// };
+    ///
+    /// let mut session = Session::new();
+    ///
+    /// let outcome = session.eval_line("template sq(x) := x^2").unwrap();
+    /// assert_eq!(Outcome::Defined("sq".to_string()), outcome);
+    ///
+    /// let outcome = session.eval_line("sq(3) + 1").unwrap();
+    /// assert_eq!(Outcome::Value(Value::Number(10.0)), outcome);
+    /// assert_eq!(Some(10.0), session.ans());
+    ///
+    /// session.eval_line("ans * 2").unwrap();
+    /// session.eval_line("_1 + _2").unwrap();
+    /// assert_eq!(Some(30.0), session.ans());
+    /// assert_eq!(Some(&Value::Number(20.0)), session.result(2));
+    ///
+    /// assert!(session.eval_line("_4").is_err());
+    /// assert_eq!(5, session.history().len());
+    /// ```
+    pub fn eval_line(&mut self, line: &str) -> Result<Outcome> {
+        if line.trim().is_empty() {
+            return Ok(Outcome::Nothing);
+        }
+        self.history.push(line.to_string());
+
+        if line.trim_start().starts_with("template ") {
+            let template = line.parse::<Template>()?;
+            let name = template.name().to_string();
+            let previous = self.evaluator.template(&name).cloned();
+            self.evaluator.define_template(template)?;
+            self.record(line, vec![Change::Template(name.clone(), previous)]);
+
+            return Ok(Outcome::Defined(name));
+        }
+        if let Some(path) = import_path(line) {
+            let defined = self.import(&path)?;
+            return Ok(Outcome::Imported { path, defined });
+        }
+
+        let value = self.evaluator.eval_value(line)?;
+        self.push_result(value.clone());
+
+        Ok(Outcome::Value(value))
+    }
+
+    /// Run the script at `path` like `script::import`, so that the templates
+    /// it defines can be used, returning how many it defined.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the script.
+    pub fn import(&mut self, path: &str) -> Result<usize> {
+        let before: HashMap<String, Template> = self
+            .evaluator
+            .templates()
+            .into_iter()
+            .map(|template| (template.name().to_string(), template.clone()))
+            .collect();
+        let result = import(&mut self.evaluator, path);
+
+        // the definitions before a line which fails are kept, so they can
+        // be undone whether or not the script succeeded
+        let changes = self
+            .evaluator
+            .templates()
+            .into_iter()
+            .filter(|template| before.get(template.name()) != Some(template))
+            .map(|template| {
+                let name = template.name();
+                Change::Template(name.to_string(), before.get(name).cloned())
+            })
+            .collect();
+        self.record(&format!("import \"{}\"", path), changes);
+
+        result
+    }
+
+    /// Set the variable `name` to `value` like `Evaluator::set_variable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable.
+    /// * `value` - The value of the variable.
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<()> {
+        let previous = self.evaluator.variable(name);
+        self.evaluator.set_variable(name, value)?;
+
+        let description = format!("{} = {}", name, value);
+        self.record(
+            &description,
+            vec![Change::Variable(name.into(), previous)],
+        );
+
+        Ok(())
+    }
+
+    /// Remove the template `name` like `Evaluator::remove_template`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template.
+    pub fn remove_template(&mut self, name: &str) -> Option<Template> {
+        let removed = self.evaluator.remove_template(name)?;
+
+        let change = Change::Template(name.into(), Some(removed.clone()));
+        self.record(&format!("untemplate {}", name), vec![change]);
+
+        Some(removed)
+    }
+
+    /// Make `name` an alias of `target` like `Evaluator::set_alias`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the alias.
+    /// * `target` - The name of the function or constant it stands for.
+    pub fn set_alias(&mut self, name: &str, target: &str) -> Result<()> {
+        let previous = self.evaluator.alias(name).map(String::from);
+        self.evaluator.set_alias(name, target)?;
+
+        let description = format!("alias {} = {}", name, target);
+        self.record(&description, vec![Change::Alias(name.into(), previous)]);
+
+        Ok(())
+    }
+
+    /// Remove the alias `name` like `Evaluator::remove_alias`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the alias.
+    pub fn remove_alias(&mut self, name: &str) -> Option<String> {
+        let removed = self.evaluator.remove_alias(name)?;
+
+        let change = Change::Alias(name.into(), Some(removed.clone()));
+        self.record(&format!("unalias {}", name), vec![change]);
+
+        Some(removed)
+    }
+
+    /// Undo the last change to the templates, variables and aliases made
+    /// through `self` which has not been undone yet, returning the line or
+    /// command which made it, or `None` if there is nothing to undo.
+    ///
+    /// Answers are not changes, so `ans` and `_N` are left as they are. At
+    /// most `UNDO_LIMIT` changes are remembered.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::session::Session;
+    ///
+    /// let mut session = Session::new();
+    /// session.set_variable("x", 2.0).unwrap();
+    /// session.set_variable("x", 50.0).unwrap();
+    /// session.eval_line("template f(t) := t + x").unwrap();
+    /// assert_eq!(52.0, session.evaluator().eval("f(2)").unwrap());
+    ///
+    /// let undone = session.undo().unwrap();
+    /// assert_eq!(Some("template f(t) := t + x".to_string()), undone);
+    /// assert!(session.evaluator().eval("f(0)").is_err());
+    ///
+    /// session.undo().unwrap();
+    /// assert_eq!(Some(2.0), session.evaluator().variable("x"));
+    /// session.undo().unwrap();
+    /// assert_eq!(None, session.evaluator().variable("x"));
+    /// assert_eq!(None, session.undo().unwrap());
+    ///
+    /// session.redo().unwrap();
+    /// assert_eq!(Some(2.0), session.evaluator().variable("x"));
+    /// ```
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let Some(entry) = self.undo.pop() else {
+            return Ok(None);
+        };
+        let undone = self.revert(entry)?;
+        let description = undone.description.clone();
+        self.redo.push(undone);
+
+        Ok(Some(description))
+    }
+
+    /// Redo the change undone last by `undo`, returning the line or command
+    /// which made it, or `None` if there is nothing to redo.
+    ///
+    /// Making any other change forgets what there was to redo.
+    pub fn redo(&mut self) -> Result<Option<String>> {
+        let Some(entry) = self.redo.pop() else {
+            return Ok(None);
+        };
+        let redone = self.revert(entry)?;
+        let description = redone.description.clone();
+        self.undo.push(redone);
+
+        Ok(Some(description))
+    }
+
+    /// Remember the changes a line or command made, so that they can be
+    /// undone, forgetting what there was to redo.
+    fn record(&mut self, description: &str, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(Entry {
+            description: description.to_string(),
+            changes,
+        });
+        self.redo.clear();
+    }
+
+    /// Set the names `entry` changed back to the values they had before,
+    /// last change first, returning the entry which would set them to the
+    /// values they have now.
+    fn revert(&mut self, entry: Entry) -> Result<Entry> {
+        let changes = entry
+            .changes
+            .into_iter()
+            .rev()
+            .map(|change| self.apply(change))
+            .collect::<Result<_>>()?;
+
+        Ok(Entry {
+            description: entry.description,
+            changes,
+        })
+    }
+
+    /// Set the name `change` is about to the value it holds, returning the
+    /// change which would set it back.
+    fn apply(&mut self, change: Change) -> Result<Change> {
+        let evaluator = &mut self.evaluator;
+
+        Ok(match change {
+            Change::Template(name, template) => {
+                let current = evaluator.template(&name).cloned();
+                match template {
+                    Some(template) => evaluator.define_template(template)?,
+                    None => {
+                        evaluator.remove_template(&name);
+                    }
+                }
+                Change::Template(name, current)
+            }
+            Change::Variable(name, value) => {
+                let current = evaluator.variable(&name);
+                match value {
+                    Some(value) => evaluator.set_variable(&name, value)?,
+                    None => {
+                        evaluator.remove_variable(&name);
+                    }
+                }
+                Change::Variable(name, current)
+            }
+            Change::Alias(name, target) => {
+                let current = evaluator.alias(&name).map(String::from);
+                match target {
+                    Some(target) => evaluator.set_alias(&name, &target)?,
+                    None => {
+                        evaluator.remove_alias(&name);
+                    }
+                }
+                Change::Alias(name, current)
+            }
+        })
+    }
+
+    /// Add `line` to the history without running it, e.g. for commands which
+    /// a frontend runs itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line to add.
+    pub fn remember(&mut self, line: &str) {
+        self.history.push(line.to_string());
+    }
+
+    /// Add `value` as the next result, as if a line had evaluated to it,
+    /// returning its number.
+    ///
+    /// Once there are `RESULT_LIMIT` results, the oldest is dropped to make
+    /// room for it, along with its `_N`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The result to add.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{
+    ///     session::{Session, RESULT_LIMIT},
+    ///     value::Value,
+    /// };
+    ///
+    /// let mut session = Session::new();
+    /// assert_eq!(1, session.push_result(Value::Number(4.0)));
+    ///
+    /// let n = session.evaluator().eval("_1 + ans").unwrap();
+    /// assert_eq!(8.0, n);
+    ///
+    /// for n in 0..RESULT_LIMIT {
+    ///     session.push_result(Value::Number(n as f64));
+    /// }
+    /// assert_eq!(None, session.result(1));
+    /// assert!(session.evaluator().eval("_1").is_err());
+    /// assert_eq!(0.0, session.evaluator().eval("_2").unwrap());
+    /// assert_eq!(RESULT_LIMIT, session.results().len());
+    /// ```
+    pub fn push_result(&mut self, value: Value) -> usize {
+        if self.results.len() == RESULT_LIMIT {
+            self.results.remove(0);
+            self.dropped += 1;
+            self.evaluator
+                .remove_variable(&format!("_{}", self.dropped));
+        }
+        self.results.push(value.clone());
+        let n = self.dropped + self.results.len();
+
+        // anything else named ans, such as a template, is left alone
+        if let Ok(number) = value.into_number() {
+            self.evaluator.set_variable(ANS, number).ok();
+            self.evaluator.set_variable(&format!("_{}", n), number).ok();
+        }
+
+        n
+    }
+
+    /// Get the lines run so far, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Get the results which are still kept, oldest first.
+    pub fn results(&self) -> &[Value] {
+        &self.results
+    }
+
+    /// Get the result numbered `n`, counting from 1, if it is still kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of the result.
+    pub fn result(&self, n: usize) -> Option<&Value> {
+        self.results.get(n.checked_sub(self.dropped + 1)?)
+    }
+
+    /// Get the last result which is a number, which `ans` is bound to.
+    pub fn ans(&self) -> Option<f64> {
+        self.results
+            .iter()
+            .rev()
+            .find_map(|value| value.clone().into_number().ok())
+    }
+}
+
+/// Makes the session of each new client of a `Sessions`.
+struct Factory(Box<dyn Fn() -> Session + Send>);
+
+impl fmt::Debug for Factory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Factory")
+    }
+}
+
+/// A separate session for each client of a server, identified by a token
+/// such as the id of its connection, so that the variables and templates of
+/// one client are never seen by another.
+///
+/// Sessions which have not been used for longer than the idle time are
+/// forgotten. The time is passed to each method rather than read from a
+/// clock, so that servers can use the time a request arrived.
+#[derive(Debug)]
+pub struct Sessions {
+    /// The session of each client, along with when it was last used.
+    sessions: HashMap<String, (Session, Instant)>,
+    idle: Duration,
+    factory: Factory,
+}
+
+impl Sessions {
+    /// Create sessions which are forgotten after being idle for `idle`, each
+    /// of which starts as a `Session::new`.
+    ///
+    /// # Arguments
+    ///
+    /// * `idle` - How long a session may go unused before it is forgotten.
+    pub fn new(idle: Duration) -> Self {
+        Self::with_factory(idle, Session::new)
+    }
+
+    /// Create sessions like `new`, each of which starts as the session made
+    /// by `factory`, e.g. with limits on what untrusted clients can do.
+    ///
+    /// # Arguments
+    ///
+    /// * `idle` - How long a session may go unused before it is forgotten.
+    /// * `factory` - Makes the session of each new client.
+    pub fn with_factory(
+        idle: Duration,
+        factory: impl Fn() -> Session + Send + 'static,
+    ) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            idle,
+            factory: Factory(Box::new(factory)),
+        }
+    }
+
+    /// Get the session of the client `token`, which is used at `now`,
+    /// starting a new one if it has none or its last one was forgotten.
+    ///
+    /// Sessions idle for longer than the idle time at `now` are forgotten
+    /// first.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The client whose session it is.
+    /// * `now` - The time the session is used at.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use cocoa::session::Sessions;
+    ///
+    /// let mut sessions = Sessions::new(Duration::from_secs(60));
+    /// let start = Instant::now();
+    ///
+    /// sessions.session("alice", start).eval_line("6 * 7").unwrap();
+    /// let bob = sessions.session("bob", start);
+    /// assert!(bob.evaluator().eval("ans").is_err());
+    ///
+    /// let later = start + Duration::from_secs(30);
+    /// let alice = sessions.session("alice", later);
+    /// assert_eq!(Some(42.0), alice.ans());
+    ///
+    /// // bob has been idle for more than a minute, unlike alice
+    /// sessions.expire(start + Duration::from_secs(80));
+    /// assert_eq!(1, sessions.len());
+    /// ```
+    pub fn session(&mut self, token: &str, now: Instant) -> &mut Session {
+        self.expire(now);
+
+        let (session, used) = self
+            .sessions
+            .entry(token.to_string())
+            .or_insert_with(|| ((self.factory.0)(), now));
+        *used = now;
+
+        session
+    }
+
+    /// Forget the sessions idle for longer than the idle time at `now`,
+    /// returning how many were forgotten.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The time to measure how long sessions have been idle from.
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let before = self.sessions.len();
+        let idle = self.idle;
+        self.sessions.retain(|_, (_, used)| {
+            now.saturating_duration_since(*used) <= idle
+        });
+
+        before - self.sessions.len()
+    }
+
+    /// Forget the session of the client `token`, e.g. once it disconnects,
+    /// returning it if it had one.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The client whose session it is.
+    pub fn remove(&mut self, token: &str) -> Option<Session> {
+        self.sessions.remove(token).map(|(session, _)| session)
+    }
+
+    /// Set how long a session may go unused before it is forgotten.
+    ///
+    /// # Arguments
+    ///
+    /// * `idle` - How long a session may be idle.
+    pub fn set_idle(&mut self, idle: Duration) {
+        self.idle = idle;
+    }
+
+    /// Get how long a session may go unused before it is forgotten.
+    pub fn idle(&self) -> Duration {
+        self.idle
+    }
+
+    /// Get the number of clients with a session.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Get whether no client has a session.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
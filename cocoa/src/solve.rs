@@ -0,0 +1,124 @@
+//! Solving equations by rearranging their trees, which gives the solution
+//! directly rather than searching for it numerically.
+
+use anyhow::{bail, Result};
+
+use crate::{
+    ast::{Expr, ExprKind},
+    error::spanned,
+    message::Code,
+    token::OpKind,
+};
+
+/// An expression which is linear in the unknown, `slope * x + constant`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Linear {
+    slope: f64,
+    constant: f64,
+}
+
+impl Linear {
+    fn constant(constant: f64) -> Self {
+        Self {
+            slope: 0.0,
+            constant,
+        }
+    }
+
+    fn scale(self, k: f64) -> Self {
+        Self {
+            slope: self.slope * k,
+            constant: self.constant * k,
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            slope: self.slope + other.slope,
+            constant: self.constant + other.constant,
+        }
+    }
+}
+
+/// Solve `lhs = rhs` for the unknown `var` by rearranging it into the form
+/// `ax + b = 0`, failing unless it is linear in `var` and has exactly one
+/// solution.
+///
+/// Parts of the equation which do not depend on `var` are evaluated with
+/// `value`.
+pub(crate) fn solve_linear(
+    lhs: &Expr,
+    rhs: &Expr,
+    var: &str,
+    value: &dyn Fn(&Expr) -> Result<f64>,
+) -> Result<f64> {
+    let lhs = linear(lhs, var, value)?;
+    let rhs = linear(rhs, var, value)?;
+    let Linear { slope, constant } = lhs.add(rhs.scale(-1.0));
+
+    if slope == 0.0 {
+        if constant == 0.0 {
+            bail!(Code::EverySolution.with(&[&var]))
+        }
+        bail!(Code::NoSolution.with(&[&var]))
+    }
+    if !slope.is_finite() || !constant.is_finite() {
+        bail!(Code::Unsolvable.with(&[&var]))
+    }
+
+    Ok(-constant / slope)
+}
+
+/// Write `expr` in terms of the unknown `var` as a `Linear`, failing if it
+/// is not linear in `var`.
+fn linear(
+    expr: &Expr,
+    var: &str,
+    value: &dyn Fn(&Expr) -> Result<f64>,
+) -> Result<Linear> {
+    if !expr.idents().contains(var) {
+        if let ExprKind::Binary(OpKind::Equals, _, _) = expr.kind {
+            return Err(spanned(expr.span, Code::MultipleEquals));
+        }
+
+        return value(expr).map(Linear::constant);
+    }
+
+    let nonlinear = || spanned(expr.span, Code::NotLinear.with(&[&var]));
+    let linear = |e: &Expr| linear(e, var, value);
+
+    let result = match &expr.kind {
+        ExprKind::Ident(_) => Linear {
+            slope: 1.0,
+            constant: 0.0,
+        },
+        ExprKind::Group(e) | ExprKind::Unary(OpKind::Plus, e) => linear(e)?,
+        ExprKind::Unary(OpKind::Minus, e) => linear(e)?.scale(-1.0),
+        ExprKind::Binary(op, lhs, rhs) => {
+            let (a, b) = (linear(lhs)?, linear(rhs)?);
+
+            match op {
+                OpKind::Plus => a.add(b),
+                OpKind::Minus => a.add(b.scale(-1.0)),
+                // one side of a product must be constant for it to stay
+                // linear
+                OpKind::Star if a.slope == 0.0 => b.scale(a.constant),
+                OpKind::Star if b.slope == 0.0 => a.scale(b.constant),
+                OpKind::Slash if b.slope == 0.0 => {
+                    if b.constant == 0.0 {
+                        return Err(spanned(expr.span, Code::DivisionByZero));
+                    }
+                    a.scale(1.0 / b.constant)
+                }
+                OpKind::Caret if b.slope == 0.0 && b.constant == 1.0 => a,
+                OpKind::Equals => {
+                    return Err(spanned(expr.span, Code::MultipleEquals))
+                }
+                _ => return Err(nonlinear()),
+            }
+        }
+        _ => return Err(nonlinear()),
+    };
+
+    Ok(result)
+}
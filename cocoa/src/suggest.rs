@@ -0,0 +1,132 @@
+//! Suggestions for names that are not known, which are most likely typos of
+//! names that are.
+
+use crate::message::Message;
+
+/// Count the edits it takes to turn `a` into `b`, where an edit inserts,
+/// deletes or replaces a character, or swaps two adjacent characters.
+///
+/// # Examples
+/// ```
+/// use cocoa::suggest::edit_distance;
+///
+/// assert_eq!(0, edit_distance("sqrt", "sqrt"));
+/// assert_eq!(1, edit_distance("sqr", "sqrt"));
+/// assert_eq!(1, edit_distance("sirt", "sqrt"));
+/// assert_eq!(1, edit_distance("sqtr", "sqrt"));
+/// assert_eq!(4, edit_distance("cos", "sqrt"));
+/// ```
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) =
+        (a.chars().collect(), b.chars().collect());
+
+    // d[i][j] is the distance between the first i characters of a and the
+    // first j characters of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// The most edits a name may be from a known name for it to be suggested,
+/// where short names allow fewer so that e.g. `x` does not suggest `e`.
+fn max_distance(name: &str) -> usize {
+    match name.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Find the names in `candidates` that `name` is likely a typo of, closest
+/// first.
+///
+/// # Arguments
+///
+/// * `name` - The unknown name.
+/// * `candidates` - The names that are known.
+///
+/// # Examples
+/// ```
+/// use cocoa::suggest::suggestions;
+///
+/// assert_eq!(vec!["sqrt"], suggestions("sqr", ["sin", "sqrt", "exp"]));
+/// assert_eq!(vec!["normcdf", "normpdf"], suggestions("nrmcdf", ["normpdf", "normcdf"]));
+/// assert!(suggestions("foo", ["sin", "sqrt"]).is_empty());
+/// ```
+pub fn suggestions<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let max = max_distance(name);
+
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max && distance > 0)
+        .collect();
+    close.sort_unstable();
+    close.dedup();
+
+    close.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// Find the name in `candidates` that `name` is an obvious typo of, which is
+/// the only one a single edit away from it.
+///
+/// # Arguments
+///
+/// * `name` - The unknown name.
+/// * `candidates` - The names that are known.
+///
+/// # Examples
+/// ```
+/// use cocoa::suggest::correction;
+///
+/// assert_eq!(Some("sqrt"), correction("sqr", ["sin", "sqrt", "exp"]));
+/// assert_eq!(Some("ln"), correction("lnn", ["ln", "log", "exp"]));
+/// // ambiguous typos are not corrected
+/// assert_eq!(None, correction("nrmcdf", ["normpdf", "normcdf"]));
+/// ```
+pub fn correction<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    match suggestions(name, candidates).as_slice() {
+        [only] if edit_distance(name, only) == 1 => Some(only),
+        _ => None,
+    }
+}
+
+/// Describe `suggestions` as a hint to add to an error, which is empty if
+/// there are none.
+///
+/// # Examples
+/// ```
+/// use cocoa::suggest::did_you_mean;
+///
+/// assert_eq!(", did you mean 'sqrt'?", did_you_mean(&["sqrt"]));
+/// assert_eq!(", did you mean 'sin', 'sinh' or 'sign'?", did_you_mean(&["sin", "sinh", "sign"]));
+/// assert_eq!("", did_you_mean(&[]));
+/// ```
+pub fn did_you_mean(suggestions: &[&str]) -> String {
+    Message::did_you_mean(suggestions)
+        .map(|hint| format!(", {}", hint))
+        .unwrap_or_default()
+}
@@ -0,0 +1,718 @@
+//! Exact values which keep `pi`, `e`, square roots of non-squares and
+//! variables without a value symbolic, so that `2 * pi + pi` is `3 * pi`
+//! rather than a rounded number until it is approximated, and `(x + 1)^2` is
+//! expanded to `x ^ 2 + 2 * x + 1`.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    f64::consts::{E, PI},
+    fmt,
+};
+
+use crate::{
+    math::{
+        exact::{trig_degrees, Rational, Surd},
+        gcd,
+    },
+    token::FuncKind,
+};
+
+/// The most terms a symbolic value may have, so that e.g. raising a long sum
+/// to a large power cannot exhaust memory.
+const MAX_TERMS: usize = 1000;
+
+/// The largest magnitude of an exponent which a symbolic value is raised to
+/// exactly.
+pub(crate) const MAX_POWER: i64 = 64;
+
+/// The largest number whose square root is simplified, since finding its
+/// square factors takes time proportional to its square root.
+const MAX_RADICAND: u64 = 1 << 40;
+
+/// The product of the constants, square root and variables in a term, such
+/// as `pi ^ 2 * sqrt(3) * x`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Factors {
+    /// The power of `pi`.
+    pi: i32,
+    /// The power of `e`.
+    e: i32,
+    /// The number without square factors whose square root is taken, which
+    /// is 1 if there is none.
+    radicand: u64,
+    /// The variables without a value, mapped to their powers, which are
+    /// never 0.
+    vars: BTreeMap<String, i32>,
+}
+
+impl Factors {
+    /// The factors of a rational term, which has none.
+    const ONE: Factors = Factors {
+        pi: 0,
+        e: 0,
+        radicand: 1,
+        vars: BTreeMap::new(),
+    };
+
+    /// Get the sum of the powers of the variables.
+    fn degree(&self) -> i64 {
+        self.vars.values().map(|&power| i64::from(power)).sum()
+    }
+
+    /// Get the power of the variable `name`, which is 0 if it is not a
+    /// factor.
+    fn power(&self, name: &str) -> i32 {
+        self.vars.get(name).copied().unwrap_or(0)
+    }
+}
+
+impl Ord for Factors {
+    /// Order factors so that terms of higher degree come first when sorted in
+    /// descending order, and terms of the same degree come in alphabetical
+    /// order of their variables, e.g. `x ^ 2 + x * y + y ^ 2 + x + 1`.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let names: BTreeSet<&String> =
+            self.vars.keys().chain(other.vars.keys()).collect();
+
+        self.degree()
+            .cmp(&other.degree())
+            .then_with(|| {
+                names
+                    .into_iter()
+                    .map(|name| self.power(name).cmp(&other.power(name)))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .then_with(|| {
+                (self.pi, self.e, self.radicand).cmp(&(
+                    other.pi,
+                    other.e,
+                    other.radicand,
+                ))
+            })
+    }
+}
+
+impl PartialOrd for Factors {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A rational coefficient multiplied by factors.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Term {
+    coefficient: Rational,
+    factors: Factors,
+}
+
+impl Term {
+    /// Multiply `self` by `other`, returning `None` if the result does not
+    /// fit.
+    fn checked_mul(&self, other: &Term) -> Option<Term> {
+        let (a, b) = (&self.factors, &other.factors);
+
+        // sqrt(gx) * sqrt(gy) is g * sqrt(xy), where xy has no square factors
+        // since neither gx nor gy do
+        let g = gcd(a.radicand, b.radicand);
+        let radicand = (a.radicand / g).checked_mul(b.radicand / g)?;
+        let coefficient = self
+            .coefficient
+            .checked_mul(other.coefficient)?
+            .checked_mul(Rational::integer(i64::try_from(g).ok()?))?;
+
+        let mut vars = a.vars.clone();
+        for (name, &power) in &b.vars {
+            let sum =
+                vars.get(name).copied().unwrap_or(0).checked_add(power)?;
+            match sum {
+                0 => vars.remove(name),
+                sum => vars.insert(name.clone(), sum),
+            };
+        }
+
+        Some(Term {
+            coefficient,
+            factors: Factors {
+                pi: a.pi.checked_add(b.pi)?,
+                e: a.e.checked_add(b.e)?,
+                radicand,
+                vars,
+            },
+        })
+    }
+
+    /// Get the reciprocal of `self`, returning `None` if it is 0 or does not
+    /// fit.
+    fn recip(&self) -> Option<Term> {
+        let Factors {
+            pi,
+            e,
+            radicand,
+            ref vars,
+        } = self.factors;
+
+        // 1 / sqrt(x) is sqrt(x) / x
+        let rationalized = Rational::new(1, i64::try_from(radicand).ok()?)?;
+        let vars = vars
+            .iter()
+            .map(|(name, &power)| Some((name.clone(), power.checked_neg()?)))
+            .collect::<Option<_>>()?;
+
+        Some(Term {
+            coefficient: self.coefficient.recip()?.checked_mul(rationalized)?,
+            factors: Factors {
+                pi: pi.checked_neg()?,
+                e: e.checked_neg()?,
+                radicand,
+                vars,
+            },
+        })
+    }
+
+    /// Get the float closest to `self`, which is NaN if it has variables.
+    fn value(&self) -> f64 {
+        let Factors {
+            pi,
+            e,
+            radicand,
+            ref vars,
+        } = self.factors;
+
+        if !vars.is_empty() {
+            return f64::NAN;
+        }
+
+        self.coefficient.value()
+            * PI.powi(pi)
+            * E.powi(e)
+            * (radicand as f64).sqrt()
+    }
+}
+
+/// An exact number which is a sum of terms, each of which is a rational
+/// coefficient multiplied by powers of `pi`, `e` and variables and a square
+/// root, such as `3 * pi * x ^ 2 + sqrt(2) / 2`.
+///
+/// Sums are always expanded, with like terms collected, so operations on
+/// symbolic values do simple algebra. The operations are checked, returning
+/// `None` when their result cannot be held exactly, so that it can be
+/// approximated instead.
+///
+/// # Examples
+/// ```
+/// use cocoa::{math::exact::Rational, symbolic::Symbolic};
+///
+/// let pi = Symbolic::pi();
+/// let two = Symbolic::from(Rational::integer(2));
+///
+/// let sum = two.checked_mul(&pi).unwrap().checked_add(&pi).unwrap();
+/// assert_eq!("3 * pi", sum.to_string());
+/// assert_eq!(3.0 * std::f64::consts::PI, sum.value());
+///
+/// let root = Symbolic::sqrt(Rational::integer(8)).unwrap();
+/// assert_eq!("2 * sqrt(2)", root.to_string());
+/// assert_eq!("8", root.checked_mul(&root).unwrap().to_string());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Symbolic {
+    // sorted by their factors, largest first, with no two terms having the
+    // same factors and none having a coefficient of 0
+    terms: Vec<Term>,
+}
+
+impl Symbolic {
+    /// Get `pi`.
+    pub fn pi() -> Self {
+        Self {
+            terms: vec![Term {
+                coefficient: Rational::integer(1),
+                factors: Factors {
+                    pi: 1,
+                    ..Factors::ONE
+                },
+            }],
+        }
+    }
+
+    /// Get the variable `name`, which has no value.
+    ///
+    /// A variable named `e` is shown the same as Euler's number, so the
+    /// evaluator does not make one.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{math::exact::Rational, symbolic::Symbolic};
+    ///
+    /// let (x, y) = (Symbolic::variable("x"), Symbolic::variable("y"));
+    /// let one = Symbolic::from(Rational::integer(1));
+    ///
+    /// // (x + y)(x - y + 1)
+    /// let product = x
+    ///     .checked_add(&y)
+    ///     .unwrap()
+    ///     .checked_mul(&x.checked_sub(&y).unwrap().checked_add(&one).unwrap())
+    ///     .unwrap();
+    /// assert_eq!("x ^ 2 - y ^ 2 + x + y", product.to_string());
+    /// assert_eq!(vec!["x", "y"], product.variables());
+    /// assert!(product.value().is_nan());
+    /// ```
+    pub fn variable(name: &str) -> Self {
+        let mut vars = BTreeMap::new();
+        vars.insert(name.to_string(), 1);
+
+        Self {
+            terms: vec![Term {
+                coefficient: Rational::integer(1),
+                factors: Factors {
+                    vars,
+                    ..Factors::ONE
+                },
+            }],
+        }
+    }
+
+    /// Get the names of the variables of `self`, in alphabetical order.
+    pub fn variables(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> = self
+            .terms
+            .iter()
+            .flat_map(|term| term.factors.vars.keys())
+            .map(String::as_str)
+            .collect();
+
+        names.into_iter().collect()
+    }
+
+    /// Get `e` raised to the power of `n`, returning `None` if `n` is not an
+    /// integer, or is too large.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The power to raise `e` to.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{math::exact::Rational, symbolic::Symbolic};
+    ///
+    /// let e = Symbolic::exp(Rational::integer(-2)).unwrap();
+    /// assert_eq!("1 / e ^ 2", e.to_string());
+    /// assert_eq!(None, Symbolic::exp(Rational::new(1, 2).unwrap()));
+    /// ```
+    pub fn exp(n: Rational) -> Option<Self> {
+        if !n.is_integer() {
+            return None;
+        }
+
+        Some(Self {
+            terms: vec![Term {
+                coefficient: Rational::integer(1),
+                factors: Factors {
+                    e: i32::try_from(n.num()).ok()?,
+                    ..Factors::ONE
+                },
+            }],
+        })
+    }
+
+    /// Get the square root of `n`, with its square factors taken out,
+    /// returning `None` if `n` is negative or too large.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number to take the square root of.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{math::exact::Rational, symbolic::Symbolic};
+    ///
+    /// let root = Symbolic::sqrt(Rational::new(3, 4).unwrap()).unwrap();
+    /// assert_eq!("sqrt(3) / 2", root.to_string());
+    ///
+    /// let nine = Rational::integer(9);
+    /// assert_eq!("3", Symbolic::sqrt(nine).unwrap().to_string());
+    /// assert_eq!(None, Symbolic::sqrt(Rational::integer(-1)));
+    /// ```
+    pub fn sqrt(n: Rational) -> Option<Self> {
+        if n.num() == 0 {
+            return Some(Self::default());
+        }
+
+        // sqrt(p / q) is sqrt(pq) / q
+        let den = n.den();
+        let product = u64::try_from(n.num()).ok()?.checked_mul(den as u64)?;
+        if product > MAX_RADICAND {
+            return None;
+        }
+
+        let (outer, radicand) = split_square(product);
+        let term = Term {
+            // casting is safe since outer is at most the square root of
+            // MAX_RADICAND
+            coefficient: Rational::new(outer as i64, den)?,
+            factors: Factors {
+                radicand,
+                ..Factors::ONE
+            },
+        };
+
+        Self::from_terms(vec![term])
+    }
+
+    /// Get the exact value of `n`, which is taken to be what it is written
+    /// as like `Rational::from_f64`, returning `None` if it does not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number to convert.
+    pub fn from_f64(n: f64) -> Option<Self> {
+        Rational::from_f64(n).map(Self::from)
+    }
+
+    /// Get the rational number `self` is, if it has no factors.
+    pub fn to_rational(&self) -> Option<Rational> {
+        match self.terms.as_slice() {
+            [] => Some(Rational::default()),
+            [term] if term.factors == Factors::ONE => Some(term.coefficient),
+            _ => None,
+        }
+    }
+
+    /// Get the float closest to `self`, which approximates it, or NaN if it
+    /// has variables.
+    pub fn value(&self) -> f64 {
+        self.terms.iter().map(Term::value).sum()
+    }
+
+    /// Add `other` to `self`, returning `None` if the result does not fit.
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        Self::from_terms(
+            self.terms.iter().chain(&other.terms).cloned().collect(),
+        )
+    }
+
+    /// Subtract `other` from `self`, returning `None` if the result does not
+    /// fit.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    /// Multiply `self` by `other`, returning `None` if the result does not
+    /// fit.
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        if self.terms.len().saturating_mul(other.terms.len()) > MAX_TERMS {
+            return None;
+        }
+
+        let mut terms = vec![];
+        for a in &self.terms {
+            for b in &other.terms {
+                terms.push(a.checked_mul(b)?);
+            }
+        }
+
+        Self::from_terms(terms)
+    }
+
+    /// Divide `self` by `other`, returning `None` if `other` is 0, if it is
+    /// a sum of several terms which does not divide `self` exactly, or if the
+    /// result does not fit.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{math::exact::Rational, symbolic::Symbolic};
+    ///
+    /// let two = Symbolic::from(Rational::integer(2));
+    /// let root = Symbolic::sqrt(Rational::integer(2)).unwrap();
+    ///
+    /// assert_eq!("sqrt(2)", two.checked_div(&root).unwrap().to_string());
+    /// assert_eq!(None, two.checked_div(&Symbolic::default()));
+    ///
+    /// // (x^2 - 1) / (x + 1)
+    /// let x = Symbolic::variable("x");
+    /// let one = Symbolic::from(Rational::integer(1));
+    /// let square = x.checked_mul(&x).unwrap().checked_sub(&one).unwrap();
+    /// let quotient = square.checked_div(&x.checked_add(&one).unwrap());
+    /// assert_eq!("x - 1", quotient.unwrap().to_string());
+    /// assert_eq!(None, x.checked_div(&x.checked_add(&one).unwrap()));
+    /// ```
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        match other.terms.as_slice() {
+            [term] => self.checked_mul(&Self {
+                terms: vec![term.recip()?],
+            }),
+            [lead, ..] if !lead.factors.vars.is_empty() => self.long_div(other),
+            _ => None,
+        }
+    }
+
+    /// Divide `self` by the sum `other` by long division, returning `None`
+    /// unless `other` divides `self` exactly.
+    fn long_div(&self, other: &Self) -> Option<Self> {
+        let lead = other.terms.first()?.recip()?;
+        let (mut quotient, mut rest) = (Self::default(), self.clone());
+
+        for _ in 0..MAX_TERMS {
+            let Some(first) = rest.terms.first() else {
+                return Some(quotient);
+            };
+
+            // the leading term of the rest must be a multiple of that of
+            // other, or other does not divide self
+            let step = first.checked_mul(&lead)?;
+            if step.factors.vars.values().any(|&power| power < 0) {
+                return None;
+            }
+
+            let step = Self { terms: vec![step] };
+            quotient = quotient.checked_add(&step)?;
+            rest = rest.checked_sub(&step.checked_mul(other)?)?;
+        }
+
+        None
+    }
+
+    /// Negate `self`, returning `None` if the result does not fit.
+    pub fn checked_neg(&self) -> Option<Self> {
+        let terms = self
+            .terms
+            .iter()
+            .map(|term| {
+                Some(Term {
+                    coefficient: term.coefficient.checked_neg()?,
+                    factors: term.factors.clone(),
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self { terms })
+    }
+
+    /// Raise `self` to the power of `exp`, returning `None` unless `exp` is
+    /// a small integer, or a half when `self` is rational, or if the result
+    /// does not fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `exp` - The power to raise `self` to.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{math::exact::Rational, symbolic::Symbolic};
+    ///
+    /// let sum = Symbolic::pi()
+    ///     .checked_add(&Symbolic::from(Rational::integer(1)))
+    ///     .unwrap();
+    /// let square = sum.checked_pow(&Rational::integer(2)).unwrap();
+    /// assert_eq!("pi ^ 2 + 2 * pi + 1", square.to_string());
+    ///
+    /// let three = Symbolic::from(Rational::integer(3));
+    /// let half = Rational::new(-1, 2).unwrap();
+    /// let root = three.checked_pow(&half).unwrap();
+    /// assert_eq!("sqrt(3) / 3", root.to_string());
+    /// ```
+    pub fn checked_pow(&self, exp: &Rational) -> Option<Self> {
+        if exp.den() == 2 {
+            let root = Self::sqrt(self.to_rational()?)?;
+            return root.checked_pow(&Rational::integer(exp.num()));
+        } else if !exp.is_integer() || exp.num().abs() > MAX_POWER {
+            return None;
+        }
+
+        let base = match exp.num() {
+            n if n < 0 => Self::from(Rational::integer(1)).checked_div(self)?,
+            _ => self.clone(),
+        };
+
+        let mut result = Self::from(Rational::integer(1));
+        for _ in 0..exp.num().abs() {
+            result = result.checked_mul(&base)?;
+        }
+
+        Some(result)
+    }
+
+    /// Get the exact value of the trigonometric function `f` at `self`,
+    /// returning `None` unless `self` is a multiple of `pi / 6` or `pi / 4`
+    /// and `f` is `sin`, `cos` or `tan`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The trigonometric function.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{math::exact::Rational, symbolic::Symbolic, token::FuncKind};
+    ///
+    /// let sixth = Symbolic::from(Rational::new(1, 6).unwrap());
+    /// let angle = Symbolic::pi().checked_mul(&sixth).unwrap();
+    ///
+    /// assert_eq!("1 / 2", angle.trig(FuncKind::Sin).unwrap().to_string());
+    /// let cos = angle.trig(FuncKind::Cos).unwrap();
+    /// assert_eq!("sqrt(3) / 2", cos.to_string());
+    /// assert_eq!(None, angle.trig(FuncKind::Exp));
+    /// ```
+    pub fn trig(&self, f: FuncKind) -> Option<Self> {
+        let turns = match self.terms.as_slice() {
+            [] => Rational::default(),
+            [term]
+                if term.factors
+                    == (Factors {
+                        pi: 1,
+                        ..Factors::ONE
+                    }) =>
+            {
+                term.coefficient
+            }
+            _ => return None,
+        };
+
+        let degrees = turns.checked_mul(Rational::integer(180))?;
+        if !degrees.is_integer() {
+            return None;
+        }
+
+        trig_degrees(f, degrees.num()).and_then(Self::from_surd)
+    }
+
+    /// Convert `surd`, returning `None` if its radicand is too large.
+    fn from_surd(surd: Surd) -> Option<Self> {
+        let radicand = i64::try_from(surd.radicand()).ok()?;
+        let root = Self::sqrt(Rational::integer(radicand))?;
+
+        root.checked_mul(&Self::from(surd.coefficient()))
+    }
+
+    /// Combine the terms with the same factors of the sum `terms`, returning
+    /// `None` if the result does not fit.
+    fn from_terms(mut terms: Vec<Term>) -> Option<Self> {
+        terms.sort_by(|a, b| b.factors.cmp(&a.factors));
+
+        let mut combined: Vec<Term> = vec![];
+        for term in terms {
+            match combined.last_mut() {
+                Some(last) if last.factors == term.factors => {
+                    last.coefficient =
+                        last.coefficient.checked_add(term.coefficient)?;
+                }
+                _ => combined.push(term),
+            }
+        }
+        combined.retain(|term| term.coefficient.num() != 0);
+
+        (combined.len() <= MAX_TERMS).then_some(Self { terms: combined })
+    }
+}
+
+impl From<Rational> for Symbolic {
+    fn from(n: Rational) -> Self {
+        let term = Term {
+            coefficient: n,
+            factors: Factors::ONE,
+        };
+
+        Self {
+            terms: if n.num() == 0 { vec![] } else { vec![term] },
+        }
+    }
+}
+
+impl fmt::Display for Symbolic {
+    /// Format `self` as an expression which evaluates to it, such as
+    /// `pi ^ 2 / 2 - sqrt(3)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+
+        for (i, term) in self.terms.iter().enumerate() {
+            let negative = term.coefficient.num() < 0;
+            match (i, negative) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => (),
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+
+            write!(f, "{}", format_term(term))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Format the magnitude of `term` as a product divided by a product, such as
+/// `3 * pi / (2 * e)`.
+fn format_term(term: &Term) -> String {
+    let Factors {
+        pi,
+        e,
+        radicand,
+        ref vars,
+    } = term.factors;
+    let (mut above, mut below) = (vec![], vec![]);
+
+    for (name, power) in [("pi", pi), ("e", e)] {
+        push_power(&mut above, &mut below, name, power);
+    }
+    if radicand != 1 {
+        above.push(format!("sqrt({})", radicand));
+    }
+    for (name, &power) in vars {
+        push_power(&mut above, &mut below, name, power);
+    }
+
+    let num = term.coefficient.num().unsigned_abs();
+    if num != 1 || above.is_empty() {
+        above.insert(0, num.to_string());
+    }
+    let den = term.coefficient.den();
+    if den != 1 {
+        below.insert(0, den.to_string());
+    }
+
+    let above = above.join(" * ");
+    match below.len() {
+        0 => above,
+        1 => format!("{} / {}", above, below[0]),
+        _ => format!("{} / ({})", above, below.join(" * ")),
+    }
+}
+
+/// Add `name` raised to `power` to the factors `above` the division, or to
+/// those `below` it if `power` is negative.
+fn push_power(
+    above: &mut Vec<String>,
+    below: &mut Vec<String>,
+    name: &str,
+    power: i32,
+) {
+    let side = if power < 0 { below } else { above };
+    match power.unsigned_abs() {
+        0 => (),
+        1 => side.push(name.to_string()),
+        power => side.push(format!("{} ^ {}", name, power)),
+    }
+}
+
+/// Split `n` into `a` and `b` where `n` is `a^2 * b` and `b` has no square
+/// factors.
+fn split_square(n: u64) -> (u64, u64) {
+    let (mut outer, mut inner) = (1, n);
+
+    let mut i: u64 = 2;
+    while i * i <= inner {
+        while inner % (i * i) == 0 {
+            inner /= i * i;
+            outer *= i;
+        }
+        i += 1;
+    }
+
+    (outer, inner)
+}
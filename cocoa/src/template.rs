@@ -0,0 +1,382 @@
+//! Templates, which are named expressions with parameters such as
+//! `template area(r) := pi * r^2`, whose bodies are kept as trees rather
+//! than evaluated, so that they can be printed, differentiated and typeset
+//! as well as called.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Error, Result};
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    error::SpanError,
+    function::Arity,
+    lexer::{lex, lex_spanned},
+    message::{Code, Message},
+    parser::{expect_end, parse_expr},
+    render::{latex_name, to_latex},
+    token::{FuncKind, Span, Token},
+};
+
+/// The keyword a template definition starts with.
+const KEYWORD: &str = "template";
+
+/// An expression with named parameters, which a call to the template is
+/// replaced with, its parameters replaced by the arguments of the call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    name: String,
+    params: Vec<String>,
+    body: Expr,
+}
+
+impl Template {
+    /// Create the template `name` with the parameters `params`, which stands
+    /// for `body`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name the template is called by, which must be a valid
+    ///   identifier that is not builtin.
+    /// * `params` - The names of the parameters, which must be distinct
+    ///   valid identifiers.
+    /// * `body` - The expression the template stands for.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::template::Template;
+    ///
+    /// let body = "a * x + b".parse().unwrap();
+    /// let line = Template::new("line", &["a", "b", "x"], body).unwrap();
+    /// assert_eq!("template line(a, b, x) := a * x + b", line.to_string());
+    ///
+    /// assert!(Template::new("sin", &["x"], "x".parse().unwrap()).is_err());
+    /// assert!(Template::new("f", &["x", "x"], "x".parse().unwrap()).is_err());
+    /// assert!(Template::new("f", &["pi"], "pi".parse().unwrap()).is_err());
+    /// ```
+    pub fn new(name: &str, params: &[&str], body: Expr) -> Result<Self> {
+        match lex(&mut name.chars().peekable()).as_deref() {
+            Ok([Token::Ident(_)]) => (),
+            Ok([Token::Func(f)]) => {
+                bail!(Code::CannotRedefineBuiltin.with(&[&f.name()]))
+            }
+            _ => bail!(Code::InvalidTemplateName.with(&[&name])),
+        }
+
+        for (i, param) in params.iter().enumerate() {
+            match lex(&mut param.chars().peekable()).as_deref() {
+                Ok([Token::Ident(_)]) => (),
+                _ => bail!(Code::InvalidParameterName.with(&[param])),
+            }
+
+            if params[..i].contains(param) {
+                bail!(Code::DuplicateParameter.with(&[param]))
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            params: params.iter().map(|param| param.to_string()).collect(),
+            body,
+        })
+    }
+
+    /// Get the name the template is called by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the names of the parameters, in the order arguments are passed.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// Get the expression the template stands for.
+    pub fn body(&self) -> &Expr {
+        &self.body
+    }
+
+    /// Get the number of arguments the template is called with.
+    pub fn arity(&self) -> Arity {
+        Arity::Exact(self.params.len())
+    }
+
+    /// Replace a call to the template with its body, where each parameter is
+    /// replaced by the corresponding argument of `args`.
+    ///
+    /// Names bound by higher order functions in the body, such as `x` in
+    /// `map(x^2, x, list)`, are not parameters even if they share a name.
+    /// Calls to other templates in the body are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments of the call.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{ast::Expr, template::Template};
+    ///
+    /// let area: Template = "template area(r) := pi * r^2".parse().unwrap();
+    /// let args = ["a + 1".parse::<Expr>().unwrap()];
+    ///
+    /// assert_eq!("pi * (a + 1) ^ 2", area.expand(&args).unwrap().to_string());
+    /// assert_eq!(
+    ///     "area expects 1 argument, got 0",
+    ///     area.expand(&[]).unwrap_err().to_string()
+    /// );
+    /// ```
+    pub fn expand(&self, args: &[Expr]) -> Result<Expr> {
+        if !self.arity().accepts(args.len()) {
+            bail!(Code::WrongArity.with(&[
+                &self.name,
+                &self.arity(),
+                &args.len()
+            ]))
+        }
+
+        let bindings: Vec<(&str, &Expr)> =
+            self.params.iter().map(String::as_str).zip(args).collect();
+
+        Ok(substitute(&self.body, &bindings))
+    }
+
+    /// Get the names of the functions and templates called in the body of
+    /// the template, which are not builtin.
+    pub(crate) fn calls(&self) -> Vec<&str> {
+        let mut calls = vec![];
+        collect_calls(&self.body, &mut calls);
+
+        calls
+    }
+
+    /// Typeset the definition of the template as a LaTeX equation.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::template::Template;
+    ///
+    /// let area: Template = "template area(r) := pi * r^2".parse().unwrap();
+    ///
+    /// assert_eq!(
+    ///     "\\operatorname{area}\\left(r\\right) = \\pi \\cdot r^{2}",
+    ///     area.to_latex()
+    /// );
+    /// ```
+    pub fn to_latex(&self) -> String {
+        let params: Vec<String> =
+            self.params.iter().map(|param| latex_name(param)).collect();
+
+        format!(
+            "\\operatorname{{{}}}\\left({}\\right) = {}",
+            self.name.replace('_', "\\_"),
+            params.join(", "),
+            to_latex(&self.body)
+        )
+    }
+}
+
+/// Replace the names in `bindings` with what they are bound to throughout
+/// `e`.
+fn substitute(e: &Expr, bindings: &[(&str, &Expr)]) -> Expr {
+    let kind = match &e.kind {
+        ExprKind::Ident(name) => {
+            match bindings.iter().find(|(param, _)| param == name) {
+                Some((_, arg)) => return (*arg).clone(),
+                None => e.kind.clone(),
+            }
+        }
+        ExprKind::Number(_) | ExprKind::Str(_) => e.kind.clone(),
+        ExprKind::Unary(op, inner) => {
+            ExprKind::Unary(*op, Box::new(substitute(inner, bindings)))
+        }
+        ExprKind::Postfix(op, inner) => {
+            ExprKind::Postfix(*op, Box::new(substitute(inner, bindings)))
+        }
+        ExprKind::Binary(op, lhs, rhs) => ExprKind::Binary(
+            *op,
+            Box::new(substitute(lhs, bindings)),
+            Box::new(substitute(rhs, bindings)),
+        ),
+        ExprKind::Group(inner) => {
+            ExprKind::Group(Box::new(substitute(inner, bindings)))
+        }
+        ExprKind::List(elements) => ExprKind::List(
+            elements.iter().map(|e| substitute(e, bindings)).collect(),
+        ),
+        ExprKind::Call(callee, args) => {
+            let (bound, names) = bound_names(callee, args);
+
+            // the names bound by a higher order function are left alone, and
+            // shadow the parameters in its body, which comes first
+            let inner: Vec<(&str, &Expr)> = bindings
+                .iter()
+                .filter(|(param, _)| !bound.contains(param))
+                .copied()
+                .collect();
+            let args = args
+                .iter()
+                .enumerate()
+                .map(|(i, arg)| match i {
+                    0 if !bound.is_empty() => substitute(arg, &inner),
+                    i if (1..=names).contains(&i) => arg.clone(),
+                    _ => substitute(arg, bindings),
+                })
+                .collect();
+
+            ExprKind::Call(callee.clone(), args)
+        }
+    };
+
+    Expr::spanned(kind, e.span)
+}
+
+/// Get the names a call to `callee` with `args` binds in its first argument,
+/// if it is a higher order function, along with how many of the arguments
+/// after the first are those names.
+fn bound_names<'a>(callee: &Callee, args: &'a [Expr]) -> (Vec<&'a str>, usize) {
+    let names = match callee {
+        // the body of odesolve always refers to x and y
+        Callee::Builtin(FuncKind::OdeSolve) => return (vec!["x", "y"], 0),
+        Callee::Builtin(FuncKind::Reduce) => 2,
+        Callee::Builtin(f) if f.is_higher_order() => 1,
+        _ => 0,
+    };
+
+    let bound = args
+        .iter()
+        .skip(1)
+        .take(names)
+        .filter_map(|arg| match &arg.kind {
+            ExprKind::Ident(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    (bound, names)
+}
+
+/// Add the names of the functions called in `e` which are not builtin to
+/// `calls`.
+fn collect_calls<'a>(e: &'a Expr, calls: &mut Vec<&'a str>) {
+    match &e.kind {
+        ExprKind::Number(_) | ExprKind::Ident(_) | ExprKind::Str(_) => (),
+        ExprKind::Unary(_, inner)
+        | ExprKind::Postfix(_, inner)
+        | ExprKind::Group(inner) => collect_calls(inner, calls),
+        ExprKind::Binary(_, lhs, rhs) => {
+            collect_calls(lhs, calls);
+            collect_calls(rhs, calls);
+        }
+        ExprKind::List(args) => {
+            args.iter().for_each(|arg| collect_calls(arg, calls))
+        }
+        ExprKind::Call(callee, args) => {
+            if let Callee::Named(name) = callee {
+                calls.push(name);
+            }
+            args.iter().for_each(|arg| collect_calls(arg, calls))
+        }
+    }
+}
+
+/// Move the span of the error `e`, if it has one, `offset` characters to
+/// the right, for errors in a part of a line which was lexed on its own.
+fn shift(e: Error, offset: usize) -> Error {
+    match e.downcast::<SpanError>() {
+        Ok(SpanError { span, message }) => SpanError::new(
+            Span::new(span.start + offset, span.end + offset),
+            message,
+        )
+        .into(),
+        Err(e) => e,
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    /// Parse a template definition such as `template area(r) := pi * r^2`.
+    ///
+    /// Errors in the body point at where they are in the whole definition.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{error::SpanError, template::Template, token::Span};
+    ///
+    /// let area: Template = "template area(r) := pi * r^2".parse().unwrap();
+    /// assert_eq!("area", area.name());
+    /// assert_eq!(vec!["r"], area.params());
+    ///
+    /// let e = "template f(x) := x + * 2".parse::<Template>().unwrap_err();
+    /// assert_eq!(Some(Span::new(21, 22)), e.downcast_ref::<SpanError>().map(|e| e.span));
+    ///
+    /// assert!("template f(x) = x".parse::<Template>().is_err());
+    /// assert!("template f(x, 2) := x".parse::<Template>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s
+            .trim_start()
+            .strip_prefix(KEYWORD)
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .ok_or_else(|| Message::new(Code::ExpectedTemplate))?;
+        let (head, body) = rest
+            .split_once(":=")
+            .ok_or_else(|| Message::new(Code::ExpectedDefinition))?;
+
+        let head = lex(&mut head.chars().peekable())?;
+        let (name, params) = match head.as_slice() {
+            [Token::Ident(name), Token::LParen, params @ .., Token::RParen] => {
+                (name.as_str(), params)
+            }
+            [Token::Func(f), ..] => {
+                bail!(Code::CannotRedefineBuiltin.with(&[&f.name()]))
+            }
+            _ => bail!(Message::new(Code::ExpectedTemplateHead)),
+        };
+
+        let params = match params {
+            [] => vec![],
+            params => params
+                .split(|t| *t == Token::Comma)
+                .map(|param| match param {
+                    [Token::Ident(param)] => Ok(param.as_str()),
+                    _ => {
+                        Err(Code::ExpectedParameterNames.with(&[&name]).into())
+                    }
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        // the body is lexed on its own, so its spans are moved to where it
+        // starts in the whole definition
+        let offset = s.chars().count() - body.chars().count();
+        let tokens = lex_spanned(&mut body.chars().peekable())
+            .map_err(|e| shift(e, offset))?;
+        let mut tokens = tokens
+            .into_iter()
+            .map(|(t, span)| {
+                (t, Span::new(span.start + offset, span.end + offset))
+            })
+            .peekable();
+
+        let body = parse_expr(&mut tokens, 0)?;
+        expect_end(&mut tokens)?;
+
+        Template::new(name, &params, body)
+    }
+}
+
+impl fmt::Display for Template {
+    /// Format the definition of the template, which parses back to the same
+    /// template.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}({}) := {}",
+            KEYWORD,
+            self.name,
+            self.params.join(", "),
+            self.body
+        )
+    }
+}
@@ -0,0 +1,238 @@
+//! Generators of random expressions, for programs which use cocoa to fuzz
+//! their own code with expressions that cocoa can parse, either through
+//! `arbitrary` or through `proptest`.
+//!
+//! Every expression generated is a tree that the parser could have made:
+//! it has a group wherever its formatted text needs brackets, and no
+//! negative numbers, so it parses back to itself once formatted.
+
+use arbitrary::{Arbitrary, Unstructured};
+use proptest::{
+    num,
+    prelude::{BoxedStrategy, Strategy},
+    prop_oneof,
+    sample::select,
+};
+
+use crate::{
+    ast::{Callee, Expr, ExprKind},
+    function::Arity,
+    token::{FuncKind, OpKind, Token},
+};
+
+/// The names of the variables in generated expressions, none of which are
+/// builtin.
+const NAMES: &[&str] = &["x", "y", "z", "a", "b", "n", "t"];
+
+/// The infix operators in generated expressions. The equals sign is left out
+/// since it only has a meaning inside `solve`.
+const OPERATORS: &[OpKind] = &[
+    OpKind::Plus,
+    OpKind::Minus,
+    OpKind::Star,
+    OpKind::Slash,
+    OpKind::Modulo,
+    OpKind::Caret,
+    OpKind::Shl,
+    OpKind::Shr,
+];
+
+/// How many levels deep generated expressions may be.
+const DEPTH: u32 = 4;
+
+/// A sequence of tokens which parses to an expression, generated from a
+/// random expression with `Expr::to_tokens`.
+///
+/// # Examples
+/// ```
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use cocoa::{parser::parse_expr, testing::TokenStream};
+///
+/// let bytes: Vec<u8> = (0..=255).collect();
+/// let TokenStream(tokens) =
+///     TokenStream::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+///
+/// assert!(parse_expr(&mut tokens.into_iter().peekable(), 0).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenStream(pub Vec<Token>);
+
+impl<'a> Arbitrary<'a> for Expr {
+    /// Generate an expression from the bytes in `u`.
+    ///
+    /// # Examples
+    /// ```
+    /// use arbitrary::{Arbitrary, Unstructured};
+    /// use cocoa::{ast::Expr, fmt::roundtrip_check};
+    ///
+    /// let bytes: Vec<u8> = (0..=255).rev().collect();
+    /// let expr = Expr::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+    ///
+    /// assert!(roundtrip_check(&expr).is_ok());
+    /// ```
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(reparse(&arbitrary_expr(u, DEPTH)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for TokenStream {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(TokenStream(Expr::arbitrary(u)?.to_tokens()))
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for Expr {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Expr>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        expr_strategy()
+    }
+}
+
+/// A proptest strategy for expressions, which is also used by
+/// `proptest::arbitrary::any::<Expr>()`.
+///
+/// Expressions shrink towards their leaves, and numbers towards zero.
+///
+/// # Examples
+/// ```
+/// use cocoa::{ast::Expr, fmt::roundtrip_check, testing::expr_strategy};
+/// use proptest::{strategy::Strategy, test_runner::TestRunner};
+///
+/// TestRunner::default()
+///     .run(&expr_strategy(), |expr| {
+///         assert!(roundtrip_check(&expr).is_ok());
+///         assert_eq!(expr, expr.to_string().parse::<Expr>().unwrap());
+///         Ok(())
+///     })
+///     .unwrap();
+/// ```
+pub fn expr_strategy() -> BoxedStrategy<Expr> {
+    let leaf = prop_oneof![
+        4 => (0u32..1000).prop_map(f64::from).prop_map(ExprKind::Number),
+        1 => (num::f64::POSITIVE | num::f64::NORMAL).prop_map(ExprKind::Number),
+        4 => select(NAMES).prop_map(|name| ExprKind::Ident(name.to_string())),
+        1 => "[ -~]{0,8}".prop_map(ExprKind::Str),
+    ]
+    .prop_map(Expr::new);
+
+    leaf.prop_recursive(DEPTH, 64, 3, |inner| {
+        prop_oneof![
+            (select(&[OpKind::Plus, OpKind::Minus][..]), inner.clone())
+                .prop_map(|(op, e)| ExprKind::Unary(op, Box::new(e))),
+            inner.clone().prop_map(|e| {
+                ExprKind::Postfix(OpKind::Factorial, Box::new(e))
+            }),
+            (select(OPERATORS), inner.clone(), inner.clone()).prop_map(
+                |(op, lhs, rhs)| {
+                    ExprKind::Binary(op, Box::new(lhs), Box::new(rhs))
+                }
+            ),
+            proptest::collection::vec(inner.clone(), 0..=3)
+                .prop_map(ExprKind::List),
+            select(functions()).prop_flat_map(move |f| {
+                let (min, max) = arg_counts(f);
+                proptest::collection::vec(inner.clone(), min..=max).prop_map(
+                    move |args| ExprKind::Call(Callee::Builtin(f), args),
+                )
+            }),
+        ]
+        .prop_map(Expr::new)
+    })
+    .prop_map(|e| reparse(&e))
+    .boxed()
+}
+
+/// A proptest strategy for sequences of tokens which parse to an
+/// expression, made from `expr_strategy`.
+///
+/// # Examples
+/// ```
+/// use cocoa::{parser::parse_expr, testing::token_strategy};
+/// use proptest::test_runner::TestRunner;
+///
+/// TestRunner::default()
+///     .run(&token_strategy(), |tokens| {
+///         assert!(parse_expr(&mut tokens.into_iter().peekable(), 0).is_ok());
+///         Ok(())
+///     })
+///     .unwrap();
+/// ```
+pub fn token_strategy() -> impl Strategy<Value = Vec<Token>> {
+    expr_strategy().prop_map(|e| e.to_tokens())
+}
+
+/// Generate an expression from the bytes in `u` which is at most `depth`
+/// levels deep, which may lack the groups the parser would add.
+fn arbitrary_expr(
+    u: &mut Unstructured<'_>,
+    depth: u32,
+) -> arbitrary::Result<Expr> {
+    let operand =
+        |u: &mut Unstructured<'_>| arbitrary_expr(u, depth - 1).map(Box::new);
+
+    // only leaves can be generated at the bottom of the tree
+    let choices = if depth == 0 { 3 } else { 8 };
+    let kind = match u.int_in_range(0..=choices - 1)? {
+        0 => {
+            let n = f64::arbitrary(u)?;
+            ExprKind::Number(if n.is_finite() { n.abs() } else { 0.0 })
+        }
+        1 => ExprKind::Ident(u.choose(NAMES)?.to_string()),
+        2 => ExprKind::Str(String::arbitrary(u)?),
+        3 => ExprKind::Unary(
+            *u.choose(&[OpKind::Plus, OpKind::Minus])?,
+            operand(u)?,
+        ),
+        4 => ExprKind::Postfix(OpKind::Factorial, operand(u)?),
+        5 => ExprKind::List(
+            (0..u.int_in_range(0..=3)?)
+                .map(|_| arbitrary_expr(u, depth - 1))
+                .collect::<arbitrary::Result<_>>()?,
+        ),
+        6 => {
+            let f = *u.choose(&functions())?;
+            let (min, max) = arg_counts(f);
+            let args = (0..u.int_in_range(min..=max)?)
+                .map(|_| arbitrary_expr(u, depth - 1))
+                .collect::<arbitrary::Result<_>>()?;
+
+            ExprKind::Call(Callee::Builtin(f), args)
+        }
+        _ => ExprKind::Binary(*u.choose(OPERATORS)?, operand(u)?, operand(u)?),
+    };
+
+    Ok(Expr::new(kind))
+}
+
+/// Get the builtin functions which generated expressions call, which are
+/// those whose arguments are ordinary expressions, rather than names bound
+/// by the function.
+fn functions() -> Vec<FuncKind> {
+    FuncKind::ALL
+        .iter()
+        .copied()
+        .filter(|f| !f.is_higher_order())
+        .collect()
+}
+
+/// Get the fewest and the most arguments `f` is called with in generated
+/// expressions.
+fn arg_counts(f: FuncKind) -> (usize, usize) {
+    match f.arity() {
+        Arity::Exact(n) => (n, n),
+        Arity::AtLeast(n) => (n, n + 2),
+        Arity::Between(min, max) => (min, max),
+    }
+}
+
+/// Parse the formatted text of `expr` back into a tree, adding the groups
+/// which the parser would have and the spans of each node.
+fn reparse(expr: &Expr) -> Expr {
+    // formatting never gives text which does not parse, and the numbers
+    // generated are finite so they are never formatted as names
+    expr.to_string()
+        .parse()
+        .expect("formatted expressions parse")
+}
@@ -1,24 +1,187 @@
 //! Implementation of data structures that represent expresso's input.
 
+use std::{collections::HashSet, fmt};
+
+use anyhow::{bail, Result};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+use crate::{
+    function::{check_arg, check_args, Arity, Category, Domain},
+    math::{
+        approx_eq, bits, catalan, cfrac, fibonacci, gcd, lambertw,
+        linalg::linsolve,
+        nextafter, nt,
+        poly::{cubic_roots, poly_roots, quadratic_roots},
+        relative_change, round,
+        special::{
+            binomcdf, binompdf, erf, erfc, ln_factorial, ln_gamma, normcdf,
+            norminv, normpdf, poissonpdf, tcdf,
+        },
+        stats, triangular, ulp, Rounding, DEFAULT_EPSILON,
+    },
+    message::{Code, Message},
+    value::{count_steps, Value, MAX_LEN},
+};
+
+#[cfg(feature = "bigint")]
+use crate::math::big;
+#[cfg(feature = "special-functions")]
+use crate::math::special::{besselj, beta, zeta};
+
 /// A valid token expresso understands.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     /// A valid operator.
     Op(OpKind),
     /// Builtin functions.
     Func(FuncKind),
+    /// A name that is not builtin, such as a function or constant provided by
+    /// a plugin.
+    Ident(String),
 
     /// A valid number represented as a 64-bit floating point value.
     Number(f64),
+    /// A string in double quotes, which can only be passed to functions
+    /// such as `env`.
+    Str(String),
 
     /// A left bracket (`(`).
     LParen,
     /// A right bracket (`)`).
     RParen,
+    /// A left square bracket (`[`) starting a list.
+    LBracket,
+    /// A right square bracket (`]`) ending a list.
+    RBracket,
+    /// A comma (`,`) separating the arguments of a function call or the
+    /// elements of a list.
+    Comma,
+}
+
+impl fmt::Display for Token {
+    /// Format `self` as it is written in an expression.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::{OpKind, Token};
+    ///
+    /// assert_eq!("2", Token::Number(2.0).to_string());
+    /// assert_eq!("<<", Token::Op(OpKind::Shl).to_string());
+    /// assert_eq!("\"a\"", Token::Str("a".to_string()).to_string());
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Op(op) => write!(f, "{}", op),
+            Token::Func(func) => write!(f, "{}", func.name()),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::LBracket => write!(f, "["),
+            Token::RBracket => write!(f, "]"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+/// A range of characters in the input, from `start` up to but not including
+/// `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// The index of the first character in the span.
+    pub start: usize,
+    /// The index of the character after the last character in the span.
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a span from `start` up to but not including `end`.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Create a span covering both `self` and `other`, and anything in
+    /// between.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::Span;
+    ///
+    /// assert_eq!(Span::new(2, 9), Span::new(2, 4).to(Span::new(7, 9)));
+    /// ```
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// A token which may know the span of the input it was lexed from.
+///
+/// This lets the parser accept both plain `Token`s and the `(Token, Span)`
+/// pairs produced by `lex_spanned`, only reporting spans in errors for the
+/// latter.
+pub trait Spanned {
+    /// Get the token itself.
+    fn token(&self) -> &Token;
+
+    /// Get the span of the input the token was lexed from, if known.
+    fn span(&self) -> Option<Span>;
+
+    /// Discard the span, returning the token itself.
+    fn into_token(self) -> Token;
+}
+
+impl Spanned for Token {
+    fn token(&self) -> &Token {
+        self
+    }
+
+    fn span(&self) -> Option<Span> {
+        None
+    }
+
+    fn into_token(self) -> Token {
+        self
+    }
+}
+
+impl Spanned for (Token, Option<Span>) {
+    fn token(&self) -> &Token {
+        &self.0
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.1
+    }
+
+    fn into_token(self) -> Token {
+        self.0
+    }
+}
+
+impl Spanned for (Token, Span) {
+    fn token(&self) -> &Token {
+        &self.0
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.1)
+    }
+
+    fn into_token(self) -> Token {
+        self.0
+    }
 }
 
 /// All operators that expresso supports.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum OpKind {
     /// Addition operator.
     Plus,
@@ -34,10 +197,242 @@ pub enum OpKind {
     Caret,
     /// Factorial operator.
     Factorial,
+    /// Left shift operator.
+    Shl,
+    /// Arithmetic right shift operator.
+    Shr,
+    /// The equals sign of an equation, which only has a meaning in the
+    /// equation passed to `solve`.
+    Equals,
+}
+
+impl fmt::Display for OpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            OpKind::Plus => "+",
+            OpKind::Minus => "-",
+            OpKind::Star => "*",
+            OpKind::Slash => "/",
+            OpKind::Modulo => "%",
+            OpKind::Caret => "^",
+            OpKind::Factorial => "!",
+            OpKind::Shl => "<<",
+            OpKind::Shr => ">>",
+            OpKind::Equals => "=",
+        };
+
+        write!(f, "{}", symbol)
+    }
+}
+
+impl OpKind {
+    /// Every operator, in the order they are declared.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{lexer::lex, token::{OpKind, Token}};
+    ///
+    /// for &op in OpKind::ALL {
+    ///     let symbol = op.to_string();
+    ///     let tokens = lex(&mut symbol.chars().peekable()).unwrap();
+    ///     assert_eq!(vec![Token::Op(op)], tokens);
+    /// }
+    /// ```
+    pub const ALL: &'static [OpKind] = &[
+        OpKind::Plus,
+        OpKind::Minus,
+        OpKind::Star,
+        OpKind::Slash,
+        OpKind::Modulo,
+        OpKind::Caret,
+        OpKind::Factorial,
+        OpKind::Shl,
+        OpKind::Shr,
+        OpKind::Equals,
+    ];
+
+    /// Iterate over every operator, in the order they are declared.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::OpKind;
+    ///
+    /// assert_eq!(Some(OpKind::Plus), OpKind::all().next());
+    /// assert_eq!(OpKind::ALL.len(), OpKind::all().count());
+    /// ```
+    pub fn all() -> impl Iterator<Item = OpKind> {
+        Self::ALL.iter().copied()
+    }
+
+    /// Get the name of the operation `self` performs, as opposed to the
+    /// symbol it is written with, which is its `Display`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::OpKind;
+    ///
+    /// assert_eq!("remainder", OpKind::Modulo.name());
+    /// assert_eq!("%", OpKind::Modulo.to_string());
+    /// ```
+    pub fn name(self) -> &'static str {
+        match self {
+            OpKind::Plus => "addition",
+            OpKind::Minus => "subtraction",
+            OpKind::Star => "multiplication",
+            OpKind::Slash => "division",
+            OpKind::Modulo => "remainder",
+            OpKind::Caret => "exponentiation",
+            OpKind::Factorial => "factorial",
+            OpKind::Shl => "left shift",
+            OpKind::Shr => "right shift",
+            OpKind::Equals => "equation",
+        }
+    }
+
+    /// Get the number of operands `self` takes. `+` and `-` also take a
+    /// single operand when written before it, e.g. `-2`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{function::Arity, token::OpKind};
+    ///
+    /// assert_eq!(Arity::Exact(2), OpKind::Caret.arity());
+    /// assert_eq!(Arity::Exact(1), OpKind::Factorial.arity());
+    /// ```
+    pub fn arity(self) -> Arity {
+        Arity::Exact(self.params().len())
+    }
+
+    /// Get the kind of token `self` is, which is always
+    /// `Category::Operators`.
+    pub fn category(self) -> Category {
+        Category::Operators
+    }
+
+    /// Get the names of the operands of `self`, which describe what each is
+    /// in its documentation.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::OpKind;
+    ///
+    /// assert_eq!(&["a", "b"], OpKind::Plus.params());
+    /// assert_eq!(&["n"], OpKind::Factorial.params());
+    /// ```
+    pub fn params(self) -> &'static [&'static str] {
+        match self {
+            OpKind::Factorial => &["n"],
+            OpKind::Equals => &["lhs", "rhs"],
+            _ => &["a", "b"],
+        }
+    }
+
+    /// Get how `self` is written between or after its operands.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::OpKind;
+    ///
+    /// assert_eq!("a % b", OpKind::Modulo.signature());
+    /// assert_eq!("n!", OpKind::Factorial.signature());
+    /// ```
+    pub fn signature(self) -> String {
+        match self.params() {
+            [operand] => format!("{}{}", operand, self),
+            params => params.join(&format!(" {} ", self)),
+        }
+    }
+
+    /// Get the set of values the operand of `self` at index `arg` may take.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg` - The index of the operand.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{function::Domain, token::OpKind};
+    ///
+    /// assert_eq!(Domain::Natural, OpKind::Factorial.domain(0));
+    /// assert_eq!(Domain::Real, OpKind::Plus.domain(1));
+    /// ```
+    pub fn domain(self, arg: usize) -> Domain {
+        match (self, arg) {
+            (OpKind::Factorial, _) => Domain::Natural,
+            (OpKind::Shl | OpKind::Shr, 0) => Domain::Integer,
+            (OpKind::Shl | OpKind::Shr, _) => Domain::Natural,
+            _ => Domain::Real,
+        }
+    }
+
+    /// Get a description of what `self` evaluates to.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::OpKind;
+    ///
+    /// assert_eq!("The product of a and b.", OpKind::Star.description());
+    /// ```
+    pub fn description(self) -> &'static str {
+        match self {
+            OpKind::Plus => "The sum of a and b.",
+            OpKind::Minus => {
+                "The difference of a and b, or the negation of b when it is \
+                 written before b alone."
+            }
+            OpKind::Star => "The product of a and b.",
+            OpKind::Slash => "The quotient of a and b.",
+            OpKind::Modulo => {
+                "The Euclidean remainder of dividing a by b, which is never \
+                 negative."
+            }
+            OpKind::Caret => {
+                "a raised to the power b, where a ^ b ^ c is a ^ (b ^ c)."
+            }
+            OpKind::Factorial => "The factorial of n, 1 * 2 * ... * n.",
+            OpKind::Shl => "a shifted left by b bits, i.e. a * 2^b.",
+            OpKind::Shr => {
+                "a shifted right by b bits, rounding toward negative infinity."
+            }
+            OpKind::Equals => {
+                "The equals sign of an equation, which only has a meaning in \
+                 the equation passed to solve."
+            }
+        }
+    }
+
+    /// Get expressions showing how `self` is used.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, token::OpKind};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// for &op in OpKind::ALL {
+    ///     for example in op.examples() {
+    ///         assert!(evaluator.eval_value(example).is_ok(), "{}", example);
+    ///     }
+    /// }
+    /// ```
+    pub fn examples(self) -> &'static [&'static str] {
+        match self {
+            OpKind::Plus => &["1 + 2"],
+            OpKind::Minus => &["5 - 3", "-(2 + 3)"],
+            OpKind::Star => &["6 * 7"],
+            OpKind::Slash => &["1 / 4"],
+            OpKind::Modulo => &["7 % 3", "-7 % 3"],
+            OpKind::Caret => &["2 ^ 10", "2 ^ 3 ^ 2"],
+            OpKind::Factorial => &["5!"],
+            OpKind::Shl => &["1 << 10"],
+            OpKind::Shr => &["-9 >> 1"],
+            OpKind::Equals => &["solve(2 * x = 8, x)"],
+        }
+    }
 }
 
 /// All functions that expresso supports.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FuncKind {
     /// Trignometric sine.
     Sin,
@@ -63,6 +458,380 @@ pub enum FuncKind {
     Log,
     /// Square root
     Sqrt,
+    /// The largest of any number of arguments.
+    Max,
+    /// The smallest of any number of arguments.
+    Min,
+    /// The sum of any number of arguments.
+    Sum,
+    /// The arithmetic mean of any number of arguments.
+    Avg,
+    /// The greatest common divisor of two or more integers.
+    Gcd,
+    /// Rounding to a given number of digits after the decimal point, or to the
+    /// nearest integer if the number of digits is omitted.
+    Round,
+    /// The change from a first number to a second relative to the first, as
+    /// a percentage, `pctchange(a, b)`.
+    PctChange,
+    /// The change from a first number to a second relative to the first, as
+    /// a fraction, `relchange(a, b)`.
+    RelChange,
+    /// The gap between a number and the next representable float further
+    /// from zero, i.e. its unit in the last place.
+    Ulp,
+    /// The next representable float after the first argument in the direction
+    /// of the second.
+    NextAfter,
+    /// Fused multiply-add; `fma(a, b, c)` is `a * b + c` with a single
+    /// rounding error.
+    Fma,
+    /// The error function.
+    Erf,
+    /// The complementary error function, `1 - erf(x)`.
+    Erfc,
+    /// The probability density of a normal distribution, `normpdf(x, mu,
+    /// sigma)`, where the mean and standard deviation default to 0 and 1.
+    NormPdf,
+    /// The cumulative distribution function of a normal distribution,
+    /// `normcdf(x, mu, sigma)`, where the mean and standard deviation default to
+    /// 0 and 1.
+    NormCdf,
+    /// The inverse of `normcdf`, `norminv(p, mu, sigma)`, where the mean and
+    /// standard deviation default to 0 and 1.
+    NormInv,
+    /// The probability of exactly k successes in n trials which each succeed
+    /// with probability p, `binompdf(n, p, k)`.
+    BinomPdf,
+    /// The probability of at most k successes in n trials which each succeed
+    /// with probability p, `binomcdf(n, p, k)`.
+    BinomCdf,
+    /// The probability of exactly k events in a Poisson process where lambda
+    /// events are expected, `poissonpdf(lambda, k)`.
+    PoissonPdf,
+    /// The cumulative distribution function of Student's t-distribution with
+    /// nu degrees of freedom, `tcdf(x, nu)`.
+    TCdf,
+    /// The Bessel function of the first kind of integer order n,
+    /// `besselj(n, x)`.
+    #[cfg(feature = "special-functions")]
+    BesselJ,
+    /// The Riemann zeta function.
+    #[cfg(feature = "special-functions")]
+    Zeta,
+    /// The beta function, `beta(a, b)`.
+    #[cfg(feature = "special-functions")]
+    Beta,
+    /// The principal branch of the Lambert W function, the inverse of
+    /// `x * e^x`.
+    LambertW,
+    /// The natural logarithm of the absolute value of the gamma function,
+    /// which can be found for numbers whose gamma function overflows.
+    LGamma,
+    /// The natural logarithm of the factorial of a number, which can be found
+    /// for numbers whose factorial overflows.
+    LnFact,
+    /// The nth fibonacci number.
+    Fib,
+    /// The nth triangular number.
+    Tri,
+    /// The nth catalan number.
+    Catalan,
+    /// The number of primes less than or equal to a number.
+    PrimePi,
+    /// The kth prime, where the 1st prime is 2.
+    Prime,
+    /// Euler's totient function, the number of integers from 1 to n that are
+    /// coprime to n.
+    Totient,
+    /// The number of positive divisors of n.
+    Divisors,
+    /// The sum of the kth powers of the positive divisors of n,
+    /// `sigma(n, k)`, where k defaults to 1.
+    Sigma,
+    /// The first terms of the simple continued fraction expansion of a number
+    /// as a list, `cfrac(x, n)`, which stops early once it is exact.
+    Cfrac,
+    /// The number of set bits in the two's complement representation of an
+    /// integer.
+    Popcount,
+    /// Rotate the bits of an integer left, `rotl(x, n, width)`, where the width
+    /// defaults to 64 bits.
+    Rotl,
+    /// Rotate the bits of an integer right, `rotr(x, n, width)`, where the
+    /// width defaults to 64 bits.
+    Rotr,
+    /// The bit of an integer at an index, `bit(x, i)`, where bit 0 is the
+    /// least significant bit.
+    Bit,
+    /// The mantissa of a float, such that `x = mantissa(x) * 2^exponent(x)`.
+    Mantissa,
+    /// The exponent of a float, such that `x = mantissa(x) * 2^exponent(x)`.
+    Exponent,
+    /// The sign, biased exponent and mantissa fields of the IEEE-754
+    /// representation of a float as a list, `bits(x)`.
+    Bits,
+    /// The float with the given IEEE-754 fields, `frombits(sign, exponent,
+    /// mantissa)`, the inverse of `bits`.
+    FromBits,
+    /// A uniformly distributed pseudo-random number in [0, 1), `rand()`,
+    /// which is reproducible if the evaluator is seeded.
+    Rand,
+    /// Fails unless its argument is non-zero, `assert(x)`, and otherwise
+    /// evaluates to it.
+    Assert,
+    /// Fails unless its first two arguments differ by at most a tolerance,
+    /// `assert_eq(a, b, tol)`, and otherwise evaluates to the first. The
+    /// tolerance defaults to 0.
+    AssertEq,
+    /// 1 if its first two arguments are equal to within a tolerance, relative
+    /// to their magnitude once it is larger than 1, and 0 otherwise,
+    /// `approx(a, b, eps)`. The tolerance defaults to `1e-9`.
+    Approx,
+    /// The value of an environment variable as a number, `env("VAR")`.
+    Env,
+    /// The list of whitespace separated numbers in a file,
+    /// `load("data.txt")`.
+    Load,
+    /// The list of whitespace separated numbers read from standard input,
+    /// `stdin()`.
+    Stdin,
+    /// A list sorted in ascending order.
+    Sort,
+    /// A list in reverse order.
+    Reverse,
+    /// The element of a list at an index, `nth(list, i)`, where the first
+    /// element is at index 1.
+    Nth,
+    /// The first element of a list.
+    Head,
+    /// A list without its first element.
+    Tail,
+    /// A list without any repeated elements, keeping the first of each.
+    Unique,
+    /// The list of numbers from a start up to and including a stop in
+    /// increments of a step, `range(start, stop, step)`, where the step
+    /// defaults to 1.
+    Range,
+    /// The distinct real roots of `ax^2 + bx + c` in ascending order,
+    /// `quadroots(a, b, c)`.
+    QuadRoots,
+    /// The distinct real roots of `ax^3 + bx^2 + cx + d` in ascending order,
+    /// `cubicroots(a, b, c, d)`.
+    CubicRoots,
+    /// The distinct real roots of a polynomial in ascending order,
+    /// `polyroots([an, ..., a1, a0])`, where `ai` is the coefficient of x^i.
+    PolyRoots,
+    /// The solution of the system of linear equations `ax = b`,
+    /// `linsolve(a, b)`, where `a` is a square matrix given row by row as a
+    /// list.
+    LinSolve,
+    /// The coefficients `[an, ..., a1, a0]` of the polynomial of degree n
+    /// fitted to points by least squares, `fit(xs, ys, n)`, where the degree
+    /// defaults to 1 for a line.
+    Fit,
+    /// A list with an expression evaluated at each element,
+    /// `map(expr, var, list)`, where `var` is bound to each element in turn.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// assert_eq!(
+    ///     Value::List(vec![1.0, 9.0]),
+    ///     evaluator
+    ///         .eval_value("map(x^2, x, filter(x % 2, x, [1, 2, 3]))")
+    ///         .unwrap()
+    /// );
+    /// ```
+    Map,
+    /// The list of values of an expression as a variable goes from a start to
+    /// a stop in steps, `table(expr, var, start, stop, step)`, where the step
+    /// defaults to 1.
+    Table,
+    /// The elements of a list for which a condition is not zero,
+    /// `filter(cond, var, list)`, where `var` is bound to each element in
+    /// turn.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// assert_eq!(
+    ///     Value::List(vec![1.0, 3.0]),
+    ///     evaluator.eval_value("filter(x % 2, x, [1, 2, 3])").unwrap()
+    /// );
+    /// ```
+    Filter,
+    /// A list combined into a single number, `reduce(expr, acc, var, list)`,
+    /// where `acc` starts as the first element and is replaced by `expr`
+    /// evaluated with `var` bound to each later element in turn.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// assert_eq!(
+    ///     24.0,
+    ///     evaluator.eval("reduce(acc * x, acc, x, range(1, 4))").unwrap()
+    /// );
+    /// ```
+    Reduce,
+    /// The integral of an expression estimated from random samples,
+    /// `montecarlo(expr, var, a, b, n)`, where `var` is bound to `n` points
+    /// drawn uniformly from [a, b]. This is the list of the estimate and its
+    /// standard error.
+    ///
+    /// The estimate is reproducible if the evaluator is seeded. The body is
+    /// evaluated with the settings of the evaluator, such as how `round`
+    /// rounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, math::Rounding, value::Value};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    /// evaluator.set_seed(Some(7));
+    ///
+    /// let estimate = evaluator
+    ///     .eval_value("montecarlo(x^2, x, 0, 3, 10000)")
+    ///     .unwrap();
+    /// match estimate.into_list()[..] {
+    ///     [n, error] => assert!((n - 9.0).abs() < 5.0 * error),
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// evaluator.set_rounding(Rounding::HalfEven);
+    /// assert_eq!(2.0, evaluator.eval("round(2.5)").unwrap());
+    /// assert_eq!(
+    ///     Value::List(vec![2.0, 0.0]),
+    ///     evaluator
+    ///         .eval_value("montecarlo(round(2.5), x, 0, 1, 10)")
+    ///         .unwrap()
+    /// );
+    /// ```
+    MonteCarlo,
+    /// The solution of dy/dx = expr at `x1`, `odesolve(expr, x0, y0, x1,
+    /// steps, every)`, where `expr` refers to `x` and `y` and y is `y0` at
+    /// `x0`. If `every` is non-zero, this is the list of the values of y at
+    /// `x0` and after each step instead. `every` defaults to 0.
+    ///
+    /// The body is evaluated with the settings of the evaluator, such as the
+    /// width integers wrap at.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, value::Value};
+    ///
+    /// let mut evaluator = Evaluator::new();
+    ///
+    /// // y = e^x from 0 to 1
+    /// let e = evaluator.eval("odesolve(y, 0, 1, 1, 100)").unwrap();
+    /// assert!((e - std::f64::consts::E).abs() < 1e-9);
+    /// assert_eq!(
+    ///     Value::List(vec![0.0, 1.0, 4.0]),
+    ///     evaluator.eval_value("odesolve(2 * x, 0, 0, 2, 2, 1)").unwrap()
+    /// );
+    ///
+    /// // a constant slope over a single step of 6
+    /// evaluator.set_width(Some(8)).unwrap();
+    /// assert_eq!(-128.0, evaluator.eval("127 + 1").unwrap());
+    /// assert_eq!(
+    ///     -768.0,
+    ///     evaluator.eval("odesolve(127 + 1, 0, 0, 6, 1)").unwrap()
+    /// );
+    /// ```
+    OdeSolve,
+    /// The solution of an equation which is linear in a variable,
+    /// `solve(lhs = rhs, var)`, found by rearranging it.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::evaluator::Evaluator;
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// assert_eq!(4.0, evaluator.eval("solve(2 * x + 3 = 11, x)").unwrap());
+    /// assert!(evaluator.eval("solve(x^2 = 4, x)").is_err());
+    /// // equations are only allowed in solve
+    /// assert!(evaluator.eval("2 = 2").is_err());
+    /// ```
+    Solve,
+}
+
+/// The named constants that expresso supports, which are lexed as the numbers
+/// they stand for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Constant {
+    /// The ratio of a circle's circumference to its diameter.
+    Pi,
+}
+
+impl Constant {
+    /// Every constant, in the order they are declared.
+    pub const ALL: &'static [Constant] = &[Constant::Pi];
+
+    /// Iterate over every constant, in the order they are declared.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, token::Constant};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// for c in Constant::all() {
+    ///     assert_eq!(c.value(), evaluator.eval(c.name()).unwrap());
+    /// }
+    /// ```
+    pub fn all() -> impl Iterator<Item = Constant> {
+        Self::ALL.iter().copied()
+    }
+
+    /// Get the name `self` is referred to by in expressions.
+    pub fn name(self) -> &'static str {
+        match self {
+            Constant::Pi => "pi",
+        }
+    }
+
+    /// Get the number `self` stands for, which is rounded to the nearest
+    /// float.
+    pub fn value(self) -> f64 {
+        match self {
+            Constant::Pi => std::f64::consts::PI,
+        }
+    }
+
+    /// Get a description of what `self` is.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::Constant;
+    ///
+    /// assert_eq!(
+    ///     "The ratio of a circle's circumference to its diameter.",
+    ///     Constant::Pi.description()
+    /// );
+    /// ```
+    pub fn description(self) -> &'static str {
+        match self {
+            Constant::Pi => {
+                "The ratio of a circle's circumference to its diameter."
+            }
+        }
+    }
+
+    /// Get the kind of token `self` is, which is always
+    /// `Category::Constants`.
+    pub fn category(self) -> Category {
+        Category::Constants
+    }
 }
 
 /// All sets of tokens with associated binding powers implement `Bindable`.
@@ -98,13 +867,19 @@ impl Bindable for OpKind {
     /// use cocoa::token::{Bindable, OpKind};
     ///
     /// assert_eq!(OpKind::Plus.bp(), OpKind::Minus.bp());
+    /// assert!(OpKind::Plus.bp() > OpKind::Shl.bp());
     /// assert!(OpKind::Star.bp() > OpKind::Plus.bp());
     /// assert!(OpKind::Modulo.bp() > OpKind::Star.bp());
     /// assert!(OpKind::Caret.bp() > OpKind::Modulo.bp());
     /// assert!(OpKind::Factorial.bp() > OpKind::Modulo.bp());
+    /// assert!(OpKind::Shl.bp() > OpKind::Equals.bp());
     /// ```
     fn bp(self) -> u8 {
         match self {
+            // the sides of an equation are whole expressions
+            OpKind::Equals => 1,
+            // shifts bind looser than arithmetic, as they do in C
+            OpKind::Shl | OpKind::Shr => 3,
             OpKind::Plus | OpKind::Minus => 5,
             OpKind::Star | OpKind::Slash => 10,
             OpKind::Modulo => 15,
@@ -134,12 +909,131 @@ impl Bindable for FuncKind {
 }
 
 impl FuncKind {
+    /// Every function, in the order they are declared.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{lexer::lex, token::{FuncKind, Token}};
+    ///
+    /// for &f in FuncKind::ALL {
+    ///     assert_eq!(vec![Token::Func(f)], lex(&mut f.name().chars().peekable()).unwrap());
+    /// }
+    /// ```
+    pub const ALL: &'static [FuncKind] = &[
+        FuncKind::Sin,
+        FuncKind::Cos,
+        FuncKind::Tan,
+        FuncKind::Asin,
+        FuncKind::Acos,
+        FuncKind::Atan,
+        FuncKind::Deg,
+        FuncKind::Rad,
+        FuncKind::Exp,
+        FuncKind::Ln,
+        FuncKind::Log,
+        FuncKind::Sqrt,
+        FuncKind::Max,
+        FuncKind::Min,
+        FuncKind::Sum,
+        FuncKind::Avg,
+        FuncKind::Gcd,
+        FuncKind::Round,
+        FuncKind::PctChange,
+        FuncKind::RelChange,
+        FuncKind::Ulp,
+        FuncKind::NextAfter,
+        FuncKind::Fma,
+        FuncKind::Erf,
+        FuncKind::Erfc,
+        FuncKind::NormPdf,
+        FuncKind::NormCdf,
+        FuncKind::NormInv,
+        FuncKind::BinomPdf,
+        FuncKind::BinomCdf,
+        FuncKind::PoissonPdf,
+        FuncKind::TCdf,
+        #[cfg(feature = "special-functions")]
+        FuncKind::BesselJ,
+        #[cfg(feature = "special-functions")]
+        FuncKind::Zeta,
+        #[cfg(feature = "special-functions")]
+        FuncKind::Beta,
+        FuncKind::LambertW,
+        FuncKind::LGamma,
+        FuncKind::LnFact,
+        FuncKind::Fib,
+        FuncKind::Tri,
+        FuncKind::Catalan,
+        FuncKind::PrimePi,
+        FuncKind::Prime,
+        FuncKind::Totient,
+        FuncKind::Divisors,
+        FuncKind::Sigma,
+        FuncKind::Cfrac,
+        FuncKind::Popcount,
+        FuncKind::Rotl,
+        FuncKind::Rotr,
+        FuncKind::Bit,
+        FuncKind::Mantissa,
+        FuncKind::Exponent,
+        FuncKind::Bits,
+        FuncKind::FromBits,
+        FuncKind::Rand,
+        FuncKind::Assert,
+        FuncKind::AssertEq,
+        FuncKind::Approx,
+        FuncKind::Env,
+        FuncKind::Load,
+        FuncKind::Stdin,
+        FuncKind::Sort,
+        FuncKind::Reverse,
+        FuncKind::Nth,
+        FuncKind::Head,
+        FuncKind::Tail,
+        FuncKind::Unique,
+        FuncKind::Range,
+        FuncKind::QuadRoots,
+        FuncKind::CubicRoots,
+        FuncKind::PolyRoots,
+        FuncKind::LinSolve,
+        FuncKind::Fit,
+        FuncKind::Map,
+        FuncKind::Table,
+        FuncKind::Filter,
+        FuncKind::Reduce,
+        FuncKind::MonteCarlo,
+        FuncKind::OdeSolve,
+        FuncKind::Solve,
+    ];
+
+    /// Iterate over every function, in the order they are declared.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{function::Category, token::FuncKind};
+    ///
+    /// let trigonometry: Vec<&str> = FuncKind::all()
+    ///     .filter(|f| f.category() == Category::Trigonometry)
+    ///     .map(FuncKind::name)
+    ///     .collect();
+    /// assert_eq!(
+    ///     vec!["sin", "cos", "tan", "asin", "acos", "atan", "deg", "rad"],
+    ///     trigonometry
+    /// );
+    /// ```
+    pub fn all() -> impl Iterator<Item = FuncKind> {
+        Self::ALL.iter().copied()
+    }
+
     /// Evaluate the given function at `input`.
     ///
     /// The result of these functions is entirely dependant on the way floating
     /// point arithmetic is implemented in rust, hence a floating point
     /// error may appear in calculations.
     ///
+    /// Functions that cannot be called with a single argument, such as `fma`,
+    /// evaluate to NaN, `call` evaluates them with all of their arguments.
+    ///
     /// # Arguments
     ///
     /// * `input` - The value to evaluate the function at.
@@ -164,6 +1058,1418 @@ impl FuncKind {
             FuncKind::Ln => input.ln(),
             FuncKind::Log => input.log10(),
             FuncKind::Sqrt => input.sqrt(),
+            // variadic functions called with a single argument
+            FuncKind::Max | FuncKind::Min | FuncKind::Sum | FuncKind::Avg => {
+                input
+            }
+            FuncKind::Gcd => input.abs(),
+            FuncKind::Round => round(input, 0, Rounding::default()),
+            FuncKind::Ulp => ulp(input),
+            FuncKind::Erf => erf(input),
+            FuncKind::Erfc => erfc(input),
+            FuncKind::NormPdf => normpdf(input, 0.0, 1.0),
+            FuncKind::NormCdf => normcdf(input, 0.0, 1.0),
+            FuncKind::NormInv => norminv(input, 0.0, 1.0),
+            #[cfg(feature = "special-functions")]
+            FuncKind::Zeta => zeta(input),
+            FuncKind::LambertW => lambertw(input),
+            FuncKind::LGamma => ln_gamma(input),
+            FuncKind::LnFact => ln_factorial(input),
+            // too large to be exact, though not to be represented
+            FuncKind::Fib | FuncKind::Tri | FuncKind::Catalan => {
+                sequence(self, input).unwrap_or(f64::INFINITY)
+            }
+            // beyond the limits of the sieve
+            FuncKind::PrimePi | FuncKind::Prime => {
+                primes(self, input).unwrap_or(f64::NAN)
+            }
+            // casting is safe since only integers are in the domain of these,
+            // though huge ones saturate
+            FuncKind::Totient => nt::totient(input as u64) as f64,
+            FuncKind::Divisors => nt::sigma(input as u64, 0),
+            FuncKind::Sigma => nt::sigma(input as u64, 1),
+            FuncKind::Popcount => bits::popcount(input) as f64,
+            FuncKind::Mantissa => bits::mantissa(input),
+            FuncKind::Exponent => bits::exponent(input) as f64,
+            // functions which cannot be called with a single argument
+            FuncKind::PctChange
+            | FuncKind::RelChange
+            | FuncKind::NextAfter
+            | FuncKind::Fma
+            | FuncKind::FromBits
+            | FuncKind::BinomPdf
+            | FuncKind::BinomCdf
+            | FuncKind::PoissonPdf
+            | FuncKind::TCdf
+            | FuncKind::Rotl
+            | FuncKind::Rotr
+            | FuncKind::Bit
+            | FuncKind::Rand
+            | FuncKind::Assert
+            | FuncKind::AssertEq
+            | FuncKind::Approx
+            | FuncKind::Env
+            | FuncKind::Load => f64::NAN,
+            // functions of lists, which `call_list` evaluates
+            FuncKind::Sort
+            | FuncKind::Reverse
+            | FuncKind::Nth
+            | FuncKind::Head
+            | FuncKind::Tail
+            | FuncKind::Unique
+            | FuncKind::Range
+            | FuncKind::Cfrac
+            | FuncKind::Bits
+            | FuncKind::QuadRoots
+            | FuncKind::CubicRoots
+            | FuncKind::PolyRoots
+            | FuncKind::LinSolve
+            | FuncKind::Fit
+            | FuncKind::Stdin
+            | FuncKind::Map
+            | FuncKind::Table
+            | FuncKind::Filter
+            | FuncKind::Reduce
+            | FuncKind::MonteCarlo
+            | FuncKind::OdeSolve
+            | FuncKind::Solve => f64::NAN,
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ | FuncKind::Beta => f64::NAN,
+        }
+    }
+
+    /// Get the name `self` is referred to by in expressions.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert_eq!("asin", FuncKind::Asin.name());
+    /// ```
+    pub fn name(self) -> &'static str {
+        match self {
+            FuncKind::Sin => "sin",
+            FuncKind::Cos => "cos",
+            FuncKind::Tan => "tan",
+            FuncKind::Asin => "asin",
+            FuncKind::Acos => "acos",
+            FuncKind::Atan => "atan",
+            FuncKind::Deg => "deg",
+            FuncKind::Rad => "rad",
+            FuncKind::Exp => "exp",
+            FuncKind::Ln => "ln",
+            FuncKind::Log => "log",
+            FuncKind::Sqrt => "sqrt",
+            FuncKind::Max => "max",
+            FuncKind::Min => "min",
+            FuncKind::Sum => "sum",
+            FuncKind::Avg => "avg",
+            FuncKind::Gcd => "gcd",
+            FuncKind::Round => "round",
+            FuncKind::PctChange => "pctchange",
+            FuncKind::RelChange => "relchange",
+            FuncKind::Ulp => "ulp",
+            FuncKind::NextAfter => "nextafter",
+            FuncKind::Fma => "fma",
+            FuncKind::Erf => "erf",
+            FuncKind::Erfc => "erfc",
+            FuncKind::NormPdf => "normpdf",
+            FuncKind::NormCdf => "normcdf",
+            FuncKind::NormInv => "norminv",
+            FuncKind::BinomPdf => "binompdf",
+            FuncKind::BinomCdf => "binomcdf",
+            FuncKind::PoissonPdf => "poissonpdf",
+            FuncKind::TCdf => "tcdf",
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ => "besselj",
+            #[cfg(feature = "special-functions")]
+            FuncKind::Zeta => "zeta",
+            #[cfg(feature = "special-functions")]
+            FuncKind::Beta => "beta",
+            FuncKind::LambertW => "lambertw",
+            FuncKind::LGamma => "lgamma",
+            FuncKind::LnFact => "lnfact",
+            FuncKind::Fib => "fib",
+            FuncKind::Tri => "tri",
+            FuncKind::Catalan => "catalan",
+            FuncKind::PrimePi => "primepi",
+            FuncKind::Prime => "prime",
+            FuncKind::Totient => "totient",
+            FuncKind::Divisors => "divisors",
+            FuncKind::Sigma => "sigma",
+            FuncKind::Cfrac => "cfrac",
+            FuncKind::Popcount => "popcount",
+            FuncKind::Rotl => "rotl",
+            FuncKind::Rotr => "rotr",
+            FuncKind::Bit => "bit",
+            FuncKind::Mantissa => "mantissa",
+            FuncKind::Exponent => "exponent",
+            FuncKind::Bits => "bits",
+            FuncKind::FromBits => "frombits",
+            FuncKind::Rand => "rand",
+            FuncKind::Assert => "assert",
+            FuncKind::AssertEq => "assert_eq",
+            FuncKind::Approx => "approx",
+            FuncKind::Env => "env",
+            FuncKind::Load => "load",
+            FuncKind::Stdin => "stdin",
+            FuncKind::Sort => "sort",
+            FuncKind::Reverse => "reverse",
+            FuncKind::Nth => "nth",
+            FuncKind::Head => "head",
+            FuncKind::Tail => "tail",
+            FuncKind::Unique => "unique",
+            FuncKind::Range => "range",
+            FuncKind::QuadRoots => "quadroots",
+            FuncKind::CubicRoots => "cubicroots",
+            FuncKind::PolyRoots => "polyroots",
+            FuncKind::LinSolve => "linsolve",
+            FuncKind::Fit => "fit",
+            FuncKind::Map => "map",
+            FuncKind::Table => "table",
+            FuncKind::Filter => "filter",
+            FuncKind::Reduce => "reduce",
+            FuncKind::MonteCarlo => "montecarlo",
+            FuncKind::OdeSolve => "odesolve",
+            FuncKind::Solve => "solve",
+        }
+    }
+
+    /// Get the number of arguments `self` takes.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{function::Arity, token::FuncKind};
+    ///
+    /// assert_eq!(Arity::Exact(1), FuncKind::Sin.arity());
+    /// assert_eq!(Arity::AtLeast(1), FuncKind::Max.arity());
+    /// ```
+    pub fn arity(self) -> Arity {
+        match self {
+            FuncKind::Max | FuncKind::Min | FuncKind::Sum | FuncKind::Avg => {
+                Arity::AtLeast(1)
+            }
+            FuncKind::Gcd => Arity::AtLeast(2),
+            FuncKind::Round => Arity::Between(1, 2),
+            FuncKind::PctChange | FuncKind::RelChange => Arity::Exact(2),
+            FuncKind::NextAfter => Arity::Exact(2),
+            FuncKind::Fma | FuncKind::FromBits => Arity::Exact(3),
+            FuncKind::NormPdf | FuncKind::NormCdf | FuncKind::NormInv => {
+                Arity::Between(1, 3)
+            }
+            FuncKind::BinomPdf | FuncKind::BinomCdf => Arity::Exact(3),
+            FuncKind::PoissonPdf | FuncKind::TCdf => Arity::Exact(2),
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ | FuncKind::Beta => Arity::Exact(2),
+            FuncKind::Sigma => Arity::Between(1, 2),
+            FuncKind::Cfrac => Arity::Exact(2),
+            FuncKind::Rotl | FuncKind::Rotr => Arity::Between(2, 3),
+            FuncKind::Bit | FuncKind::Nth => Arity::Exact(2),
+            FuncKind::Range => Arity::Between(2, 3),
+            FuncKind::QuadRoots => Arity::Exact(3),
+            FuncKind::CubicRoots => Arity::Exact(4),
+            FuncKind::LinSolve => Arity::Exact(2),
+            FuncKind::Fit => Arity::Between(2, 3),
+            FuncKind::Rand | FuncKind::Stdin => Arity::Exact(0),
+            FuncKind::AssertEq | FuncKind::Approx => Arity::Between(2, 3),
+            FuncKind::Map | FuncKind::Filter => Arity::Exact(3),
+            FuncKind::Table => Arity::Between(4, 5),
+            FuncKind::Reduce => Arity::Exact(4),
+            FuncKind::MonteCarlo => Arity::Exact(5),
+            FuncKind::OdeSolve => Arity::Between(5, 6),
+            FuncKind::Solve => Arity::Exact(2),
+            _ => Arity::Exact(1),
+        }
+    }
+
+    /// Get the set of values the argument of `self` at index `arg` may take.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg` - The index of the argument.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{function::Domain, token::FuncKind};
+    ///
+    /// assert_eq!(Domain::Closed(-1.0, 1.0), FuncKind::Asin.domain(0));
+    /// assert_eq!(Domain::Real, FuncKind::Sin.domain(0));
+    /// ```
+    pub fn domain(self, arg: usize) -> Domain {
+        match (self, arg) {
+            (FuncKind::Asin | FuncKind::Acos, _) => Domain::Closed(-1.0, 1.0),
+            (FuncKind::Ln | FuncKind::Log, _) => Domain::Above(0.0),
+            (FuncKind::Sqrt, _) => Domain::AtLeast(0.0),
+            (FuncKind::Gcd, _) => Domain::Integer,
+            // the number of digits to round to
+            (FuncKind::Round, 1) => Domain::Integer,
+            // changes are relative to the first number
+            (FuncKind::PctChange | FuncKind::RelChange, 0) => {
+                Domain::Except(0.0)
+            }
+            // the probability passed to norminv
+            (FuncKind::NormInv, 0) => Domain::Closed(0.0, 1.0),
+            // the standard deviation of a normal distribution
+            (FuncKind::NormPdf | FuncKind::NormCdf | FuncKind::NormInv, 2) => {
+                Domain::Above(0.0)
+            }
+            // the number of trials, probability of success and number of
+            // successes of a binomial distribution
+            (FuncKind::BinomPdf | FuncKind::BinomCdf, 0) => Domain::Natural,
+            (FuncKind::BinomPdf | FuncKind::BinomCdf, 1) => {
+                Domain::Closed(0.0, 1.0)
+            }
+            (FuncKind::BinomPdf | FuncKind::BinomCdf, 2) => Domain::Integer,
+            // the expected and actual number of events of a poisson process
+            (FuncKind::PoissonPdf, 0) => Domain::AtLeast(0.0),
+            (FuncKind::PoissonPdf, 1) => Domain::Natural,
+            // the degrees of freedom of a t-distribution
+            (FuncKind::TCdf, 1) => Domain::Above(0.0),
+            // the order of a bessel function
+            #[cfg(feature = "special-functions")]
+            (FuncKind::BesselJ, 0) => Domain::Integer,
+            // the pole of the zeta function
+            #[cfg(feature = "special-functions")]
+            (FuncKind::Zeta, _) => Domain::Except(1.0),
+            #[cfg(feature = "special-functions")]
+            (FuncKind::Beta, _) => Domain::Above(0.0),
+            // the branch point of the lambert w function
+            (FuncKind::LambertW, _) => {
+                Domain::AtLeast(-1.0 / std::f64::consts::E)
+            }
+            (FuncKind::LnFact, _) => Domain::Natural,
+            (FuncKind::Fib | FuncKind::Tri | FuncKind::Catalan, _) => {
+                Domain::Natural
+            }
+            (FuncKind::Prime, _) => Domain::Natural,
+            (FuncKind::Totient | FuncKind::Divisors | FuncKind::Sigma, 0) => {
+                Domain::PositiveInteger
+            }
+            // the power divisors are raised to
+            (FuncKind::Sigma, 1) => Domain::Natural,
+            // the number of terms of a continued fraction
+            (FuncKind::Cfrac, 1) => Domain::PositiveInteger,
+            // the integer bits are taken from, the number of places to rotate
+            // by, the width of the integer and the index of a bit
+            (FuncKind::Popcount | FuncKind::Bit, 0) => Domain::Integer,
+            (FuncKind::Rotl | FuncKind::Rotr, 0 | 1) => Domain::Integer,
+            (FuncKind::Rotl | FuncKind::Rotr, 2) => {
+                Domain::IntegerIn(1.0, 64.0)
+            }
+            (FuncKind::Bit, 1) => Domain::Natural,
+            // the sign, exponent and mantissa fields of a float
+            (FuncKind::FromBits, 0) => Domain::IntegerIn(0.0, 1.0),
+            (FuncKind::FromBits, 1) => Domain::IntegerIn(0.0, 2047.0),
+            (FuncKind::FromBits, 2) => {
+                Domain::IntegerIn(0.0, 4_503_599_627_370_495.0)
+            }
+            // the index of an element of a list
+            (FuncKind::Nth, 1) => Domain::PositiveInteger,
+            // the degree of a fitted polynomial, which needs more points than
+            // a list can hold beyond the length of one
+            (FuncKind::Fit, 2) => Domain::IntegerIn(0.0, MAX_LEN as f64),
+            // the tolerance of assert_eq and approx
+            (FuncKind::AssertEq | FuncKind::Approx, 2) => Domain::AtLeast(0.0),
+            _ => Domain::Real,
+        }
+    }
+
+    /// Get the names of the arguments of `self`, which describe what each is
+    /// in its documentation. Functions taking any number of arguments end
+    /// with `...`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert_eq!(&["x", "digits"], FuncKind::Round.params());
+    /// assert_eq!(&["x", "..."], FuncKind::Max.params());
+    /// ```
+    pub fn params(self) -> &'static [&'static str] {
+        match self {
+            FuncKind::Max | FuncKind::Min | FuncKind::Sum | FuncKind::Avg => {
+                &["x", "..."]
+            }
+            FuncKind::Gcd => &["a", "b", "..."],
+            FuncKind::Round => &["x", "digits"],
+            FuncKind::PctChange | FuncKind::RelChange => &["a", "b"],
+            FuncKind::NextAfter => &["x", "toward"],
+            FuncKind::Fma | FuncKind::QuadRoots => &["a", "b", "c"],
+            FuncKind::NormPdf | FuncKind::NormCdf => &["x", "mu", "sigma"],
+            FuncKind::NormInv => &["p", "mu", "sigma"],
+            FuncKind::BinomPdf | FuncKind::BinomCdf => &["n", "p", "k"],
+            FuncKind::PoissonPdf => &["lambda", "k"],
+            FuncKind::TCdf => &["x", "nu"],
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ => &["n", "x"],
+            #[cfg(feature = "special-functions")]
+            FuncKind::Zeta => &["s"],
+            #[cfg(feature = "special-functions")]
+            FuncKind::Beta => &["a", "b"],
+            FuncKind::LnFact
+            | FuncKind::Fib
+            | FuncKind::Tri
+            | FuncKind::Catalan
+            | FuncKind::Totient
+            | FuncKind::Divisors => &["n"],
+            FuncKind::Prime => &["k"],
+            FuncKind::Sigma => &["n", "k"],
+            FuncKind::Cfrac => &["x", "n"],
+            FuncKind::Rotl | FuncKind::Rotr => &["x", "n", "width"],
+            FuncKind::Bit => &["x", "i"],
+            FuncKind::Rand | FuncKind::Stdin => &[],
+            FuncKind::AssertEq => &["a", "b", "tol"],
+            FuncKind::Approx => &["a", "b", "eps"],
+            FuncKind::Env => &["name"],
+            FuncKind::Load => &["path"],
+            FuncKind::Sort
+            | FuncKind::Reverse
+            | FuncKind::Head
+            | FuncKind::Tail
+            | FuncKind::Unique => &["list"],
+            FuncKind::Nth => &["list", "i"],
+            FuncKind::Range => &["start", "stop", "step"],
+            FuncKind::CubicRoots => &["a", "b", "c", "d"],
+            FuncKind::LinSolve => &["a", "b"],
+            FuncKind::Fit => &["xs", "ys", "n"],
+            FuncKind::PolyRoots => &["coefficients"],
+            FuncKind::Map => &["expr", "var", "list"],
+            FuncKind::Table => &["expr", "var", "start", "stop", "step"],
+            FuncKind::Filter => &["cond", "var", "list"],
+            FuncKind::Reduce => &["expr", "acc", "var", "list"],
+            FuncKind::MonteCarlo => &["expr", "var", "a", "b", "n"],
+            FuncKind::OdeSolve => &["expr", "x0", "y0", "x1", "steps", "every"],
+            FuncKind::Solve => &["equation", "var"],
+            _ => &["x"],
+        }
+    }
+
+    /// Get how `self` is called, with the arguments that may be omitted in
+    /// brackets.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert_eq!("sin(x)", FuncKind::Sin.signature());
+    /// assert_eq!("round(x, [digits])", FuncKind::Round.signature());
+    /// assert_eq!("gcd(a, b, ...)", FuncKind::Gcd.signature());
+    /// ```
+    pub fn signature(self) -> String {
+        let required = match self.arity() {
+            Arity::Exact(n) | Arity::Between(n, _) => n,
+            Arity::AtLeast(_) => self.params().len(),
+        };
+        let params: Vec<String> = self
+            .params()
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                if i < required {
+                    param.to_string()
+                } else {
+                    format!("[{}]", param)
+                }
+            })
+            .collect();
+
+        format!("{}({})", self.name(), params.join(", "))
+    }
+
+    /// Get a description of what `self` evaluates to.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert_eq!("The nth fibonacci number.", FuncKind::Fib.description());
+    /// ```
+    pub fn description(self) -> &'static str {
+        match self {
+            FuncKind::Sin => "The sine of x, an angle in radians.",
+            FuncKind::Cos => "The cosine of x, an angle in radians.",
+            FuncKind::Tan => "The tangent of x, an angle in radians.",
+            FuncKind::Asin => {
+                "The angle in radians whose sine is x, between -pi/2 and pi/2."
+            }
+            FuncKind::Acos => {
+                "The angle in radians whose cosine is x, between 0 and pi."
+            }
+            FuncKind::Atan => {
+                "The angle in radians whose tangent is x, between -pi/2 and \
+                 pi/2."
+            }
+            FuncKind::Deg => "The angle x, in radians, in degrees.",
+            FuncKind::Rad => "The angle x, in degrees, in radians.",
+            FuncKind::Exp => "e raised to the power x.",
+            FuncKind::Ln => "The natural logarithm of x.",
+            FuncKind::Log => "The logarithm of x to base 10.",
+            FuncKind::Sqrt => "The non-negative square root of x.",
+            FuncKind::Max => "The largest of any number of arguments.",
+            FuncKind::Min => "The smallest of any number of arguments.",
+            FuncKind::Sum => "The sum of any number of arguments.",
+            FuncKind::Avg => "The arithmetic mean of any number of arguments.",
+            FuncKind::Gcd => {
+                "The greatest common divisor of two or more integers."
+            }
+            FuncKind::Round => {
+                "x rounded to a number of digits after the decimal point, or \
+                 to the nearest integer if the number of digits is omitted."
+            }
+            FuncKind::PctChange => {
+                "The change from a to b as a percentage of a, which is \
+                 negative for a fall, i.e. (b - a) / |a| * 100."
+            }
+            FuncKind::RelChange => {
+                "The change from a to b as a fraction of a, which is negative \
+                 for a fall, i.e. (b - a) / |a|."
+            }
+            FuncKind::Ulp => {
+                "The gap between x and the next representable float further \
+                 from zero, i.e. its unit in the last place."
+            }
+            FuncKind::NextAfter => {
+                "The next representable float after x in the direction of \
+                 toward."
+            }
+            FuncKind::Fma => {
+                "a * b + c with a single rounding error, i.e. a fused \
+                 multiply-add."
+            }
+            FuncKind::Erf => "The error function.",
+            FuncKind::Erfc => "The complementary error function, 1 - erf(x).",
+            FuncKind::NormPdf => {
+                "The probability density at x of a normal distribution with \
+                 mean mu and standard deviation sigma, which default to 0 and \
+                 1."
+            }
+            FuncKind::NormCdf => {
+                "The probability that a value of a normal distribution with \
+                 mean mu and standard deviation sigma is at most x. mu and \
+                 sigma default to 0 and 1."
+            }
+            FuncKind::NormInv => {
+                "The value of a normal distribution with mean mu and standard \
+                 deviation sigma which is greater than a proportion p of its \
+                 values, the inverse of normcdf. mu and sigma default to 0 and \
+                 1."
+            }
+            FuncKind::BinomPdf => {
+                "The probability of exactly k successes in n trials which each \
+                 succeed with probability p."
+            }
+            FuncKind::BinomCdf => {
+                "The probability of at most k successes in n trials which each \
+                 succeed with probability p."
+            }
+            FuncKind::PoissonPdf => {
+                "The probability of exactly k events in a Poisson process \
+                 where lambda events are expected."
+            }
+            FuncKind::TCdf => {
+                "The probability that a value of Student's t-distribution with \
+                 nu degrees of freedom is at most x."
+            }
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ => {
+                "The Bessel function of the first kind of integer order n."
+            }
+            #[cfg(feature = "special-functions")]
+            FuncKind::Zeta => "The Riemann zeta function.",
+            #[cfg(feature = "special-functions")]
+            FuncKind::Beta => "The beta function.",
+            FuncKind::LambertW => {
+                "The principal branch of the Lambert W function, the inverse \
+                 of x * e^x."
+            }
+            FuncKind::LGamma => {
+                "The natural logarithm of the absolute value of the gamma \
+                 function, which can be found for numbers whose gamma function \
+                 overflows."
+            }
+            FuncKind::LnFact => {
+                "The natural logarithm of n!, which can be found for numbers \
+                 whose factorial overflows."
+            }
+            FuncKind::Fib => "The nth fibonacci number.",
+            FuncKind::Tri => "The nth triangular number, 1 + 2 + ... + n.",
+            FuncKind::Catalan => "The nth catalan number.",
+            FuncKind::PrimePi => {
+                "The number of primes less than or equal to x."
+            }
+            FuncKind::Prime => "The kth prime, where the 1st prime is 2.",
+            FuncKind::Totient => {
+                "Euler's totient function, the number of integers from 1 to n \
+                 that are coprime to n."
+            }
+            FuncKind::Divisors => "The number of positive divisors of n.",
+            FuncKind::Sigma => {
+                "The sum of the kth powers of the positive divisors of n, \
+                 where k defaults to 1."
+            }
+            FuncKind::Cfrac => {
+                "The first n terms [a0, a1, a2, ...] of the simple continued \
+                 fraction expansion of x = a0 + 1 / (a1 + 1 / (a2 + ...)), \
+                 fewer if it is exact sooner."
+            }
+            FuncKind::Popcount => {
+                "The number of set bits in the two's complement representation \
+                 of the integer x."
+            }
+            FuncKind::Rotl => {
+                "The bits of the integer x rotated left by n places, as an \
+                 integer of a width of bits which defaults to 64."
+            }
+            FuncKind::Rotr => {
+                "The bits of the integer x rotated right by n places, as an \
+                 integer of a width of bits which defaults to 64."
+            }
+            FuncKind::Bit => {
+                "The bit of the integer x at index i, where bit 0 is the least \
+                 significant bit."
+            }
+            FuncKind::Mantissa => {
+                "The mantissa of x, such that x = mantissa(x) * 2^exponent(x)."
+            }
+            FuncKind::Exponent => {
+                "The exponent of x, such that x = mantissa(x) * 2^exponent(x)."
+            }
+            FuncKind::Bits => {
+                "The fields [sign, exponent, mantissa] of the IEEE-754 \
+                 representation of x, where the exponent is biased by 1023 and \
+                 the mantissa leaves out the implicit leading 1."
+            }
+            FuncKind::FromBits => {
+                "The float whose IEEE-754 representation has the given sign, \
+                 biased exponent and mantissa fields, the inverse of bits."
+            }
+            FuncKind::Rand => {
+                "A uniformly distributed pseudo-random number in [0, 1), which \
+                 is reproducible if the evaluator is seeded."
+            }
+            FuncKind::Assert => "x, failing unless it is non-zero.",
+            FuncKind::AssertEq => {
+                "a, failing unless a and b differ by at most tol, which \
+                 defaults to 0."
+            }
+            FuncKind::Approx => {
+                "1 if a and b are equal to within eps, relative to their \
+                 magnitude once it is larger than 1, and 0 otherwise. eps \
+                 defaults to 1e-9."
+            }
+            FuncKind::Env => {
+                "The value of the environment variable name as a number."
+            }
+            FuncKind::Load => {
+                "The list of whitespace separated numbers in the file at path."
+            }
+            FuncKind::Stdin => {
+                "The list of whitespace separated numbers read from standard \
+                 input."
+            }
+            FuncKind::Sort => "list sorted in ascending order.",
+            FuncKind::Reverse => "list in reverse order.",
+            FuncKind::Nth => {
+                "The element of list at index i, where the first element is at \
+                 index 1."
+            }
+            FuncKind::Head => "The first element of list.",
+            FuncKind::Tail => "list without its first element.",
+            FuncKind::Unique => {
+                "list without any repeated elements, keeping the first of \
+                 each."
+            }
+            FuncKind::Range => {
+                "The list of numbers from start up to and including stop in \
+                 increments of step, which defaults to 1."
+            }
+            FuncKind::QuadRoots => {
+                "The distinct real roots of ax^2 + bx + c in ascending order."
+            }
+            FuncKind::CubicRoots => {
+                "The distinct real roots of ax^3 + bx^2 + cx + d in ascending \
+                 order."
+            }
+            FuncKind::PolyRoots => {
+                "The distinct real roots in ascending order of the polynomial \
+                 with coefficients [an, ..., a1, a0], where ai is the \
+                 coefficient of x^i."
+            }
+            FuncKind::LinSolve => {
+                "The solution of the system of linear equations ax = b, where \
+                 the square matrix a is given row by row as a list."
+            }
+            FuncKind::Fit => {
+                "The coefficients [an, ..., a1, a0] of the polynomial of \
+                 degree n, which defaults to 1, fitted by least squares to the \
+                 points with x values xs and y values ys."
+            }
+            FuncKind::Map => {
+                "The list of expr evaluated with var bound to each element of \
+                 list in turn."
+            }
+            FuncKind::Table => {
+                "The list of expr evaluated with var bound to each value from \
+                 start to stop in increments of step, which defaults to 1, the \
+                 way range counts."
+            }
+            FuncKind::Filter => {
+                "The elements of list for which cond is not zero when var is \
+                 bound to them."
+            }
+            FuncKind::Reduce => {
+                "list combined into a single number, where acc starts as its \
+                 first element and is replaced by expr evaluated with var \
+                 bound to each later element in turn."
+            }
+            FuncKind::MonteCarlo => {
+                "The integral of expr from a to b estimated from n random \
+                 samples of var, as the list of the estimate and its standard \
+                 error."
+            }
+            FuncKind::OdeSolve => {
+                "The solution of dy/dx = expr at x1, where expr refers to x \
+                 and y and y is y0 at x0, found in a number of steps. If every \
+                 is non-zero, this is the list of the values of y at x0 and \
+                 after each step instead."
+            }
+            FuncKind::Solve => {
+                "The solution of an equation which is linear in var, found by \
+                 rearranging it."
+            }
+        }
+    }
+
+    /// Get the kind of function `self` is.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{function::Category, token::FuncKind};
+    ///
+    /// assert_eq!(Category::Trigonometry, FuncKind::Sin.category());
+    /// assert_eq!(Category::Lists, FuncKind::Map.category());
+    /// ```
+    pub fn category(self) -> Category {
+        match self {
+            FuncKind::Sin
+            | FuncKind::Cos
+            | FuncKind::Tan
+            | FuncKind::Asin
+            | FuncKind::Acos
+            | FuncKind::Atan
+            | FuncKind::Deg
+            | FuncKind::Rad => Category::Trigonometry,
+            FuncKind::Exp | FuncKind::Ln | FuncKind::Log | FuncKind::Sqrt => {
+                Category::Exponential
+            }
+            FuncKind::Max
+            | FuncKind::Min
+            | FuncKind::Sum
+            | FuncKind::Avg
+            | FuncKind::Gcd
+            | FuncKind::Round
+            | FuncKind::PctChange
+            | FuncKind::RelChange => Category::Arithmetic,
+            FuncKind::Ulp
+            | FuncKind::NextAfter
+            | FuncKind::Fma
+            | FuncKind::Mantissa
+            | FuncKind::Exponent
+            | FuncKind::Bits
+            | FuncKind::FromBits => Category::FloatingPoint,
+            FuncKind::Erf
+            | FuncKind::Erfc
+            | FuncKind::NormPdf
+            | FuncKind::NormCdf
+            | FuncKind::NormInv
+            | FuncKind::BinomPdf
+            | FuncKind::BinomCdf
+            | FuncKind::PoissonPdf
+            | FuncKind::TCdf
+            | FuncKind::Fit
+            | FuncKind::Rand => Category::Statistics,
+            FuncKind::LambertW | FuncKind::LGamma | FuncKind::LnFact => {
+                Category::Special
+            }
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ | FuncKind::Zeta | FuncKind::Beta => {
+                Category::Special
+            }
+            FuncKind::Fib
+            | FuncKind::Tri
+            | FuncKind::Catalan
+            | FuncKind::PrimePi
+            | FuncKind::Prime
+            | FuncKind::Totient
+            | FuncKind::Divisors
+            | FuncKind::Sigma
+            | FuncKind::Cfrac => Category::NumberTheory,
+            FuncKind::Popcount
+            | FuncKind::Rotl
+            | FuncKind::Rotr
+            | FuncKind::Bit => Category::Bits,
+            FuncKind::Assert | FuncKind::AssertEq | FuncKind::Approx => {
+                Category::Testing
+            }
+            FuncKind::Env | FuncKind::Load | FuncKind::Stdin => Category::Input,
+            FuncKind::Sort
+            | FuncKind::Reverse
+            | FuncKind::Nth
+            | FuncKind::Head
+            | FuncKind::Tail
+            | FuncKind::Unique
+            | FuncKind::Range
+            | FuncKind::Map
+            | FuncKind::Table
+            | FuncKind::Filter
+            | FuncKind::Reduce => Category::Lists,
+            FuncKind::QuadRoots
+            | FuncKind::CubicRoots
+            | FuncKind::PolyRoots
+            | FuncKind::LinSolve
+            | FuncKind::Solve => Category::Algebra,
+            FuncKind::MonteCarlo | FuncKind::OdeSolve => Category::Calculus,
+        }
+    }
+
+    /// Get expressions showing how `self` is used, which functions reading
+    /// from outside the expression, such as `load`, have none of.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{evaluator::Evaluator, token::FuncKind};
+    ///
+    /// let evaluator = Evaluator::new();
+    ///
+    /// for &f in FuncKind::ALL {
+    ///     for example in f.examples() {
+    ///         assert!(evaluator.eval_value(example).is_ok(), "{}", example);
+    ///     }
+    /// }
+    /// assert!(FuncKind::Load.examples().is_empty());
+    /// ```
+    pub fn examples(self) -> &'static [&'static str] {
+        match self {
+            FuncKind::Sin => &["sin(pi / 6)"],
+            FuncKind::Cos => &["cos(pi)"],
+            FuncKind::Tan => &["tan(pi / 4)"],
+            FuncKind::Asin => &["asin(1)"],
+            FuncKind::Acos => &["acos(0)"],
+            FuncKind::Atan => &["atan(1)"],
+            FuncKind::Deg => &["deg(pi)"],
+            FuncKind::Rad => &["rad(180)"],
+            FuncKind::Exp => &["exp(1)", "exp(0)"],
+            FuncKind::Ln => &["ln(exp(2))"],
+            FuncKind::Log => &["log(1000)"],
+            FuncKind::Sqrt => &["sqrt(2)"],
+            FuncKind::Max => &["max(3, 1, 2)"],
+            FuncKind::Min => &["min(3, 1, 2)"],
+            FuncKind::Sum => &["sum(1, 2, 3)", "sum(range(1, 100))"],
+            FuncKind::Avg => &["avg(1, 2, 6)"],
+            FuncKind::Gcd => &["gcd(12, 18)"],
+            FuncKind::Round => &["round(2.5)", "round(3.14159, 2)"],
+            FuncKind::PctChange => &["pctchange(80, 100)", "pctchange(50, 40)"],
+            FuncKind::RelChange => &["relchange(80, 100)"],
+            FuncKind::Ulp => &["ulp(1)"],
+            FuncKind::NextAfter => &["nextafter(1, 2)"],
+            FuncKind::Fma => &["fma(2, 3, 1)"],
+            FuncKind::Erf => &["erf(1)"],
+            FuncKind::Erfc => &["erfc(1)"],
+            FuncKind::NormPdf => &["normpdf(0)"],
+            FuncKind::NormCdf => &["normcdf(1.96)", "normcdf(130, 100, 15)"],
+            FuncKind::NormInv => &["norminv(0.975)"],
+            FuncKind::BinomPdf => &["binompdf(10, 0.5, 5)"],
+            FuncKind::BinomCdf => &["binomcdf(10, 0.5, 5)"],
+            FuncKind::PoissonPdf => &["poissonpdf(3, 2)"],
+            FuncKind::TCdf => &["tcdf(2, 10)"],
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ => &["besselj(0, 1)"],
+            #[cfg(feature = "special-functions")]
+            FuncKind::Zeta => &["zeta(2)"],
+            #[cfg(feature = "special-functions")]
+            FuncKind::Beta => &["beta(2, 3)"],
+            FuncKind::LambertW => &["lambertw(exp(1))"],
+            FuncKind::LGamma => &["lgamma(200)"],
+            FuncKind::LnFact => &["lnfact(1000)"],
+            FuncKind::Fib => &["fib(10)"],
+            FuncKind::Tri => &["tri(10)"],
+            FuncKind::Catalan => &["catalan(5)"],
+            FuncKind::PrimePi => &["primepi(100)"],
+            FuncKind::Prime => &["prime(10)"],
+            FuncKind::Totient => &["totient(12)"],
+            FuncKind::Divisors => &["divisors(12)"],
+            FuncKind::Sigma => &["sigma(12)", "sigma(12, 2)"],
+            FuncKind::Cfrac => &["cfrac(pi, 5)", "cfrac(0.75, 10)"],
+            FuncKind::Popcount => &["popcount(255)"],
+            FuncKind::Rotl => &["rotl(144, 1, 8)"],
+            FuncKind::Rotr => &["rotr(33, 1, 8)"],
+            FuncKind::Bit => &["bit(5, 2)"],
+            FuncKind::Mantissa => &["mantissa(10)"],
+            FuncKind::Exponent => &["exponent(10)"],
+            FuncKind::Bits => &["bits(1)", "bits(-0.1)"],
+            FuncKind::FromBits => {
+                &["frombits(0, 1023, 0)", "frombits(bits(0.1))"]
+            }
+            FuncKind::Rand => &["rand()"],
+            FuncKind::Assert => &["assert(approx(sqrt(2)^2, 2))"],
+            FuncKind::AssertEq => &["assert_eq(22 / 7, pi, 0.01)"],
+            FuncKind::Approx => &["approx(0.1 + 0.2, 0.3)"],
+            FuncKind::Env => &[],
+            FuncKind::Load => &[],
+            FuncKind::Stdin => &[],
+            FuncKind::Sort => &["sort([3, 1, 2])"],
+            FuncKind::Reverse => &["reverse([1, 2, 3])"],
+            FuncKind::Nth => &["nth([4, 5, 6], 2)"],
+            FuncKind::Head => &["head([4, 5, 6])"],
+            FuncKind::Tail => &["tail([4, 5, 6])"],
+            FuncKind::Unique => &["unique([1, 2, 1, 3])"],
+            FuncKind::Range => &["range(1, 5)", "range(0, 1, 0.25)"],
+            FuncKind::QuadRoots => &["quadroots(1, -3, 2)"],
+            FuncKind::CubicRoots => &["cubicroots(1, -6, 11, -6)"],
+            FuncKind::PolyRoots => &["polyroots([1, 0, -4])"],
+            FuncKind::LinSolve => &["linsolve([1, 1, 1, -1], [3, 1])"],
+            FuncKind::Fit => &[
+                "fit([0, 1, 2], [1, 3, 5])",
+                "fit([-1, 0, 1, 2], [6, 3, 4, 9], 2)",
+            ],
+            FuncKind::Map => &["map(x^2, x, [1, 2, 3])"],
+            FuncKind::Table => &["table(x^2, x, 0, 1, 0.25)"],
+            FuncKind::Filter => &["filter(x % 2, x, range(1, 10))"],
+            FuncKind::Reduce => &["reduce(acc * x, acc, x, range(1, 5))"],
+            FuncKind::MonteCarlo => &["montecarlo(x^2, x, 0, 1, 1000)"],
+            FuncKind::OdeSolve => &["odesolve(y, 0, 1, 1, 100)"],
+            FuncKind::Solve => &["solve(2 * x + 3 = 11, x)"],
+        }
+    }
+
+    /// Check `args` against the arity and domain of `self`, then evaluate
+    /// `self` with them.
+    ///
+    /// Functions that depend on settings, such as the rounding mode used by
+    /// `round`, use the default settings. `Evaluator::call` evaluates them
+    /// with the settings of an evaluator instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments to evaluate the function with.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert_eq!(2.0, FuncKind::Sqrt.call(&[4.0]).unwrap());
+    /// assert!(FuncKind::Sqrt.call(&[-4.0]).is_err());
+    /// assert!(FuncKind::Sqrt.call(&[4.0, 9.0]).is_err());
+    /// assert_eq!(3.0, FuncKind::Max.call(&[1.0, 3.0, 2.0]).unwrap());
+    /// assert_eq!(6.0, FuncKind::Gcd.call(&[12.0, -18.0]).unwrap());
+    /// assert_eq!(3.14, FuncKind::Round.call(&[3.14159, 2.0]).unwrap());
+    /// assert_eq!(25.0, FuncKind::PctChange.call(&[80.0, 100.0]).unwrap());
+    /// assert!(FuncKind::PctChange.call(&[0.0, 100.0]).is_err());
+    /// assert_eq!(7.0, FuncKind::Fma.call(&[2.0, 3.0, 1.0]).unwrap());
+    /// assert_eq!(0.5, FuncKind::NormCdf.call(&[100.0, 100.0, 15.0]).unwrap());
+    /// assert!(FuncKind::NormCdf.call(&[1.0, 0.0, -1.0]).is_err());
+    /// assert!(FuncKind::BinomPdf.call(&[10.0, 1.5, 5.0]).is_err());
+    /// assert_eq!(55.0, FuncKind::Fib.call(&[10.0]).unwrap());
+    /// assert!(FuncKind::Fib.call(&[100.0]).is_err());
+    /// assert_eq!(28.0, FuncKind::Sigma.call(&[12.0]).unwrap());
+    /// assert_eq!(33.0, FuncKind::Rotl.call(&[144.0, 1.0, 8.0]).unwrap());
+    /// let fields = [1.0, 1024.0, 2f64.powi(51)];
+    /// assert_eq!(-3.0, FuncKind::FromBits.call(&fields).unwrap());
+    /// assert!(FuncKind::FromBits.call(&[2.0, 0.0, 0.0]).is_err());
+    /// assert!(FuncKind::Assert.call(&[0.0]).is_err());
+    /// assert_eq!(3.0, FuncKind::AssertEq.call(&[3.0, 3.01, 0.1]).unwrap());
+    /// ```
+    pub fn call(self, args: &[f64]) -> Result<f64> {
+        if self.is_list_function() || self.is_higher_order() {
+            let args: Vec<Value> = args.iter().map(|&n| n.into()).collect();
+            return self.call_list(&args)?.into_number();
+        }
+
+        check_args(self.name(), self.arity(), |i| self.domain(i), args)?;
+
+        let n = match self {
+            // the state of the generator is part of the evaluator
+            FuncKind::Rand => bail!(Code::NeedsEvaluator.with(&[&"rand"])),
+            FuncKind::Env | FuncKind::Load => {
+                bail!(Code::NeedsString.with(&[&self.name()]))
+            }
+            FuncKind::Max => {
+                args.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+            }
+            FuncKind::Min => args.iter().copied().fold(f64::INFINITY, f64::min),
+            FuncKind::Sum => args.iter().sum(),
+            FuncKind::Avg => args.iter().sum::<f64>() / args.len() as f64,
+            // casting is safe since every argument is an integer, though ones
+            // too large for a u64 saturate
+            FuncKind::Gcd => {
+                args.iter().fold(0, |acc, &x| gcd(acc, x.abs() as u64)) as f64
+            }
+            FuncKind::Round => round_args(args, Rounding::default()),
+            FuncKind::PctChange => relative_change(args[0], args[1]) * 100.0,
+            FuncKind::RelChange => relative_change(args[0], args[1]),
+            FuncKind::NextAfter => nextafter(args[0], args[1]),
+            FuncKind::Fma => args[0].mul_add(args[1], args[2]),
+            // casting is exact since every field is a non-negative integer
+            // within its width
+            FuncKind::FromBits => bits::from_fields(
+                args[0] as u64,
+                args[1] as u64,
+                args[2] as u64,
+            ),
+            FuncKind::NormPdf => normal_args(args, normpdf),
+            FuncKind::NormCdf => normal_args(args, normcdf),
+            FuncKind::NormInv => normal_args(args, norminv),
+            FuncKind::BinomPdf => binompdf(args[0], args[1], args[2]),
+            FuncKind::BinomCdf => binomcdf(args[0], args[1], args[2]),
+            FuncKind::PoissonPdf => poissonpdf(args[0], args[1]),
+            FuncKind::TCdf => tcdf(args[0], args[1]),
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ => besselj(args[0], args[1]),
+            #[cfg(feature = "special-functions")]
+            FuncKind::Beta => beta(args[0], args[1]),
+            FuncKind::Fib | FuncKind::Tri | FuncKind::Catalan => {
+                sequence(self, args[0])?
+            }
+            FuncKind::PrimePi | FuncKind::Prime => primes(self, args[0])?,
+            // casting is safe since both arguments are integers, though huge
+            // ones saturate
+            FuncKind::Sigma => {
+                nt::sigma(args[0] as u64, args.get(1).map_or(1, |&k| k as u32))
+            }
+            FuncKind::Rotl | FuncKind::Rotr => rotate(self, args),
+            // casting is safe since the index is a non-negative integer, and
+            // huge ones saturate, which is harmless as they are all beyond
+            // the 64th bit
+            FuncKind::Bit => bits::bit(args[0], args[1] as u64) as u8 as f64,
+            FuncKind::Assert | FuncKind::AssertEq => assertion(self, args)?,
+            FuncKind::Approx => {
+                let epsilon = args.get(2).copied().unwrap_or(DEFAULT_EPSILON);
+                approx_eq(args[0], args[1], epsilon) as u8 as f64
+            }
+            _ => self.eval(args[0]),
+        };
+
+        Ok(n)
+    }
+
+    /// Check whether `self` is slow enough to evaluate that its results are
+    /// worth caching, see `Evaluator::set_cache_size`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert!(FuncKind::Prime.is_expensive());
+    /// assert!(!FuncKind::Sin.is_expensive());
+    /// ```
+    pub fn is_expensive(self) -> bool {
+        match self {
+            FuncKind::Fib
+            | FuncKind::Catalan
+            | FuncKind::PrimePi
+            | FuncKind::Prime
+            | FuncKind::Totient
+            | FuncKind::Divisors
+            | FuncKind::Sigma => true,
+            #[cfg(feature = "special-functions")]
+            FuncKind::BesselJ | FuncKind::Zeta => true,
+            _ => false,
         }
     }
+
+    /// Check whether `self` takes a string rather than numbers, so is
+    /// evaluated by `Evaluator::call_string` rather than `call`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert!(FuncKind::Env.takes_string());
+    /// assert!(!FuncKind::Sin.takes_string());
+    /// ```
+    pub fn takes_string(self) -> bool {
+        matches!(self, FuncKind::Env | FuncKind::Load)
+    }
+
+    /// Check whether `self` takes or returns lists, so is evaluated by
+    /// `call_list` rather than `call`.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert!(FuncKind::Sort.is_list_function());
+    /// assert!(!FuncKind::Max.is_list_function());
+    /// ```
+    pub fn is_list_function(self) -> bool {
+        matches!(
+            self,
+            FuncKind::Sort
+                | FuncKind::Reverse
+                | FuncKind::Nth
+                | FuncKind::Head
+                | FuncKind::Tail
+                | FuncKind::Unique
+                | FuncKind::Range
+                | FuncKind::Cfrac
+                | FuncKind::Bits
+                | FuncKind::QuadRoots
+                | FuncKind::CubicRoots
+                | FuncKind::PolyRoots
+                | FuncKind::LinSolve
+                | FuncKind::Fit
+                | FuncKind::Stdin
+                | FuncKind::Map
+                | FuncKind::Table
+                | FuncKind::Filter
+                | FuncKind::Reduce
+                | FuncKind::MonteCarlo
+                | FuncKind::OdeSolve
+        )
+    }
+
+    /// Check whether `self` takes an expression as its first argument which
+    /// is evaluated many times, such as once for each element of a list,
+    /// rather than a value.
+    ///
+    /// Such functions can only be called in expressions, since the parser
+    /// has to defer evaluating their first argument.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::token::FuncKind;
+    ///
+    /// assert!(FuncKind::Map.is_higher_order());
+    /// assert!(!FuncKind::Sort.is_higher_order());
+    /// ```
+    pub fn is_higher_order(self) -> bool {
+        matches!(
+            self,
+            FuncKind::Map
+                | FuncKind::Table
+                | FuncKind::Filter
+                | FuncKind::Reduce
+                | FuncKind::MonteCarlo
+                | FuncKind::OdeSolve
+                | FuncKind::Solve
+        )
+    }
+
+    /// Check `args` against the arity and domain of `self`, then evaluate
+    /// `self` with them, where each argument may be a number or a list.
+    ///
+    /// Functions which only take numbers are called with the elements of any
+    /// lists spread into their arguments, so `max([1, 2], 3)` is `max(1, 2,
+    /// 3)`. Functions of lists treat a number as a list of one element.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments to evaluate the function with.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::{token::FuncKind, value::Value};
+    ///
+    /// let list = Value::List(vec![3.0, 1.0, 2.0, 1.0]);
+    /// let call = |f: FuncKind, args: &[Value]| f.call_list(args).unwrap();
+    ///
+    /// assert_eq!(Value::List(vec![1.0, 1.0, 2.0, 3.0]), call(FuncKind::Sort, &[list.clone()]));
+    /// assert_eq!(Value::List(vec![3.0, 1.0, 2.0]), call(FuncKind::Unique, &[list.clone()]));
+    /// assert_eq!(Value::Number(1.0), call(FuncKind::Nth, &[list.clone(), 2.0.into()]));
+    /// assert_eq!(Value::Number(3.0), call(FuncKind::Max, &[list.clone()]));
+    /// assert_eq!(
+    ///     Value::List(vec![0.0, 0.5, 1.0]),
+    ///     call(FuncKind::Range, &[0.0.into(), 1.0.into(), 0.5.into()])
+    /// );
+    /// assert_eq!(
+    ///     Value::List(vec![3.0, 7.0, 15.0, 1.0]),
+    ///     call(FuncKind::Cfrac, &[std::f64::consts::PI.into(), 4.0.into()])
+    /// );
+    /// assert!(FuncKind::Cfrac.call_list(&[0.5.into(), 0.0.into()]).is_err());
+    /// assert_eq!(
+    ///     Value::List(vec![1.0, 1024.0, 2f64.powi(51)]),
+    ///     call(FuncKind::Bits, &[(-3.0).into()])
+    /// );
+    /// let (xs, ys) = (vec![0.0, 1.0, 2.0], vec![1.0, 3.0, 5.0]);
+    /// assert_eq!(
+    ///     Value::List(vec![2.0, 1.0]),
+    ///     call(FuncKind::Fit, &[Value::List(xs), Value::List(ys)])
+    /// );
+    /// let fields = call(FuncKind::Bits, &[0.1.into()]);
+    /// assert_eq!(Value::Number(0.1), call(FuncKind::FromBits, &[fields]));
+    /// assert!(FuncKind::Nth.call_list(&[list, 5.0.into()]).is_err());
+    /// assert!(FuncKind::Head.call_list(&[Value::List(vec![])]).is_err());
+    /// ```
+    pub fn call_list(self, args: &[Value]) -> Result<Value> {
+        if self.is_higher_order() {
+            bail!(Code::NeedsExpression.with(&[&self.name()]))
+        }
+        if !self.is_list_function() {
+            let args: Vec<f64> =
+                args.iter().cloned().flat_map(Value::into_list).collect();
+
+            return self.call(&args).map(Value::Number);
+        }
+
+        if !self.arity().accepts(args.len()) {
+            bail!(Code::WrongArity.with(&[
+                &self.name(),
+                &self.arity(),
+                &args.len()
+            ]))
+        }
+
+        // get the argument at index i, which must be a number
+        let number = |i: usize| match args[i] {
+            Value::List(_) => {
+                bail!(Code::ArgumentIsList.with(&[&self.name(), &(i + 1)]))
+            }
+            ref arg => {
+                let n = arg.clone().into_number()?;
+                check_arg(self.name(), self.arity(), i, self.domain(i), n)?;
+                Ok(n)
+            }
+        };
+        let list = || args[0].clone().into_list();
+
+        let value = match self {
+            FuncKind::Sort => {
+                let mut list = list();
+                list.sort_by(f64::total_cmp);
+                Value::List(list)
+            }
+            FuncKind::Reverse => {
+                Value::List(list().into_iter().rev().collect())
+            }
+            FuncKind::Nth => {
+                let (list, i) = (list(), number(1)?);
+
+                // casting is safe since i is a positive integer, and huge
+                // ones saturate, which is harmless as they are out of range
+                match list.get(i as usize - 1) {
+                    Some(&n) => Value::Number(n),
+                    None => {
+                        bail!(Code::IndexOutOfRange.with(&[&list.len(), &i]))
+                    }
+                }
+            }
+            FuncKind::Head => match list().first() {
+                Some(&n) => Value::Number(n),
+                None => bail!(Message::new(Code::EmptyHead)),
+            },
+            FuncKind::Tail => match list().split_first() {
+                Some((_, rest)) => Value::List(rest.to_vec()),
+                None => bail!(Message::new(Code::EmptyTail)),
+            },
+            FuncKind::Unique => {
+                // 0 and -0 are equal, so are treated as the same element
+                let mut seen = HashSet::new();
+                let key = |n: f64| if n == 0.0 { 0 } else { n.to_bits() };
+
+                Value::List(
+                    list()
+                        .into_iter()
+                        .filter(|&n| seen.insert(key(n)))
+                        .collect(),
+                )
+            }
+            FuncKind::Range => {
+                let (start, stop) = (number(0)?, number(1)?);
+                let step = match args.len() {
+                    3 => number(2)?,
+                    _ => 1.0,
+                };
+                let steps = count_steps(start, stop, step, MAX_LEN)?;
+
+                // multiplying rather than repeatedly adding avoids
+                // accumulating error in the elements
+                Value::List(
+                    (0..=steps).map(|i| start + i as f64 * step).collect(),
+                )
+            }
+            // casting is safe since the number of terms is a positive
+            // integer, and huge ones saturate, which is harmless since the
+            // expansion of a float is exact after far fewer terms
+            FuncKind::Cfrac => Value::List(
+                cfrac(number(0)?, number(1)? as usize)
+                    .into_iter()
+                    .map(|a| a as f64)
+                    .collect(),
+            ),
+            FuncKind::Bits => Value::List(
+                bits::fields(number(0)?).iter().map(|&f| f as f64).collect(),
+            ),
+            FuncKind::QuadRoots => Value::List(quadratic_roots(
+                number(0)?,
+                number(1)?,
+                number(2)?,
+            )?),
+            FuncKind::CubicRoots => Value::List(cubic_roots(
+                number(0)?,
+                number(1)?,
+                number(2)?,
+                number(3)?,
+            )?),
+            FuncKind::PolyRoots => Value::List(poly_roots(&list())?),
+            FuncKind::LinSolve => {
+                let b = args[1].clone().into_list();
+                Value::List(linsolve(&list(), &b)?)
+            }
+            FuncKind::Fit => {
+                let (xs, ys) = (list(), args[1].clone().into_list());
+                // casting is safe since the degree is a natural number no
+                // larger than the length of a list
+                let degree = match args.len() {
+                    3 => number(2)? as usize,
+                    _ => 1,
+                };
+
+                // lines have a closed form fit, which needs no decomposition
+                let mut coefficients = match degree {
+                    1 => {
+                        let line = stats::fit(&xs, &ys)?;
+                        vec![line.intercept, line.slope]
+                    }
+                    _ => stats::fitpoly(&xs, &ys, degree)?.coefficients,
+                };
+                // in the order polyroots takes them
+                coefficients.reverse();
+
+                Value::List(coefficients)
+            }
+            // standard input is read once by the evaluator
+            FuncKind::Stdin => {
+                bail!(Code::NeedsEvaluator.with(&[&"stdin"]))
+            }
+            _ => unreachable!("{} is not a function of lists", self.name()),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Calculate the nth term of the integer sequence `f`, failing if it is too
+/// large to be computed exactly.
+fn sequence(f: FuncKind, n: f64) -> Result<f64> {
+    let seq = match f {
+        FuncKind::Fib => fibonacci,
+        FuncKind::Tri => triangular,
+        FuncKind::Catalan => catalan,
+        _ => unreachable!("{} is not an integer sequence", f.name()),
+    };
+
+    // casting is safe since n is a non-negative integer, though huge ones
+    // saturate, which is harmless since their terms overflow anyway
+    match seq(n as u64) {
+        Some(term) => Ok(term as f64),
+        None => bail!(Code::TooLargeToComputeExactly.with(&[&f.name(), &n])),
+    }
+}
+
+/// Calculate the nth term of the integer sequence `f` exactly, where `args`
+/// is `[n]`, returning `None` if `f` is not an integer sequence or the term is
+/// too large to be calculated exactly.
+#[cfg(feature = "bigint")]
+pub(crate) fn exact_sequence(
+    f: FuncKind,
+    args: &[f64],
+) -> Result<Option<BigInt>> {
+    let seq = match f {
+        FuncKind::Fib => big::fibonacci,
+        FuncKind::Tri => |n| Some(big::triangular(n)),
+        FuncKind::Catalan => big::catalan,
+        _ => return Ok(None),
+    };
+
+    check_args(f.name(), f.arity(), |i| f.domain(i), args)?;
+
+    // casting is safe since n is a non-negative integer, though huge ones
+    // saturate, which is harmless since their terms are too large anyway
+    Ok(seq(args[0] as u64))
+}
+
+/// Evaluate the prime function `f` at n, failing if n is beyond the limits of
+/// the sieve used.
+fn primes(f: FuncKind, n: f64) -> Result<f64> {
+    // the number of primes below a negative number is 0, and casting rounds
+    // it up to 0, while huge numbers saturate and are rejected as too large
+    let n = n.floor() as u64;
+
+    let result = match f {
+        FuncKind::PrimePi => nt::primepi(n)?,
+        FuncKind::Prime => nt::prime(n)?,
+        _ => unreachable!("{} is not a prime function", f.name()),
+    };
+
+    Ok(result as f64)
+}
+
+/// Rotate the bits of the first of `args` by the second, within the width
+/// given by the third if any, in the direction of the rotation function `f`.
+fn rotate(f: FuncKind, args: &[f64]) -> f64 {
+    // casting is safe since all the arguments are integers, the width is at
+    // most 64, and huge numbers of places saturate, which is harmless since
+    // only their remainder modulo the width matters
+    let n = args[1] as i64;
+    let width = args.get(2).map_or(64, |&w| w as u32);
+
+    let rotated = match f {
+        FuncKind::Rotl => bits::rotl(args[0], n, width),
+        FuncKind::Rotr => bits::rotr(args[0], n, width),
+        _ => unreachable!("{} is not a rotation", f.name()),
+    };
+
+    rotated as f64
+}
+
+/// Check the assertion `f` about `args`, evaluating to the first of `args` if
+/// it holds.
+fn assertion(f: FuncKind, args: &[f64]) -> Result<f64> {
+    match f {
+        // NaN is not a true condition either
+        FuncKind::Assert if args[0] == 0.0 || args[0].is_nan() => {
+            bail!(Code::AssertNonZero.with(&[&args[0]]))
+        }
+        FuncKind::Assert => (),
+        FuncKind::AssertEq => {
+            let (a, b) = (args[0], args[1]);
+            let tolerance = args.get(2).copied().unwrap_or(0.0);
+            // comparing directly first means equal infinities are equal
+            let difference = if a == b { 0.0 } else { (a - b).abs() };
+
+            if tolerance == 0.0 && difference != 0.0 {
+                bail!(Code::AssertNotEqual.with(&[&a, &b]))
+            }
+            if difference > tolerance || difference.is_nan() {
+                bail!(Code::AssertDiffer.with(&[
+                    &a,
+                    &b,
+                    &difference,
+                    &tolerance
+                ]))
+            }
+        }
+        _ => unreachable!("{} is not an assertion", f.name()),
+    }
+
+    Ok(args[0])
+}
+
+/// Evaluate the normal distribution function `f` at the first of `args`, with
+/// the mean and standard deviation given by the rest of `args`, if any.
+fn normal_args(args: &[f64], f: fn(f64, f64, f64) -> f64) -> f64 {
+    let mu = args.get(1).copied().unwrap_or(0.0);
+    let sigma = args.get(2).copied().unwrap_or(1.0);
+
+    f(args[0], mu, sigma)
+}
+
+/// Round the first of `args` to the number of digits given by the second, if
+/// any, using `mode`.
+pub(crate) fn round_args(args: &[f64], mode: Rounding) -> f64 {
+    // casting is safe since the number of digits is an integer, though huge
+    // ones saturate, which is harmless as they keep every digit anyway
+    let digits = args.get(1).map_or(0, |&d| d as i32);
+
+    round(args[0], digits, mode)
 }
@@ -0,0 +1,162 @@
+//! The values expressions evaluate to, which are numbers, lists of numbers
+//! or exact forms of numbers.
+
+use std::fmt;
+
+use anyhow::{bail, Result};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+use crate::{
+    message::{Code, Message},
+    symbolic::Symbolic,
+};
+
+/// The most elements a list may have, so that e.g. a mistyped range cannot
+/// exhaust memory.
+pub const MAX_LEN: usize = 1_000_000;
+
+/// The result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A single number.
+    Number(f64),
+    /// A list of numbers, such as `[1, 2, 3]`.
+    List(Vec<f64>),
+    /// An exact integer too large to be held exactly by a number, such as
+    /// `50!`.
+    #[cfg(feature = "bigint")]
+    Integer(BigInt),
+    /// An exact number which keeps constants and square roots symbolic, such
+    /// as `3 * pi`, from evaluating with `Evaluator::set_symbolic`.
+    Symbolic(Symbolic),
+}
+
+impl Value {
+    /// Get the number `self` holds, failing if it is a list or an exact value
+    /// with a variable.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::value::Value;
+    ///
+    /// assert_eq!(2.0, Value::Number(2.0).into_number().unwrap());
+    /// assert!(Value::List(vec![2.0]).into_number().is_err());
+    /// ```
+    pub fn into_number(self) -> Result<f64> {
+        match self {
+            Value::Number(n) => Ok(n),
+            Value::List(_) => bail!(Message::new(Code::ExpectedNumber)),
+            #[cfg(feature = "bigint")]
+            Value::Integer(n) => Ok(to_f64(&n)),
+            Value::Symbolic(s) => match s.variables().first() {
+                Some(name) => bail!(Code::NoValue.with(&[name])),
+                None => Ok(s.value()),
+            },
+        }
+    }
+
+    /// Get the numbers `self` holds, treating a number as a list of one
+    /// element.
+    ///
+    /// # Examples
+    /// ```
+    /// use cocoa::value::Value;
+    ///
+    /// assert_eq!(vec![2.0], Value::Number(2.0).into_list());
+    /// assert_eq!(vec![1.0, 2.0], Value::List(vec![1.0, 2.0]).into_list());
+    /// ```
+    pub fn into_list(self) -> Vec<f64> {
+        match self {
+            Value::Number(n) => vec![n],
+            Value::List(list) => list,
+            #[cfg(feature = "bigint")]
+            Value::Integer(n) => vec![to_f64(&n)],
+            Value::Symbolic(s) => vec![s.value()],
+        }
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl From<BigInt> for Value {
+    /// Integers small enough to be held exactly by a number become numbers.
+    fn from(n: BigInt) -> Self {
+        if n.magnitude().bits() <= 53 {
+            Value::Number(to_f64(&n))
+        } else {
+            Value::Integer(n)
+        }
+    }
+}
+
+/// Convert `n` to the nearest number, which is infinite if `n` is too large.
+#[cfg(feature = "bigint")]
+pub(crate) fn to_f64(n: &BigInt) -> f64 {
+    use num_traits::ToPrimitive;
+
+    // numbers out of range are converted to infinity rather than failing
+    n.to_f64().unwrap_or(f64::NAN)
+}
+
+impl From<Symbolic> for Value {
+    /// Symbolic values which are integers small enough to be held exactly by
+    /// a number become numbers.
+    fn from(s: Symbolic) -> Self {
+        match s.to_rational() {
+            Some(n) if n.is_integer() && n.num().unsigned_abs() <= 1 << 53 => {
+                Value::Number(n.value())
+            }
+            _ => Value::Symbolic(s),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::List(list) => {
+                let elements: Vec<String> =
+                    list.iter().map(|n| n.to_string()).collect();
+
+                write!(f, "[{}]", elements.join(", "))
+            }
+            #[cfg(feature = "bigint")]
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Symbolic(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Count how many steps of `step` it takes to go from `start` to `stop`
+/// without passing it, failing if `step` never reaches `stop` or if there are
+/// more than `limit` steps.
+pub(crate) fn count_steps(
+    start: f64,
+    stop: f64,
+    step: f64,
+    limit: usize,
+) -> Result<usize> {
+    if !(start.is_finite() && stop.is_finite() && step.is_finite()) {
+        bail!(Message::new(Code::RangeNotFinite));
+    }
+    if step == 0.0 || (stop - start) * step < 0.0 {
+        bail!(Code::RangeNeverReaches.with(&[&step, &stop, &start]));
+    }
+
+    // allow for the error in dividing, e.g. (0.3 - 0) / 0.1 is slightly less
+    // than 3
+    let steps = ((stop - start) / step * (1.0 + f64::EPSILON)).floor();
+    if steps >= limit as f64 {
+        bail!(Code::TooManyValues.with(&[&limit, &(steps + 1.0)]));
+    }
+
+    // casting is safe since steps is a non-negative integer below limit
+    Ok(steps as usize)
+}